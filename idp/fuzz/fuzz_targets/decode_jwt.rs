@@ -0,0 +1,20 @@
+//! `cargo +nightly fuzz run decode_jwt`
+//!
+//! Decoding never panics, whatever the token.
+
+#![no_main]
+
+use idp::{
+    domain::mail_address::MailAddress,
+    token::{jwt::decode_jwt, signing_key::SigningKeys},
+};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(token) = std::str::from_utf8(data) else {
+        return;
+    };
+    let keys = SigningKeys::of("fuzz-secret");
+    let email = MailAddress::of("kamino@example.com").unwrap();
+    let _ = decode_jwt(&keys, "http://localhost:8080", token, &email);
+});
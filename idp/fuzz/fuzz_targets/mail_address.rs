@@ -0,0 +1,19 @@
+//! `cargo +nightly fuzz run mail_address`
+//!
+//! Parsing never panics, and what it accepts is already canonical.
+
+#![no_main]
+
+use idp::domain::mail_address::MailAddress;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(input) = std::str::from_utf8(data) else {
+        return;
+    };
+    if let Ok(email) = MailAddress::of(input) {
+        let canonical = String::from(email.clone());
+        assert!(canonical.len() <= 254);
+        assert_eq!(MailAddress::of(canonical).ok(), Some(email));
+    }
+});
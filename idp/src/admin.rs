@@ -0,0 +1,562 @@
+//! Admin commands.
+//!
+//! Run by the `idp-admin` binary against the same realms as the server. Changes
+//! go to the state file, which the server applies when it starts: a running
+//! server keeps its users, clients and keys until it is restarted.
+
+use std::{collections::BTreeMap, error::Error, io::BufRead, path::PathBuf};
+
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use serde_json::{json, Value};
+
+use crate::{
+    credential::passwd_hasher::{hash_password, is_supported_hash},
+    domain::{mail_address::MailAddress, password::Password},
+    entity::{
+        client::Client,
+        group::{contains_group, is_group_name, Group},
+        user::User,
+    },
+    error::my_error::MyError,
+    realm::{
+        realm_config::{configured_realms, registry_of},
+        realm_registry::Realm,
+        realm_state::{ClientRecord, KeyRecord, RealmState, StateFile, UserRecord},
+    },
+    resource::token_resource::{client_token, user_token},
+    token::{
+        jwt::TOKEN_LIFETIME_HOURS,
+        signing_key::{generate_secret, SigningKey},
+    },
+};
+
+pub type Result<T> = std::result::Result<T, Box<dyn Error + Send + Sync>>;
+
+/// Operates the idp without going through HTTP.
+#[derive(Parser, Debug)]
+#[command(name = "idp-admin")]
+pub struct AdminCli {
+    /// State file the server reads at start.
+    #[arg(long, env = "IDP_STATE_FILE")]
+    pub state: PathBuf,
+    /// Defaults to the first configured realm.
+    #[arg(long, global = true)]
+    pub realm: Option<String>,
+    #[arg(long, value_enum, default_value_t = Output::Table, global = true)]
+    pub output: Output,
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Output {
+    Json,
+    Table,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    #[command(subcommand)]
+    Users(UserCommand),
+    #[command(subcommand)]
+    Clients(ClientCommand),
+    #[command(subcommand)]
+    Groups(GroupCommand),
+    #[command(subcommand)]
+    Keys(KeyCommand),
+    #[command(subcommand)]
+    Token(TokenCommand),
+}
+
+#[derive(Subcommand, Debug)]
+pub enum UserCommand {
+    /// Creates a user, the password is read from stdin.
+    Create {
+        #[arg(value_parser = mail_address)]
+        email: MailAddress,
+    },
+    /// Keeps the user from signing in and getting tokens, once the server
+    /// restarts. Tokens issued before stay valid until they expire.
+    Disable {
+        #[arg(value_parser = mail_address)]
+        email: MailAddress,
+    },
+    Enable {
+        #[arg(value_parser = mail_address)]
+        email: MailAddress,
+    },
+    /// Sets the password read from stdin, used once the server restarts.
+    ResetPassword {
+        #[arg(value_parser = mail_address)]
+        email: MailAddress,
+    },
+    /// Creates a user from another system with the password hash read from
+    /// stdin: an Argon2 or PBKDF2 PHC string, or a bcrypt hash. It becomes
+    /// Argon2id with the current parameters at the first login.
+    Import {
+        #[arg(value_parser = mail_address)]
+        email: MailAddress,
+    },
+    List,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ClientCommand {
+    /// Registers an OAuth client. Confidential ones get a secret, shown once.
+    Register {
+        client_id: String,
+        #[arg(long)]
+        confidential: bool,
+        /// Scopes the client may ask users for.
+        #[arg(long = "scope")]
+        scopes: Vec<String>,
+        #[arg(long)]
+        require_consent: bool,
+        /// Most groups listed in its tokens, 0 for none.
+        #[arg(long)]
+        groups_limit: Option<usize>,
+    },
+    List,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum GroupCommand {
+    Create {
+        name: String,
+    },
+    /// Deletes the group and takes it out of the groups it is nested in.
+    Delete {
+        name: String,
+    },
+    /// Adds a user to the group, or nests another group in it.
+    AddMember {
+        name: String,
+        #[command(flatten)]
+        member: Member,
+    },
+    RemoveMember {
+        name: String,
+        #[command(flatten)]
+        member: Member,
+    },
+    /// Every group the user is in, nested ones expanded, as in tokens.
+    Memberships {
+        #[arg(value_parser = mail_address)]
+        email: MailAddress,
+    },
+    List,
+}
+
+#[derive(Args, Debug)]
+#[group(required = true, multiple = false)]
+pub struct Member {
+    #[arg(long, value_parser = mail_address)]
+    pub user: Option<MailAddress>,
+    /// A group whose members become members too.
+    #[arg(long)]
+    pub group: Option<String>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum KeyCommand {
+    /// Prints a new secret for `IDP_JWT_SECRET` or a realm's `secret_env`.
+    Generate,
+    /// Signs with a new key from now on. The previous ones keep verifying
+    /// until they are retired.
+    Rotate,
+    /// Stops verifying tokens signed with a key, one rotated out or the
+    /// configured one. The current key cannot be retired.
+    Retire {
+        kid: String,
+    },
+    List,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum TokenCommand {
+    /// Mints a token for a user, to test clients and APIs with.
+    Mint {
+        #[arg(value_parser = mail_address)]
+        email: MailAddress,
+        /// Issues it to the client, with its custom claims.
+        #[arg(long)]
+        client: Option<String>,
+    },
+}
+
+/// Message for commands that changed the state file.
+pub const RESTART_NOTICE: &str = "saved, restart the server to apply the change";
+
+/// Runs a command, reading passwords from `input`, and answers with what it
+/// created or found, and whether it changed the state file. A running server
+/// does not see the change before it restarts.
+pub fn run<R: BufRead>(cli: &AdminCli, input: R) -> Result<(Value, bool)> {
+    let _lock = StateFile::lock(&cli.state)?;
+    let mut state = StateFile::load(&cli.state)?;
+    let mut realms = configured_realms()?;
+    state.apply(&mut realms)?;
+    let registry = registry_of(realms)?;
+    let realm = match &cli.realm {
+        Some(name) => registry
+            .find(name)
+            .ok_or_else(|| format!("realm {} does not exist", name))?,
+        None => registry.default_realm(),
+    };
+
+    let (value, changed) = match &cli.command {
+        Command::Users(command) => users(command, &realm, state.realm_mut(&realm.name), input)?,
+        Command::Clients(command) => clients(command, &realm, state.realm_mut(&realm.name))?,
+        Command::Groups(command) => groups(command, &realm, state.realm_mut(&realm.name))?,
+        Command::Keys(command) => keys(command, &realm, state.realm_mut(&realm.name))?,
+        Command::Token(command) => (token(command, &realm)?, false),
+    };
+    if changed {
+        state.save(&cli.state)?;
+    }
+    Ok((value, changed))
+}
+
+fn users<R: BufRead>(
+    command: &UserCommand,
+    realm: &Realm,
+    state: &mut RealmState,
+    input: R,
+) -> Result<(Value, bool)> {
+    match command {
+        UserCommand::Create { email } => {
+            if realm.users.find(email)?.is_some() {
+                return Err(MyError::AlreadyExists.into());
+            }
+            let record = UserRecord::of(email.clone(), read_password(realm, email, input)?);
+            let value = user_value(&record.to_user());
+            state.users.push(record);
+            Ok((value, true))
+        }
+        UserCommand::Disable { email } | UserCommand::Enable { email } => {
+            let record = state.user_mut(email).ok_or(MyError::NotFound)?;
+            record.disabled = matches!(command, UserCommand::Disable { .. });
+            Ok((user_value(&record.to_user()), true))
+        }
+        UserCommand::ResetPassword { email } => {
+            let passwd_hash = read_password(realm, email, input)?;
+            let record = state.user_mut(email).ok_or(MyError::NotFound)?;
+            record.passwd_hash = Some(passwd_hash);
+            Ok((user_value(&record.to_user()), true))
+        }
+        UserCommand::Import { email } => {
+            if realm.users.find(email)?.is_some() {
+                return Err(MyError::AlreadyExists.into());
+            }
+            let passwd_hash = read_line(input)?;
+            if !is_supported_hash(&passwd_hash) {
+                return Err("unsupported password hash".into());
+            }
+            let record = UserRecord::of(email.clone(), passwd_hash);
+            let value = user_value(&record.to_user());
+            state.users.push(record);
+            Ok((value, true))
+        }
+        UserCommand::List => Ok((realm.users.list()?.iter().map(user_value).collect(), false)),
+    }
+}
+
+fn clients(
+    command: &ClientCommand,
+    realm: &Realm,
+    state: &mut RealmState,
+) -> Result<(Value, bool)> {
+    match command {
+        ClientCommand::Register {
+            client_id,
+            confidential,
+            scopes,
+            require_consent,
+            groups_limit,
+        } => {
+            if realm.clients.find(client_id)?.is_some() {
+                return Err(MyError::AlreadyExists.into());
+            }
+            let secret = match confidential {
+                true => Some(generate_secret()?),
+                false => None,
+            };
+            let mut client = Client::of(client_id.clone()).with_scopes(scopes.clone());
+            if let Some(secret) = &secret {
+                client = client.with_secret(secret.clone());
+            }
+            if *require_consent {
+                client = client.with_required_consent();
+            }
+            if let Some(limit) = groups_limit {
+                client = client.with_groups_limit(*limit);
+            }
+            state.clients.push(ClientRecord {
+                client_id: client.client_id.clone(),
+                secret_digest: client.secret_digest.clone(),
+                scopes: client.scopes.clone(),
+                require_consent: client.require_consent,
+                groups_limit: *groups_limit,
+            });
+            let mut value = client_value(&client);
+            if let Some(secret) = secret {
+                value["client_secret"] = Value::String(secret);
+            }
+            Ok((value, true))
+        }
+        ClientCommand::List => Ok((
+            realm.clients.list()?.iter().map(client_value).collect(),
+            false,
+        )),
+    }
+}
+
+fn groups(command: &GroupCommand, realm: &Realm, state: &mut RealmState) -> Result<(Value, bool)> {
+    let name = match command {
+        GroupCommand::Create { name } => {
+            if !is_group_name(name) {
+                return Err(format!("invalid group name {:?}", name).into());
+            }
+            if state.group(name).is_some() {
+                return Err(MyError::AlreadyExists.into());
+            }
+            state.groups.push(Group::of(name.clone()));
+            name
+        }
+        GroupCommand::Delete { name } => {
+            let before = state.groups.len();
+            state.groups.retain(|group| group.name != *name);
+            if state.groups.len() == before {
+                return Err(MyError::NotFound.into());
+            }
+            for group in &mut state.groups {
+                group.subgroups.remove(name);
+            }
+            return Ok((json!({ "name": name }), true));
+        }
+        GroupCommand::AddMember { name, member } => {
+            if let Some(subgroup) = &member.group {
+                let groups: BTreeMap<String, Group> = state
+                    .groups
+                    .iter()
+                    .map(|group| (group.name.clone(), group.clone()))
+                    .collect();
+                if !groups.contains_key(subgroup) {
+                    return Err(format!("group {} does not exist", subgroup).into());
+                }
+                if contains_group(&groups, subgroup, name) {
+                    return Err(format!("group {} contains {} already", subgroup, name).into());
+                }
+            }
+            let group = state.group_mut(name).ok_or(MyError::NotFound)?;
+            group.members.extend(member.user.clone());
+            group.subgroups.extend(member.group.clone());
+            name
+        }
+        GroupCommand::RemoveMember { name, member } => {
+            let group = state.group_mut(name).ok_or(MyError::NotFound)?;
+            let removed = match (&member.user, &member.group) {
+                (Some(email), _) => group.members.remove(email),
+                (_, Some(subgroup)) => group.subgroups.remove(subgroup),
+                _ => false,
+            };
+            if !removed {
+                return Err(MyError::NotFound.into());
+            }
+            name
+        }
+        GroupCommand::Memberships { email } => {
+            return Ok((json!(realm.groups.memberships(email)?), false));
+        }
+        GroupCommand::List => {
+            return Ok((
+                realm.groups.list()?.iter().map(group_value).collect(),
+                false,
+            ));
+        }
+    };
+    let group = state.group(name).ok_or(MyError::NotFound)?;
+    Ok((group_value(group), true))
+}
+
+fn keys(command: &KeyCommand, realm: &Realm, state: &mut RealmState) -> Result<(Value, bool)> {
+    match command {
+        KeyCommand::Generate => Ok((json!({ "secret": generate_secret()? }), false)),
+        KeyCommand::Rotate => {
+            let key = SigningKey::generate()?;
+            state.signing_keys.insert(0, KeyRecord::of(&key));
+            // Checks the new set before it is saved.
+            state.signing_keys(&realm.keys)?;
+            Ok((key_value(&key, true), true))
+        }
+        KeyCommand::Retire { kid } => {
+            if realm.keys.current().kid == *kid {
+                return Err("the current key cannot be retired".into());
+            }
+            let key = realm
+                .keys
+                .iter()
+                .find(|key| key.kid == *kid)
+                .ok_or(MyError::NotFound)?;
+            state.signing_keys.retain(|record| record.kid != *kid);
+            state.retired_keys.push(kid.clone());
+            // Checks the new set before it is saved.
+            state.signing_keys(&realm.keys)?;
+            Ok((key_value(key, false), true))
+        }
+        KeyCommand::List => {
+            let current = &realm.keys.current().kid;
+            Ok((
+                realm
+                    .keys
+                    .iter()
+                    .map(|key| key_value(key, key.kid == *current))
+                    .collect(),
+                false,
+            ))
+        }
+    }
+}
+
+fn token(command: &TokenCommand, realm: &Realm) -> Result<Value> {
+    let TokenCommand::Mint { email, client } = command;
+    let user = realm
+        .users
+        .find(email)?
+        .unwrap_or_else(|| User::of(email.clone()));
+    if user.disabled {
+        return Err(format!("user {} is disabled", String::from(email.clone())).into());
+    }
+    let token = match client {
+        Some(client_id) => {
+            let client = realm
+                .clients
+                .find(client_id)?
+                .ok_or_else(|| format!("client {} does not exist", client_id))?;
+            client_token(realm, &client, &user, &[], None, None)?
+        }
+        None => user_token(realm, &user, None)?,
+    };
+    Ok(json!({
+        "access_token": token,
+        "token_type": "Bearer",
+        "expires_in": TOKEN_LIFETIME_HOURS * 3600,
+    }))
+}
+
+// The first line, checked against the realm's password policy.
+fn read_password<R: BufRead>(realm: &Realm, email: &MailAddress, input: R) -> Result<String> {
+    let passwd = Password::of(read_line(input)?, &realm.password_policy, email)?;
+    Ok(hash_password(&passwd)?)
+}
+
+fn read_line<R: BufRead>(mut input: R) -> Result<String> {
+    let mut line = String::new();
+    input.read_line(&mut line)?;
+    Ok(line.trim_end_matches(['\r', '\n']).to_owned())
+}
+
+fn mail_address(value: &str) -> std::result::Result<MailAddress, String> {
+    MailAddress::of(value).map_err(|err| err.to_string())
+}
+
+fn user_value(user: &User) -> Value {
+    json!({
+        "email": user.email,
+        "disabled": user.disabled,
+        "attributes": user.attributes,
+    })
+}
+
+fn client_value(client: &Client) -> Value {
+    json!({
+        "client_id": client.client_id,
+        "confidential": client.is_confidential(),
+        "scopes": client.scopes,
+        "require_consent": client.require_consent,
+    })
+}
+
+fn group_value(group: &Group) -> Value {
+    json!({
+        "name": group.name,
+        "members": group.members,
+        "subgroups": group.subgroups,
+    })
+}
+
+fn key_value(key: &SigningKey, current: bool) -> Value {
+    json!({
+        "kid": key.kid,
+        "created_at": key.created_at,
+        "current": current,
+    })
+}
+
+impl Output {
+    pub fn render(&self, value: &Value) -> String {
+        match self {
+            Output::Json => serde_json::to_string_pretty(value).unwrap_or_default(),
+            Output::Table => render_table(value),
+        }
+    }
+}
+
+/// Lists become one row per item under a header, single items one row per field.
+pub fn render_table(value: &Value) -> String {
+    let rows: Vec<Vec<String>> = match value {
+        Value::Array(items) => {
+            let keys: Vec<&String> = match items.first() {
+                Some(Value::Object(fields)) => fields.keys().collect(),
+                _ => vec![],
+            };
+            std::iter::once(keys.iter().map(|key| key.to_uppercase()).collect())
+                .chain(items.iter().map(|item| {
+                    keys.iter()
+                        .map(|key| cell(item.get(key.as_str()).unwrap_or(&Value::Null)))
+                        .collect()
+                }))
+                .collect()
+        }
+        Value::Object(fields) => fields
+            .iter()
+            .map(|(key, value)| vec![key.to_uppercase(), cell(value)])
+            .collect(),
+        value => vec![vec![cell(value)]],
+    };
+    let columns = rows.iter().map(Vec::len).max().unwrap_or(0);
+    let widths: Vec<usize> = (0..columns)
+        .map(|i| {
+            rows.iter()
+                .filter_map(|row| row.get(i))
+                .map(|cell| cell.chars().count())
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+    rows.iter()
+        .map(|row| {
+            row.iter()
+                .zip(&widths)
+                .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+                .collect::<Vec<_>>()
+                .join("  ")
+                .trim_end()
+                .to_owned()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn cell(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        Value::Array(items) => items.iter().map(cell).collect::<Vec<_>>().join(" "),
+        Value::Object(fields) => fields
+            .iter()
+            .map(|(key, value)| format!("{}={}", key, cell(value)))
+            .collect::<Vec<_>>()
+            .join(" "),
+        value => value.to_string(),
+    }
+}
@@ -0,0 +1,132 @@
+//! App factory.
+//!
+//! Shared by the server and the tests, so that both drive the same routes.
+
+use actix_web::{
+    dev::{ServiceFactory, ServiceRequest, ServiceResponse},
+    http::header,
+    middleware, web, App,
+};
+
+use crate::app_state::AppState;
+use crate::error::problem::{
+    form_error_handler, json_error_handler, localize_problem, not_found_handler,
+    query_error_handler,
+};
+use crate::resource::broker_resource::{broker_callback_handler, broker_login_handler};
+use crate::resource::consent_resource::{consents_page_handler, revoke_consent_handler};
+use crate::resource::device_resource::{
+    device_authorization_handler, device_page_handler, device_verification_handler,
+};
+use crate::resource::email_login_resource::{
+    email_code_handler, email_link_handler, email_login_handler,
+};
+use crate::resource::health_resource::{healthz_handler, readyz_handler};
+use crate::resource::hello_html::hello_html_handler;
+use crate::resource::hello_resource::hello_handler;
+use crate::resource::idp_resource::{make_jwt_handler, userinfo_handler, validate_jwt_handler};
+use crate::resource::login_resource::password_login_handler;
+use crate::resource::logout_resource::{logout_post_handler, logout_redirect_handler};
+use crate::resource::openapi_resource::{openapi_json_handler, swagger_ui, swagger_ui_handler};
+use crate::resource::saml_resource::{
+    saml_metadata_handler, sso_login_handler, sso_post_handler, sso_redirect_handler,
+};
+use crate::resource::token_resource::token_handler;
+use crate::resource::webauthn_resource::{
+    passkey_login_handler, passkey_login_options_handler, passkey_registration_handler,
+    passkey_registration_options_handler,
+};
+use crate::telemetry::request_id::request_id;
+use crate::tls::HSTS_HEADER_VALUE;
+
+/// Limit of JSON request bodies, in bytes.
+pub const JSON_LIMIT: usize = 4096;
+
+// Registers the routes of a table, and lists them under `$listed`, so
+// that the OpenAPI document can be checked against what is served.
+macro_rules! routes {
+    ($configure:ident, $listed:ident, { $($path:literal => [$($method:ident => $handler:path),+ $(,)?]),* $(,)? }) => {
+        pub fn $configure(cfg: &mut web::ServiceConfig) {
+            $(cfg.service(web::resource($path)$(.route(web::$method().to($handler)))+);)*
+        }
+
+        /// Path and method of each route, as registered.
+        pub const $listed: &[(&str, &str)] = &[$($(($path, stringify!($method)),)+)*];
+    };
+}
+
+// Served once for the default realm and once under `/realms/{realm}`.
+routes!(idp_routes, IDP_ROUTES, {
+    "/jwt" => [post => make_jwt_handler],
+    "/validate" => [post => validate_jwt_handler],
+    "/userinfo" => [get => userinfo_handler],
+    "/login" => [post => password_login_handler],
+    "/login/email" => [post => email_login_handler],
+    "/login/email/verify" => [get => email_link_handler, post => email_code_handler],
+    "/logout" => [get => logout_redirect_handler, post => logout_post_handler],
+    "/webauthn/register/options" => [post => passkey_registration_options_handler],
+    "/webauthn/register" => [post => passkey_registration_handler],
+    "/webauthn/login/options" => [post => passkey_login_options_handler],
+    "/webauthn/login" => [post => passkey_login_handler],
+    "/token" => [post => token_handler],
+    "/device_authorization" => [post => device_authorization_handler],
+    "/device" => [get => device_page_handler, post => device_verification_handler],
+    "/consents" => [get => consents_page_handler],
+    "/consents/revoke" => [post => revoke_consent_handler],
+    "/saml/metadata" => [get => saml_metadata_handler],
+    "/saml/sso" => [get => sso_redirect_handler, post => sso_post_handler],
+    "/saml/login" => [post => sso_login_handler],
+    "/broker/{alias}/login" => [get => broker_login_handler],
+    "/broker/{alias}/callback" => [get => broker_callback_handler],
+});
+
+// Served once, outside of any realm.
+routes!(root_routes, ROOT_ROUTES, {
+    "/healthz" => [get => healthz_handler],
+    "/readyz" => [get => readyz_handler],
+    "/rest" => [post => hello_handler],
+    "/openapi.json" => [get => openapi_json_handler],
+    "/swagger-ui" => [get => swagger_ui_handler],
+});
+
+/// The whole idp, HSTS added when it is served over TLS. Problems are
+/// rendered in the language of the client, and each request runs in a span
+/// with its id.
+pub fn app(
+    state: web::Data<AppState>,
+    tls_enabled: bool,
+) -> App<
+    impl ServiceFactory<
+        ServiceRequest,
+        Config = (),
+        Response = ServiceResponse,
+        Error = actix_web::Error,
+        InitError = (),
+    >,
+> {
+    App::new()
+        .wrap(middleware::ErrorHandlers::new().default_handler(localize_problem))
+        .wrap(middleware::Condition::new(
+            tls_enabled,
+            middleware::DefaultHeaders::new()
+                .add((header::STRICT_TRANSPORT_SECURITY, HSTS_HEADER_VALUE)),
+        ))
+        // Outermost, so that the whole request runs in its span. Boxes the
+        // body, so that callers can name the response type.
+        .wrap(middleware::from_fn(request_id))
+        .app_data(state)
+        .app_data(
+            web::JsonConfig::default()
+                .limit(JSON_LIMIT) // limit request payload size
+                .content_type(|mime| mime == mime::TEXT_PLAIN) // only accept text/plain content type
+                .error_handler(json_error_handler), // render body errors as problem+json
+        )
+        .app_data(web::FormConfig::default().error_handler(form_error_handler))
+        .app_data(web::QueryConfig::default().error_handler(query_error_handler))
+        .service(hello_html_handler)
+        .configure(root_routes)
+        .configure(idp_routes) // default realm
+        .service(web::scope("/realms/{realm}").configure(idp_routes))
+        .service(swagger_ui())
+        .default_service(web::to(not_found_handler))
+}
@@ -0,0 +1,59 @@
+//! Shared application state.
+
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+use crate::{
+    mail::mailer::Mailer,
+    realm::realm_registry::RealmRegistry,
+    saml::saml_credential::SamlCredential,
+    store::{dpop_replay_store::DpopReplayStore, token_denylist::TokenDenylist},
+};
+
+/// State shared by every worker through `web::Data`.
+#[derive(Debug)]
+pub struct AppState {
+    pub realms: RealmRegistry,
+    pub denylist: TokenDenylist,
+    /// Shared by the realms, thumbprints tell keys apart.
+    pub dpop_proofs: DpopReplayStore,
+    /// SAML endpoints answer 404 without it.
+    pub saml: Option<SamlCredential>,
+    /// Login by mail answers 404 without it.
+    pub mailer: Option<Arc<dyn Mailer>>,
+    draining: AtomicBool,
+}
+
+impl AppState {
+    pub fn of(realms: RealmRegistry) -> Self {
+        Self {
+            realms,
+            denylist: TokenDenylist::default(),
+            dpop_proofs: DpopReplayStore::default(),
+            saml: None,
+            mailer: None,
+            draining: AtomicBool::new(false),
+        }
+    }
+
+    pub fn with_saml_credential(mut self, saml: Option<SamlCredential>) -> Self {
+        self.saml = saml;
+        self
+    }
+
+    pub fn with_mailer(mut self, mailer: Option<Arc<dyn Mailer>>) -> Self {
+        self.mailer = mailer;
+        self
+    }
+
+    /// Marks the server as shutting down so that readiness starts failing.
+    pub fn begin_draining(&self) {
+        self.draining.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_draining(&self) -> bool {
+        self.draining.load(Ordering::SeqCst)
+    }
+}
@@ -0,0 +1,34 @@
+//! Idp Admin CLI
+//!
+
+use std::{io, process::ExitCode};
+
+use clap::Parser;
+use tracing_subscriber::EnvFilter;
+
+use idp::admin::{run, AdminCli, RESTART_NOTICE};
+
+fn main() -> ExitCode {
+    // Plain lines on stderr, stdout is for the output.
+    tracing_subscriber::fmt()
+        .with_writer(io::stderr)
+        .with_env_filter(
+            EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("warn")),
+        )
+        .init();
+
+    let cli = AdminCli::parse();
+    match run(&cli, io::stdin().lock()) {
+        Ok((value, changed)) => {
+            println!("{}", cli.output.render(&value));
+            if changed {
+                eprintln!("idp-admin: {}", RESTART_NOTICE);
+            }
+            ExitCode::SUCCESS
+        }
+        Err(err) => {
+            eprintln!("idp-admin: {}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,3 @@
+pub mod account_link;
+pub mod broker_login;
+pub mod upstream_provider;
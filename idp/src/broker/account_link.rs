@@ -0,0 +1,43 @@
+//! Account linking by verified mail address.
+
+use crate::{
+    broker::upstream_provider::UpstreamIdentity,
+    entity::user::{LinkedIdentity, User},
+    error::my_error::{self, MyError},
+    store::user_store::UserStore,
+};
+
+/// Finds the user with the verified mail address of `identity`, or provisions one,
+/// and links the upstream subject to it. Once linked, another subject of the same
+/// provider claiming the address is refused.
+pub fn link_account(
+    users: &UserStore,
+    provider: &str,
+    identity: UpstreamIdentity,
+) -> my_error::Result<User> {
+    let UpstreamIdentity { subject, email } = identity;
+    let mut user = match users.find(&email)? {
+        Some(user) => user,
+        None => {
+            let user = User::of(email);
+            users.create(user.clone())?;
+            user
+        }
+    };
+    match user
+        .linked_identities
+        .iter()
+        .find(|linked| linked.provider == provider)
+    {
+        Some(linked) if linked.subject == subject => Ok(user),
+        Some(_) => Err(MyError::Unauthorized),
+        None => {
+            user.linked_identities.push(LinkedIdentity {
+                provider: provider.to_owned(),
+                subject,
+            });
+            users.save(user.clone())?;
+            Ok(user)
+        }
+    }
+}
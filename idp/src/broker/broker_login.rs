@@ -0,0 +1,43 @@
+//! A login redirected to an upstream provider and not called back yet.
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, Duration, Utc};
+use ring::digest::{digest, SHA256};
+
+use crate::{error::my_error, token::opaque_token::opaque_token};
+
+/// How long the user may take at the upstream provider.
+pub const BROKER_LOGIN_LIFETIME_MINUTES: i64 = 10;
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct BrokerLogin {
+    /// `state` parameter of the round trip, the key of the login.
+    pub state: String,
+    /// Alias of the upstream provider the login was sent to.
+    pub provider: String,
+    pub nonce: String,
+    /// PKCE verifier, RFC 7636.
+    pub code_verifier: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl BrokerLogin {
+    pub fn start<P: Into<String>>(provider: P) -> my_error::Result<Self> {
+        Ok(Self {
+            state: opaque_token()?,
+            provider: provider.into(),
+            nonce: opaque_token()?,
+            code_verifier: opaque_token()?,
+            expires_at: Utc::now() + Duration::minutes(BROKER_LOGIN_LIFETIME_MINUTES),
+        })
+    }
+
+    /// `S256` code challenge of the verifier.
+    pub fn code_challenge(&self) -> String {
+        URL_SAFE_NO_PAD.encode(digest(&SHA256, self.code_verifier.as_bytes()))
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.expires_at <= Utc::now()
+    }
+}
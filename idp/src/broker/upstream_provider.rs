@@ -0,0 +1,246 @@
+//! Upstream OpenID Connect provider users can sign in through.
+//!
+//! The calls here block, handlers run them on `web::block`.
+
+use std::{
+    fmt,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+use jsonwebtoken::{decode, decode_header, jwk::JwkSet, Algorithm, DecodingKey, Validation};
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::{
+    broker::broker_login::BrokerLogin,
+    domain::mail_address::MailAddress,
+    error::my_error::{self, MyError},
+};
+
+const UPSTREAM_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Only signatures verifiable with a published key, never a shared secret.
+const ID_TOKEN_ALGORITHMS: [Algorithm; 7] = [
+    Algorithm::RS256,
+    Algorithm::RS384,
+    Algorithm::RS512,
+    Algorithm::PS256,
+    Algorithm::PS384,
+    Algorithm::PS512,
+    Algorithm::ES256,
+];
+
+/// The part of the discovery document the authorization-code flow needs.
+#[derive(Deserialize, Debug)]
+pub struct ProviderMetadata {
+    pub issuer: String,
+    pub authorization_endpoint: String,
+    pub token_endpoint: String,
+    pub jwks_uri: String,
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    id_token: String,
+}
+
+#[derive(Deserialize)]
+struct IdTokenClaims {
+    sub: String,
+    nonce: Option<String>,
+    email: Option<String>,
+    /// Some providers send the string `"true"`.
+    email_verified: Option<Value>,
+}
+
+/// Who the upstream provider says signed in.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct UpstreamIdentity {
+    pub subject: String,
+    /// Only ever a verified address.
+    pub email: MailAddress,
+}
+
+pub struct UpstreamProvider {
+    pub alias: String,
+    pub issuer: String,
+    pub client_id: String,
+    client_secret: String,
+    pub scopes: Vec<String>,
+    agent: ureq::Agent,
+    metadata: RwLock<Option<Arc<ProviderMetadata>>>,
+}
+
+impl UpstreamProvider {
+    pub fn of<A, I, C, S>(alias: A, issuer: I, client_id: C, client_secret: S) -> Self
+    where
+        A: Into<String>,
+        I: Into<String>,
+        C: Into<String>,
+        S: Into<String>,
+    {
+        Self {
+            alias: alias.into(),
+            issuer: issuer.into(),
+            client_id: client_id.into(),
+            client_secret: client_secret.into(),
+            scopes: vec!["openid".to_owned(), "email".to_owned()],
+            agent: ureq::AgentBuilder::new().timeout(UPSTREAM_TIMEOUT).build(),
+            metadata: RwLock::new(None),
+        }
+    }
+
+    pub fn with_scopes(mut self, scopes: Vec<String>) -> Self {
+        self.scopes = scopes;
+        self
+    }
+
+    /// Fetches the discovery document once and keeps it.
+    pub fn metadata(&self) -> my_error::Result<Arc<ProviderMetadata>> {
+        if let Some(metadata) = self.metadata.read().map_err(|_| MyError::Storage)?.clone() {
+            return Ok(metadata);
+        }
+        let url = format!(
+            "{}/.well-known/openid-configuration",
+            self.issuer.trim_end_matches('/')
+        );
+        let metadata: ProviderMetadata = self.get_json(&url)?;
+        if metadata.issuer != self.issuer {
+            tracing::warn!(provider = %self.alias, issuer = %metadata.issuer, "upstream announces another issuer");
+            return Err(MyError::Upstream);
+        }
+        let metadata = Arc::new(metadata);
+        *self.metadata.write().map_err(|_| MyError::Storage)? = Some(metadata.clone());
+        Ok(metadata)
+    }
+
+    /// Where to send the browser, with `state`, `nonce` and a PKCE challenge.
+    pub fn authorization_url(
+        &self,
+        metadata: &ProviderMetadata,
+        redirect_uri: &str,
+        login: &BrokerLogin,
+    ) -> my_error::Result<String> {
+        let query = serde_urlencoded::to_string([
+            ("response_type", "code"),
+            ("client_id", &self.client_id),
+            ("redirect_uri", redirect_uri),
+            ("scope", &self.scopes.join(" ")),
+            ("state", &login.state),
+            ("nonce", &login.nonce),
+            ("code_challenge", &login.code_challenge()),
+            ("code_challenge_method", "S256"),
+        ])
+        .map_err(|_| MyError::Encode)?;
+        let separator = if metadata.authorization_endpoint.contains('?') {
+            '&'
+        } else {
+            '?'
+        };
+        Ok(format!(
+            "{}{}{}",
+            metadata.authorization_endpoint, separator, query
+        ))
+    }
+
+    /// Redeems the authorization code and validates the ID token it is answered with.
+    pub fn redeem(
+        &self,
+        code: &str,
+        redirect_uri: &str,
+        login: &BrokerLogin,
+    ) -> my_error::Result<UpstreamIdentity> {
+        let metadata = self.metadata()?;
+        let response: TokenResponse = self
+            .agent
+            .post(&metadata.token_endpoint)
+            .send_form(&[
+                ("grant_type", "authorization_code"),
+                ("code", code),
+                ("redirect_uri", redirect_uri),
+                ("client_id", &self.client_id),
+                ("client_secret", &self.client_secret),
+                ("code_verifier", &login.code_verifier),
+            ])
+            .map_err(|err| self.upstream_error(err))?
+            .into_json()
+            .map_err(|err| self.upstream_error(err))?;
+        self.validate_id_token(&metadata, &response.id_token, &login.nonce)
+    }
+
+    // Keys are fetched on every login so that rotations upstream need no restart.
+    fn validate_id_token(
+        &self,
+        metadata: &ProviderMetadata,
+        id_token: &str,
+        nonce: &str,
+    ) -> my_error::Result<UpstreamIdentity> {
+        let header = decode_header(id_token).map_err(|_| MyError::Decode)?;
+        if !ID_TOKEN_ALGORITHMS.contains(&header.alg) {
+            return Err(MyError::Decode);
+        }
+        let jwks: JwkSet = self.get_json(&metadata.jwks_uri)?;
+        let jwk = match &header.kid {
+            Some(kid) => jwks.find(kid),
+            None if jwks.keys.len() == 1 => jwks.keys.first(),
+            None => None,
+        }
+        .ok_or(MyError::Decode)?;
+        let key = DecodingKey::from_jwk(jwk).map_err(|_| MyError::Decode)?;
+
+        let mut validation = Validation::new(header.alg);
+        validation.set_issuer(&[&self.issuer]);
+        validation.set_audience(&[&self.client_id]);
+        validation.set_required_spec_claims(&["exp", "iss", "aud", "sub"]);
+        let claims = decode::<IdTokenClaims>(id_token, &key, &validation)
+            .map_err(|err| {
+                tracing::debug!(provider = %self.alias, error = ?err.kind(), "rejected ID token");
+                MyError::Decode
+            })?
+            .claims;
+        if claims.nonce.as_deref() != Some(nonce) {
+            return Err(MyError::Decode);
+        }
+
+        let verified = match &claims.email_verified {
+            Some(Value::Bool(verified)) => *verified,
+            Some(Value::String(verified)) => verified == "true",
+            _ => false,
+        };
+        let email = claims
+            .email
+            .filter(|_| verified)
+            .and_then(|email| MailAddress::of(email).ok())
+            .ok_or(MyError::Unauthorized)?;
+        Ok(UpstreamIdentity {
+            subject: claims.sub,
+            email,
+        })
+    }
+
+    fn get_json<T: serde::de::DeserializeOwned>(&self, url: &str) -> my_error::Result<T> {
+        self.agent
+            .get(url)
+            .call()
+            .map_err(|err| self.upstream_error(err))?
+            .into_json()
+            .map_err(|err| self.upstream_error(err))
+    }
+
+    fn upstream_error<E: fmt::Display>(&self, err: E) -> MyError {
+        tracing::warn!(provider = %self.alias, error = %err, "upstream provider failed");
+        MyError::Upstream
+    }
+}
+
+impl fmt::Debug for UpstreamProvider {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("UpstreamProvider")
+            .field("alias", &self.alias)
+            .field("issuer", &self.issuer)
+            .field("client_id", &self.client_id)
+            .field("scopes", &self.scopes)
+            .finish()
+    }
+}
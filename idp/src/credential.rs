@@ -0,0 +1,2 @@
+pub mod email_login;
+pub mod passwd_hasher;
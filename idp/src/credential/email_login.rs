@@ -0,0 +1,165 @@
+//! Passwordless login by mail.
+//!
+//! The user asks for a login at their mail address and gets either a signed
+//! link or a six digit code. Both work once and expire. A code only works in
+//! the browser that asked for it, which holds the login id in a cookie, and
+//! only for a few guesses. Each address and client may only start so many
+//! logins and enter so many wrong codes an hour, whichever logins they use.
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, Duration, Utc};
+use jsonwebtoken::{Algorithm, Validation};
+use ring::{
+    digest::{digest, SHA256},
+    rand::{SecureRandom, SystemRandom},
+};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::{
+    domain::mail_address::MailAddress,
+    error::my_error::{self, MyError},
+    token::{
+        jwt::{decode_claims, encode_claims},
+        opaque_token::opaque_token,
+        signing_key::SigningKeys,
+    },
+};
+
+/// How long the link or code works.
+pub const EMAIL_LOGIN_LIFETIME_MINUTES: i64 = 10;
+/// Wrong codes a login takes before it is dropped.
+pub const MAX_CODE_ATTEMPTS: u32 = 5;
+/// Unexpired logins an address keeps, a new one replaces the oldest.
+pub const MAX_PENDING_LOGINS: usize = 3;
+/// Window over which logins started and wrong codes are counted, across
+/// all logins of an address or client.
+pub const ATTEMPT_WINDOW_MINUTES: i64 = 60;
+/// Logins an address may be sent within the window.
+pub const MAX_LOGINS_PER_ADDRESS: usize = 10;
+/// Logins a client may start within the window, for any addresses.
+pub const MAX_LOGINS_PER_CLIENT: usize = 30;
+/// Wrong codes an address may take within the window.
+pub const MAX_GUESSES_PER_ADDRESS: usize = 10;
+/// Wrong codes a client may enter within the window.
+pub const MAX_GUESSES_PER_CLIENT: usize = 30;
+
+const CODE_DIGITS: u32 = 6;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum EmailLoginMethod {
+    Link,
+    Code,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct EmailLogin {
+    /// `jti` of the link, or the value of the cookie a code is bound to.
+    pub id: String,
+    pub email: MailAddress,
+    pub method: EmailLoginMethod,
+    /// SHA-256 of the code, for logins by code.
+    code_digest: Option<String>,
+    /// Wrong codes entered so far.
+    pub attempts: u32,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Claims of the token in a login link. Its audience keeps it apart from
+/// the tokens the realm issues to users.
+#[derive(Debug, Serialize, Deserialize)]
+struct LinkClaims {
+    iss: String,
+    aud: String,
+    sub: String,
+    jti: String,
+    exp: i64,
+}
+
+impl EmailLogin {
+    /// Starts a login, with the code to send when it is by code.
+    pub fn start(
+        email: MailAddress,
+        method: EmailLoginMethod,
+    ) -> my_error::Result<(Self, Option<String>)> {
+        let code = match method {
+            EmailLoginMethod::Link => None,
+            EmailLoginMethod::Code => Some(login_code()?),
+        };
+        let login = Self {
+            id: opaque_token()?,
+            email,
+            method,
+            code_digest: code.as_deref().map(code_digest),
+            attempts: 0,
+            expires_at: Utc::now() + Duration::minutes(EMAIL_LOGIN_LIFETIME_MINUTES),
+        };
+        Ok((login, code))
+    }
+
+    /// Spaces and dashes the user typed along do not count.
+    pub fn matches_code(&self, code: &str) -> bool {
+        let code: String = code.chars().filter(char::is_ascii_digit).collect();
+        self.code_digest.as_deref() == Some(code_digest(&code).as_str())
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.expires_at <= Utc::now()
+    }
+
+    /// Signed token of the link, expiring with the login.
+    pub fn link_token(&self, keys: &SigningKeys, issuer: &str) -> my_error::Result<String> {
+        encode_claims(
+            keys,
+            &LinkClaims {
+                iss: issuer.to_owned(),
+                aud: link_audience(issuer),
+                sub: String::from(self.email.clone()),
+                jti: self.id.clone(),
+                exp: self.expires_at.timestamp(),
+            },
+        )
+    }
+}
+
+/// Mail address and login id of a link token the realm signed.
+pub fn decode_link_token(
+    keys: &SigningKeys,
+    issuer: &str,
+    token: &str,
+) -> my_error::Result<(MailAddress, String)> {
+    let mut validation = Validation::new(Algorithm::HS256);
+    validation.set_issuer(&[issuer]);
+    validation.set_audience(&[link_audience(issuer)]);
+    let claims: LinkClaims = decode_claims(keys, token, &validation)?;
+    let email = MailAddress::of(claims.sub).map_err(|_| MyError::Decode)?;
+    Ok((email, claims.jti))
+}
+
+fn link_audience(issuer: &str) -> String {
+    format!("{}/login/email", issuer.trim_end_matches('/'))
+}
+
+fn code_digest(code: &str) -> String {
+    URL_SAFE_NO_PAD.encode(digest(&SHA256, code.as_bytes()))
+}
+
+// Uniform over 000000 to 999999, the values past the last full million rejected.
+fn login_code() -> my_error::Result<String> {
+    let rng = SystemRandom::new();
+    let range = 10u32.pow(CODE_DIGITS);
+    let limit = u32::MAX - u32::MAX % range;
+    loop {
+        let mut bytes = [0u8; 4];
+        rng.fill(&mut bytes).map_err(|_| MyError::Internal)?;
+        let value = u32::from_be_bytes(bytes);
+        if value < limit {
+            return Ok(format!(
+                "{:0width$}",
+                value % range,
+                width = CODE_DIGITS as usize
+            ));
+        }
+    }
+}
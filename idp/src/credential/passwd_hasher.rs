@@ -0,0 +1,112 @@
+//! Password hashing.
+//!
+//! New hashes are Argon2id with the current parameters. Hashes imported from
+//! other systems, and Argon2 ones with older parameters, still verify and are
+//! replaced on the next successful login.
+
+use std::sync::OnceLock;
+
+use argon2::{
+    password_hash::{rand_core::OsRng, PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+    Algorithm, Argon2, Params, Version,
+};
+use pbkdf2::Pbkdf2;
+
+use crate::{
+    domain::password::Password,
+    error::my_error::{self, MyError},
+};
+
+// Prefixes of the modular crypt format bcrypt hashes come in.
+const BCRYPT_PREFIXES: [&str; 3] = ["$2a$", "$2b$", "$2y$"];
+const PHC_ALGORITHMS: [&str; 6] = [
+    "argon2id",
+    "argon2i",
+    "argon2d",
+    "pbkdf2",
+    "pbkdf2-sha256",
+    "pbkdf2-sha512",
+];
+
+// Raising these upgrades every stored hash as its user next signs in.
+fn current_hasher() -> Argon2<'static> {
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, Params::default())
+}
+
+/// Hashes with Argon2id and the current parameters into a PHC string.
+pub fn hash_password(passwd: &Password) -> my_error::Result<String> {
+    hash_bytes(passwd.as_bytes())
+}
+
+fn hash_bytes(passwd: &[u8]) -> my_error::Result<String> {
+    let salt = SaltString::generate(&mut OsRng);
+    current_hasher()
+        .hash_password(passwd, &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|_| MyError::Encode)
+}
+
+/// Checks a password candidate against a stored Argon2 or PBKDF2 PHC string,
+/// or a bcrypt hash.
+pub fn verify_password(passwd: &str, passwd_hash: &str) -> my_error::Result<bool> {
+    if is_bcrypt(passwd_hash) {
+        return bcrypt::verify(passwd, passwd_hash).map_err(|_| MyError::Internal);
+    }
+    let parsed = phc_hash(passwd_hash).ok_or(MyError::Internal)?;
+    let verifiers: [&dyn PasswordVerifier; 2] = [&Argon2::default(), &Pbkdf2];
+    Ok(parsed.verify_password(&verifiers, passwd).is_ok())
+}
+
+/// Whether `verify_password` understands the hash, for imports.
+pub fn is_supported_hash(passwd_hash: &str) -> bool {
+    is_bcrypt(passwd_hash) || phc_hash(passwd_hash).is_some()
+}
+
+/// Whether the hash is anything but Argon2id with the current parameters.
+pub fn needs_rehash(passwd_hash: &str) -> bool {
+    let Ok(parsed) = PasswordHash::new(passwd_hash) else {
+        return true;
+    };
+    let current = current_hasher();
+    parsed.algorithm != Algorithm::Argon2id.ident()
+        || parsed.version != Some(Version::V0x13.into())
+        || Params::try_from(&parsed).map_or(true, |params| {
+            params.m_cost() != current.params().m_cost()
+                || params.t_cost() != current.params().t_cost()
+                || params.p_cost() != current.params().p_cost()
+        })
+}
+
+/// The current hash of a password that has just verified against
+/// `passwd_hash`, if that one is outdated.
+pub fn upgraded_hash(passwd: &str, passwd_hash: &str) -> my_error::Result<Option<String>> {
+    if !needs_rehash(passwd_hash) {
+        return Ok(None);
+    }
+    hash_bytes(passwd.as_bytes()).map(Some)
+}
+
+/// Argon2id hash with the current parameters of a password no one has. It
+/// is checked for unknown users and users without a password, so that they
+/// are refused as slowly as a wrong password.
+pub fn dummy_hash() -> &'static str {
+    static DUMMY_HASH: OnceLock<String> = OnceLock::new();
+    DUMMY_HASH.get_or_init(|| hash_bytes(b"dummy password of no one").unwrap_or_default())
+}
+
+// `$2b$` and the cost, then 22 characters of salt and 31 of hash.
+fn is_bcrypt(passwd_hash: &str) -> bool {
+    BCRYPT_PREFIXES
+        .iter()
+        .any(|prefix| passwd_hash.starts_with(prefix))
+        && passwd_hash.len() == 60
+        && passwd_hash.as_bytes()[4..6].iter().all(u8::is_ascii_digit)
+        && passwd_hash.as_bytes()[6] == b'$'
+}
+
+fn phc_hash(passwd_hash: &str) -> Option<PasswordHash<'_>> {
+    let parsed = PasswordHash::new(passwd_hash).ok()?;
+    PHC_ALGORITHMS
+        .contains(&parsed.algorithm.as_str())
+        .then_some(parsed)
+}
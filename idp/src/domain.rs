@@ -1,2 +1,4 @@
 pub mod mail_address;
 pub mod my_float;
+pub mod password;
+pub mod password_policy;
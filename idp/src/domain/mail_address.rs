@@ -1,27 +1,45 @@
-use regex::Regex;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use std::convert::TryFrom;
+use std::net::{Ipv4Addr, Ipv6Addr};
+use utoipa::openapi::schema::{KnownFormat, ObjectBuilder, Schema, SchemaFormat, Type};
+use utoipa::openapi::RefOr;
+use utoipa::{PartialSchema, ToSchema};
 
 use crate::error::my_error::{self, MyError};
 
+/// RFC 5321 4.5.3.1.1
+const MAX_LOCAL_PART_LEN: usize = 64;
+/// RFC 1035 2.3.4, without the length octets of the wire format.
+const MAX_DOMAIN_LEN: usize = 253;
+const MAX_LABEL_LEN: usize = 63;
+/// RFC 5321 4.5.3.1.3, the 256 octet path minus the angle brackets.
+const MAX_ADDRESS_LEN: usize = 254;
+
 /// Value objects are tuple structures because they are one primitive-based.
 /// Uniquely identifies a user.
-#[derive(PartialEq, Eq, Clone, PartialOrd, Ord, Debug, Serialize)]
+///
+/// Holds the canonical form: the local part as given (with redundant quoting removed)
+/// and the domain lowercased and converted to punycode, so that equality, ordering
+/// and hashing are case-insensitive on the domain.
+#[derive(PartialEq, Eq, Hash, Clone, PartialOrd, Ord, Debug, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
 pub struct MailAddress {
     mail_string: String,
 }
 
-// Constructs a value object following the regular expression of an email address.
+// Constructs a value object following the addr-spec grammar of RFC 5321 / RFC 5322.
 impl TryFrom<String> for MailAddress {
     type Error = MyError;
 
     fn try_from(mail_string: String) -> my_error::Result<Self> {
-        let regex = Regex::new(r#"^[a-zA-Z0-9_+-]+(.[a-zA-Z0-9_+-]+)*@([a-zA-Z0-9][a-zA-Z0-9-]*[a-zA-Z0-9]*\.)+[a-zA-Z]{2,}$"#).unwrap();
-        if regex.is_match(mail_string.as_str()) {
-            Ok(Self { mail_string })
-        } else {
-            Err(my_error::MyError::InvalidValue)
+        let (local_part, domain) = mail_string.rsplit_once('@').ok_or(MyError::InvalidValue)?;
+        let local_part = canonical_local_part(local_part)?;
+        let domain = canonical_domain(domain)?;
+        let mail_string = format!("{}@{}", local_part, domain);
+        if mail_string.len() > MAX_ADDRESS_LEN {
+            return Err(MyError::InvalidValue);
         }
+        Ok(Self { mail_string })
     }
 }
 
@@ -29,6 +47,13 @@ impl MailAddress {
     pub fn of<T: Into<String>>(mail_string: T) -> my_error::Result<Self> {
         MailAddress::try_from(mail_string.into())
     }
+
+    /// The part before the last `@`, quoted when it is not a dot-atom.
+    pub fn local_part(&self) -> &str {
+        self.mail_string
+            .rsplit_once('@')
+            .map_or("", |(local_part, _)| local_part)
+    }
 }
 
 /// MailAddress to String conversion process
@@ -37,3 +62,119 @@ impl From<MailAddress> for String {
         email.mail_string
     }
 }
+
+impl PartialSchema for MailAddress {
+    fn schema() -> RefOr<Schema> {
+        ObjectBuilder::new()
+            .schema_type(Type::String)
+            .format(Some(SchemaFormat::KnownFormat(KnownFormat::Email)))
+            .max_length(Some(MAX_ADDRESS_LEN))
+            .into()
+    }
+}
+
+impl ToSchema for MailAddress {}
+
+// atext of RFC 5322 3.2.3
+fn is_atext(c: char) -> bool {
+    c.is_ascii_alphanumeric() || "!#$%&'*+-/=?^_`{|}~".contains(c)
+}
+
+// Dot-string of RFC 5321: atoms separated by single dots.
+fn is_dot_string(s: &str) -> bool {
+    !s.is_empty()
+        && s.split('.')
+            .all(|atom| !atom.is_empty() && atom.chars().all(is_atext))
+}
+
+// qtextSMTP and the printable part of quoted-pairSMTP of RFC 5321.
+fn is_qtext(c: char) -> bool {
+    matches!(c, ' '..='~') && c != '"' && c != '\\'
+}
+
+/// Unquotes a Quoted-string local part, or checks a Dot-string one,
+/// and renders it without quotes whenever they are not needed.
+fn canonical_local_part(local_part: &str) -> my_error::Result<String> {
+    let canonical = match local_part
+        .strip_prefix('"')
+        .and_then(|s| s.strip_suffix('"'))
+    {
+        Some(quoted) => {
+            let mut content = String::with_capacity(quoted.len());
+            let mut chars = quoted.chars();
+            while let Some(c) = chars.next() {
+                match c {
+                    '\\' => match chars.next() {
+                        Some(escaped) if matches!(escaped, ' '..='~') => content.push(escaped),
+                        _ => return Err(MyError::InvalidValue),
+                    },
+                    c if is_qtext(c) => content.push(c),
+                    _ => return Err(MyError::InvalidValue),
+                }
+            }
+            if content.is_empty() {
+                return Err(MyError::InvalidValue);
+            }
+            if is_dot_string(&content) {
+                content
+            } else {
+                let mut requoted = String::with_capacity(content.len() + 2);
+                requoted.push('"');
+                for c in content.chars() {
+                    if c == '"' || c == '\\' {
+                        requoted.push('\\');
+                    }
+                    requoted.push(c);
+                }
+                requoted.push('"');
+                requoted
+            }
+        }
+        None if is_dot_string(local_part) => local_part.to_owned(),
+        None => return Err(MyError::InvalidValue),
+    };
+    if canonical.len() > MAX_LOCAL_PART_LEN {
+        return Err(MyError::InvalidValue);
+    }
+    Ok(canonical)
+}
+
+/// Lowercases and punycode-encodes a host name, or normalizes an address literal.
+fn canonical_domain(domain: &str) -> my_error::Result<String> {
+    if let Some(literal) = domain.strip_prefix('[').and_then(|s| s.strip_suffix(']')) {
+        return canonical_address_literal(literal);
+    }
+    let ascii = idna::domain_to_ascii_strict(domain).map_err(|_| MyError::InvalidValue)?;
+    let labels: Vec<&str> = ascii.split('.').collect();
+    let valid = ascii.len() <= MAX_DOMAIN_LEN
+        && labels.len() >= 2
+        && labels.iter().all(|label| {
+            !label.is_empty()
+                && label.len() <= MAX_LABEL_LEN
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        })
+        && !labels
+            .last()
+            .is_some_and(|tld| tld.chars().all(|c| c.is_ascii_digit()));
+    if valid {
+        Ok(ascii)
+    } else {
+        Err(MyError::InvalidValue)
+    }
+}
+
+// address-literal of RFC 5321 4.1.3, general literals are not supported.
+fn canonical_address_literal(literal: &str) -> my_error::Result<String> {
+    if let Some(v6) = literal
+        .get(..5)
+        .filter(|tag| tag.eq_ignore_ascii_case("IPv6:"))
+        .map(|_| &literal[5..])
+    {
+        let addr: Ipv6Addr = v6.parse().map_err(|_| MyError::InvalidValue)?;
+        return Ok(format!("[IPv6:{}]", addr));
+    }
+    let addr: Ipv4Addr = literal.parse().map_err(|_| MyError::InvalidValue)?;
+    Ok(format!("[{}]", addr))
+}
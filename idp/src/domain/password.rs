@@ -0,0 +1,48 @@
+use std::{convert::TryFrom, fmt};
+
+use crate::domain::{mail_address::MailAddress, password_policy::PasswordPolicy};
+use crate::error::my_error::{self, MyError};
+
+/// A plain-text password, only constructed once the realm's policy accepts
+/// it for the user's mail address. It is never serialized, and prints as
+/// `Password(***)`, so that it stays out of responses and logs.
+#[derive(PartialEq, Eq, Clone)]
+pub struct Password {
+    passwd_string: String,
+}
+
+// Constructs a value object only when every rule of the policy is met.
+impl TryFrom<(String, &PasswordPolicy, &MailAddress)> for Password {
+    type Error = MyError;
+
+    fn try_from(
+        (passwd_string, policy, email): (String, &PasswordPolicy, &MailAddress),
+    ) -> my_error::Result<Self> {
+        let violations = policy.violations(&passwd_string, email.local_part());
+        if violations.is_empty() {
+            Ok(Self { passwd_string })
+        } else {
+            Err(MyError::PasswordPolicy(violations))
+        }
+    }
+}
+
+impl Password {
+    pub fn of<T: Into<String>>(
+        passwd_string: T,
+        policy: &PasswordPolicy,
+        email: &MailAddress,
+    ) -> my_error::Result<Self> {
+        Password::try_from((passwd_string.into(), policy, email))
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        self.passwd_string.as_bytes()
+    }
+}
+
+impl fmt::Debug for Password {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("Password(***)")
+    }
+}
@@ -0,0 +1,287 @@
+use std::{collections::HashSet, env, fmt, fs, io, path::Path};
+
+use crate::i18n::{
+    catalog::{format_message, message},
+    locale::Locale,
+};
+
+const MIN_LENGTH_ENV: &str = "IDP_PASSWORD_MIN_LENGTH";
+const MIN_ENTROPY_ENV: &str = "IDP_PASSWORD_MIN_ENTROPY_BITS";
+const CHARACTER_CLASSES_ENV: &str = "IDP_PASSWORD_CHARACTER_CLASSES";
+const BREACHED_LIST_ENV: &str = "IDP_BREACHED_PASSWORDS_FILE";
+
+/// Local parts shorter than this are too common to be banned from passwords.
+const MIN_BANNED_LOCAL_PART_LEN: usize = 3;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum CharacterClass {
+    Lowercase,
+    Uppercase,
+    Digit,
+    Symbol,
+}
+
+impl CharacterClass {
+    fn of(c: char) -> Self {
+        if c.is_lowercase() {
+            CharacterClass::Lowercase
+        } else if c.is_uppercase() {
+            CharacterClass::Uppercase
+        } else if c.is_numeric() {
+            CharacterClass::Digit
+        } else {
+            CharacterClass::Symbol
+        }
+    }
+
+    // Number of symbols an attacker has to try per position.
+    fn pool_size(&self) -> f64 {
+        match self {
+            CharacterClass::Lowercase | CharacterClass::Uppercase => 26.0,
+            CharacterClass::Digit => 10.0,
+            CharacterClass::Symbol => 33.0,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            CharacterClass::Lowercase => "lowercase",
+            CharacterClass::Uppercase => "uppercase",
+            CharacterClass::Digit => "digit",
+            CharacterClass::Symbol => "symbol",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.trim() {
+            "lowercase" => Some(CharacterClass::Lowercase),
+            "uppercase" => Some(CharacterClass::Uppercase),
+            "digit" => Some(CharacterClass::Digit),
+            "symbol" => Some(CharacterClass::Symbol),
+            _ => None,
+        }
+    }
+}
+
+/// A single rule a password candidate failed.
+#[derive(Clone, PartialEq, Debug)]
+pub enum PolicyViolation {
+    TooShort { min_length: usize },
+    MissingCharacterClass { class: CharacterClass },
+    ContainsLocalPart,
+    LowEntropy { estimated_bits: f64, min_bits: f64 },
+    Breached,
+}
+
+impl PolicyViolation {
+    /// Stable identifier of the rule, for clients to branch on.
+    pub fn rule(&self) -> &'static str {
+        match self {
+            PolicyViolation::TooShort { .. } => "too_short",
+            PolicyViolation::MissingCharacterClass { .. } => "missing_character_class",
+            PolicyViolation::ContainsLocalPart => "contains_local_part",
+            PolicyViolation::LowEntropy { .. } => "low_entropy",
+            PolicyViolation::Breached => "breached",
+        }
+    }
+
+    /// A sentence about the password for the user, in their language.
+    pub fn message(&self, locale: Locale) -> String {
+        let id = format!("password.{}", self.rule());
+        match self {
+            PolicyViolation::TooShort { min_length } => {
+                format_message(locale, &id, &[("min_length", &min_length.to_string())])
+            }
+            PolicyViolation::MissingCharacterClass { class } => {
+                let class = message(locale, &format!("character_class.{}", class.name()));
+                format_message(locale, &id, &[("class", &class)])
+            }
+            PolicyViolation::LowEntropy {
+                estimated_bits,
+                min_bits,
+            } => format_message(
+                locale,
+                &id,
+                &[
+                    ("estimated_bits", &format!("{:.1}", estimated_bits)),
+                    ("min_bits", &format!("{:.1}", min_bits)),
+                ],
+            ),
+            PolicyViolation::ContainsLocalPart | PolicyViolation::Breached => message(locale, &id),
+        }
+    }
+}
+
+impl fmt::Display for PolicyViolation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PolicyViolation::TooShort { min_length } => {
+                write!(f, "must be at least {} characters long", min_length)
+            }
+            PolicyViolation::MissingCharacterClass { class } => {
+                write!(f, "must contain a {} character", class.name())
+            }
+            PolicyViolation::ContainsLocalPart => {
+                f.write_str("must not contain the local part of the mail address")
+            }
+            PolicyViolation::LowEntropy {
+                estimated_bits,
+                min_bits,
+            } => write!(
+                f,
+                "is too predictable ({:.1} bits, at least {:.1} required)",
+                estimated_bits, min_bits
+            ),
+            PolicyViolation::Breached => f.write_str("appears in a list of breached passwords"),
+        }
+    }
+}
+
+/// Strength rules a `Password` has to satisfy.
+#[derive(Clone, Debug)]
+pub struct PasswordPolicy {
+    pub min_length: usize,
+    pub required_classes: Vec<CharacterClass>,
+    pub min_entropy_bits: f64,
+    breached: HashSet<String>,
+}
+
+impl Default for PasswordPolicy {
+    fn default() -> Self {
+        Self {
+            min_length: 12,
+            required_classes: vec![
+                CharacterClass::Lowercase,
+                CharacterClass::Uppercase,
+                CharacterClass::Digit,
+            ],
+            min_entropy_bits: 50.0,
+            breached: HashSet::new(),
+        }
+    }
+}
+
+impl PasswordPolicy {
+    /// Starts from the defaults and overrides whatever is set in the environment.
+    /// A value that does not parse refuses the start rather than weakening
+    /// the policy.
+    pub fn from_env() -> io::Result<Self> {
+        Self::from_vars(|name| env::var(name).ok())
+    }
+
+    /// Like `from_env`, with the variables looked up by `var`.
+    pub fn from_vars<F: Fn(&str) -> Option<String>>(var: F) -> io::Result<Self> {
+        let mut policy = Self::default();
+        if let Some(min_length) = var(MIN_LENGTH_ENV) {
+            policy.min_length = min_length
+                .trim()
+                .parse()
+                .map_err(|_| invalid_setting(MIN_LENGTH_ENV, &min_length))?;
+        }
+        if let Some(bits) = var(MIN_ENTROPY_ENV) {
+            policy.min_entropy_bits = bits
+                .trim()
+                .parse::<f64>()
+                .ok()
+                .filter(|bits| bits.is_finite() && *bits >= 0.0)
+                .ok_or_else(|| invalid_setting(MIN_ENTROPY_ENV, &bits))?;
+        }
+        if let Some(classes) = var(CHARACTER_CLASSES_ENV) {
+            // Empty means none is required.
+            policy.required_classes = classes
+                .split(',')
+                .filter(|name| !name.trim().is_empty())
+                .map(|name| {
+                    CharacterClass::parse(name)
+                        .ok_or_else(|| invalid_setting(CHARACTER_CLASSES_ENV, name))
+                })
+                .collect::<io::Result<_>>()?;
+        }
+        if let Some(path) = var(BREACHED_LIST_ENV) {
+            policy = policy.with_breached_list(path)?;
+            tracing::info!(
+                "loaded {} breached passwords from {}",
+                policy.breached.len(),
+                BREACHED_LIST_ENV
+            );
+        }
+        Ok(policy)
+    }
+
+    /// Reads a breached-password list with one entry per line.
+    pub fn with_breached_list<P: AsRef<Path>>(mut self, path: P) -> io::Result<Self> {
+        let list = fs::read_to_string(path)?;
+        self.breached.extend(
+            list.lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                .map(str::to_lowercase),
+        );
+        Ok(self)
+    }
+
+    /// Collects every rule the candidate breaks, not only the first one.
+    pub fn violations(&self, candidate: &str, local_part: &str) -> Vec<PolicyViolation> {
+        let mut violations = vec![];
+        if candidate.chars().count() < self.min_length {
+            violations.push(PolicyViolation::TooShort {
+                min_length: self.min_length,
+            });
+        }
+        for class in &self.required_classes {
+            if !candidate.chars().any(|c| CharacterClass::of(c) == *class) {
+                violations.push(PolicyViolation::MissingCharacterClass { class: *class });
+            }
+        }
+        let lowered = candidate.to_lowercase();
+        let local_part = local_part.trim_matches('"').to_lowercase();
+        if local_part.chars().count() >= MIN_BANNED_LOCAL_PART_LEN && lowered.contains(&local_part)
+        {
+            violations.push(PolicyViolation::ContainsLocalPart);
+        }
+        let estimated_bits = estimate_entropy(candidate);
+        if estimated_bits < self.min_entropy_bits {
+            violations.push(PolicyViolation::LowEntropy {
+                estimated_bits,
+                min_bits: self.min_entropy_bits,
+            });
+        }
+        if self.breached.contains(&lowered) {
+            violations.push(PolicyViolation::Breached);
+        }
+        violations
+    }
+}
+
+/// Estimates entropy as length times log2 of the character pool in use.
+/// Repeated and sequential characters (`aaaa`, `1234`) only count for half.
+pub fn estimate_entropy(candidate: &str) -> f64 {
+    let chars: Vec<char> = candidate.chars().collect();
+    let mut classes: Vec<CharacterClass> = vec![];
+    for c in &chars {
+        let class = CharacterClass::of(*c);
+        if !classes.contains(&class) {
+            classes.push(class);
+        }
+    }
+    let pool: f64 = classes.iter().map(CharacterClass::pool_size).sum();
+    if pool == 0.0 {
+        return 0.0;
+    }
+    let effective_len: f64 = chars
+        .iter()
+        .enumerate()
+        .map(|(i, c)| match i.checked_sub(1).map(|p| chars[p]) {
+            Some(prev) if (*c as i64 - prev as i64).abs() <= 1 => 0.5,
+            _ => 1.0,
+        })
+        .sum();
+    effective_len * pool.log2()
+}
+
+fn invalid_setting(name: &str, value: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!("{} has an invalid value {:?}", name, value),
+    )
+}
@@ -1 +1,7 @@
+pub mod client;
+pub mod consent;
+pub mod group;
+pub mod passkey;
+pub mod service_provider;
+pub mod session;
 pub mod user;
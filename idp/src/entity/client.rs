@@ -0,0 +1,143 @@
+use ring::digest::{digest, SHA256};
+use serde::Deserialize;
+
+use crate::token::{claim_mapping::ClaimMapper, jwe::EncryptionKey, signing_key::SigningKey};
+
+/// Groups listed in a token before `groups_overage` replaces them.
+pub const DEFAULT_GROUPS_LIMIT: usize = 50;
+
+/// Entities consist of classic structures.
+/// An application that obtains tokens from a realm.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Client {
+    pub client_id: String,
+    /// SHA-256 of the secret confidential clients authenticate with at the
+    /// token endpoint, hex. Public ones such as CLIs have none. Only the
+    /// digest is kept, so that neither memory nor the state file holds it.
+    pub secret_digest: Option<String>,
+    /// Scopes the client may ask users for.
+    pub scopes: Vec<String>,
+    /// Third-party clients only get scopes the user allowed them.
+    pub require_consent: bool,
+    /// Without one the client may not exchange tokens.
+    pub token_exchange: Option<TokenExchangePolicy>,
+    /// Custom claims of the tokens issued to the client.
+    pub claim_mappers: Vec<ClaimMapper>,
+    /// Where `/logout` may send the browser back to, compared exactly.
+    pub post_logout_redirect_uris: Vec<String>,
+    /// Receives a logout token when a session the client took part in ends.
+    pub backchannel_logout_uri: Option<String>,
+    /// Secret shared with the client only, which signs its logout tokens.
+    /// The client can check them, but not mint the realm's tokens with it.
+    pub backchannel_logout_key: Option<SigningKey>,
+    /// Tokens issued to the client are encrypted to it, so that only it
+    /// reads their claims.
+    pub encryption_key: Option<EncryptionKey>,
+    /// Most groups listed in the `groups` claim, which keeps tokens small
+    /// enough for headers and cookies. 0 leaves the claim out.
+    pub groups_limit: usize,
+}
+
+/// What a client may ask for when it exchanges a token, RFC 8693.
+#[derive(Clone, PartialEq, Eq, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TokenExchangePolicy {
+    /// Downstream APIs the client may get tokens for.
+    pub audiences: Vec<String>,
+    /// Scopes the client may pass on, never more than the subject token has.
+    #[serde(default)]
+    pub scopes: Vec<String>,
+}
+
+impl Client {
+    pub fn of<T: Into<String>>(client_id: T) -> Self {
+        Self {
+            client_id: client_id.into(),
+            secret_digest: None,
+            scopes: vec![],
+            require_consent: false,
+            token_exchange: None,
+            claim_mappers: vec![],
+            post_logout_redirect_uris: vec![],
+            backchannel_logout_uri: None,
+            backchannel_logout_key: None,
+            encryption_key: None,
+            groups_limit: DEFAULT_GROUPS_LIMIT,
+        }
+    }
+
+    pub fn with_secret<T: Into<String>>(mut self, client_secret: T) -> Self {
+        self.secret_digest = Some(secret_digest(&client_secret.into()));
+        self
+    }
+
+    pub fn with_secret_digest<T: Into<String>>(mut self, secret_digest: T) -> Self {
+        self.secret_digest = Some(secret_digest.into());
+        self
+    }
+
+    pub fn is_confidential(&self) -> bool {
+        self.secret_digest.is_some()
+    }
+
+    pub fn with_scopes(mut self, scopes: Vec<String>) -> Self {
+        self.scopes = scopes;
+        self
+    }
+
+    pub fn with_required_consent(mut self) -> Self {
+        self.require_consent = true;
+        self
+    }
+
+    pub fn with_token_exchange(mut self, policy: TokenExchangePolicy) -> Self {
+        self.token_exchange = Some(policy);
+        self
+    }
+
+    pub fn with_claim_mappers(mut self, claim_mappers: Vec<ClaimMapper>) -> Self {
+        self.claim_mappers = claim_mappers;
+        self
+    }
+
+    pub fn with_post_logout_redirect_uris(mut self, uris: Vec<String>) -> Self {
+        self.post_logout_redirect_uris = uris;
+        self
+    }
+
+    pub fn with_backchannel_logout<U: Into<String>, S: Into<String>>(
+        mut self,
+        uri: U,
+        secret: S,
+    ) -> Self {
+        self.backchannel_logout_uri = Some(uri.into());
+        self.backchannel_logout_key = Some(SigningKey::of(secret));
+        self
+    }
+
+    pub fn with_encryption_key(mut self, encryption_key: EncryptionKey) -> Self {
+        self.encryption_key = Some(encryption_key);
+        self
+    }
+
+    pub fn with_groups_limit(mut self, groups_limit: usize) -> Self {
+        self.groups_limit = groups_limit;
+        self
+    }
+
+    /// Compares digests, so that the time taken tells nothing about the secret.
+    pub fn verify_secret(&self, client_secret: &str) -> bool {
+        self.secret_digest
+            .as_ref()
+            .is_some_and(|expected| *expected == secret_digest(client_secret))
+    }
+}
+
+// Client secrets are generated, long and random, a plain digest is enough.
+fn secret_digest(client_secret: &str) -> String {
+    digest(&SHA256, client_secret.as_bytes())
+        .as_ref()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
@@ -0,0 +1,31 @@
+use std::collections::BTreeSet;
+
+use chrono::{DateTime, Utc};
+
+use crate::domain::mail_address::MailAddress;
+
+/// Entities consist of classic structures.
+/// Scopes a user allowed a client, so that they are not asked again.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Consent {
+    pub email: MailAddress,
+    pub client_id: String,
+    pub scopes: BTreeSet<String>,
+    pub granted_at: DateTime<Utc>,
+}
+
+impl Consent {
+    pub fn of<C: Into<String>>(email: MailAddress, client_id: C, scopes: &[String]) -> Self {
+        Self {
+            email,
+            client_id: client_id.into(),
+            scopes: scopes.iter().cloned().collect(),
+            granted_at: Utc::now(),
+        }
+    }
+
+    /// Whether every one of `scopes` was allowed.
+    pub fn covers(&self, scopes: &[String]) -> bool {
+        scopes.iter().all(|scope| self.scopes.contains(scope))
+    }
+}
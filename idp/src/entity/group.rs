@@ -0,0 +1,84 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::{Deserialize, Serialize};
+
+use crate::domain::mail_address::MailAddress;
+
+/// Entities consist of classic structures.
+/// A team of users. Members of the groups nested in it are its members too.
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Group {
+    pub name: String,
+    /// Users in the group itself.
+    #[serde(default)]
+    pub members: BTreeSet<MailAddress>,
+    /// Names of the groups nested in this one.
+    #[serde(default)]
+    pub subgroups: BTreeSet<String>,
+}
+
+impl Group {
+    pub fn of<N: Into<String>>(name: N) -> Self {
+        Self {
+            name: name.into(),
+            members: BTreeSet::new(),
+            subgroups: BTreeSet::new(),
+        }
+    }
+
+    pub fn with_member(mut self, email: MailAddress) -> Self {
+        self.members.insert(email);
+        self
+    }
+
+    pub fn with_subgroup<N: Into<String>>(mut self, name: N) -> Self {
+        self.subgroups.insert(name.into());
+        self
+    }
+}
+
+/// Names end up in the `groups` claim, so they are printable and have no
+/// spaces.
+pub fn is_group_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= 64
+        && name.chars().all(|c| !c.is_whitespace() && !c.is_control())
+}
+
+/// Every group the user belongs to, directly or through nested groups, by name.
+pub fn memberships(groups: &BTreeMap<String, Group>, email: &MailAddress) -> BTreeSet<String> {
+    let mut found: BTreeSet<String> = groups
+        .values()
+        .filter(|group| group.members.contains(email))
+        .map(|group| group.name.clone())
+        .collect();
+    let mut pending: Vec<String> = found.iter().cloned().collect();
+    while let Some(name) = pending.pop() {
+        for group in groups.values() {
+            if group.subgroups.contains(&name) && found.insert(group.name.clone()) {
+                pending.push(group.name.clone());
+            }
+        }
+    }
+    found
+}
+
+/// Whether `inner` is `outer` or nested in it at any depth. Nesting `outer`
+/// into `inner` then would make a cycle.
+pub fn contains_group(groups: &BTreeMap<String, Group>, outer: &str, inner: &str) -> bool {
+    let mut seen = BTreeSet::new();
+    let mut pending = vec![outer];
+    while let Some(name) = pending.pop() {
+        if name == inner {
+            return true;
+        }
+        if !seen.insert(name) {
+            continue;
+        }
+        if let Some(group) = groups.get(name) {
+            pending.extend(group.subgroups.iter().map(String::as_str));
+        }
+    }
+    false
+}
@@ -0,0 +1,18 @@
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::webauthn::cose_key::CoseKey;
+
+/// Entities consist of classic structures.
+/// A WebAuthn credential registered by a user.
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, ToSchema)]
+pub struct Passkey {
+    /// Credential id, base64url.
+    pub id: String,
+    #[serde(skip)]
+    pub public_key: CoseKey,
+    /// Signature counter last reported, 0 when the authenticator keeps none.
+    pub sign_count: u32,
+    /// Attestation statement format it registered with.
+    pub attestation_format: String,
+}
@@ -0,0 +1,15 @@
+use serde::{Deserialize, Serialize};
+
+/// Entities consist of classic structures.
+/// A SAML service provider allowed to request assertions from a realm.
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ServiceProvider {
+    pub entity_id: String,
+    /// Assertion consumer service the response is posted to.
+    pub acs_url: String,
+    /// What the assertion releases besides the NameID: `email`, or the name
+    /// of a user attribute such as `preferred_username`.
+    #[serde(default)]
+    pub attributes: Vec<String>,
+}
@@ -0,0 +1,37 @@
+use std::collections::BTreeSet;
+
+use chrono::{DateTime, Duration, Utc};
+
+use crate::{
+    domain::mail_address::MailAddress, error::my_error, token::opaque_token::opaque_token,
+};
+
+/// Sessions last as long as the tokens issued with them.
+pub const SESSION_LIFETIME_HOURS: i64 = 8;
+
+/// Entities consist of classic structures.
+/// A signed-in browser, identified by the id in its session cookie.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Session {
+    pub id: String,
+    pub email: MailAddress,
+    pub expires_at: DateTime<Utc>,
+    /// Clients that got tokens in the session, told when it ends.
+    pub clients: BTreeSet<String>,
+}
+
+impl Session {
+    /// Starts a session for a user who has just authenticated.
+    pub fn start(email: MailAddress) -> my_error::Result<Self> {
+        Ok(Self {
+            id: opaque_token()?,
+            email,
+            expires_at: Utc::now() + Duration::hours(SESSION_LIFETIME_HOURS),
+            clients: BTreeSet::new(),
+        })
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.expires_at <= Utc::now()
+    }
+}
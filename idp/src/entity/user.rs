@@ -1,17 +1,54 @@
+use std::collections::BTreeMap;
+
 use serde::Serialize;
+use utoipa::ToSchema;
 
-use crate::domain::mail_address::MailAddress;
+use crate::{domain::mail_address::MailAddress, entity::passkey::Passkey};
 
 /// Entities consist of classic structures.
 /// Represents a mutable object.
-#[derive(Clone, PartialEq, Eq, Debug, Serialize)]
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, ToSchema)]
 pub struct User {
     pub email: MailAddress,
+    /// Profile data such as `preferred_username`, which clients map to claims.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub attributes: BTreeMap<String, String>,
+    /// PHC string of the password, never part of a response.
+    #[serde(skip)]
+    pub passwd_hash: Option<String>,
+    /// Accounts at upstream identity providers this user signs in with.
+    #[serde(skip)]
+    pub linked_identities: Vec<LinkedIdentity>,
+    /// WebAuthn credentials, a first factor or a second one after the password.
+    #[serde(skip)]
+    pub passkeys: Vec<Passkey>,
+    /// Random WebAuthn user handle, made up with the first passkey.
+    #[serde(skip)]
+    pub user_handle: Option<String>,
+    /// Disabled users keep their data but can neither sign in nor get tokens.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub disabled: bool,
+}
+
+/// The subject an upstream identity provider knows the user by.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct LinkedIdentity {
+    /// Alias of the upstream provider in the realm.
+    pub provider: String,
+    pub subject: String,
 }
 
 // Factory that instantiates from field values
 impl User {
     pub fn of(email: MailAddress) -> Self {
-        Self { email }
+        Self {
+            email,
+            attributes: BTreeMap::new(),
+            passwd_hash: None,
+            linked_identities: vec![],
+            passkeys: vec![],
+            user_handle: None,
+            disabled: false,
+        }
     }
 }
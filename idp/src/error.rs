@@ -1 +1,3 @@
 pub mod my_error;
+pub mod oauth_error;
+pub mod problem;
@@ -1,10 +1,27 @@
 use std::{error::Error, fmt};
 
+use crate::domain::password_policy::PolicyViolation;
+use crate::error::oauth_error::OAuthError;
+use crate::error::problem::FieldError;
+
 #[derive(Debug)]
 pub enum MyError {
     Decode,
     Encode,
     InvalidValue,
+    Storage,
+    AlreadyExists,
+    PasswordPolicy(Vec<PolicyViolation>),
+    Unauthorized,
+    NotFound,
+    MalformedBody,
+    InvalidRequest(Vec<FieldError>),
+    PayloadTooLarge,
+    UnsupportedMediaType,
+    Upstream,
+    TooManyRequests,
+    OAuth(OAuthError),
+    Internal,
 }
 
 impl Error for MyError {}
@@ -13,10 +30,38 @@ pub type Result<T, E = MyError> = std::result::Result<T, E>;
 
 impl fmt::Display for MyError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match *self {
+        match self {
             MyError::InvalidValue => f.write_str("Invalid Value Error"),
             MyError::Decode => f.write_str("Decode Error"),
             MyError::Encode => f.write_str("Encode Error"),
+            MyError::Storage => f.write_str("Storage Error"),
+            MyError::AlreadyExists => f.write_str("Already Exists Error"),
+            MyError::PasswordPolicy(violations) => {
+                f.write_str("Password Policy Error: password ")?;
+                for (i, violation) in violations.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{}", violation)?;
+                }
+                Ok(())
+            }
+            MyError::Unauthorized => f.write_str("Unauthorized Error"),
+            MyError::NotFound => f.write_str("Not Found Error"),
+            MyError::MalformedBody => f.write_str("Malformed Body Error"),
+            MyError::InvalidRequest(errors) => {
+                f.write_str("Invalid Request Error")?;
+                for error in errors {
+                    write!(f, ", {}: {}", error.field, error.message)?;
+                }
+                Ok(())
+            }
+            MyError::PayloadTooLarge => f.write_str("Payload Too Large Error"),
+            MyError::UnsupportedMediaType => f.write_str("Unsupported Media Type Error"),
+            MyError::Upstream => f.write_str("Upstream Error"),
+            MyError::TooManyRequests => f.write_str("Too Many Requests Error"),
+            MyError::OAuth(err) => write!(f, "OAuth Error: {}", err.code()),
+            MyError::Internal => f.write_str("Internal Error"),
         }
     }
 }
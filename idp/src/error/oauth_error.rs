@@ -0,0 +1,39 @@
+//! Error codes of the token endpoint.
+
+/// RFC 6749 section 5.2, the polling errors of RFC 8628 section 3.5 and
+/// `invalid_target` of RFC 8693 and `invalid_dpop_proof` of RFC 9449.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum OAuthError {
+    InvalidRequest,
+    InvalidClient,
+    InvalidGrant,
+    UnauthorizedClient,
+    UnsupportedGrantType,
+    InvalidScope,
+    InvalidTarget,
+    AuthorizationPending,
+    SlowDown,
+    AccessDenied,
+    ExpiredToken,
+    InvalidDpopProof,
+}
+
+impl OAuthError {
+    /// Value of the `error` member.
+    pub fn code(&self) -> &'static str {
+        match self {
+            OAuthError::InvalidRequest => "invalid_request",
+            OAuthError::InvalidClient => "invalid_client",
+            OAuthError::InvalidGrant => "invalid_grant",
+            OAuthError::UnauthorizedClient => "unauthorized_client",
+            OAuthError::UnsupportedGrantType => "unsupported_grant_type",
+            OAuthError::InvalidScope => "invalid_scope",
+            OAuthError::InvalidTarget => "invalid_target",
+            OAuthError::AuthorizationPending => "authorization_pending",
+            OAuthError::SlowDown => "slow_down",
+            OAuthError::AccessDenied => "access_denied",
+            OAuthError::ExpiredToken => "expired_token",
+            OAuthError::InvalidDpopProof => "invalid_dpop_proof",
+        }
+    }
+}
@@ -0,0 +1,243 @@
+//! RFC 7807 problem details.
+
+use actix_web::{
+    dev::ServiceResponse,
+    error::{BlockingError, JsonPayloadError, QueryPayloadError, UrlencodedError},
+    http::{header, StatusCode},
+    middleware::ErrorHandlerResponse,
+    HttpRequest, HttpResponse, ResponseError,
+};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::{
+    error::{my_error::MyError, oauth_error::OAuthError},
+    i18n::{
+        catalog::{fill, lookup, message},
+        locale::Locale,
+    },
+};
+
+pub const PROBLEM_JSON: &str = "application/problem+json";
+
+/// A single invalid field of a request.
+#[derive(Serialize, Clone, PartialEq, Eq, Debug, ToSchema)]
+pub struct FieldError {
+    pub field: String,
+    pub code: String,
+    pub message: String,
+}
+
+impl FieldError {
+    pub fn of<F: Into<String>, C: Into<String>, M: Into<String>>(
+        field: F,
+        code: C,
+        message: M,
+    ) -> Self {
+        Self {
+            field: field.into(),
+            code: code.into(),
+            message: message.into(),
+        }
+    }
+}
+
+/// Body of every error response.
+#[derive(Serialize, Debug, ToSchema)]
+pub struct ProblemDetails {
+    #[serde(rename = "type")]
+    pub problem_type: String,
+    pub title: String,
+    pub status: u16,
+    pub detail: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<FieldError>,
+    /// OAuth error code, for clients of the token endpoint.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl MyError {
+    // Slug of the problem type, its title and detail are in the catalogs.
+    fn problem_slug(&self) -> &'static str {
+        match self {
+            MyError::Decode => "invalid-token",
+            MyError::Encode => "token-encoding",
+            MyError::InvalidValue => "invalid-value",
+            MyError::Storage => "storage",
+            MyError::AlreadyExists => "already-exists",
+            MyError::PasswordPolicy(_) => "weak-password",
+            MyError::Unauthorized => "unauthorized",
+            MyError::NotFound => "not-found",
+            MyError::MalformedBody => "malformed-body",
+            MyError::InvalidRequest(_) => "invalid-request",
+            MyError::PayloadTooLarge => "payload-too-large",
+            MyError::UnsupportedMediaType => "unsupported-media-type",
+            MyError::Upstream => "upstream",
+            MyError::TooManyRequests => "too-many-requests",
+            MyError::OAuth(_) => "oauth",
+            MyError::Internal => "internal",
+        }
+    }
+
+    fn field_errors(&self, locale: Locale) -> Vec<FieldError> {
+        match self {
+            MyError::PasswordPolicy(violations) => violations
+                .iter()
+                .map(|v| FieldError::of("passwd", v.rule(), v.message(locale)))
+                .collect(),
+            // Messages of the body parser are kept where the catalog has no
+            // translation by code.
+            MyError::InvalidRequest(errors) => errors
+                .iter()
+                .map(
+                    |error| match lookup(locale, &format!("field.{}", error.code)) {
+                        Some(template) => FieldError {
+                            message: fill(template, &[("field", &error.field)]),
+                            ..error.clone()
+                        },
+                        None => error.clone(),
+                    },
+                )
+                .collect(),
+            _ => vec![],
+        }
+    }
+}
+
+impl ProblemDetails {
+    /// The problem in the language of the client.
+    pub fn localized(err: &MyError, locale: Locale) -> Self {
+        let slug = err.problem_slug();
+        let detail = match err {
+            MyError::OAuth(err) => format!("oauth.{}", err.code()),
+            _ => format!("problem.{}.detail", slug),
+        };
+        Self {
+            problem_type: format!("/problems/{}", slug),
+            title: message(locale, &format!("problem.{}.title", slug)),
+            status: err.status_code().as_u16(),
+            detail: message(locale, &detail),
+            errors: err.field_errors(locale),
+            error: match err {
+                MyError::OAuth(err) => Some(err.code().to_owned()),
+                _ => None,
+            },
+        }
+    }
+}
+
+impl From<&MyError> for ProblemDetails {
+    fn from(err: &MyError) -> Self {
+        Self::localized(err, Locale::default())
+    }
+}
+
+impl ResponseError for MyError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            MyError::Decode | MyError::Unauthorized | MyError::OAuth(OAuthError::InvalidClient) => {
+                StatusCode::UNAUTHORIZED
+            }
+            MyError::OAuth(_) => StatusCode::BAD_REQUEST,
+            MyError::InvalidValue
+            | MyError::PasswordPolicy(_)
+            | MyError::MalformedBody
+            | MyError::InvalidRequest(_) => StatusCode::BAD_REQUEST,
+            MyError::AlreadyExists => StatusCode::CONFLICT,
+            MyError::NotFound => StatusCode::NOT_FOUND,
+            MyError::PayloadTooLarge => StatusCode::PAYLOAD_TOO_LARGE,
+            MyError::UnsupportedMediaType => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            MyError::Storage => StatusCode::SERVICE_UNAVAILABLE,
+            MyError::Upstream => StatusCode::BAD_GATEWAY,
+            MyError::TooManyRequests => StatusCode::TOO_MANY_REQUESTS,
+            MyError::Encode | MyError::Internal => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        if self.status_code().is_server_error() {
+            tracing::error!(error = %self, "request failed");
+        }
+        HttpResponse::build(self.status_code())
+            .content_type(PROBLEM_JSON)
+            .json(ProblemDetails::from(self))
+    }
+}
+
+impl From<JsonPayloadError> for MyError {
+    fn from(err: JsonPayloadError) -> Self {
+        match err {
+            JsonPayloadError::OverflowKnownLength { .. } | JsonPayloadError::Overflow { .. } => {
+                MyError::PayloadTooLarge
+            }
+            JsonPayloadError::ContentType => MyError::UnsupportedMediaType,
+            _ => MyError::MalformedBody,
+        }
+    }
+}
+
+impl From<UrlencodedError> for MyError {
+    fn from(err: UrlencodedError) -> Self {
+        match err {
+            UrlencodedError::Overflow { .. } => MyError::PayloadTooLarge,
+            UrlencodedError::ContentType => MyError::UnsupportedMediaType,
+            _ => MyError::InvalidValue,
+        }
+    }
+}
+
+impl From<BlockingError> for MyError {
+    fn from(_: BlockingError) -> Self {
+        MyError::Internal
+    }
+}
+
+/// `JsonConfig` error handler so that body errors render as problems too.
+pub fn json_error_handler(err: JsonPayloadError, req: &HttpRequest) -> actix_web::Error {
+    tracing::info!(path = req.path(), error = %err, "request is rejected");
+    MyError::from(err).into()
+}
+
+/// `FormConfig` error handler.
+pub fn form_error_handler(err: UrlencodedError, req: &HttpRequest) -> actix_web::Error {
+    tracing::info!(path = req.path(), error = %err, "request is rejected");
+    MyError::from(err).into()
+}
+
+/// `QueryConfig` error handler.
+pub fn query_error_handler(err: QueryPayloadError, req: &HttpRequest) -> actix_web::Error {
+    tracing::info!(path = req.path(), error = %err, "request is rejected");
+    MyError::InvalidValue.into()
+}
+
+/// Default service for unknown routes.
+pub async fn not_found_handler() -> Result<HttpResponse, MyError> {
+    Err(MyError::NotFound)
+}
+
+/// `ErrorHandlers` default handler that renders problems again in the
+/// language the client asked for. English ones pass as they are.
+pub fn localize_problem<B>(res: ServiceResponse<B>) -> actix_web::Result<ErrorHandlerResponse<B>> {
+    let locale = Locale::of_request(res.request());
+    let problem = match res
+        .response()
+        .error()
+        .and_then(|err| err.as_error::<MyError>())
+    {
+        Some(err) if locale != Locale::default() => ProblemDetails::localized(err, locale),
+        _ => return Ok(ErrorHandlerResponse::Response(res.map_into_left_body())),
+    };
+    let mut localized = HttpResponse::build(res.status())
+        .content_type(PROBLEM_JSON)
+        .insert_header((header::CONTENT_LANGUAGE, locale.tag()))
+        .json(problem);
+    for (name, value) in res.headers() {
+        if name != header::CONTENT_TYPE && name != header::CONTENT_LENGTH {
+            localized.headers_mut().append(name.clone(), value.clone());
+        }
+    }
+    Ok(ErrorHandlerResponse::Response(
+        res.into_response(localized).map_into_right_body(),
+    ))
+}
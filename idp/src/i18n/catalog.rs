@@ -0,0 +1,75 @@
+//! Message catalogs.
+//!
+//! One JSON file per language under `locales/`, keyed by message id. Values
+//! may hold `{name}` placeholders. A message missing in a language falls
+//! back to English, and to its id when English lacks it too.
+
+use std::{collections::HashMap, sync::OnceLock};
+
+use crate::i18n::locale::Locale;
+
+const EN: &str = include_str!("../../locales/en.json");
+const JA: &str = include_str!("../../locales/ja.json");
+
+type Catalog = HashMap<String, String>;
+
+fn catalog(locale: Locale) -> &'static Catalog {
+    static EN_CATALOG: OnceLock<Catalog> = OnceLock::new();
+    static JA_CATALOG: OnceLock<Catalog> = OnceLock::new();
+    let (cell, source) = match locale {
+        Locale::En => (&EN_CATALOG, EN),
+        Locale::Ja => (&JA_CATALOG, JA),
+    };
+    // The catalogs are built in, a broken one fails every test.
+    cell.get_or_init(|| serde_json::from_str(source).expect("message catalog is valid JSON"))
+}
+
+/// Every message id of the language.
+pub fn message_ids(locale: Locale) -> impl Iterator<Item = &'static str> {
+    catalog(locale).keys().map(String::as_str)
+}
+
+/// The message in the language only, without fallback.
+pub fn lookup(locale: Locale, id: &str) -> Option<&'static str> {
+    catalog(locale).get(id).map(String::as_str)
+}
+
+pub fn message(locale: Locale, id: &str) -> String {
+    format_message(locale, id, &[])
+}
+
+/// The message with its placeholders filled in.
+pub fn format_message(locale: Locale, id: &str, args: &[(&str, &str)]) -> String {
+    let template = lookup(locale, id)
+        .or_else(|| lookup(Locale::En, id))
+        .unwrap_or(id);
+    fill(template, args)
+}
+
+/// Replaces each `{name}` of the template with its value, in one pass so
+/// that values are never read as placeholders. Unknown ones are kept.
+pub fn fill(template: &str, args: &[(&str, &str)]) -> String {
+    let mut text = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        text.push_str(&rest[..start]);
+        let placeholder = &rest[start..];
+        let value = placeholder.find('}').and_then(|end| {
+            let name = &placeholder[1..end];
+            let (_, value) = args.iter().find(|(arg, _)| *arg == name)?;
+            Some((*value, end + 1))
+        });
+        match value {
+            Some((value, len)) => {
+                text.push_str(value);
+                rest = &placeholder[len..];
+            }
+            None => {
+                text.push('{');
+                rest = &placeholder[1..];
+            }
+        }
+    }
+    text.push_str(rest);
+    text
+}
@@ -0,0 +1,85 @@
+//! Language of responses.
+//!
+//! Picked from the `ui_locales` parameter of OpenID Connect first, then from
+//! `Accept-Language`, and English when neither names a supported language.
+
+use std::{
+    convert::Infallible,
+    future::{ready, Ready},
+};
+
+use actix_web::{dev::Payload, http::header, FromRequest, HttpRequest};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Locale {
+    #[default]
+    En,
+    Ja,
+}
+
+impl Locale {
+    /// BCP 47 tag, for `lang` attributes.
+    pub fn tag(&self) -> &'static str {
+        match self {
+            Locale::En => "en",
+            Locale::Ja => "ja",
+        }
+    }
+
+    /// Matches on the primary subtag, so that `ja-JP` is Japanese.
+    pub fn of_tag(tag: &str) -> Option<Self> {
+        let primary = tag.trim().split(['-', '_']).next()?;
+        match primary.to_ascii_lowercase().as_str() {
+            "en" => Some(Locale::En),
+            "ja" => Some(Locale::Ja),
+            _ => None,
+        }
+    }
+
+    /// The first supported one of the space separated tags.
+    pub fn from_ui_locales(ui_locales: &str) -> Option<Self> {
+        ui_locales.split_whitespace().find_map(Self::of_tag)
+    }
+
+    /// The supported language the browser weighs highest. Ranges with
+    /// `q=0` are refused, `*` leaves the choice to the fallback.
+    pub fn from_accept_language(accept_language: &str) -> Option<Self> {
+        let mut ranges: Vec<(&str, f32)> = accept_language
+            .split(',')
+            .filter_map(|range| {
+                let mut parts = range.split(';');
+                let tag = parts.next()?.trim();
+                let q = parts
+                    .find_map(|param| param.trim().strip_prefix("q="))
+                    .map_or(Some(1.0), |q| q.trim().parse::<f32>().ok())?;
+                (q > 0.0).then_some((tag, q))
+            })
+            .collect();
+        // Stable, so that ranges of equal weight keep their order.
+        ranges.sort_by(|a, b| b.1.total_cmp(&a.1));
+        ranges.into_iter().find_map(|(tag, _)| Self::of_tag(tag))
+    }
+
+    pub fn of_request(req: &HttpRequest) -> Self {
+        let ui_locales = url::form_urlencoded::parse(req.query_string().as_bytes())
+            .find(|(name, _)| name == "ui_locales")
+            .and_then(|(_, value)| Self::from_ui_locales(&value));
+        ui_locales
+            .or_else(|| {
+                req.headers()
+                    .get(header::ACCEPT_LANGUAGE)
+                    .and_then(|value| value.to_str().ok())
+                    .and_then(Self::from_accept_language)
+            })
+            .unwrap_or_default()
+    }
+}
+
+impl FromRequest for Locale {
+    type Error = Infallible;
+    type Future = Ready<Result<Self, Infallible>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        ready(Ok(Self::of_request(req)))
+    }
+}
@@ -0,0 +1,24 @@
+//! Idp
+//!
+//! Shared by the web server and the admin CLI.
+pub mod admin;
+pub mod app;
+pub mod app_state;
+pub mod broker;
+pub mod credential;
+pub mod domain;
+pub mod entity;
+pub mod error;
+pub mod i18n;
+pub mod mail;
+pub mod oauth;
+pub mod realm;
+pub mod resource;
+pub mod saml;
+pub mod shutdown;
+pub mod store;
+pub mod telemetry;
+mod test;
+pub mod tls;
+pub mod token;
+pub mod webauthn;
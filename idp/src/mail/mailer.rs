@@ -0,0 +1,76 @@
+//! Outgoing mail.
+
+use std::{
+    env, fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+use chrono::Utc;
+
+use crate::{
+    domain::mail_address::MailAddress,
+    error::my_error::{self, MyError},
+    token::opaque_token::opaque_token,
+};
+
+const OUTBOX_DIR_ENV: &str = "IDP_MAIL_OUTBOX";
+
+/// A plain text message to one recipient.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Mail {
+    pub to: MailAddress,
+    pub subject: String,
+    pub body: String,
+}
+
+/// Hands mail over for delivery. Sending may block, so handlers call it
+/// off the worker thread.
+pub trait Mailer: fmt::Debug + Send + Sync {
+    fn send(&self, mail: &Mail) -> my_error::Result<()>;
+}
+
+/// Writes every message as a file into a local directory, for development
+/// and for a relay that picks them up.
+#[derive(Debug)]
+pub struct OutboxMailer {
+    dir: PathBuf,
+}
+
+impl OutboxMailer {
+    /// Reads `IDP_MAIL_OUTBOX`. No mail is sent when it is not set.
+    pub fn from_env() -> io::Result<Option<Self>> {
+        env::var_os(OUTBOX_DIR_ENV)
+            .map(|dir| Self::of(PathBuf::from(dir)))
+            .transpose()
+    }
+
+    /// Creates the directory if it does not exist yet.
+    pub fn of<P: AsRef<Path>>(dir: P) -> io::Result<Self> {
+        fs::create_dir_all(&dir)?;
+        Ok(Self {
+            dir: dir.as_ref().to_path_buf(),
+        })
+    }
+}
+
+impl Mailer for OutboxMailer {
+    fn send(&self, mail: &Mail) -> my_error::Result<()> {
+        // Sorts by time, the random part keeps messages of the same millisecond apart.
+        let name = format!(
+            "{}-{}.eml",
+            Utc::now().format("%Y%m%dT%H%M%S%.3fZ"),
+            &opaque_token()?[..8]
+        );
+        let message = format!(
+            "To: {}\r\nSubject: {}\r\nDate: {}\r\nContent-Type: text/plain; charset=utf-8\r\n\r\n{}",
+            String::from(mail.to.clone()),
+            mail.subject,
+            Utc::now().to_rfc2822(),
+            mail.body
+        );
+        fs::write(self.dir.join(name), message).map_err(|err| {
+            tracing::error!(error = %err, "could not write to the mail outbox");
+            MyError::Internal
+        })
+    }
+}
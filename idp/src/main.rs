@@ -1,46 +1,63 @@
 //! Idp Web Server
 //!
-mod domain;
-mod entity;
-mod error;
-mod resource;
-mod test;
-mod token;
-
-use actix_web::{error as actix_error, middleware, web, App, HttpResponse, HttpServer};
-use resource::hello_html::hello_html_handler;
-
-use crate::resource::hello_resource::hello_handler;
-use crate::resource::idp_resource::{make_jwt_handler, validate_jwt_handler};
-
-#[actix_web::main]
-async fn main() -> std::io::Result<()> {
-    env_logger::init_from_env(env_logger::Env::new().default_filter_or("info"));
-
-    log::info!("starting HTTP server at http://localhost:8080");
-
-    HttpServer::new(|| {
-        App::new()
-            .wrap(middleware::Logger::default())
-            .app_data(
-                web::JsonConfig::default()
-                    .limit(4096) // limit request payload size
-                    .content_type(|mime| mime == mime::TEXT_PLAIN) // only accept text/plain content type
-                    .error_handler(|err, req| {
-                        log::info!("error request {}", req.path());
-                        actix_error::InternalError::from_response(
-                            err,
-                            HttpResponse::Conflict().into(),
-                        )
-                        .into()
-                    }), // use custom error handler
-            )
-            .service(hello_html_handler)
-            .service(web::resource("/rest").route(web::post().to(hello_handler)))
-            .service(web::resource("/jwt").route(web::post().to(make_jwt_handler)))
-            .service(web::resource("/validate").route(web::post().to(validate_jwt_handler)))
-    })
-    .bind("127.0.0.1:8080")?
-    .run()
-    .await
+
+use std::{io, sync::Arc};
+
+use actix_web::{web, HttpServer};
+
+use idp::app::app;
+use idp::app_state::AppState;
+use idp::mail::mailer::{Mailer, OutboxMailer};
+use idp::realm::realm_config::load_realms;
+use idp::saml::saml_credential::SamlCredential;
+use idp::shutdown::{drain_on_signal, SHUTDOWN_TIMEOUT_SECS};
+use idp::telemetry::subscriber;
+use idp::tls::{server_config, ReloadingCertResolver, TlsConfig};
+
+// The tracer provider is set up and shut down outside of the runtime, as its
+// exporter blocks.
+fn main() -> io::Result<()> {
+    let tracer_provider = subscriber::init("info")?;
+    let result = actix_web::rt::System::new().block_on(serve());
+    if let Some(provider) = tracer_provider {
+        if let Err(err) = provider.shutdown() {
+            eprintln!("idp: could not flush spans: {}", err);
+        }
+    }
+    result
+}
+
+async fn serve() -> io::Result<()> {
+    let mailer = OutboxMailer::from_env()?.map(|mailer| Arc::new(mailer) as Arc<dyn Mailer>);
+    let state = web::Data::new(
+        AppState::of(load_realms()?)
+            .with_saml_credential(SamlCredential::from_env()?)
+            .with_mailer(mailer),
+    );
+
+    let tls = match TlsConfig::from_env() {
+        Some(config) => Some(Arc::new(ReloadingCertResolver::load(config)?)),
+        None => None,
+    };
+    let tls_enabled = tls.is_some();
+
+    let app_state = state.clone();
+    let server = HttpServer::new(move || app(app_state.clone(), tls_enabled));
+    let server = match tls {
+        Some(resolver) => {
+            tracing::info!("starting HTTPS server at https://localhost:8443");
+            resolver.clone().spawn_reloader();
+            server.bind_rustls_0_23("127.0.0.1:8443", server_config(resolver)?)?
+        }
+        None => {
+            tracing::info!("starting HTTP server at http://localhost:8080");
+            server.bind("127.0.0.1:8080")?
+        }
+    }
+    .shutdown_timeout(SHUTDOWN_TIMEOUT_SECS)
+    .disable_signals() // handled by drain_on_signal
+    .run();
+
+    actix_web::rt::spawn(drain_on_signal(server.handle(), state));
+    server.await
 }
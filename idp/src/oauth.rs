@@ -0,0 +1,3 @@
+pub mod backchannel_logout;
+pub mod device_grant;
+pub mod token_exchange;
@@ -0,0 +1,132 @@
+//! OpenID Connect Back-Channel Logout 1.0.
+//!
+//! Deliveries run in the background with retries, so that a slow or
+//! unreachable client does not hold up the browser that logs out.
+
+use std::{collections::BTreeMap, time::Duration};
+
+use actix_web::{rt, web};
+use chrono::Utc;
+use jsonwebtoken::{encode, Algorithm, EncodingKey, Header};
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use crate::{
+    entity::session::Session,
+    error::my_error::{self, MyError},
+    realm::realm_registry::Realm,
+    token::{opaque_token::opaque_token, signing_key::SigningKey},
+};
+
+pub const BACKCHANNEL_LOGOUT_EVENT: &str = "http://schemas.openid.net/event/backchannel-logout";
+/// `typ` header of logout tokens, section 2.4.
+pub const LOGOUT_TOKEN_TYPE: &str = "logout+jwt";
+/// Tries per client, the delay between two doubles each time.
+pub const DELIVERY_ATTEMPTS: u32 = 4;
+const FIRST_RETRY_DELAY: Duration = Duration::from_millis(500);
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(5);
+const LOGOUT_TOKEN_LIFETIME_SECS: i64 = 120;
+
+/// Section 2.4, never with a `nonce` so that it cannot pass for an ID token.
+#[derive(Debug, Serialize)]
+pub struct LogoutClaims {
+    iss: String,
+    aud: String,
+    iat: i64,
+    exp: i64,
+    jti: String,
+    sub: String,
+    sid: String,
+    events: BTreeMap<String, Value>,
+}
+
+/// Logout token telling `client_id` that the session has ended, signed with
+/// the secret the realm shares with it.
+#[tracing::instrument(skip_all, fields(client_id = %client_id))]
+pub fn logout_token(
+    realm: &Realm,
+    client_id: &str,
+    key: &SigningKey,
+    session: &Session,
+) -> my_error::Result<String> {
+    let iat = Utc::now().timestamp();
+    let claims = LogoutClaims {
+        iss: realm.issuer.clone(),
+        aud: client_id.to_owned(),
+        iat,
+        exp: iat + LOGOUT_TOKEN_LIFETIME_SECS,
+        jti: opaque_token()?,
+        sub: String::from(session.email.clone()),
+        sid: session.id.clone(),
+        events: BTreeMap::from([(BACKCHANNEL_LOGOUT_EVENT.to_owned(), json!({}))]),
+    };
+    let mut header = Header::new(Algorithm::HS256);
+    header.typ = Some(LOGOUT_TOKEN_TYPE.to_owned());
+    header.kid = Some(key.kid.clone());
+    encode(
+        &header,
+        &claims,
+        &EncodingKey::from_secret(key.secret().as_ref()),
+    )
+    .map_err(|_| MyError::Encode)
+}
+
+/// Sends a logout token to every client of the ended session that
+/// registered a back-channel logout URI.
+pub fn notify_clients(realm: &Realm, session: &Session) -> my_error::Result<()> {
+    for client_id in &session.clients {
+        let Some((Some(uri), Some(key))) = realm
+            .clients
+            .find(client_id)?
+            .map(|client| (client.backchannel_logout_uri, client.backchannel_logout_key))
+        else {
+            continue;
+        };
+        let token = logout_token(realm, client_id, &key, session)?;
+        rt::spawn(deliver(uri, token));
+    }
+    Ok(())
+}
+
+async fn deliver(uri: String, logout_token: String) {
+    let mut delay = FIRST_RETRY_DELAY;
+    for attempt in 1..=DELIVERY_ATTEMPTS {
+        let (target, token) = (uri.clone(), logout_token.clone());
+        match web::block(move || post_logout_token(&target, &token)).await {
+            Ok(Ok(Delivery::Done)) => return,
+            Ok(Ok(Delivery::Rejected(status))) => {
+                tracing::warn!(%uri, status, "back-channel logout rejected");
+                return;
+            }
+            Ok(Err(_)) | Err(_) if attempt < DELIVERY_ATTEMPTS => {
+                rt::time::sleep(delay).await;
+                delay *= 2;
+            }
+            Ok(Err(_)) | Err(_) => {}
+        }
+    }
+    tracing::warn!(
+        %uri,
+        attempts = DELIVERY_ATTEMPTS,
+        "back-channel logout failed, giving up"
+    );
+}
+
+enum Delivery {
+    Done,
+    /// The client turned the token down, sending it again would not help.
+    Rejected(u16),
+}
+
+// Server errors and transport failures are worth another try.
+fn post_logout_token(uri: &str, logout_token: &str) -> my_error::Result<Delivery> {
+    let agent = ureq::AgentBuilder::new().timeout(DELIVERY_TIMEOUT).build();
+    match agent.post(uri).send_form(&[("logout_token", logout_token)]) {
+        Ok(_) => Ok(Delivery::Done),
+        Err(ureq::Error::Status(status, _)) if status < 500 => Ok(Delivery::Rejected(status)),
+        Err(err) => {
+            tracing::debug!(uri, error = %err, "back-channel logout failed");
+            Err(MyError::Upstream)
+        }
+    }
+}
@@ -0,0 +1,132 @@
+//! Device authorization grant, RFC 8628.
+
+use chrono::{DateTime, Duration, Utc};
+use ring::rand::{SecureRandom, SystemRandom};
+
+use crate::{
+    domain::mail_address::MailAddress,
+    error::{
+        my_error::{self, MyError},
+        oauth_error::OAuthError,
+    },
+    token::opaque_token::opaque_token,
+};
+
+pub const DEVICE_CODE_GRANT_TYPE: &str = "urn:ietf:params:oauth:grant-type:device_code";
+/// How long the user has to enter the code.
+pub const DEVICE_CODE_LIFETIME_SECS: i64 = 600;
+/// Minimum seconds between two polls, until the client is told to slow down.
+pub const POLLING_INTERVAL_SECS: i64 = 5;
+
+// Consonants only, so that no word comes up and nothing looks alike (RFC 8628 section 6.1).
+const USER_CODE_ALPHABET: &[u8] = b"BCDFGHJKLMNPQRSTVWXZ";
+const USER_CODE_LENGTH: usize = 8;
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum DeviceGrantStatus {
+    Pending,
+    Approved(Approval),
+    Denied,
+}
+
+/// The user who approved the device, and the session they did it in.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Approval {
+    pub email: MailAddress,
+    /// Tokens of the device end with the session, when there was one.
+    pub session_id: Option<String>,
+    /// Scopes of the grant, which the user saw and allowed.
+    pub scopes: Vec<String>,
+}
+
+/// A device waiting for its user to enter the user code on another screen.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct DeviceGrant {
+    /// Polled with by the device, the key of the grant.
+    pub device_code: String,
+    /// Typed by the user, in the `BCDF-GHJK` form.
+    pub user_code: String,
+    pub client_id: String,
+    /// Asked for by the device, always ones the client may ask for.
+    pub scopes: Vec<String>,
+    pub status: DeviceGrantStatus,
+    /// Grows by 5 seconds with every `slow_down`.
+    pub interval_secs: i64,
+    pub last_polled_at: Option<DateTime<Utc>>,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl DeviceGrant {
+    pub fn start<C: Into<String>>(client_id: C, scopes: Vec<String>) -> my_error::Result<Self> {
+        Ok(Self {
+            device_code: opaque_token()?,
+            user_code: user_code()?,
+            client_id: client_id.into(),
+            scopes,
+            status: DeviceGrantStatus::Pending,
+            interval_secs: POLLING_INTERVAL_SECS,
+            last_polled_at: None,
+            expires_at: Utc::now() + Duration::seconds(DEVICE_CODE_LIFETIME_SECS),
+        })
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.expires_at <= Utc::now()
+    }
+
+    /// Answers a poll of the device at `now`, with the user once approved.
+    /// Every other answer is an error to hand to the device as is.
+    pub fn poll(&mut self, now: DateTime<Utc>) -> my_error::Result<Approval> {
+        if self.expires_at <= now {
+            return Err(MyError::OAuth(OAuthError::ExpiredToken));
+        }
+        let too_fast = self
+            .last_polled_at
+            .is_some_and(|last| now < last + Duration::seconds(self.interval_secs));
+        self.last_polled_at = Some(now);
+        match &self.status {
+            DeviceGrantStatus::Approved(approval) => Ok(approval.clone()),
+            DeviceGrantStatus::Denied => Err(MyError::OAuth(OAuthError::AccessDenied)),
+            DeviceGrantStatus::Pending if too_fast => {
+                self.interval_secs += POLLING_INTERVAL_SECS;
+                Err(MyError::OAuth(OAuthError::SlowDown))
+            }
+            DeviceGrantStatus::Pending => Err(MyError::OAuth(OAuthError::AuthorizationPending)),
+        }
+    }
+}
+
+/// Upper-cases what the user typed and drops the dash or any spaces, so that
+/// `bcdf ghjk` finds `BCDF-GHJK`.
+pub fn normalize_user_code(input: &str) -> String {
+    let code: String = input
+        .chars()
+        .map(|c| c.to_ascii_uppercase())
+        .filter(|c| c.is_ascii_alphabetic())
+        .collect();
+    match code.len() {
+        USER_CODE_LENGTH => format!("{}-{}", &code[..4], &code[4..]),
+        _ => code,
+    }
+}
+
+fn user_code() -> my_error::Result<String> {
+    let rng = SystemRandom::new();
+    let mut code = String::with_capacity(USER_CODE_LENGTH + 1);
+    while code.len() < USER_CODE_LENGTH + 1 {
+        let mut byte = [0u8; 1];
+        rng.fill(&mut byte).map_err(|_| MyError::Internal)?;
+        // Rejects the bytes past the last full round of the alphabet, to stay uniform.
+        let limit = 256 - 256 % USER_CODE_ALPHABET.len();
+        if usize::from(byte[0]) >= limit {
+            continue;
+        }
+        if code.len() == USER_CODE_LENGTH / 2 {
+            code.push('-');
+        }
+        code.push(char::from(
+            USER_CODE_ALPHABET[usize::from(byte[0]) % USER_CODE_ALPHABET.len()],
+        ));
+    }
+    Ok(code)
+}
@@ -0,0 +1,53 @@
+//! Token exchange, RFC 8693.
+//!
+//! The new token keeps the user as `sub`, so downstream APIs authorize the
+//! user, and records in `act` the client that acts for them.
+
+use crate::{
+    entity::client::TokenExchangePolicy,
+    error::{
+        my_error::{self, MyError},
+        oauth_error::OAuthError,
+    },
+    token::jwt::Claims,
+};
+
+pub const TOKEN_EXCHANGE_GRANT_TYPE: &str = "urn:ietf:params:oauth:grant-type:token-exchange";
+pub const ACCESS_TOKEN_TYPE: &str = "urn:ietf:params:oauth:token-type:access_token";
+pub const JWT_TOKEN_TYPE: &str = "urn:ietf:params:oauth:token-type:jwt";
+
+/// Claims of the token `actor` gets for `audience` in exchange for `subject`.
+/// Without a requested scope the token carries every scope still allowed,
+/// and a subject token without scopes passes none on.
+#[tracing::instrument(skip_all, fields(audience = %audience))]
+pub fn exchange(
+    policy: &TokenExchangePolicy,
+    subject: &Claims,
+    audience: &str,
+    requested_scope: Option<&str>,
+    actor: &str,
+) -> my_error::Result<Claims> {
+    if !policy.audiences.iter().any(|allowed| allowed == audience) {
+        return Err(MyError::OAuth(OAuthError::InvalidTarget));
+    }
+    // Down-scoped: what the policy allows, and the subject token still has.
+    let held = subject.scopes().unwrap_or_default();
+    let allowed: Vec<&str> = policy
+        .scopes
+        .iter()
+        .map(String::as_str)
+        .filter(|scope| held.contains(scope))
+        .collect();
+    let scopes = match requested_scope {
+        Some(requested) => {
+            let requested: Vec<&str> = requested.split_whitespace().collect();
+            if !requested.iter().all(|scope| allowed.contains(scope)) {
+                return Err(MyError::OAuth(OAuthError::InvalidScope));
+            }
+            requested
+        }
+        None => allowed,
+    };
+    let scope = (!scopes.is_empty()).then(|| scopes.join(" "));
+    Ok(Claims::exchanged(subject, audience, scope, actor))
+}
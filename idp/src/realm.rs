@@ -0,0 +1,3 @@
+pub mod realm_config;
+pub mod realm_registry;
+pub mod realm_state;
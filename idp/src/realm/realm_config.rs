@@ -0,0 +1,331 @@
+//! Realm configuration.
+
+use std::{collections::BTreeMap, env, fs, io, path::PathBuf};
+
+use serde::Deserialize;
+use serde_json::Value;
+use url::Url;
+
+use crate::{
+    broker::upstream_provider::UpstreamProvider,
+    domain::password_policy::{CharacterClass, PasswordPolicy},
+    entity::{
+        client::{Client, TokenExchangePolicy},
+        service_provider::ServiceProvider,
+    },
+    realm::{
+        realm_registry::{Realm, RealmRegistry, DEFAULT_REALM},
+        realm_state::StateFile,
+    },
+    token::{
+        claim_mapping::{check_mappers, ClaimMapper},
+        jwe::EncryptionKey,
+        signing_key::SigningKeys,
+    },
+};
+
+const REALMS_FILE_ENV: &str = "IDP_REALMS_FILE";
+const ISSUER_ENV: &str = "IDP_ISSUER";
+const DEFAULT_ISSUER: &str = "http://localhost:8080";
+
+/// Shape of the file pointed to by `IDP_REALMS_FILE`.
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct RealmsFile {
+    pub realms: Vec<RealmConfig>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct RealmConfig {
+    pub name: String,
+    /// Defaults to `{IDP_ISSUER}/realms/{name}`.
+    pub issuer: Option<String>,
+    /// Name of the environment variable that holds the signing secret,
+    /// so that secrets stay out of the file.
+    pub secret_env: String,
+    #[serde(default)]
+    pub password_policy: PolicyOverrides,
+    #[serde(default)]
+    pub clients: Vec<ClientConfig>,
+    #[serde(default)]
+    pub service_providers: Vec<ServiceProvider>,
+    #[serde(default)]
+    pub upstream_providers: Vec<UpstreamProviderConfig>,
+    /// Custom claims of exchanged tokens, keyed by their audience.
+    #[serde(default)]
+    pub audience_claim_mappers: BTreeMap<String, Vec<ClaimMapper>>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct ClientConfig {
+    pub client_id: String,
+    /// Name of the environment variable that holds the client secret,
+    /// for confidential clients.
+    pub client_secret_env: Option<String>,
+    /// Scopes the client may ask users for.
+    #[serde(default)]
+    pub scopes: Vec<String>,
+    /// Third-party clients only get scopes the user allowed them.
+    #[serde(default)]
+    pub require_consent: bool,
+    /// Only confidential clients may exchange tokens.
+    pub token_exchange: Option<TokenExchangePolicy>,
+    /// Custom claims of the tokens issued to the client.
+    #[serde(default)]
+    pub claim_mappers: Vec<ClaimMapper>,
+    /// Absolute URLs `/logout` may redirect to.
+    #[serde(default)]
+    pub post_logout_redirect_uris: Vec<String>,
+    /// Absolute URL that receives back-channel logout tokens.
+    pub backchannel_logout_uri: Option<String>,
+    /// Name of the environment variable that holds the secret the logout
+    /// tokens are signed with, required with `backchannel_logout_uri`.
+    pub backchannel_logout_secret_env: Option<String>,
+    /// Public RSA or P-256 JWK the client's tokens are encrypted to.
+    pub encryption_key: Option<Value>,
+    /// Most groups listed in the client's tokens, 0 for none.
+    pub groups_limit: Option<usize>,
+}
+
+/// An OpenID Connect provider users of the realm can sign in through.
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct UpstreamProviderConfig {
+    /// Names the provider in `/broker/{alias}/...`.
+    pub alias: String,
+    /// Discovery happens at `{issuer}/.well-known/openid-configuration`.
+    pub issuer: String,
+    pub client_id: String,
+    /// Name of the environment variable that holds the client secret.
+    pub client_secret_env: String,
+    /// Defaults to `openid email`.
+    pub scopes: Option<Vec<String>>,
+}
+
+/// Realm specific changes on top of the environment wide password policy.
+#[derive(Deserialize, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub struct PolicyOverrides {
+    pub min_length: Option<usize>,
+    pub min_entropy_bits: Option<f64>,
+    pub character_classes: Option<Vec<String>>,
+    pub breached_passwords_file: Option<PathBuf>,
+}
+
+/// Loads the realms from `IDP_REALMS_FILE`, or serves a single default realm
+/// configured from the environment when the variable is not set. What
+/// `idp-admin` saved in `IDP_STATE_FILE` is applied on top.
+pub fn load_realms() -> io::Result<RealmRegistry> {
+    let mut realms = configured_realms()?;
+    if let Some(path) = StateFile::path_from_env() {
+        StateFile::load(path)?.apply(&mut realms)?;
+    }
+    registry_of(realms)
+}
+
+/// The realms as configured, without the state.
+pub fn configured_realms() -> io::Result<Vec<Realm>> {
+    let base_issuer = env::var(ISSUER_ENV).unwrap_or_else(|_| DEFAULT_ISSUER.to_owned());
+    let base_policy = PasswordPolicy::from_env()?;
+    match env::var_os(REALMS_FILE_ENV) {
+        Some(path) => {
+            let file: RealmsFile =
+                serde_json::from_slice(&fs::read(path)?).map_err(io::Error::other)?;
+            build_realms(file, &base_issuer, &base_policy, |name| env::var(name).ok())
+        }
+        None => Ok(vec![Realm::of(
+            DEFAULT_REALM,
+            base_issuer,
+            SigningKeys::from_env(),
+        )
+        .with_password_policy(base_policy)]),
+    }
+}
+
+pub fn registry_of(realms: Vec<Realm>) -> io::Result<RealmRegistry> {
+    RealmRegistry::of(realms).map_err(|err| invalid_input(format!("realms: {}", err)))
+}
+
+/// Turns the parsed file into realms, looking secrets up with `secret_of`.
+pub fn build_realms<F: Fn(&str) -> Option<String>>(
+    file: RealmsFile,
+    base_issuer: &str,
+    base_policy: &PasswordPolicy,
+    secret_of: F,
+) -> io::Result<Vec<Realm>> {
+    let mut realms = vec![];
+    for config in file.realms {
+        if !is_path_segment(&config.name) {
+            return Err(invalid_input(format!(
+                "invalid realm name {:?}",
+                config.name
+            )));
+        }
+        let secret = required_secret(&secret_of, &config.secret_env, &config.name)?;
+        let issuer = config.issuer.unwrap_or_else(|| {
+            format!(
+                "{}/realms/{}",
+                base_issuer.trim_end_matches('/'),
+                config.name
+            )
+        });
+        let policy = apply_overrides(base_policy.clone(), config.password_policy)?;
+        let mut realm =
+            Realm::of(config.name, issuer, SigningKeys::of(secret)).with_password_policy(policy);
+        for upstream in config.upstream_providers {
+            if !is_path_segment(&upstream.alias)
+                || realm.upstream_providers.contains_key(&upstream.alias)
+            {
+                return Err(invalid_input(format!(
+                    "invalid or duplicate upstream provider alias {:?}",
+                    upstream.alias
+                )));
+            }
+            let client_secret =
+                required_secret(&secret_of, &upstream.client_secret_env, &realm.name)?;
+            let mut provider = UpstreamProvider::of(
+                upstream.alias,
+                upstream.issuer,
+                upstream.client_id,
+                client_secret,
+            );
+            if let Some(scopes) = upstream.scopes {
+                provider = provider.with_scopes(scopes);
+            }
+            realm = realm.with_upstream_provider(provider);
+        }
+        for client_config in config.clients {
+            let mut client = Client::of(client_config.client_id).with_scopes(client_config.scopes);
+            if client_config.require_consent {
+                client = client.with_required_consent();
+            }
+            if let Some(env_name) = &client_config.client_secret_env {
+                client = client.with_secret(required_secret(&secret_of, env_name, &realm.name)?);
+            }
+            if let Some(policy) = client_config.token_exchange {
+                if !client.is_confidential() {
+                    return Err(invalid_input(format!(
+                        "client {} exchanges tokens without a client_secret_env",
+                        client.client_id
+                    )));
+                }
+                client = client.with_token_exchange(policy);
+            }
+            check_mappers(&client_config.claim_mappers).map_err(|reason| {
+                invalid_input(format!("client {}: {}", client.client_id, reason))
+            })?;
+            client = client.with_claim_mappers(client_config.claim_mappers);
+            for uri in client_config
+                .post_logout_redirect_uris
+                .iter()
+                .chain(&client_config.backchannel_logout_uri)
+            {
+                if !is_http_url(uri) {
+                    return Err(invalid_input(format!(
+                        "client {}: invalid logout uri {:?}",
+                        client.client_id, uri
+                    )));
+                }
+            }
+            client = client.with_post_logout_redirect_uris(client_config.post_logout_redirect_uris);
+            match (
+                client_config.backchannel_logout_uri,
+                &client_config.backchannel_logout_secret_env,
+            ) {
+                (Some(uri), Some(env_name)) => {
+                    let secret = required_secret(&secret_of, env_name, &realm.name)?;
+                    client = client.with_backchannel_logout(uri, secret);
+                }
+                (None, None) => {}
+                _ => {
+                    return Err(invalid_input(format!(
+                        "client {} needs both backchannel_logout_uri and backchannel_logout_secret_env",
+                        client.client_id
+                    )));
+                }
+            }
+            if let Some(jwk) = &client_config.encryption_key {
+                let key = EncryptionKey::from_jwk(jwk).map_err(|reason| {
+                    invalid_input(format!("client {}: {}", client.client_id, reason))
+                })?;
+                client = client.with_encryption_key(key);
+            }
+            if let Some(limit) = client_config.groups_limit {
+                client = client.with_groups_limit(limit);
+            }
+            realm
+                .clients
+                .save(client)
+                .map_err(|err| invalid_input(err.to_string()))?;
+        }
+        for (audience, mappers) in config.audience_claim_mappers {
+            check_mappers(&mappers)
+                .map_err(|reason| invalid_input(format!("audience {}: {}", audience, reason)))?;
+            realm = realm.with_audience_claim_mappers(audience, mappers);
+        }
+        for service_provider in config.service_providers {
+            realm
+                .service_providers
+                .save(service_provider)
+                .map_err(|err| invalid_input(err.to_string()))?;
+        }
+        realms.push(realm);
+    }
+    Ok(realms)
+}
+
+fn apply_overrides(
+    mut policy: PasswordPolicy,
+    overrides: PolicyOverrides,
+) -> io::Result<PasswordPolicy> {
+    if let Some(min_length) = overrides.min_length {
+        policy.min_length = min_length;
+    }
+    if let Some(bits) = overrides.min_entropy_bits {
+        policy.min_entropy_bits = bits;
+    }
+    if let Some(classes) = overrides.character_classes {
+        policy.required_classes = classes
+            .iter()
+            .map(|name| {
+                CharacterClass::parse(name)
+                    .ok_or_else(|| invalid_input(format!("unknown character class {}", name)))
+            })
+            .collect::<io::Result<_>>()?;
+    }
+    if let Some(path) = overrides.breached_passwords_file {
+        policy = policy.with_breached_list(path)?;
+    }
+    Ok(policy)
+}
+
+// Realm names and provider aliases end up in URL paths.
+fn is_path_segment(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+// Without a fragment, which redirects and deliveries would drop.
+fn is_http_url(uri: &str) -> bool {
+    Url::parse(uri).is_ok_and(|url| {
+        matches!(url.scheme(), "http" | "https") && url.has_host() && url.fragment().is_none()
+    })
+}
+
+fn required_secret<F: Fn(&str) -> Option<String>>(
+    secret_of: &F,
+    env_name: &str,
+    realm: &str,
+) -> io::Result<String> {
+    secret_of(env_name)
+        .filter(|s| !s.is_empty())
+        .ok_or_else(|| invalid_input(format!("{} for realm {} is not set", env_name, realm)))
+}
+
+fn invalid_input(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, message)
+}
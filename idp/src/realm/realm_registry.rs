@@ -0,0 +1,131 @@
+//! Realms and their lookup.
+
+use std::{collections::BTreeMap, sync::Arc};
+
+use crate::{
+    broker::upstream_provider::UpstreamProvider,
+    domain::password_policy::PasswordPolicy,
+    error::my_error::{self, MyError},
+    store::{
+        broker_login_store::BrokerLoginStore, client_store::ClientStore,
+        consent_store::ConsentStore, device_grant_store::DeviceGrantStore,
+        email_login_store::EmailLoginStore, group_store::GroupStore,
+        service_provider_store::ServiceProviderStore, session_store::SessionStore,
+        user_store::UserStore, webauthn_ceremony_store::WebauthnCeremonyStore,
+    },
+    token::{claim_mapping::ClaimMapper, signing_key::SigningKeys},
+};
+
+pub const DEFAULT_REALM: &str = "default";
+
+/// An isolated tenant with its own issuer, keys, users, clients and policies.
+/// The issuer doubles as the SAML entity id of the realm.
+#[derive(Debug)]
+pub struct Realm {
+    pub name: String,
+    pub issuer: String,
+    pub keys: SigningKeys,
+    pub password_policy: PasswordPolicy,
+    pub users: UserStore,
+    pub clients: ClientStore,
+    pub groups: GroupStore,
+    pub service_providers: ServiceProviderStore,
+    /// Keyed by alias.
+    pub upstream_providers: BTreeMap<String, Arc<UpstreamProvider>>,
+    /// Custom claims of exchanged tokens, keyed by their audience.
+    pub audience_claim_mappers: BTreeMap<String, Vec<ClaimMapper>>,
+    pub sessions: SessionStore,
+    pub broker_logins: BrokerLoginStore,
+    pub webauthn_ceremonies: WebauthnCeremonyStore,
+    pub device_grants: DeviceGrantStore,
+    pub email_logins: EmailLoginStore,
+    pub consents: ConsentStore,
+}
+
+impl Realm {
+    pub fn of<N: Into<String>, I: Into<String>>(name: N, issuer: I, keys: SigningKeys) -> Self {
+        Self {
+            name: name.into(),
+            issuer: issuer.into(),
+            keys,
+            password_policy: PasswordPolicy::default(),
+            users: UserStore::default(),
+            clients: ClientStore::default(),
+            groups: GroupStore::default(),
+            service_providers: ServiceProviderStore::default(),
+            upstream_providers: BTreeMap::new(),
+            audience_claim_mappers: BTreeMap::new(),
+            sessions: SessionStore::default(),
+            broker_logins: BrokerLoginStore::default(),
+            webauthn_ceremonies: WebauthnCeremonyStore::default(),
+            device_grants: DeviceGrantStore::default(),
+            email_logins: EmailLoginStore::default(),
+            consents: ConsentStore::default(),
+        }
+    }
+
+    pub fn with_password_policy(mut self, password_policy: PasswordPolicy) -> Self {
+        self.password_policy = password_policy;
+        self
+    }
+
+    pub fn with_upstream_provider(mut self, provider: UpstreamProvider) -> Self {
+        self.upstream_providers
+            .insert(provider.alias.clone(), Arc::new(provider));
+        self
+    }
+
+    pub fn with_audience_claim_mappers<A: Into<String>>(
+        mut self,
+        audience: A,
+        claim_mappers: Vec<ClaimMapper>,
+    ) -> Self {
+        self.audience_claim_mappers
+            .insert(audience.into(), claim_mappers);
+        self
+    }
+
+    /// Custom claims of an exchanged token for `audience`.
+    pub fn audience_mappers(&self, audience: &str) -> &[ClaimMapper] {
+        self.audience_claim_mappers
+            .get(audience)
+            .map_or(&[], Vec::as_slice)
+    }
+}
+
+/// Every realm served by this idp, one of which answers the unscoped routes.
+#[derive(Debug)]
+pub struct RealmRegistry {
+    realms: BTreeMap<String, Arc<Realm>>,
+    default_realm: String,
+}
+
+impl RealmRegistry {
+    /// The first realm becomes the default one.
+    pub fn of(realms: Vec<Realm>) -> my_error::Result<Self> {
+        let default_realm = realms.first().ok_or(MyError::InvalidValue)?.name.clone();
+        let mut registry = Self {
+            realms: BTreeMap::new(),
+            default_realm,
+        };
+        for realm in realms {
+            if registry.realms.contains_key(&realm.name) {
+                return Err(MyError::AlreadyExists);
+            }
+            registry.realms.insert(realm.name.clone(), Arc::new(realm));
+        }
+        Ok(registry)
+    }
+
+    pub fn find(&self, name: &str) -> Option<Arc<Realm>> {
+        self.realms.get(name).cloned()
+    }
+
+    pub fn default_realm(&self) -> Arc<Realm> {
+        self.realms[&self.default_realm].clone()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Arc<Realm>> {
+        self.realms.values()
+    }
+}
@@ -0,0 +1,314 @@
+//! Realm state.
+//!
+//! What `idp-admin` changes is kept in the file pointed to by `IDP_STATE_FILE`
+//! and applied to the realms when the server starts. The server writes to it
+//! only to keep password hashes it upgraded at login.
+
+use std::{
+    collections::BTreeMap,
+    env,
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    domain::mail_address::MailAddress,
+    entity::{
+        client::Client,
+        group::{is_group_name, Group},
+        user::User,
+    },
+    realm::realm_registry::Realm,
+    token::signing_key::{SigningKey, SigningKeys},
+};
+
+const STATE_FILE_ENV: &str = "IDP_STATE_FILE";
+
+/// Shape of the file pointed to by `IDP_STATE_FILE`, keyed by realm name.
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub struct StateFile {
+    #[serde(default)]
+    pub realms: BTreeMap<String, RealmState>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub struct RealmState {
+    #[serde(default)]
+    pub users: Vec<UserRecord>,
+    #[serde(default)]
+    pub clients: Vec<ClientRecord>,
+    /// Newest first, the first one signs.
+    #[serde(default)]
+    pub signing_keys: Vec<KeyRecord>,
+    #[serde(default)]
+    pub groups: Vec<Group>,
+    /// Kids of keys that no longer verify, configured ones included.
+    #[serde(default)]
+    pub retired_keys: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct UserRecord {
+    pub email: MailAddress,
+    /// PHC string of the password.
+    pub passwd_hash: Option<String>,
+    #[serde(default)]
+    pub attributes: BTreeMap<String, String>,
+    #[serde(default)]
+    pub disabled: bool,
+}
+
+/// A client registered by `idp-admin`, next to those of the realms file.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct ClientRecord {
+    pub client_id: String,
+    /// SHA-256 of the client secret, hex, for confidential clients.
+    pub secret_digest: Option<String>,
+    #[serde(default)]
+    pub scopes: Vec<String>,
+    #[serde(default)]
+    pub require_consent: bool,
+    pub groups_limit: Option<usize>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct KeyRecord {
+    pub kid: String,
+    pub secret: String,
+    pub created_at: DateTime<Utc>,
+}
+
+impl StateFile {
+    /// The path in `IDP_STATE_FILE`, if it is set.
+    pub fn path_from_env() -> Option<PathBuf> {
+        env::var_os(STATE_FILE_ENV).map(PathBuf::from)
+    }
+
+    /// A file that does not exist yet is an empty state.
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        match fs::read(path) {
+            Ok(bytes) => serde_json::from_slice(&bytes).map_err(io::Error::other),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Takes the advisory lock on `<path>.lock`, held until the returned file
+    /// is dropped. The server and `idp-admin` hold it from load to save, so
+    /// that neither overwrites what the other wrote in between.
+    pub fn lock<P: AsRef<Path>>(path: P) -> io::Result<File> {
+        let mut lock_path = path.as_ref().as_os_str().to_owned();
+        lock_path.push(".lock");
+        let mut options = OpenOptions::new();
+        options.write(true).create(true).truncate(false);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        let file = options.open(PathBuf::from(lock_path))?;
+        file.lock()?;
+        Ok(file)
+    }
+
+    /// Replaces the file in one step, readable by the owner only since it
+    /// holds signing secrets.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let path = path.as_ref();
+        let mut temp = path.as_os_str().to_owned();
+        temp.push(".tmp");
+        let temp = PathBuf::from(temp);
+        let mut options = OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        let mut file = options.open(&temp)?;
+        file.write_all(&serde_json::to_vec_pretty(self).map_err(io::Error::other)?)?;
+        file.sync_all()?;
+        fs::rename(&temp, path)
+    }
+
+    /// Writes a password hash upgraded at login back to the file, so that it
+    /// survives a restart. A user the file does not hold, or whose hash was
+    /// changed there since, is left alone. Answers whether it was written.
+    pub fn upgrade_passwd_hash<P: AsRef<Path>>(
+        path: P,
+        realm: &str,
+        email: &MailAddress,
+        verified_hash: &str,
+        upgraded: &str,
+    ) -> io::Result<bool> {
+        let _lock = Self::lock(&path)?;
+        let mut state = Self::load(&path)?;
+        let Some(record) = state
+            .realms
+            .get_mut(realm)
+            .and_then(|state| state.user_mut(email))
+        else {
+            return Ok(false);
+        };
+        if record.passwd_hash.as_deref() != Some(verified_hash) {
+            return Ok(false);
+        }
+        record.passwd_hash = Some(upgraded.to_owned());
+        state.save(&path)?;
+        Ok(true)
+    }
+
+    pub fn realm_mut(&mut self, name: &str) -> &mut RealmState {
+        self.realms.entry(name.to_owned()).or_default()
+    }
+
+    /// Adds the users, clients and keys to the realms they belong to.
+    pub fn apply(&self, realms: &mut [Realm]) -> io::Result<()> {
+        for (name, state) in &self.realms {
+            let realm = realms
+                .iter_mut()
+                .find(|realm| realm.name == *name)
+                .ok_or_else(|| invalid_data(format!("state of unknown realm {}", name)))?;
+            state.apply(realm)?;
+        }
+        Ok(())
+    }
+}
+
+impl RealmState {
+    fn apply(&self, realm: &mut Realm) -> io::Result<()> {
+        if !self.signing_keys.is_empty() {
+            realm.keys = self.signing_keys(&realm.keys)?;
+        }
+        for record in &self.users {
+            realm
+                .users
+                .save(record.to_user())
+                .map_err(|err| invalid_data(err.to_string()))?;
+        }
+        for record in &self.clients {
+            let configured = realm
+                .clients
+                .find(&record.client_id)
+                .map_err(|err| invalid_data(err.to_string()))?;
+            if configured.is_some() {
+                return Err(invalid_data(format!(
+                    "client {} of realm {} is in the realms file too",
+                    record.client_id, realm.name
+                )));
+            }
+            realm
+                .clients
+                .save(record.to_client())
+                .map_err(|err| invalid_data(err.to_string()))?;
+        }
+        for group in &self.groups {
+            let unknown = group
+                .subgroups
+                .iter()
+                .find(|name| self.group(name).is_none());
+            if !is_group_name(&group.name) || unknown.is_some() {
+                return Err(invalid_data(format!(
+                    "group {:?} of realm {} is invalid or nests an unknown group",
+                    group.name, realm.name
+                )));
+            }
+            realm
+                .groups
+                .save(group.clone())
+                .map_err(|err| invalid_data(err.to_string()))?;
+        }
+        Ok(())
+    }
+
+    /// The rotated keys, followed by the configured one so that tokens it
+    /// signed before the first rotation still verify, until it is retired.
+    pub fn signing_keys(&self, configured: &SigningKeys) -> io::Result<SigningKeys> {
+        let mut keys: Vec<SigningKey> = self
+            .signing_keys
+            .iter()
+            .map(|record| SigningKey::of(record.secret.clone()).with_created_at(record.created_at))
+            .collect();
+        for (record, key) in self.signing_keys.iter().zip(&keys) {
+            if record.kid != key.kid {
+                return Err(invalid_data(format!(
+                    "signing key {} does not match its secret",
+                    record.kid
+                )));
+            }
+        }
+        for key in configured.iter() {
+            if !keys.iter().any(|known| known.kid == key.kid)
+                && !self.retired_keys.contains(&key.kid)
+            {
+                keys.push(key.clone());
+            }
+        }
+        SigningKeys::from_keys(keys).map_err(|err| invalid_data(err.to_string()))
+    }
+
+    pub fn user_mut(&mut self, email: &MailAddress) -> Option<&mut UserRecord> {
+        self.users.iter_mut().find(|user| user.email == *email)
+    }
+
+    pub fn group(&self, name: &str) -> Option<&Group> {
+        self.groups.iter().find(|group| group.name == name)
+    }
+
+    pub fn group_mut(&mut self, name: &str) -> Option<&mut Group> {
+        self.groups.iter_mut().find(|group| group.name == name)
+    }
+}
+
+impl UserRecord {
+    pub fn of(email: MailAddress, passwd_hash: String) -> Self {
+        Self {
+            email,
+            passwd_hash: Some(passwd_hash),
+            attributes: BTreeMap::new(),
+            disabled: false,
+        }
+    }
+
+    pub fn to_user(&self) -> User {
+        let mut user = User::of(self.email.clone());
+        user.passwd_hash = self.passwd_hash.clone();
+        user.attributes = self.attributes.clone();
+        user.disabled = self.disabled;
+        user
+    }
+}
+
+impl ClientRecord {
+    pub fn to_client(&self) -> Client {
+        let mut client = Client::of(self.client_id.clone()).with_scopes(self.scopes.clone());
+        if let Some(digest) = &self.secret_digest {
+            client = client.with_secret_digest(digest.clone());
+        }
+        if self.require_consent {
+            client = client.with_required_consent();
+        }
+        if let Some(limit) = self.groups_limit {
+            client = client.with_groups_limit(limit);
+        }
+        client
+    }
+}
+
+impl KeyRecord {
+    /// A key generated by rotation.
+    pub fn of(key: &SigningKey) -> Self {
+        Self {
+            kid: key.kid.clone(),
+            secret: key.secret().to_owned(),
+            created_at: key.created_at.unwrap_or_else(Utc::now),
+        }
+    }
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
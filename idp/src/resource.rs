@@ -1,4 +1,21 @@
+pub mod access_token;
+pub mod broker_resource;
+pub mod consent_resource;
+pub mod current_realm;
+pub mod device_resource;
+pub mod dpop;
+pub mod email_login_resource;
+pub mod health_resource;
 pub mod hello_html;
 pub mod hello_resource;
+pub mod html_page;
 pub mod idp_resource;
+pub mod login_resource;
+pub mod logout_resource;
 pub mod model;
+pub mod openapi_resource;
+pub mod saml_resource;
+pub mod session_cookie;
+pub mod token_resource;
+pub mod validated_json;
+pub mod webauthn_resource;
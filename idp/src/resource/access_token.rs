@@ -0,0 +1,66 @@
+//! Access token extractor.
+
+use std::future::{ready, Ready};
+
+use actix_web::{dev::Payload, http::header, web, FromRequest, HttpRequest};
+
+use crate::{
+    app_state::AppState,
+    domain::mail_address::MailAddress,
+    entity::user::User,
+    error::my_error::{self, MyError},
+    resource::{current_realm::CurrentRealm, dpop::check_binding},
+    token::{
+        dpop::DPOP_TOKEN_TYPE,
+        jwt::{decode_access_token, Claims},
+    },
+};
+
+/// A valid token of the realm's user in `Authorization`, with the `Bearer`
+/// scheme, or with `DPoP` and a proof when it is bound to a key.
+#[derive(Debug)]
+pub struct AccessToken {
+    pub claims: Claims,
+    pub user: User,
+}
+
+impl FromRequest for AccessToken {
+    type Error = MyError;
+    type Future = Ready<Result<Self, MyError>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        ready(access_token(req))
+    }
+}
+
+fn access_token(req: &HttpRequest) -> my_error::Result<AccessToken> {
+    let state = req
+        .app_data::<web::Data<AppState>>()
+        .ok_or(MyError::Internal)?;
+    let realm = CurrentRealm::of_request(req)?;
+    let (scheme, token) = req
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split_once(' '))
+        .ok_or(MyError::Unauthorized)?;
+    let claims = decode_access_token(&realm.keys, &realm.issuer, token)?;
+    // Bound tokens sent as bearer tokens would skip the proof.
+    let expected_scheme = match claims.confirmation() {
+        Some(_) => DPOP_TOKEN_TYPE,
+        None => "Bearer",
+    };
+    if !scheme.eq_ignore_ascii_case(expected_scheme) {
+        return Err(MyError::Unauthorized);
+    }
+    check_binding(req, state, &realm, token, &claims)?;
+    if state.denylist.rejects(token, &claims)? {
+        return Err(MyError::Unauthorized);
+    }
+    let email = MailAddress::of(claims.subject()).map_err(|_| MyError::Unauthorized)?;
+    let user = realm.users.find(&email)?.unwrap_or_else(|| User::of(email));
+    if user.disabled {
+        return Err(MyError::Unauthorized);
+    }
+    Ok(AccessToken { claims, user })
+}
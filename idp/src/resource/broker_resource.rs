@@ -0,0 +1,163 @@
+//! Broker Resource.
+
+use std::sync::Arc;
+
+use actix_web::{
+    cookie::{time, Cookie, SameSite},
+    http::header,
+    web, HttpRequest, HttpResponse,
+};
+use serde::Deserialize;
+use utoipa::IntoParams;
+
+use crate::{
+    broker::{
+        account_link::link_account,
+        broker_login::{BrokerLogin, BROKER_LOGIN_LIFETIME_MINUTES},
+        upstream_provider::UpstreamProvider,
+    },
+    error::{
+        my_error::{self, MyError},
+        problem::ProblemDetails,
+    },
+    realm::realm_registry::Realm,
+    resource::{
+        current_realm::CurrentRealm, login_resource::sign_in_or_ask_passkey,
+        model::response_model::SingInResponse,
+    },
+    webauthn::options::RequestOptions,
+};
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Path)]
+pub struct BrokerPath {
+    /// Alias of the upstream provider in the realm.
+    alias: String,
+}
+
+/// Authorization response of the upstream provider.
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct BrokerCallbackParams {
+    state: String,
+    code: Option<String>,
+    error: Option<String>,
+}
+
+// One cookie per realm, like the session cookie.
+fn broker_login_cookie_name(realm: &Realm) -> String {
+    format!("idp_broker_login_{}", realm.name)
+}
+
+// Binds the state to the browser that started the login, so that nobody can
+// hand theirs to another browser. Lax, as the upstream provider sends the
+// browser back with a top-level GET.
+fn broker_login_cookie(realm: &Realm, login: &BrokerLogin) -> Cookie<'static> {
+    Cookie::build(broker_login_cookie_name(realm), login.state.clone())
+        .path("/")
+        .http_only(true)
+        .secure(realm.issuer.starts_with("https://"))
+        .same_site(SameSite::Lax)
+        .max_age(time::Duration::minutes(BROKER_LOGIN_LIFETIME_MINUTES))
+        .finish()
+}
+
+#[utoipa::path(
+    get,
+    path = "/broker/{alias}/login",
+    tag = "broker",
+    params(BrokerPath),
+    responses(
+        (status = 302, description = "Redirect to the upstream authorization endpoint, with the cookie that binds the login to the browser"),
+        (status = 404, description = "Realm or upstream provider does not exist", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 502, description = "Upstream discovery failed", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
+#[tracing::instrument(skip_all)]
+pub async fn broker_login_handler(
+    realm: CurrentRealm,
+    path: web::Path<BrokerPath>,
+) -> my_error::Result<HttpResponse> {
+    let provider = upstream_provider(&realm, &path.alias)?;
+    let metadata = {
+        let provider = provider.clone();
+        web::block(move || provider.metadata()).await??
+    };
+    let login = BrokerLogin::start(&provider.alias)?;
+    let location =
+        provider.authorization_url(&metadata, &callback_url(&realm, &provider), &login)?;
+    let cookie = broker_login_cookie(&realm, &login);
+    realm.broker_logins.save(login)?;
+    Ok(HttpResponse::Found()
+        .insert_header((header::LOCATION, location))
+        .insert_header((header::CACHE_CONTROL, "no-store"))
+        .cookie(cookie)
+        .finish())
+}
+
+#[utoipa::path(
+    get,
+    path = "/broker/{alias}/callback",
+    tag = "broker",
+    params(BrokerPath, BrokerCallbackParams),
+    responses(
+        (status = 200, description = "Signed in, the session cookie is set", body = SingInResponse),
+        (status = 202, description = "Upstream login accepted, a passkey has to follow at /webauthn/login", body = RequestOptions),
+        (status = 400, description = "Unknown, reused or expired state, or one started in another browser", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Login refused, ID token invalid or mail address not verified", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Realm or upstream provider does not exist", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 502, description = "Upstream token endpoint failed", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
+#[tracing::instrument(skip_all)]
+pub async fn broker_callback_handler(
+    req: HttpRequest,
+    realm: CurrentRealm,
+    path: web::Path<BrokerPath>,
+    params: web::Query<BrokerCallbackParams>,
+) -> my_error::Result<HttpResponse> {
+    let provider = upstream_provider(&realm, &path.alias)?;
+    req.cookie(&broker_login_cookie_name(&realm))
+        .filter(|cookie| cookie.value() == params.state)
+        .ok_or(MyError::InvalidValue)?;
+    let login = realm
+        .broker_logins
+        .take(&params.state)?
+        .filter(|login| login.provider == provider.alias)
+        .ok_or(MyError::InvalidValue)?;
+    if let Some(error) = &params.error {
+        tracing::info!(provider = %provider.alias, %error, "upstream refused the login");
+        return Err(MyError::Unauthorized);
+    }
+    let code = params.code.clone().ok_or(MyError::InvalidValue)?;
+    let redirect_uri = callback_url(&realm, &provider);
+    let identity = {
+        let provider = provider.clone();
+        web::block(move || provider.redeem(&code, &redirect_uri, &login)).await??
+    };
+
+    let user = link_account(&realm.users, &provider.alias, identity)?;
+    let mut res = sign_in_or_ask_passkey(&realm, user)?;
+    let mut removed = Cookie::build(broker_login_cookie_name(&realm), "")
+        .path("/")
+        .finish();
+    removed.make_removal();
+    res.add_cookie(&removed).map_err(|_| MyError::Internal)?;
+    Ok(res)
+}
+
+fn upstream_provider(realm: &Realm, alias: &str) -> my_error::Result<Arc<UpstreamProvider>> {
+    realm
+        .upstream_providers
+        .get(alias)
+        .cloned()
+        .ok_or(MyError::NotFound)
+}
+
+fn callback_url(realm: &Realm, provider: &UpstreamProvider) -> String {
+    format!(
+        "{}/broker/{}/callback",
+        realm.issuer.trim_end_matches('/'),
+        provider.alias
+    )
+}
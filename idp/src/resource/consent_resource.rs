@@ -0,0 +1,138 @@
+//! Consent Resource.
+
+use actix_web::{http::header, web, HttpRequest, HttpResponse};
+use serde::Deserialize;
+use utoipa::ToSchema;
+
+use crate::{
+    entity::consent::Consent,
+    error::{
+        my_error::{self, MyError},
+        problem::ProblemDetails,
+    },
+    i18n::{
+        catalog::{format_message, message},
+        locale::Locale,
+    },
+    resource::{
+        current_realm::CurrentRealm,
+        html_page::{escape_html, html},
+        session_cookie::current_session,
+    },
+};
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct RevokeConsentForm {
+    client_id: String,
+}
+
+#[utoipa::path(
+    get,
+    path = "/consents",
+    tag = "idp",
+    responses(
+        (status = 200, description = "Clients the signed-in user allowed scopes, each with a button to revoke", body = String, content_type = "text/html"),
+        (status = 401, description = "The browser has no session", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Realm does not exist", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
+#[tracing::instrument(skip_all)]
+pub async fn consents_page_handler(
+    req: HttpRequest,
+    realm: CurrentRealm,
+    locale: Locale,
+) -> my_error::Result<HttpResponse> {
+    let session = current_session(&req, &realm)?.ok_or(MyError::Unauthorized)?;
+    let consents = realm.consents.list(&session.email)?;
+    Ok(html(consents_page(locale, &consents)))
+}
+
+#[utoipa::path(
+    post,
+    path = "/consents/revoke",
+    tag = "idp",
+    request_body(content = RevokeConsentForm, content_type = "application/x-www-form-urlencoded"),
+    responses(
+        (status = 303, description = "Revoked, back to the consents page"),
+        (status = 401, description = "The browser has no session", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Realm does not exist, or the user gave the client no consent", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
+#[tracing::instrument(skip_all)]
+pub async fn revoke_consent_handler(
+    req: HttpRequest,
+    realm: CurrentRealm,
+    form: web::Form<RevokeConsentForm>,
+) -> my_error::Result<HttpResponse> {
+    let session = current_session(&req, &realm)?.ok_or(MyError::Unauthorized)?;
+    if !realm.consents.revoke(&session.email, &form.client_id)? {
+        return Err(MyError::NotFound);
+    }
+    Ok(HttpResponse::SeeOther()
+        .insert_header((
+            header::LOCATION,
+            format!("{}/consents", realm.issuer.trim_end_matches('/')),
+        ))
+        .finish())
+}
+
+fn consents_page(locale: Locale, consents: &[Consent]) -> String {
+    let items: String = consents
+        .iter()
+        .map(|consent| {
+            let scopes = consent
+                .scopes
+                .iter()
+                .map(String::as_str)
+                .collect::<Vec<_>>()
+                .join(", ");
+            let scopes = if scopes.is_empty() {
+                message(locale, "page.consents.sign_in_only")
+            } else {
+                scopes
+            };
+            format!(
+                r#"
+    <li>
+      <form method="post" action="consents/revoke">
+        {}
+        <input type="hidden" name="client_id" value="{}" />
+        <button type="submit">{}</button>
+      </form>
+    </li>"#,
+                format_message(
+                    locale,
+                    "page.consents.item",
+                    &[
+                        ("client", &escape_html(&consent.client_id)),
+                        ("date", &consent.granted_at.format("%Y-%m-%d").to_string()),
+                        ("scopes", &escape_html(&scopes)),
+                    ]
+                ),
+                escape_html(&consent.client_id),
+                message(locale, "page.consents.revoke")
+            )
+        })
+        .collect();
+    let list = if consents.is_empty() {
+        format!("<p>{}</p>", message(locale, "page.consents.none"))
+    } else {
+        format!("<ul>{}\n  </ul>", items)
+    };
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="{}">
+<head>
+  <meta charset="utf-8" />
+  <title>idp - {}</title>
+</head>
+<body>
+  {}
+</body>
+</html>
+"#,
+        locale.tag(),
+        message(locale, "page.consents.title"),
+        list
+    )
+}
@@ -0,0 +1,46 @@
+//! Realm extractor.
+
+use std::{
+    future::{ready, Ready},
+    ops::Deref,
+    sync::Arc,
+};
+
+use actix_web::{dev::Payload, web, FromRequest, HttpRequest};
+
+use crate::{app_state::AppState, error::my_error::MyError, realm::realm_registry::Realm};
+
+/// The realm named by the `{realm}` path segment, or the default realm
+/// on routes that are not realm-scoped.
+#[derive(Debug, Clone)]
+pub struct CurrentRealm(pub Arc<Realm>);
+
+impl Deref for CurrentRealm {
+    type Target = Realm;
+
+    fn deref(&self) -> &Realm {
+        &self.0
+    }
+}
+
+impl CurrentRealm {
+    /// For extractors that need the realm too.
+    pub fn of_request(req: &HttpRequest) -> Result<Self, MyError> {
+        let realm = req.app_data::<web::Data<AppState>>().and_then(|state| {
+            match req.match_info().get("realm") {
+                Some(name) => state.realms.find(name),
+                None => Some(state.realms.default_realm()),
+            }
+        });
+        realm.map(CurrentRealm).ok_or(MyError::NotFound)
+    }
+}
+
+impl FromRequest for CurrentRealm {
+    type Error = MyError;
+    type Future = Ready<Result<Self, MyError>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        ready(Self::of_request(req))
+    }
+}
@@ -0,0 +1,378 @@
+//! Device Resource.
+
+use actix_web::{web, HttpRequest, HttpResponse};
+use serde::Deserialize;
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{
+    domain::mail_address::MailAddress,
+    entity::{consent::Consent, session::Session},
+    error::{
+        my_error::{self, MyError},
+        oauth_error::OAuthError,
+        problem::ProblemDetails,
+    },
+    i18n::{
+        catalog::{format_message, message},
+        locale::Locale,
+    },
+    oauth::device_grant::{
+        normalize_user_code, Approval, DeviceGrant, DeviceGrantStatus, DEVICE_CODE_LIFETIME_SECS,
+    },
+    realm::realm_registry::Realm,
+    resource::{
+        current_realm::CurrentRealm,
+        html_page::{escape_html, html},
+        login_resource::verify_single_factor,
+        model::response_model::DeviceAuthorizationResponse,
+        session_cookie::{current_session, session_cookie},
+        token_resource::authenticate_client,
+    },
+};
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct DeviceAuthorizationForm {
+    client_id: Option<String>,
+    /// Confidential clients send it here or with HTTP Basic.
+    client_secret: Option<String>,
+    /// Space separated, out of the scopes the client may ask for.
+    scope: Option<String>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct DevicePageParams {
+    /// Filled in when the user came through `verification_uri_complete`.
+    user_code: Option<String>,
+}
+
+/// Submitted by the device page. The mail address and password are only
+/// asked when the browser has no session.
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct DeviceVerificationForm {
+    user_code: String,
+    email: Option<String>,
+    passwd: Option<String>,
+    /// `approve` or `deny`.
+    action: String,
+    /// Sent by the consent page, the scopes the user allowed.
+    consent: Option<String>,
+}
+
+#[utoipa::path(
+    post,
+    path = "/device_authorization",
+    tag = "oauth",
+    request_body(content = DeviceAuthorizationForm, content_type = "application/x-www-form-urlencoded"),
+    responses(
+        (status = 200, description = "Device and user code issued, the device polls /token", body = DeviceAuthorizationResponse),
+        (status = 400, description = "client_id is missing, or a scope the client may not ask for", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Client is not registered in the realm or failed to authenticate", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Realm does not exist", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
+#[tracing::instrument(skip_all)]
+pub async fn device_authorization_handler(
+    req: HttpRequest,
+    realm: CurrentRealm,
+    form: web::Form<DeviceAuthorizationForm>,
+) -> my_error::Result<HttpResponse> {
+    let client = authenticate_client(
+        &req,
+        &realm,
+        form.client_id.as_deref(),
+        form.client_secret.as_deref(),
+    )?;
+    let mut scopes: Vec<String> = vec![];
+    for scope in form.scope.iter().flat_map(|scope| scope.split_whitespace()) {
+        if !client.scopes.iter().any(|allowed| allowed == scope) {
+            return Err(MyError::OAuth(OAuthError::InvalidScope));
+        }
+        if !scopes.iter().any(|requested| requested == scope) {
+            scopes.push(scope.to_owned());
+        }
+    }
+    let grant = DeviceGrant::start(client.client_id, scopes)?;
+    let verification_uri = format!("{}/device", realm.issuer.trim_end_matches('/'));
+    let res = DeviceAuthorizationResponse {
+        device_code: grant.device_code.clone(),
+        user_code: grant.user_code.clone(),
+        verification_uri_complete: format!("{}?user_code={}", verification_uri, grant.user_code),
+        verification_uri,
+        expires_in: DEVICE_CODE_LIFETIME_SECS,
+        interval: grant.interval_secs,
+    };
+    realm.device_grants.save(grant)?;
+    Ok(HttpResponse::Ok().json(res))
+}
+
+#[utoipa::path(
+    get,
+    path = "/device",
+    tag = "oauth",
+    params(DevicePageParams),
+    responses(
+        (status = 200, description = "Page to enter the user code, or the client and scopes of a pending grant to approve", body = String, content_type = "text/html"),
+        (status = 404, description = "Realm does not exist", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
+#[tracing::instrument(skip_all)]
+pub async fn device_page_handler(
+    req: HttpRequest,
+    realm: CurrentRealm,
+    locale: Locale,
+    params: web::Query<DevicePageParams>,
+) -> my_error::Result<HttpResponse> {
+    let signed_in = current_session(&req, &realm)?.is_some();
+    let user_code = params.user_code.as_deref().map(normalize_user_code);
+    // Approving is offered only next to what the device asks for.
+    let page = match &user_code {
+        Some(user_code) => match realm.device_grants.find_pending(user_code)? {
+            Some(grant) => approval_page(locale, &grant, signed_in),
+            None => device_page(locale, Some(user_code)),
+        },
+        None => device_page(locale, None),
+    };
+    Ok(html(page))
+}
+
+#[utoipa::path(
+    post,
+    path = "/device",
+    tag = "oauth",
+    request_body(content = DeviceVerificationForm, content_type = "application/x-www-form-urlencoded"),
+    responses(
+        (status = 200, description = "The device is approved or denied, or the consent page when a third-party client needs the user to allow its scopes", body = String, content_type = "text/html"),
+        (status = 400, description = "Mail address and password are missing, or unknown action", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Wrong mail address or password", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Realm does not exist, or the user code is unknown, used or expired", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
+#[tracing::instrument(skip_all)]
+pub async fn device_verification_handler(
+    req: HttpRequest,
+    realm: CurrentRealm,
+    locale: Locale,
+    form: web::Form<DeviceVerificationForm>,
+) -> my_error::Result<HttpResponse> {
+    let DeviceVerificationForm {
+        user_code,
+        email,
+        passwd,
+        action,
+        consent,
+    } = form.into_inner();
+    let grant = realm
+        .device_grants
+        .find_pending(&normalize_user_code(&user_code))?
+        .ok_or(MyError::NotFound)?;
+    let session = current_session(&req, &realm)?;
+    let email = match &session {
+        Some(session) => session.email.clone(),
+        None => {
+            let (email, passwd) = email.zip(passwd).ok_or(MyError::InvalidValue)?;
+            let email = MailAddress::of(email).map_err(|_| MyError::Unauthorized)?;
+            verify_single_factor(&realm, &email, passwd).await?.email
+        }
+    };
+    let (status, message_id) = match action.as_str() {
+        "approve" => {
+            let client = realm
+                .clients
+                .find(&grant.client_id)?
+                .ok_or(MyError::NotFound)?;
+            if client.require_consent {
+                let allowed = consent.as_deref() == Some(grant.scopes.join(" ").as_str());
+                let granted = realm
+                    .consents
+                    .find(&email, &client.client_id)?
+                    .is_some_and(|granted| granted.covers(&grant.scopes));
+                if !allowed && !granted {
+                    return consent_step(&realm, locale, session, email, &grant);
+                }
+                realm.consents.grant(Consent::of(
+                    email.clone(),
+                    &client.client_id,
+                    &grant.scopes,
+                ))?;
+            }
+            // The device takes part in the session, and is logged out with it.
+            if let Some(session) = &session {
+                realm.sessions.add_client(&session.id, &grant.client_id)?;
+            }
+            (
+                DeviceGrantStatus::Approved(Approval {
+                    email,
+                    session_id: session.map(|session| session.id),
+                    scopes: grant.scopes,
+                }),
+                "page.device.approved",
+            )
+        }
+        "deny" => (DeviceGrantStatus::Denied, "page.device.denied"),
+        _ => return Err(MyError::InvalidValue),
+    };
+    realm.device_grants.answer(&grant.device_code, status)?;
+    Ok(html(done_page(locale, message_id)))
+}
+
+// Asked once the user is known. A password sign-in starts a session here,
+// so that allowing does not take the password again.
+fn consent_step(
+    realm: &Realm,
+    locale: Locale,
+    session: Option<Session>,
+    email: MailAddress,
+    grant: &DeviceGrant,
+) -> my_error::Result<HttpResponse> {
+    let mut res = html(consent_page(locale, grant));
+    if session.is_none() {
+        let session = Session::start(email)?;
+        realm.sessions.save(session.clone())?;
+        res.add_cookie(&session_cookie(realm, &session))
+            .map_err(|_| MyError::Internal)?;
+    }
+    Ok(res)
+}
+
+fn consent_page(locale: Locale, grant: &DeviceGrant) -> String {
+    let scopes: String = grant
+        .scopes
+        .iter()
+        .map(|scope| format!("\n    <li>{}</li>", escape_html(scope)))
+        .collect();
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="{}">
+<head>
+  <meta charset="utf-8" />
+  <title>idp - {}</title>
+</head>
+<body>
+  <p>{}</p>
+  <ul>{}
+  </ul>
+  <form method="post" action="device">
+    <input type="hidden" name="user_code" value="{}" />
+    <input type="hidden" name="consent" value="{}" />
+    <button type="submit" name="action" value="approve">{}</button>
+    <button type="submit" name="action" value="deny">{}</button>
+  </form>
+</body>
+</html>
+"#,
+        locale.tag(),
+        message(locale, "page.consent.title"),
+        format_message(
+            locale,
+            "page.consent.asks",
+            &[("client", &escape_html(&grant.client_id))]
+        ),
+        scopes,
+        escape_html(&grant.user_code),
+        escape_html(&grant.scopes.join(" ")),
+        message(locale, "page.consent.allow"),
+        message(locale, "page.deny")
+    )
+}
+
+fn device_page(locale: Locale, user_code: Option<&str>) -> String {
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="{}">
+<head>
+  <meta charset="utf-8" />
+  <title>idp - {}</title>
+</head>
+<body>
+  <form method="get" action="device">
+    <label>{} <input type="text" name="user_code" value="{}" autocomplete="off" required /></label>
+    <button type="submit">{}</button>
+  </form>
+</body>
+</html>
+"#,
+        locale.tag(),
+        message(locale, "page.device.title"),
+        message(locale, "page.device.user_code"),
+        escape_html(user_code.unwrap_or_default()),
+        message(locale, "page.device.continue")
+    )
+}
+
+fn approval_page(locale: Locale, grant: &DeviceGrant, signed_in: bool) -> String {
+    let scopes: String = if grant.scopes.is_empty() {
+        format!(
+            "\n    <li>{}</li>",
+            message(locale, "page.consents.sign_in_only")
+        )
+    } else {
+        grant
+            .scopes
+            .iter()
+            .map(|scope| format!("\n    <li>{}</li>", escape_html(scope)))
+            .collect()
+    };
+    let credentials = if signed_in {
+        String::new()
+    } else {
+        format!(
+            r#"<label>{} <input type="email" name="email" autocomplete="username" required /></label>
+    <label>{} <input type="password" name="passwd" autocomplete="current-password" required /></label>"#,
+            message(locale, "page.email"),
+            message(locale, "page.password")
+        )
+    };
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="{}">
+<head>
+  <meta charset="utf-8" />
+  <title>idp - {}</title>
+</head>
+<body>
+  <p>{}</p>
+  <ul>{}
+  </ul>
+  <form method="post" action="device">
+    <input type="hidden" name="user_code" value="{}" />
+    {}
+    <button type="submit" name="action" value="approve">{}</button>
+    <button type="submit" name="action" value="deny">{}</button>
+  </form>
+</body>
+</html>
+"#,
+        locale.tag(),
+        message(locale, "page.device.title"),
+        format_message(
+            locale,
+            "page.consent.asks",
+            &[("client", &escape_html(&grant.client_id))]
+        ),
+        scopes,
+        escape_html(&grant.user_code),
+        credentials,
+        message(locale, "page.device.approve"),
+        message(locale, "page.deny")
+    )
+}
+
+fn done_page(locale: Locale, message_id: &str) -> String {
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="{}">
+<head>
+  <meta charset="utf-8" />
+  <title>idp - {}</title>
+</head>
+<body>
+  <p>{}</p>
+</body>
+</html>
+"#,
+        locale.tag(),
+        message(locale, "page.device.title"),
+        message(locale, message_id)
+    )
+}
@@ -0,0 +1,80 @@
+//! DPoP checks of requests.
+
+use actix_web::HttpRequest;
+use url::Url;
+
+use crate::{
+    app_state::AppState,
+    error::{
+        my_error::{self, MyError},
+        oauth_error::OAuthError,
+    },
+    realm::realm_registry::Realm,
+    token::{
+        dpop::{verify_proof, DpopProof, DPOP_HEADER},
+        jwt::Claims,
+    },
+};
+
+/// The proof in the `DPoP` header, if the request has one. It has to be for
+/// this request and, at resources, for `access_token`, and is accepted once.
+pub fn dpop_proof(
+    req: &HttpRequest,
+    state: &AppState,
+    realm: &Realm,
+    access_token: Option<&str>,
+) -> my_error::Result<Option<DpopProof>> {
+    let mut headers = req.headers().get_all(DPOP_HEADER);
+    let Some(header) = headers.next() else {
+        return Ok(None);
+    };
+    if headers.next().is_some() {
+        return Err(invalid_proof());
+    }
+    let proof = header.to_str().map_err(|_| invalid_proof())?;
+    let proof = verify_proof(
+        proof,
+        req.method().as_str(),
+        &request_uri(realm, req)?,
+        access_token,
+    )
+    .map_err(|_| invalid_proof())?;
+    if !state.dpop_proofs.record(&proof)? {
+        tracing::debug!("Proof is replayed");
+        return Err(invalid_proof());
+    }
+    Ok(Some(proof))
+}
+
+/// A token bound to a key is only accepted with a proof by that key.
+pub fn check_binding(
+    req: &HttpRequest,
+    state: &AppState,
+    realm: &Realm,
+    token: &str,
+    claims: &Claims,
+) -> my_error::Result<()> {
+    let Some(jkt) = claims.confirmation() else {
+        return Ok(());
+    };
+    match dpop_proof(req, state, realm, Some(token)) {
+        Ok(Some(proof)) if proof.jkt == jkt => Ok(()),
+        Err(MyError::Storage) => Err(MyError::Storage),
+        _ => Err(MyError::Unauthorized),
+    }
+}
+
+// Scheme and host of the realm's issuer, with the path of the request.
+// Forwarded and Host headers are left out, the client picks them freely.
+fn request_uri(realm: &Realm, req: &HttpRequest) -> my_error::Result<String> {
+    let issuer = Url::parse(&realm.issuer).map_err(|_| MyError::Internal)?;
+    Ok(format!(
+        "{}{}",
+        issuer.origin().ascii_serialization(),
+        req.path()
+    ))
+}
+
+fn invalid_proof() -> MyError {
+    MyError::OAuth(OAuthError::InvalidDpopProof)
+}
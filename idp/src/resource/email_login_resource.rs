@@ -0,0 +1,230 @@
+//! Email Login Resource.
+
+use std::net::IpAddr;
+
+use actix_web::{
+    cookie::{time, Cookie, SameSite},
+    http::header,
+    web, HttpRequest, HttpResponse,
+};
+use serde::Deserialize;
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{
+    app_state::AppState,
+    credential::email_login::{
+        decode_link_token, EmailLogin, EmailLoginMethod, EMAIL_LOGIN_LIFETIME_MINUTES,
+    },
+    domain::mail_address::MailAddress,
+    entity::user::User,
+    error::{
+        my_error::{self, MyError},
+        problem::ProblemDetails,
+    },
+    i18n::{catalog::format_message, locale::Locale},
+    mail::mailer::Mail,
+    realm::realm_registry::Realm,
+    resource::{
+        current_realm::CurrentRealm,
+        login_resource::sign_in_or_ask_passkey,
+        model::response_model::{EmailLoginResponse, SingInResponse},
+        validated_json::ValidatedJson,
+    },
+    webauthn::options::RequestOptions,
+};
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct EmailLoginReqBody {
+    email: MailAddress,
+    /// `link` or `code`.
+    method: EmailLoginMethod,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct EmailLinkParams {
+    /// Signed token of the link in the mail.
+    token: String,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct EmailCodeReqBody {
+    /// The six digits from the mail.
+    code: String,
+}
+
+// One cookie per realm, like the session cookie.
+fn email_login_cookie_name(realm: &Realm) -> String {
+    format!("idp_email_login_{}", realm.name)
+}
+
+// Binds a code to the browser that asked for it.
+fn email_login_cookie(realm: &Realm, login: &EmailLogin) -> Cookie<'static> {
+    Cookie::build(email_login_cookie_name(realm), login.id.clone())
+        .path("/")
+        .http_only(true)
+        .secure(realm.issuer.starts_with("https://"))
+        .same_site(SameSite::Strict)
+        .max_age(time::Duration::minutes(EMAIL_LOGIN_LIFETIME_MINUTES))
+        .finish()
+}
+
+#[utoipa::path(
+    post,
+    path = "/login/email",
+    tag = "idp",
+    request_body = EmailLoginReqBody,
+    responses(
+        (status = 202, description = "A link or code is mailed if the address belongs to a user, and replaces the oldest pending one when there are too many. For codes, the cookie that binds it to the browser is set", body = EmailLoginResponse),
+        (status = 400, description = "Invalid request body", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Realm does not exist, or no mailer is configured", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 429, description = "The address or client started too many logins within the hour", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
+#[tracing::instrument(skip_all)]
+pub async fn email_login_handler(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    realm: CurrentRealm,
+    locale: Locale,
+    body: ValidatedJson<EmailLoginReqBody>,
+) -> my_error::Result<HttpResponse> {
+    let mailer = state.mailer.clone().ok_or(MyError::NotFound)?;
+    let EmailLoginReqBody { email, method } = body.into_inner();
+    let (login, code) = EmailLogin::start(email.clone(), method)?;
+    let mail = login_mail(&realm, locale, &login, code.as_deref())?;
+    let cookie = email_login_cookie(&realm, &login);
+    realm.email_logins.save(login, client_ip(&req))?;
+
+    // Unknown and disabled users get the same answer, only no mail. It is
+    // sent after the answer, which would take longer for users otherwise.
+    if realm.users.find(&email)?.is_some_and(|user| !user.disabled) {
+        let realm_name = realm.name.clone();
+        actix_web::rt::spawn(async move {
+            if !matches!(web::block(move || mailer.send(&mail)).await, Ok(Ok(()))) {
+                tracing::warn!(realm = %realm_name, "could not send the login mail");
+            }
+        });
+    }
+    let mut res = HttpResponse::Accepted();
+    res.insert_header((header::CACHE_CONTROL, "no-store"));
+    if method == EmailLoginMethod::Code {
+        res.cookie(cookie);
+    }
+    Ok(res.json(EmailLoginResponse {
+        expires_in: EMAIL_LOGIN_LIFETIME_MINUTES * 60,
+    }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/login/email/verify",
+    tag = "idp",
+    params(EmailLinkParams),
+    responses(
+        (status = 200, description = "Signed in, the session cookie is set", body = SingInResponse),
+        (status = 202, description = "Link accepted, a passkey has to follow at /webauthn/login", body = RequestOptions),
+        (status = 400, description = "token is missing", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Link is invalid, expired or used", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Realm does not exist, or no mailer is configured", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
+#[tracing::instrument(skip_all)]
+pub async fn email_link_handler(
+    state: web::Data<AppState>,
+    realm: CurrentRealm,
+    params: web::Query<EmailLinkParams>,
+) -> my_error::Result<HttpResponse> {
+    state.mailer.as_ref().ok_or(MyError::NotFound)?;
+    let (email, id) = decode_link_token(&realm.keys, &realm.issuer, &params.token)
+        .map_err(|_| MyError::Unauthorized)?;
+    let login = realm
+        .email_logins
+        .take_link(&id)?
+        .filter(|login| login.email == email)
+        .ok_or(MyError::Unauthorized)?;
+    sign_in_or_ask_passkey(&realm, login_user(&realm, &login)?)
+}
+
+#[utoipa::path(
+    post,
+    path = "/login/email/verify",
+    tag = "idp",
+    request_body = EmailCodeReqBody,
+    responses(
+        (status = 200, description = "Signed in, the session cookie is set", body = SingInResponse),
+        (status = 202, description = "Code accepted, a passkey has to follow at /webauthn/login", body = RequestOptions),
+        (status = 400, description = "Invalid request body", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Code is wrong, expired, used, out of attempts or asked for in another browser", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Realm does not exist, or no mailer is configured", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 429, description = "The address or client entered too many wrong codes within the hour", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
+#[tracing::instrument(skip_all)]
+pub async fn email_code_handler(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    realm: CurrentRealm,
+    body: ValidatedJson<EmailCodeReqBody>,
+) -> my_error::Result<HttpResponse> {
+    state.mailer.as_ref().ok_or(MyError::NotFound)?;
+    let cookie = req
+        .cookie(&email_login_cookie_name(&realm))
+        .ok_or(MyError::Unauthorized)?;
+    let login = realm
+        .email_logins
+        .take_code(cookie.value(), &body.code, client_ip(&req))?
+        .ok_or(MyError::Unauthorized)?;
+    let mut res = sign_in_or_ask_passkey(&realm, login_user(&realm, &login)?)?;
+    let mut removed = Cookie::build(email_login_cookie_name(&realm), "")
+        .path("/")
+        .finish();
+    removed.make_removal();
+    res.add_cookie(&removed).map_err(|_| MyError::Internal)?;
+    Ok(res)
+}
+
+// The peer, not a forwarded address, which the client could pick freely.
+fn client_ip(req: &HttpRequest) -> Option<IpAddr> {
+    req.peer_addr().map(|addr| addr.ip())
+}
+
+// Logins of unknown users were never mailed, and cannot be redeemed.
+fn login_user(realm: &Realm, login: &EmailLogin) -> my_error::Result<User> {
+    realm.users.find(&login.email)?.ok_or(MyError::Unauthorized)
+}
+
+// In the language of the browser that asked, which is most likely where
+// the user reads it.
+fn login_mail(
+    realm: &Realm,
+    locale: Locale,
+    login: &EmailLogin,
+    code: Option<&str>,
+) -> my_error::Result<Mail> {
+    let minutes = EMAIL_LOGIN_LIFETIME_MINUTES.to_string();
+    let body = match code {
+        Some(code) => format_message(
+            locale,
+            "mail.login.code",
+            &[("code", code), ("minutes", &minutes)],
+        ),
+        None => {
+            let link = format!(
+                "{}/login/email/verify?token={}",
+                realm.issuer.trim_end_matches('/'),
+                login.link_token(&realm.keys, &realm.issuer)?
+            );
+            format_message(
+                locale,
+                "mail.login.link",
+                &[("link", &link), ("minutes", &minutes)],
+            )
+        }
+    };
+    Ok(Mail {
+        to: login.email.clone(),
+        subject: format_message(locale, "mail.login.subject", &[("realm", &realm.name)]),
+        body,
+    })
+}
@@ -0,0 +1,115 @@
+//! Health Resource.
+
+use std::time::{Duration, Instant};
+
+use actix_web::{rt::time::timeout, web, HttpResponse};
+
+use crate::{
+    app_state::AppState,
+    domain::mail_address::MailAddress,
+    error::my_error::{self, MyError},
+    token::jwt::{decode_jwt, make_jwt},
+};
+
+use super::model::response_model::{HealthCheck, HealthResponse, HealthStatus};
+
+/// Upper bound for a single readiness check.
+const CHECK_TIMEOUT: Duration = Duration::from_secs(2);
+const PROBE_ADDRESS: &str = "readyz@idp.local";
+
+/// Liveness only tells that the process still serves requests.
+#[utoipa::path(
+    get,
+    path = "/healthz",
+    tag = "health",
+    servers((url = "/", description = "Served once, not per realm")),
+    responses((status = 200, description = "Process is alive", body = HealthResponse))
+)]
+#[tracing::instrument(skip_all)]
+pub async fn healthz_handler() -> HttpResponse {
+    HttpResponse::Ok().json(HealthResponse {
+        status: HealthStatus::Up,
+        checks: vec![],
+    })
+}
+
+/// Readiness checks every dependency needed to issue and validate tokens.
+#[utoipa::path(
+    get,
+    path = "/readyz",
+    tag = "health",
+    servers((url = "/", description = "Served once, not per realm")),
+    responses(
+        (status = 200, description = "Every check is up", body = HealthResponse),
+        (status = 503, description = "At least one check is down", body = HealthResponse)
+    )
+)]
+#[tracing::instrument(skip_all)]
+pub async fn readyz_handler(state: web::Data<AppState>) -> HttpResponse {
+    let mut checks = vec![HealthCheck {
+        name: "server",
+        status: if state.is_draining() {
+            HealthStatus::Down
+        } else {
+            HealthStatus::Up
+        },
+        elapsed_ms: 0,
+        detail: state.is_draining().then(|| "draining".to_owned()),
+    }];
+    checks.push(run_check("signing_keys", state.clone(), check_signing_keys).await);
+    checks.push(
+        run_check("user_store", state.clone(), |s| {
+            s.realms.iter().try_for_each(|realm| realm.users.ping())
+        })
+        .await,
+    );
+    checks.push(run_check("token_denylist", state.clone(), |s| s.denylist.ping()).await);
+
+    let status = if checks.iter().all(|c| c.status == HealthStatus::Up) {
+        HealthStatus::Up
+    } else {
+        HealthStatus::Down
+    };
+    let mut res = match status {
+        HealthStatus::Up => HttpResponse::Ok(),
+        HealthStatus::Down => HttpResponse::ServiceUnavailable(),
+    };
+    res.json(HealthResponse { status, checks })
+}
+
+/// Runs a blocking check on the thread pool and gives up after `CHECK_TIMEOUT`.
+async fn run_check<F>(name: &'static str, state: web::Data<AppState>, check: F) -> HealthCheck
+where
+    F: FnOnce(&AppState) -> my_error::Result<()> + Send + 'static,
+{
+    let started = Instant::now();
+    let outcome = timeout(CHECK_TIMEOUT, web::block(move || check(&state))).await;
+    let detail = match outcome {
+        Ok(Ok(Ok(()))) => None,
+        Ok(Ok(Err(err))) => Some(err.to_string()),
+        Ok(Err(_)) => Some("check aborted".to_owned()),
+        Err(_) => Some("timed out".to_owned()),
+    };
+    HealthCheck {
+        name,
+        status: if detail.is_none() {
+            HealthStatus::Up
+        } else {
+            HealthStatus::Down
+        },
+        elapsed_ms: started.elapsed().as_millis(),
+        detail,
+    }
+}
+
+// Signs and verifies a throwaway token with the keys of every realm.
+fn check_signing_keys(state: &AppState) -> my_error::Result<()> {
+    let probe = MailAddress::of(PROBE_ADDRESS)?;
+    state.realms.iter().try_for_each(|realm| {
+        if !realm.keys.is_loaded() {
+            return Err(MyError::InvalidValue);
+        }
+        let token = make_jwt(&realm.keys, &realm.issuer, &probe, None, None)?;
+        decode_jwt(&realm.keys, &realm.issuer, &token, &probe).map(|_| ())
+    })
+}
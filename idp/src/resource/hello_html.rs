@@ -1,6 +1,18 @@
 use actix_web::{get, web, Responder};
 
+#[utoipa::path(
+    tag = "hello",
+    servers((url = "/", description = "Served once, not per realm")),
+    params(
+        ("id" = u32, Path, description = "Greeting id"),
+        ("name" = String, Path, description = "Name to greet")
+    ),
+    responses(
+        (status = 200, description = "Greeting text", body = String, content_type = "text/plain")
+    )
+)]
 #[get("/{id}/{name}/index.html")]
+#[tracing::instrument(skip_all)]
 async fn hello_html_handler(params: web::Path<(u32, String)>) -> impl Responder {
     let (id, name) = params.into_inner();
     format!("Hello {}! id:{}", name, id)
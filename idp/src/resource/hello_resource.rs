@@ -1,15 +1,27 @@
 //! Hello and Resource.
 
-use actix_web::{web, HttpResponse};
+use actix_web::HttpResponse;
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
-#[derive(Debug, Serialize, Deserialize)]
+use crate::{error::my_error, resource::validated_json::ValidatedJson};
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct TestReqBody {
     name: String,
     number: i32,
 }
 
-pub async fn hello_handler(body: web::Json<TestReqBody>) -> actix_web::Result<HttpResponse> {
-    println!("model: {:?}", &body);
+#[utoipa::path(
+    post,
+    path = "/rest",
+    tag = "hello",
+    servers((url = "/", description = "Served once, not per realm")),
+    request_body = TestReqBody,
+    responses((status = 200, description = "Echoes the request body", body = TestReqBody))
+)]
+#[tracing::instrument(skip_all)]
+pub async fn hello_handler(body: ValidatedJson<TestReqBody>) -> my_error::Result<HttpResponse> {
+    tracing::info!(model = ?body.0, "echoing the request body");
     Ok(HttpResponse::Ok().json(body.0))
 }
@@ -0,0 +1,37 @@
+//! Pages rendered for browsers.
+
+use actix_web::{http::header, HttpResponse};
+
+/// Pages carry codes and assertions, so they are never cached.
+pub fn html(body: String) -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type(mime::TEXT_HTML_UTF_8)
+        .insert_header((header::CACHE_CONTROL, "no-store"))
+        .body(body)
+}
+
+/// Text and quoted attribute values of a page.
+pub fn escape_html(text: &str) -> String {
+    escape(text, |c| match c {
+        '&' => Some("&amp;"),
+        '<' => Some("&lt;"),
+        '>' => Some("&gt;"),
+        '"' => Some("&quot;"),
+        '\'' => Some("&#x27;"),
+        _ => None,
+    })
+}
+
+/// Replaces each character `entity` has a reference for. The XML of
+/// `saml::xml` escapes through it too, with fewer references so that it
+/// stays canonical.
+pub fn escape(text: &str, entity: impl Fn(char) -> Option<&'static str>) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match entity(c) {
+            Some(reference) => escaped.push_str(reference),
+            None => escaped.push(c),
+        }
+    }
+    escaped
+}
@@ -1,53 +1,149 @@
 //! Idp Resource.
 
+use crate::app_state::AppState;
 use crate::domain::mail_address::MailAddress;
 use crate::entity::user::User;
+use crate::error::my_error::{self, MyError};
+use crate::error::oauth_error::OAuthError;
+use crate::error::problem::ProblemDetails;
+use crate::resource::access_token::AccessToken;
+use crate::resource::current_realm::CurrentRealm;
+use crate::resource::dpop::{check_binding, dpop_proof};
+use crate::resource::login_resource::verify_single_factor;
 use crate::resource::model::response_model::SingInResponse;
-use crate::token::jwt::{decode_jwt, make_jwt};
-use actix_web::{web, HttpResponse};
+use crate::resource::token_resource::{authenticate_client, client_token, user_token};
+use crate::resource::validated_json::ValidatedJson;
+use crate::token::jwt::decode_jwt;
+use actix_web::{web, HttpRequest, HttpResponse};
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
 use super::model::response_model::TokenValidatedResponse;
 
-// todo making secret
-const SECRET: &str = "secret";
-
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct AuthenticationReqBody {
-    email: String,
+    email: MailAddress,
     passwd: String,
+    /// Client the token is issued to, it has to be registered in the realm.
+    #[serde(default)]
+    client_id: Option<String>,
+    /// Confidential clients send it here or with HTTP Basic.
+    #[serde(default)]
+    client_secret: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct AuthorizationReqBody {
-    email: String,
+    email: MailAddress,
     token: String,
 }
 
+#[utoipa::path(
+    post,
+    path = "/jwt",
+    tag = "idp",
+    request_body = AuthenticationReqBody,
+    params(("DPoP" = Option<String>, Header, description = "Proof of the key the token is bound to, RFC 9449")),
+    responses(
+        (status = 200, description = "Token issued", body = SingInResponse),
+        (status = 400, description = "Invalid request body or DPoP proof, or the client needs a consent the user has not given", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Wrong mail address or password, a user with a passkey, the user is disabled, or the client is not registered in the realm or failed to authenticate", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Realm does not exist", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
+#[tracing::instrument(skip_all)]
 pub async fn make_jwt_handler(
-    body: web::Json<AuthenticationReqBody>,
-) -> actix_web::Result<HttpResponse> {
-    // todo authentication
-    let mail = MailAddress::try_from(body.email.clone()).unwrap();
-    let jwt = make_jwt(SECRET, &mail);
-    let res = SingInResponse {
-        user: User::of(mail),
-        token: jwt.unwrap(),
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    realm: CurrentRealm,
+    body: ValidatedJson<AuthenticationReqBody>,
+) -> my_error::Result<HttpResponse> {
+    let AuthenticationReqBody {
+        email,
+        passwd,
+        client_id,
+        client_secret,
+    } = body.into_inner();
+    let proof = dpop_proof(&req, &state, &realm, None)?;
+    let jkt = proof.as_ref().map(|proof| proof.jkt.as_str());
+    let client = match &client_id {
+        Some(client_id) => Some(authenticate_client(
+            &req,
+            &realm,
+            Some(client_id),
+            client_secret.as_deref(),
+        )?),
+        None => None,
+    };
+    // No second factor is asked here, users with a passkey sign in at /login.
+    let user = verify_single_factor(&realm, &email, passwd).await?;
+    // There is no page here to ask for it, the user allows the client elsewhere first.
+    if let Some(client) = client.as_ref().filter(|client| client.require_consent) {
+        if realm
+            .consents
+            .find(&user.email, &client.client_id)?
+            .is_none()
+        {
+            return Err(MyError::OAuth(OAuthError::AccessDenied));
+        }
+    }
+    let jwt = match &client {
+        Some(client) => client_token(&realm, client, &user, &[], None, jkt)?,
+        None => user_token(&realm, &user, jkt)?,
     };
+    let res = SingInResponse { user, token: jwt };
     Ok(HttpResponse::Ok().json(res))
 }
 
+#[utoipa::path(
+    post,
+    path = "/validate",
+    tag = "idp",
+    request_body = AuthorizationReqBody,
+    params(("DPoP" = Option<String>, Header, description = "Proof for this request and the token, required when it is bound to a key")),
+    responses(
+        (status = 200, description = "Token is valid", body = TokenValidatedResponse),
+        (status = 400, description = "Invalid request body", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Token is invalid, revoked or from another realm, the user is disabled, or a bound token comes without a fresh proof by its key", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Realm does not exist", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
+#[tracing::instrument(skip_all)]
 pub async fn validate_jwt_handler(
-    body: web::Json<AuthorizationReqBody>,
-) -> actix_web::Result<HttpResponse> {
-    let mail = MailAddress::try_from(body.email.clone()).unwrap();
-    let claims = match decode_jwt(SECRET, &body.token, &mail) {
-        Ok(c) => c,
-        Err(_) => panic!("Some other errors"),
-    };
-    let res = TokenValidatedResponse {
-        claims,
-        user: User::of(mail),
-    };
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    realm: CurrentRealm,
+    body: ValidatedJson<AuthorizationReqBody>,
+) -> my_error::Result<HttpResponse> {
+    let mail = body.email.clone();
+    let claims = decode_jwt(&realm.keys, &realm.issuer, &body.token, &mail)?;
+    check_binding(&req, &state, &realm, &body.token, &claims)?;
+    if state.denylist.rejects(&body.token, &claims)? {
+        return Err(MyError::Unauthorized);
+    }
+    let user = realm.users.find(&mail)?.unwrap_or_else(|| User::of(mail));
+    if user.disabled {
+        return Err(MyError::Unauthorized);
+    }
+    let res = TokenValidatedResponse { claims, user };
     Ok(HttpResponse::Ok().json(res))
 }
+
+#[utoipa::path(
+    get,
+    path = "/userinfo",
+    tag = "idp",
+    params(
+        ("Authorization" = String, Header, description = "`Bearer <token>`, or `DPoP <token>` for a bound token"),
+        ("DPoP" = Option<String>, Header, description = "Proof for this request and the token, required when it is bound to a key")
+    ),
+    responses(
+        (status = 200, description = "The user the token was issued to", body = User),
+        (status = 401, description = "Token is missing, invalid or revoked, the user is disabled, or a bound token comes without a fresh proof by its key", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Realm does not exist", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
+#[tracing::instrument(skip_all)]
+pub async fn userinfo_handler(token: AccessToken) -> my_error::Result<HttpResponse> {
+    Ok(HttpResponse::Ok().json(token.user))
+}
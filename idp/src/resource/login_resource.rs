@@ -0,0 +1,146 @@
+//! Login Resource.
+
+use std::path::PathBuf;
+
+use actix_web::{http::header, web, HttpResponse};
+use serde::Deserialize;
+use utoipa::ToSchema;
+
+use crate::{
+    credential::passwd_hasher::{dummy_hash, upgraded_hash, verify_password},
+    domain::mail_address::MailAddress,
+    entity::user::User,
+    error::{
+        my_error::{self, MyError},
+        problem::ProblemDetails,
+    },
+    realm::{realm_registry::Realm, realm_state::StateFile},
+    resource::{
+        current_realm::CurrentRealm, model::response_model::SingInResponse,
+        session_cookie::sign_in, validated_json::ValidatedJson,
+    },
+    webauthn::{
+        ceremony::{Ceremony, CeremonyKind},
+        options::RequestOptions,
+        relying_party::RelyingParty,
+    },
+};
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct LoginReqBody {
+    email: MailAddress,
+    passwd: String,
+}
+
+#[utoipa::path(
+    post,
+    path = "/login",
+    tag = "idp",
+    request_body = LoginReqBody,
+    responses(
+        (status = 200, description = "Signed in, the session cookie is set", body = SingInResponse),
+        (status = 202, description = "Password accepted, a passkey has to follow at /webauthn/login", body = RequestOptions),
+        (status = 400, description = "Invalid request body", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Wrong mail address or password", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Realm does not exist", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
+#[tracing::instrument(skip_all)]
+pub async fn password_login_handler(
+    realm: CurrentRealm,
+    body: ValidatedJson<LoginReqBody>,
+) -> my_error::Result<HttpResponse> {
+    let LoginReqBody { email, passwd } = body.into_inner();
+    let user = verify_credentials(&realm, &email, passwd).await?;
+    sign_in_or_ask_passkey(&realm, user)
+}
+
+/// Signs in a user who passed the first factor, or answers with the options
+/// for their passkey, which becomes the second factor, when they have one.
+pub fn sign_in_or_ask_passkey(realm: &Realm, user: User) -> my_error::Result<HttpResponse> {
+    if user.passkeys.is_empty() {
+        return sign_in(realm, user);
+    }
+    let rp = RelyingParty::of(realm)?;
+    let ceremony = Ceremony::start(CeremonyKind::Authentication, Some(user.email.clone()))?;
+    let options = rp.request_options(&ceremony, &user.passkeys);
+    realm.webauthn_ceremonies.save(ceremony)?;
+    Ok(HttpResponse::Accepted()
+        .insert_header((header::CACHE_CONTROL, "no-store"))
+        .json(options))
+}
+
+/// The user with the mail address, if the password is theirs. An outdated
+/// password hash is replaced by one with the current parameters, in the
+/// state file too for users `idp-admin` created or imported.
+pub async fn verify_credentials(
+    realm: &Realm,
+    email: &MailAddress,
+    passwd: String,
+) -> my_error::Result<User> {
+    let found = realm.users.find(email)?.filter(|user| !user.disabled);
+    let Some((mut user, passwd_hash)) = found.and_then(|user| {
+        let passwd_hash = user.passwd_hash.clone()?;
+        Some((user, passwd_hash))
+    }) else {
+        // As long as for a wrong password, so that the time does not tell
+        // which addresses have users.
+        web::block(move || verify_password(&passwd, dummy_hash())).await??;
+        return Err(MyError::Unauthorized);
+    };
+    let verified_hash = passwd_hash.clone();
+    let upgraded = web::block(move || {
+        if !verify_password(&passwd, &passwd_hash)? {
+            return Err(MyError::Unauthorized);
+        }
+        upgraded_hash(&passwd, &passwd_hash)
+    })
+    .await??;
+    if let Some(upgraded) = upgraded {
+        if realm
+            .users
+            .replace_passwd_hash(email, &verified_hash, upgraded.clone())?
+        {
+            tracing::info!(realm = %realm.name, "upgraded the password hash of a user");
+            if let Some(path) = StateFile::path_from_env() {
+                persist_upgrade(path, realm, email, verified_hash, upgraded.clone()).await;
+            }
+            user.passwd_hash = Some(upgraded);
+        }
+    }
+    Ok(user)
+}
+
+// The login goes on when the file cannot be written, the upgrade is then
+// done again after the next restart.
+async fn persist_upgrade(
+    path: PathBuf,
+    realm: &Realm,
+    email: &MailAddress,
+    verified_hash: String,
+    upgraded: String,
+) {
+    let name = realm.name.clone();
+    let email = email.clone();
+    let written = web::block(move || {
+        StateFile::upgrade_passwd_hash(path, &name, &email, &verified_hash, &upgraded)
+    })
+    .await;
+    if !matches!(written, Ok(Ok(_))) {
+        tracing::warn!(realm = %realm.name, "could not save the upgraded password hash");
+    }
+}
+
+/// Like `verify_credentials`, for the forms that ask no second factor. Users
+/// with a passkey sign in at /webauthn/login first and come back with the session.
+pub async fn verify_single_factor(
+    realm: &Realm,
+    email: &MailAddress,
+    passwd: String,
+) -> my_error::Result<User> {
+    let user = verify_credentials(realm, email, passwd).await?;
+    if !user.passkeys.is_empty() {
+        return Err(MyError::Unauthorized);
+    }
+    Ok(user)
+}
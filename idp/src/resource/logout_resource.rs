@@ -0,0 +1,242 @@
+//! Logout Resource.
+
+use actix_web::{http::header, web, HttpRequest, HttpResponse};
+use serde::Deserialize;
+use url::Url;
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{
+    app_state::AppState,
+    entity::session::Session,
+    error::{
+        my_error::{self, MyError},
+        oauth_error::OAuthError,
+        problem::ProblemDetails,
+    },
+    i18n::{catalog::message, locale::Locale},
+    oauth::backchannel_logout::notify_clients,
+    realm::realm_registry::Realm,
+    resource::{
+        current_realm::CurrentRealm,
+        html_page::{escape_html, html},
+        session_cookie::{current_session, removed_session_cookie},
+    },
+    token::jwt::{decode_id_token_hint, Claims},
+};
+
+/// Parameters of OpenID Connect RP-Initiated Logout 1.0, in the query or the form.
+#[derive(Debug, Deserialize, IntoParams, ToSchema)]
+#[into_params(parameter_in = Query)]
+pub struct LogoutParams {
+    /// A token the realm issued to the user, expired ones too.
+    id_token_hint: Option<String>,
+    /// Needed with `post_logout_redirect_uri` when the hint names no client.
+    client_id: Option<String>,
+    /// Has to be registered for the client.
+    post_logout_redirect_uri: Option<String>,
+    /// Passed back on the redirect.
+    state: Option<String>,
+    /// Languages of the signed-out page, space separated in order of preference.
+    ui_locales: Option<String>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/logout",
+    tag = "oauth",
+    params(LogoutParams),
+    responses(
+        (status = 200, description = "Signed out, no redirect was asked for, or the page asking to sign out when there is no `id_token_hint`", body = String, content_type = "text/html"),
+        (status = 303, description = "Signed out, back to `post_logout_redirect_uri`"),
+        (status = 400, description = "Invalid hint, or a redirect URI the client did not register", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Realm does not exist", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
+#[tracing::instrument(skip_all)]
+pub async fn logout_redirect_handler(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    realm: CurrentRealm,
+    params: web::Query<LogoutParams>,
+) -> my_error::Result<HttpResponse> {
+    logout(&req, &state, &realm, &params, false)
+}
+
+#[utoipa::path(
+    post,
+    path = "/logout",
+    tag = "oauth",
+    request_body(content = LogoutParams, content_type = "application/x-www-form-urlencoded"),
+    responses(
+        (status = 200, description = "Signed out, no redirect was asked for", body = String, content_type = "text/html"),
+        (status = 303, description = "Signed out, back to `post_logout_redirect_uri`"),
+        (status = 400, description = "Invalid hint, or a redirect URI the client did not register", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Realm does not exist", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
+#[tracing::instrument(skip_all)]
+pub async fn logout_post_handler(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    realm: CurrentRealm,
+    form: web::Form<LogoutParams>,
+) -> my_error::Result<HttpResponse> {
+    logout(&req, &state, &realm, &form, true)
+}
+
+// Everything is checked before the session ends, so that a bad request
+// leaves the user signed in rather than stranded. A link any site can
+// put on a page only signs out with a valid hint, otherwise the user is
+// asked first (RP-Initiated Logout section 2); the POST of the form is
+// the answer.
+fn logout(
+    req: &HttpRequest,
+    state: &AppState,
+    realm: &Realm,
+    params: &LogoutParams,
+    confirmed: bool,
+) -> my_error::Result<HttpResponse> {
+    let hint = params
+        .id_token_hint
+        .as_deref()
+        .map(|token| {
+            decode_id_token_hint(&realm.keys, &realm.issuer, token).map_err(|_| invalid_request())
+        })
+        .transpose()?;
+    let client_id = match (
+        hint.as_ref().and_then(Claims::authorized_party),
+        params.client_id.as_deref(),
+    ) {
+        (Some(azp), Some(client_id)) if azp != client_id => return Err(invalid_request()),
+        (azp, client_id) => azp.or(client_id),
+    };
+    let redirect = match &params.post_logout_redirect_uri {
+        Some(uri) => {
+            let client = match client_id {
+                Some(client_id) => realm.clients.find(client_id)?,
+                None => None,
+            };
+            if !client.is_some_and(|client| client.post_logout_redirect_uris.contains(uri)) {
+                return Err(invalid_request());
+            }
+            let mut url = Url::parse(uri).map_err(|_| invalid_request())?;
+            if let Some(state) = &params.state {
+                url.query_pairs_mut().append_pair("state", state);
+            }
+            Some(url)
+        }
+        None => None,
+    };
+    // The form of a POST carries `ui_locales` in the body.
+    let locale = params
+        .ui_locales
+        .as_deref()
+        .and_then(Locale::from_ui_locales)
+        .unwrap_or_else(|| Locale::of_request(req));
+
+    // The browser's own session, or the one the hint was issued in.
+    let session = match current_session(req, realm)? {
+        Some(session) => Some(session),
+        None => match hint.as_ref().and_then(Claims::session_id) {
+            Some(session_id) => realm.sessions.find(session_id)?,
+            None => None,
+        },
+    };
+    if let Some(session) = session {
+        if hint.is_none() && !confirmed {
+            return Ok(html(confirm_page(locale, params)));
+        }
+        if hint
+            .as_ref()
+            .is_some_and(|hint| hint.subject() != String::from(session.email.clone()))
+        {
+            return Err(invalid_request());
+        }
+        end_session(state, realm, &session)?;
+    }
+
+    let mut res = match redirect {
+        Some(url) => HttpResponse::SeeOther()
+            .insert_header((header::LOCATION, url.as_str()))
+            .insert_header((header::CACHE_CONTROL, "no-store"))
+            .finish(),
+        None => html(signed_out_page(locale)),
+    };
+    res.add_cookie(&removed_session_cookie(realm))
+        .map_err(|_| MyError::Internal)?;
+    Ok(res)
+}
+
+/// Ends the session, revokes the tokens issued in it and tells its clients.
+fn end_session(state: &AppState, realm: &Realm, session: &Session) -> my_error::Result<()> {
+    let Some(session) = realm.sessions.remove(&session.id)? else {
+        return Ok(());
+    };
+    state.denylist.revoke_session(&session.id)?;
+    notify_clients(realm, &session)
+}
+
+fn invalid_request() -> MyError {
+    MyError::OAuth(OAuthError::InvalidRequest)
+}
+
+fn confirm_page(locale: Locale, params: &LogoutParams) -> String {
+    let fields: String = [
+        ("client_id", &params.client_id),
+        ("post_logout_redirect_uri", &params.post_logout_redirect_uri),
+        ("state", &params.state),
+        ("ui_locales", &params.ui_locales),
+    ]
+    .into_iter()
+    .filter_map(|(name, value)| {
+        value.as_deref().map(|value| {
+            format!(
+                r#"
+    <input type="hidden" name="{}" value="{}" />"#,
+                name,
+                escape_html(value)
+            )
+        })
+    })
+    .collect();
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="{}">
+<head>
+  <meta charset="utf-8" />
+  <title>idp - {}</title>
+</head>
+<body>
+  <p>{}</p>
+  <form method="post" action="logout">{}
+    <button type="submit">{}</button>
+  </form>
+</body>
+</html>
+"#,
+        locale.tag(),
+        message(locale, "page.logout.title"),
+        message(locale, "page.logout.confirm"),
+        fields,
+        message(locale, "page.logout.sign_out")
+    )
+}
+
+fn signed_out_page(locale: Locale) -> String {
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="{}">
+<head>
+  <meta charset="utf-8" />
+  <title>idp - {}</title>
+</head>
+<body>
+  <p>{}</p>
+</body>
+</html>
+"#,
+        locale.tag(),
+        message(locale, "page.signed_out.title"),
+        message(locale, "page.signed_out.message")
+    )
+}
@@ -1,15 +1,73 @@
 use serde::Serialize;
+use utoipa::ToSchema;
 
 use crate::{entity::user::User, token::jwt::Claims};
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 pub struct SingInResponse {
     pub user: User,
     pub token: String,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 pub struct TokenValidatedResponse {
     pub claims: Claims,
     pub user: User,
 }
+
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, Debug, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum HealthStatus {
+    Up,
+    Down,
+}
+
+#[derive(Serialize, Debug, ToSchema)]
+pub struct HealthCheck {
+    pub name: &'static str,
+    pub status: HealthStatus,
+    pub elapsed_ms: u128,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+#[derive(Serialize, Debug, ToSchema)]
+pub struct HealthResponse {
+    pub status: HealthStatus,
+    pub checks: Vec<HealthCheck>,
+}
+
+/// Successful answer of the token endpoint, RFC 6749 section 5.1.
+#[derive(Serialize, Debug, ToSchema)]
+pub struct TokenResponse {
+    pub access_token: String,
+    pub token_type: String,
+    /// Seconds.
+    pub expires_in: i64,
+    /// Set on token exchange, RFC 8693 section 2.2.1.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub issued_token_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+}
+
+/// RFC 8628 section 3.2.
+#[derive(Serialize, Debug, ToSchema)]
+pub struct DeviceAuthorizationResponse {
+    pub device_code: String,
+    pub user_code: String,
+    pub verification_uri: String,
+    /// The page with the user code filled in, for a QR code.
+    pub verification_uri_complete: String,
+    /// Seconds.
+    pub expires_in: i64,
+    /// Seconds to wait between polls.
+    pub interval: i64,
+}
+
+/// A login mail is on its way.
+#[derive(Serialize, Debug, ToSchema)]
+pub struct EmailLoginResponse {
+    /// Seconds the link or code works.
+    pub expires_in: i64,
+}
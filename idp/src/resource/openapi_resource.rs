@@ -0,0 +1,167 @@
+//! OpenAPI Resource.
+
+use actix_web::{http::header, HttpResponse, Responder};
+use utoipa::OpenApi;
+use utoipa_swagger_ui::{Config, SwaggerUi};
+
+use crate::{
+    credential::email_login::EmailLoginMethod,
+    domain::mail_address::MailAddress,
+    entity::{passkey::Passkey, user::User},
+    error::problem::{FieldError, ProblemDetails},
+    resource::{
+        broker_resource, consent_resource,
+        consent_resource::RevokeConsentForm,
+        device_resource,
+        device_resource::{DeviceAuthorizationForm, DeviceVerificationForm},
+        email_login_resource,
+        email_login_resource::{EmailCodeReqBody, EmailLoginReqBody},
+        health_resource, hello_html, hello_resource,
+        hello_resource::TestReqBody,
+        idp_resource,
+        idp_resource::{AuthenticationReqBody, AuthorizationReqBody},
+        login_resource,
+        login_resource::LoginReqBody,
+        logout_resource,
+        logout_resource::LogoutParams,
+        model::response_model::{
+            DeviceAuthorizationResponse, EmailLoginResponse, HealthCheck, HealthResponse,
+            HealthStatus, SingInResponse, TokenResponse, TokenValidatedResponse,
+        },
+        saml_resource,
+        saml_resource::{SsoLoginForm, SsoParams},
+        token_resource,
+        token_resource::TokenForm,
+        webauthn_resource,
+        webauthn_resource::{
+            AssertionResponse, AttestationResponse, AuthenticationCredential,
+            RegistrationCredential,
+        },
+    },
+    token::jwt::{Actor, Claims, Confirmation},
+    webauthn::options::{
+        AuthenticatorSelection, CreationOptions, CredentialDescriptor, CredentialParameters,
+        RequestOptions, RpEntity, UserEntity,
+    },
+};
+
+/// The document is assembled from the `#[utoipa::path]` attributes on each handler.
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "idp", description = "Rust idp"),
+    servers(
+        (url = "/", description = "Default realm"),
+        (url = "/realms/{realm}", description = "A named realm",
+            variables(("realm" = (default = "default", description = "Realm name")))
+        )
+    ),
+    paths(
+        idp_resource::make_jwt_handler,
+        idp_resource::validate_jwt_handler,
+        idp_resource::userinfo_handler,
+        login_resource::password_login_handler,
+        email_login_resource::email_login_handler,
+        email_login_resource::email_link_handler,
+        email_login_resource::email_code_handler,
+        logout_resource::logout_redirect_handler,
+        logout_resource::logout_post_handler,
+        webauthn_resource::passkey_registration_options_handler,
+        webauthn_resource::passkey_registration_handler,
+        webauthn_resource::passkey_login_options_handler,
+        webauthn_resource::passkey_login_handler,
+        saml_resource::saml_metadata_handler,
+        saml_resource::sso_redirect_handler,
+        saml_resource::sso_post_handler,
+        saml_resource::sso_login_handler,
+        broker_resource::broker_login_handler,
+        broker_resource::broker_callback_handler,
+        token_resource::token_handler,
+        device_resource::device_authorization_handler,
+        device_resource::device_page_handler,
+        device_resource::device_verification_handler,
+        consent_resource::consents_page_handler,
+        consent_resource::revoke_consent_handler,
+        health_resource::healthz_handler,
+        health_resource::readyz_handler,
+        hello_resource::hello_handler,
+        hello_html::hello_html_handler,
+        openapi_json_handler,
+        swagger_ui_handler,
+    ),
+    components(schemas(
+        AuthenticationReqBody,
+        AuthorizationReqBody,
+        LoginReqBody,
+        EmailLoginReqBody,
+        EmailLoginMethod,
+        EmailCodeReqBody,
+        LogoutParams,
+        RegistrationCredential,
+        AttestationResponse,
+        AuthenticationCredential,
+        AssertionResponse,
+        CreationOptions,
+        RpEntity,
+        UserEntity,
+        CredentialParameters,
+        CredentialDescriptor,
+        AuthenticatorSelection,
+        RequestOptions,
+        SsoParams,
+        SsoLoginForm,
+        TokenForm,
+        DeviceAuthorizationForm,
+        DeviceVerificationForm,
+        RevokeConsentForm,
+        TestReqBody,
+        SingInResponse,
+        TokenResponse,
+        DeviceAuthorizationResponse,
+        EmailLoginResponse,
+        TokenValidatedResponse,
+        HealthResponse,
+        HealthCheck,
+        HealthStatus,
+        User,
+        Passkey,
+        MailAddress,
+        Claims,
+        Actor,
+        Confirmation,
+        ProblemDetails,
+        FieldError,
+    ))
+)]
+pub struct ApiDoc;
+
+#[utoipa::path(
+    get,
+    path = "/openapi.json",
+    tag = "openapi",
+    servers((url = "/", description = "Served once, not per realm")),
+    responses((status = 200, description = "This document", content_type = "application/json"))
+)]
+#[tracing::instrument(skip_all)]
+pub async fn openapi_json_handler() -> impl Responder {
+    HttpResponse::Ok().json(ApiDoc::openapi())
+}
+
+/// Swagger UI under `/swagger-ui/`, its assets embedded in the binary so
+/// that it works offline.
+pub fn swagger_ui() -> SwaggerUi {
+    SwaggerUi::new("/swagger-ui/{_:.*}").config(Config::from("/openapi.json"))
+}
+
+#[utoipa::path(
+    get,
+    path = "/swagger-ui",
+    tag = "openapi",
+    servers((url = "/", description = "Served once, not per realm")),
+    responses((status = 308, description = "Redirects to the UI under `/swagger-ui/`"))
+)]
+#[tracing::instrument(skip_all)]
+pub async fn swagger_ui_handler() -> impl Responder {
+    HttpResponse::PermanentRedirect()
+        .insert_header((header::LOCATION, "/swagger-ui/"))
+        .finish()
+}
@@ -0,0 +1,313 @@
+//! SAML Resource.
+
+use actix_web::{web, HttpRequest, HttpResponse};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::Utc;
+use serde::Deserialize;
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{
+    app_state::AppState,
+    domain::mail_address::MailAddress,
+    entity::{service_provider::ServiceProvider, session::Session, user::User},
+    error::{
+        my_error::{self, MyError},
+        problem::ProblemDetails,
+    },
+    i18n::{catalog::message, locale::Locale},
+    realm::realm_registry::Realm,
+    resource::{
+        current_realm::CurrentRealm,
+        html_page::{escape_html, html},
+        login_resource::verify_single_factor,
+        session_cookie::{current_session, session_cookie},
+    },
+    saml::{
+        authn_request::AuthnRequest,
+        metadata::{idp_metadata, SAML_METADATA},
+        saml_credential::SamlCredential,
+        saml_response::signed_response,
+    },
+};
+
+/// Parameters of both SSO bindings.
+#[derive(Debug, Deserialize, IntoParams, ToSchema)]
+#[into_params(parameter_in = Query)]
+pub struct SsoParams {
+    /// AuthnRequest, DEFLATE'd and base64 encoded on the redirect binding,
+    /// only base64 encoded on the POST binding.
+    #[serde(rename = "SAMLRequest")]
+    saml_request: String,
+    #[serde(rename = "RelayState")]
+    relay_state: Option<String>,
+}
+
+/// Submitted by the login form that the SSO endpoints render.
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct SsoLoginForm {
+    #[serde(rename = "SAMLRequest")]
+    saml_request: String,
+    #[serde(rename = "RelayState")]
+    relay_state: Option<String>,
+    email: String,
+    passwd: String,
+}
+
+#[utoipa::path(
+    get,
+    path = "/saml/metadata",
+    tag = "saml",
+    responses(
+        (status = 200, description = "IdP metadata of the realm", body = String, content_type = "application/samlmetadata+xml"),
+        (status = 404, description = "Realm does not exist or SAML is not configured", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
+#[tracing::instrument(skip_all)]
+pub async fn saml_metadata_handler(
+    state: web::Data<AppState>,
+    realm: CurrentRealm,
+) -> my_error::Result<HttpResponse> {
+    let credential = saml_credential(&state)?;
+    let sso_url = format!("{}/saml/sso", realm.issuer.trim_end_matches('/'));
+    Ok(HttpResponse::Ok()
+        .content_type(SAML_METADATA)
+        .body(idp_metadata(&realm.issuer, &sso_url, credential)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/saml/sso",
+    tag = "saml",
+    params(SsoParams),
+    responses(
+        (status = 200, description = "Login form, or the signed response when a session exists", body = String, content_type = "text/html"),
+        (status = 400, description = "Malformed request or unknown service provider", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Realm does not exist or SAML is not configured", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
+#[tracing::instrument(skip_all)]
+pub async fn sso_redirect_handler(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    realm: CurrentRealm,
+    params: web::Query<SsoParams>,
+) -> my_error::Result<HttpResponse> {
+    let request = AuthnRequest::from_redirect(&params.saml_request)?;
+    single_sign_on(
+        &req,
+        &state,
+        &realm,
+        &request,
+        params.relay_state.as_deref(),
+    )
+}
+
+#[utoipa::path(
+    post,
+    path = "/saml/sso",
+    tag = "saml",
+    request_body(content = SsoParams, content_type = "application/x-www-form-urlencoded"),
+    responses(
+        (status = 200, description = "Login form, or the signed response when a session exists", body = String, content_type = "text/html"),
+        (status = 400, description = "Malformed request or unknown service provider", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Realm does not exist or SAML is not configured", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
+#[tracing::instrument(skip_all)]
+pub async fn sso_post_handler(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    realm: CurrentRealm,
+    form: web::Form<SsoParams>,
+) -> my_error::Result<HttpResponse> {
+    let request = AuthnRequest::from_post(&form.saml_request)?;
+    single_sign_on(&req, &state, &realm, &request, form.relay_state.as_deref())
+}
+
+#[utoipa::path(
+    post,
+    path = "/saml/login",
+    tag = "saml",
+    request_body(content = SsoLoginForm, content_type = "application/x-www-form-urlencoded"),
+    responses(
+        (status = 200, description = "Form posting the signed response to the service provider, the session cookie is set", body = String, content_type = "text/html"),
+        (status = 400, description = "Malformed request or unknown service provider", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Wrong mail address or password", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Realm does not exist or SAML is not configured", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
+#[tracing::instrument(skip_all)]
+pub async fn sso_login_handler(
+    state: web::Data<AppState>,
+    realm: CurrentRealm,
+    locale: Locale,
+    form: web::Form<SsoLoginForm>,
+) -> my_error::Result<HttpResponse> {
+    let SsoLoginForm {
+        saml_request,
+        relay_state,
+        email,
+        passwd,
+    } = form.into_inner();
+    saml_credential(&state)?;
+    let request = AuthnRequest::from_post(&saml_request)?;
+    let service_provider = service_provider(&realm, &request)?;
+
+    let email = MailAddress::of(email).map_err(|_| MyError::Unauthorized)?;
+    let user = verify_single_factor(&realm, &email, passwd).await?;
+
+    let session = Session::start(user.email.clone())?;
+    realm.sessions.save(session.clone())?;
+    let mut res = respond(
+        saml_credential(&state)?,
+        locale,
+        &realm,
+        &service_provider,
+        &request,
+        &user,
+        relay_state.as_deref(),
+    )?;
+    res.add_cookie(&session_cookie(&realm, &session))
+        .map_err(|_| MyError::Internal)?;
+    Ok(res)
+}
+
+// Answers right away when the browser already has a session, asks to sign in otherwise.
+fn single_sign_on(
+    req: &HttpRequest,
+    state: &AppState,
+    realm: &Realm,
+    request: &AuthnRequest,
+    relay_state: Option<&str>,
+) -> my_error::Result<HttpResponse> {
+    let credential = saml_credential(state)?;
+    let service_provider = service_provider(realm, request)?;
+    let locale = Locale::of_request(req);
+    let user = match current_session(req, realm)? {
+        Some(session) => realm.users.find(&session.email)?,
+        None => None,
+    };
+    match user {
+        Some(user) => respond(
+            credential,
+            locale,
+            realm,
+            &service_provider,
+            request,
+            &user,
+            relay_state,
+        ),
+        None => Ok(html(login_page(locale, request, relay_state))),
+    }
+}
+
+fn respond(
+    credential: &SamlCredential,
+    locale: Locale,
+    realm: &Realm,
+    service_provider: &ServiceProvider,
+    request: &AuthnRequest,
+    user: &User,
+    relay_state: Option<&str>,
+) -> my_error::Result<HttpResponse> {
+    let response = signed_response(
+        credential,
+        &realm.issuer,
+        service_provider,
+        request,
+        user,
+        Utc::now(),
+    )?;
+    Ok(html(auto_post_page(
+        locale,
+        &service_provider.acs_url,
+        &STANDARD.encode(response),
+        relay_state,
+    )))
+}
+
+fn saml_credential(state: &AppState) -> my_error::Result<&SamlCredential> {
+    state.saml.as_ref().ok_or(MyError::NotFound)
+}
+
+// The response only ever goes to the registered assertion consumer service.
+fn service_provider(realm: &Realm, request: &AuthnRequest) -> my_error::Result<ServiceProvider> {
+    let service_provider = realm
+        .service_providers
+        .find(&request.issuer)?
+        .ok_or(MyError::InvalidValue)?;
+    match &request.acs_url {
+        Some(acs_url) if *acs_url != service_provider.acs_url => Err(MyError::InvalidValue),
+        _ => Ok(service_provider),
+    }
+}
+
+fn relay_state_input(relay_state: Option<&str>) -> String {
+    relay_state.map_or(String::new(), |relay_state| {
+        format!(
+            r#"<input type="hidden" name="RelayState" value="{}" />"#,
+            escape_html(relay_state)
+        )
+    })
+}
+
+// The request travels on in the POST encoding, whichever binding it came in on.
+fn login_page(locale: Locale, request: &AuthnRequest, relay_state: Option<&str>) -> String {
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="{}">
+<head>
+  <meta charset="utf-8" />
+  <title>idp - {}</title>
+</head>
+<body>
+  <form method="post" action="login">
+    <input type="hidden" name="SAMLRequest" value="{}" />
+    {}
+    <label>{} <input type="email" name="email" autocomplete="username" required /></label>
+    <label>{} <input type="password" name="passwd" autocomplete="current-password" required /></label>
+    <button type="submit">{}</button>
+  </form>
+</body>
+</html>
+"#,
+        locale.tag(),
+        message(locale, "page.sign_in.title"),
+        request.to_post(),
+        relay_state_input(relay_state),
+        message(locale, "page.email"),
+        message(locale, "page.password"),
+        message(locale, "page.sign_in.submit")
+    )
+}
+
+fn auto_post_page(
+    locale: Locale,
+    acs_url: &str,
+    saml_response: &str,
+    relay_state: Option<&str>,
+) -> String {
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="{}">
+<head>
+  <meta charset="utf-8" />
+  <title>idp - {}</title>
+</head>
+<body onload="document.forms[0].submit()">
+  <form method="post" action="{}">
+    <input type="hidden" name="SAMLResponse" value="{}" />
+    {}
+    <noscript><button type="submit">{}</button></noscript>
+  </form>
+</body>
+</html>
+"#,
+        locale.tag(),
+        message(locale, "page.signing_in.title"),
+        escape_html(acs_url),
+        saml_response,
+        relay_state_input(relay_state),
+        message(locale, "page.continue")
+    )
+}
@@ -0,0 +1,76 @@
+//! Session cookie.
+
+use actix_web::{
+    cookie::{time, Cookie, SameSite},
+    HttpRequest, HttpResponse,
+};
+
+use crate::{
+    entity::{
+        client::DEFAULT_GROUPS_LIMIT,
+        session::{Session, SESSION_LIFETIME_HOURS},
+        user::User,
+    },
+    error::my_error::{self, MyError},
+    realm::realm_registry::Realm,
+    resource::model::response_model::SingInResponse,
+    token::jwt::{encode_claims, Claims},
+};
+
+// One cookie per realm, so that signing in to one realm leaves the others alone.
+fn session_cookie_name(realm: &Realm) -> String {
+    format!("idp_session_{}", realm.name)
+}
+
+/// Carries the session id, out of reach of scripts.
+pub fn session_cookie(realm: &Realm, session: &Session) -> Cookie<'static> {
+    Cookie::build(session_cookie_name(realm), session.id.clone())
+        .path("/")
+        .http_only(true)
+        .secure(realm.issuer.starts_with("https://"))
+        .same_site(SameSite::Lax)
+        .max_age(time::Duration::hours(SESSION_LIFETIME_HOURS))
+        .finish()
+}
+
+/// Makes the browser drop the session cookie.
+pub fn removed_session_cookie(realm: &Realm) -> Cookie<'static> {
+    let mut cookie = Cookie::build(session_cookie_name(realm), "")
+        .path("/")
+        .finish();
+    cookie.make_removal();
+    cookie
+}
+
+/// The unexpired session of the realm the request carries a cookie for.
+/// Sessions of users disabled since they signed in no longer count.
+pub fn current_session(req: &HttpRequest, realm: &Realm) -> my_error::Result<Option<Session>> {
+    let Some(cookie) = req.cookie(&session_cookie_name(realm)) else {
+        return Ok(None);
+    };
+    let Some(session) = realm.sessions.find(cookie.value())? else {
+        return Ok(None);
+    };
+    match realm.users.find(&session.email)? {
+        Some(user) if user.disabled => Ok(None),
+        _ => Ok(Some(session)),
+    }
+}
+
+/// Starts a session for a user who has just authenticated, and answers with
+/// the session cookie and a token.
+#[tracing::instrument(skip_all, fields(realm = %realm.name))]
+pub fn sign_in(realm: &Realm, user: User) -> my_error::Result<HttpResponse> {
+    if user.disabled {
+        return Err(MyError::Unauthorized);
+    }
+    let session = Session::start(user.email.clone())?;
+    realm.sessions.save(session.clone())?;
+    let claims = Claims::of(&realm.issuer, &user.email, None)
+        .with_session_id(Some(&session.id))
+        .with_groups(realm.groups.memberships(&user.email)?, DEFAULT_GROUPS_LIMIT);
+    let token = encode_claims(&realm.keys, &claims)?;
+    Ok(HttpResponse::Ok()
+        .cookie(session_cookie(realm, &session))
+        .json(SingInResponse { user, token }))
+}
@@ -0,0 +1,297 @@
+//! Token Resource.
+
+use actix_web::{http::header, web, HttpRequest, HttpResponse};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use percent_encoding::percent_decode_str;
+use serde::Deserialize;
+use utoipa::ToSchema;
+
+use crate::{
+    app_state::AppState,
+    domain::mail_address::MailAddress,
+    entity::{
+        client::{Client, DEFAULT_GROUPS_LIMIT},
+        user::User,
+    },
+    error::{
+        my_error::{self, MyError},
+        oauth_error::OAuthError,
+        problem::ProblemDetails,
+    },
+    oauth::{
+        device_grant::DEVICE_CODE_GRANT_TYPE,
+        token_exchange::{exchange, ACCESS_TOKEN_TYPE, JWT_TOKEN_TYPE, TOKEN_EXCHANGE_GRANT_TYPE},
+    },
+    realm::realm_registry::Realm,
+    resource::{
+        current_realm::CurrentRealm, dpop::dpop_proof, model::response_model::TokenResponse,
+    },
+    token::{
+        claim_mapping::map_claims,
+        dpop::DPOP_TOKEN_TYPE,
+        jwt::{decode_subject_token, encode_claims, encrypt_for, Claims, TOKEN_LIFETIME_HOURS},
+    },
+};
+
+/// Parameters are optional here so that a missing one is reported as `invalid_request`.
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct TokenForm {
+    grant_type: Option<String>,
+    client_id: Option<String>,
+    /// Confidential clients send it here or with HTTP Basic.
+    client_secret: Option<String>,
+    /// For the device code grant.
+    device_code: Option<String>,
+    /// For token exchange, the token of the user to act for.
+    subject_token: Option<String>,
+    subject_token_type: Option<String>,
+    /// For token exchange, the downstream API the new token is for.
+    audience: Option<String>,
+    /// For token exchange, space separated.
+    scope: Option<String>,
+    requested_token_type: Option<String>,
+    /// Not supported, the authenticated client is the actor.
+    actor_token: Option<String>,
+}
+
+#[utoipa::path(
+    post,
+    path = "/token",
+    tag = "oauth",
+    request_body(content = TokenForm, content_type = "application/x-www-form-urlencoded"),
+    params(("DPoP" = Option<String>, Header, description = "Proof of the key the token is bound to, RFC 9449")),
+    responses(
+        (status = 200, description = "Token issued", body = TokenResponse),
+        (status = 400, description = "OAuth error, `authorization_pending` and `slow_down` while the device waits for its user, `invalid_dpop_proof` for a bad `DPoP` header", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Client is not registered in the realm or failed to authenticate", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Realm does not exist", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
+#[tracing::instrument(skip_all, fields(grant_type = form.grant_type.as_deref()))]
+pub async fn token_handler(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    realm: CurrentRealm,
+    form: web::Form<TokenForm>,
+) -> my_error::Result<HttpResponse> {
+    let client = authenticate_client(
+        &req,
+        &realm,
+        form.client_id.as_deref(),
+        form.client_secret.as_deref(),
+    )?;
+    let proof = dpop_proof(&req, &state, &realm, None)?;
+    let jkt = proof.as_ref().map(|proof| proof.jkt.as_str());
+    let res = match form.grant_type.as_deref() {
+        Some(DEVICE_CODE_GRANT_TYPE) => device_code_grant(&realm, &client, &form, jkt)?,
+        Some(TOKEN_EXCHANGE_GRANT_TYPE) => {
+            token_exchange_grant(&state, &realm, &client, &form, jkt)?
+        }
+        Some(_) => return Err(MyError::OAuth(OAuthError::UnsupportedGrantType)),
+        None => return Err(MyError::OAuth(OAuthError::InvalidRequest)),
+    };
+    Ok(HttpResponse::Ok()
+        .insert_header((header::CACHE_CONTROL, "no-store"))
+        .json(res))
+}
+
+#[tracing::instrument(skip_all)]
+fn device_code_grant(
+    realm: &Realm,
+    client: &Client,
+    form: &TokenForm,
+    jkt: Option<&str>,
+) -> my_error::Result<TokenResponse> {
+    let device_code = form.device_code.as_deref().ok_or(invalid_request())?;
+    let approval = realm.device_grants.poll(device_code, &client.client_id)?;
+    let user = realm
+        .users
+        .find(&approval.email)?
+        .unwrap_or_else(|| User::of(approval.email));
+    if user.disabled {
+        return Err(MyError::OAuth(OAuthError::AccessDenied));
+    }
+    Ok(TokenResponse {
+        access_token: client_token(
+            realm,
+            client,
+            &user,
+            &approval.scopes,
+            approval.session_id.as_deref(),
+            jkt,
+        )?,
+        token_type: token_type(jkt),
+        expires_in: TOKEN_LIFETIME_HOURS * 3600,
+        issued_token_type: None,
+        scope: (!approval.scopes.is_empty()).then(|| approval.scopes.join(" ")),
+    })
+}
+
+#[tracing::instrument(skip_all)]
+fn token_exchange_grant(
+    state: &AppState,
+    realm: &Realm,
+    client: &Client,
+    form: &TokenForm,
+    jkt: Option<&str>,
+) -> my_error::Result<TokenResponse> {
+    let policy = client
+        .token_exchange
+        .as_ref()
+        .ok_or(MyError::OAuth(OAuthError::UnauthorizedClient))?;
+    let subject_token = form.subject_token.as_deref().ok_or(invalid_request())?;
+    let audience = form.audience.as_deref().ok_or(invalid_request())?;
+    if !is_jwt_type(form.subject_token_type.as_deref())
+        || !form
+            .requested_token_type
+            .as_deref()
+            .is_none_or(|requested| is_jwt_type(Some(requested)))
+        || form.actor_token.is_some()
+    {
+        return Err(invalid_request());
+    }
+    let subject =
+        decode_subject_token(&realm.keys, &realm.issuer, subject_token, &client.client_id)
+            .map_err(|_| invalid_request())?;
+    if state.denylist.rejects(subject_token, &subject)? {
+        return Err(invalid_request());
+    }
+    // A bound token is exchanged only with a proof of its own key, and the
+    // token it is exchanged for stays bound to that key.
+    if subject
+        .confirmation()
+        .is_some_and(|bound| jkt != Some(bound))
+    {
+        return Err(MyError::OAuth(OAuthError::InvalidDpopProof));
+    }
+
+    let claims = exchange(
+        policy,
+        &subject,
+        audience,
+        form.scope.as_deref(),
+        &client.client_id,
+    )?;
+    let subject = MailAddress::of(claims.subject()).ok();
+    let claims = match subject.map(|email| realm.users.find(&email)).transpose()? {
+        Some(Some(user)) if user.disabled => return Err(invalid_request()),
+        Some(Some(user)) => {
+            claims.with_custom_claims(map_claims(realm.audience_mappers(audience), &user))
+        }
+        _ => claims,
+    }
+    .with_confirmation(jkt);
+    Ok(TokenResponse {
+        access_token: encode_claims(&realm.keys, &claims)?,
+        token_type: token_type(jkt),
+        expires_in: claims.expires_in(),
+        issued_token_type: Some(ACCESS_TOKEN_TYPE.to_owned()),
+        scope: claims.scopes().map(|scopes| scopes.join(" ")),
+    })
+}
+
+/// Token for `user`, issued to `client` with its custom claims, in the
+/// session if there is one and bound to the DPoP key `jkt` if given. The
+/// claims carry the user's profile, so the user has to have signed in or
+/// approved the client before: callers check the credentials first.
+#[tracing::instrument(skip_all, fields(client_id = %client.client_id))]
+pub fn client_token(
+    realm: &Realm,
+    client: &Client,
+    user: &User,
+    scopes: &[String],
+    session_id: Option<&str>,
+    jkt: Option<&str>,
+) -> my_error::Result<String> {
+    let claims = Claims::of(&realm.issuer, &user.email, Some(&client.client_id))
+        .with_scopes(scopes)
+        .with_session_id(session_id)
+        .with_confirmation(jkt)
+        .with_groups(realm.groups.memberships(&user.email)?, client.groups_limit)
+        .with_custom_claims(map_claims(&client.claim_mappers, user));
+    encrypt_for(
+        encode_claims(&realm.keys, &claims)?,
+        client.encryption_key.as_ref(),
+    )
+}
+
+/// Token for `user` that is issued to no client, bound to the DPoP key
+/// `jkt` if given.
+#[tracing::instrument(skip_all)]
+pub fn user_token(realm: &Realm, user: &User, jkt: Option<&str>) -> my_error::Result<String> {
+    let claims = Claims::of(&realm.issuer, &user.email, None)
+        .with_confirmation(jkt)
+        .with_groups(realm.groups.memberships(&user.email)?, DEFAULT_GROUPS_LIMIT);
+    encode_claims(&realm.keys, &claims)
+}
+
+/// `DPoP` for tokens bound to a key, `Bearer` for the others.
+pub fn token_type(jkt: Option<&str>) -> String {
+    match jkt {
+        Some(_) => DPOP_TOKEN_TYPE.to_owned(),
+        None => "Bearer".to_owned(),
+    }
+}
+
+// Issued tokens are JWTs, so both token types name them.
+fn is_jwt_type(token_type: Option<&str>) -> bool {
+    matches!(token_type, Some(ACCESS_TOKEN_TYPE | JWT_TOKEN_TYPE))
+}
+
+fn invalid_request() -> MyError {
+    MyError::OAuth(OAuthError::InvalidRequest)
+}
+
+/// The client a request names, which has to be registered in the realm.
+/// A confidential client has to prove its secret, with HTTP Basic or in the
+/// form, a public client sends none.
+pub fn authenticate_client(
+    req: &HttpRequest,
+    realm: &Realm,
+    client_id: Option<&str>,
+    client_secret: Option<&str>,
+) -> my_error::Result<Client> {
+    let (client_id, client_secret) = match basic_credentials(req)? {
+        Some((client_id, client_secret)) => (Some(client_id), Some(client_secret)),
+        None => (
+            client_id.map(str::to_owned),
+            client_secret.map(str::to_owned),
+        ),
+    };
+    let client_id = client_id.ok_or(invalid_request())?;
+    let client = realm
+        .clients
+        .find(&client_id)?
+        .ok_or(MyError::OAuth(OAuthError::InvalidClient))?;
+    match (&client.secret_digest, client_secret) {
+        (None, None) => Ok(client),
+        (Some(_), Some(secret)) if client.verify_secret(&secret) => Ok(client),
+        _ => Err(MyError::OAuth(OAuthError::InvalidClient)),
+    }
+}
+
+// RFC 6749 section 2.3.1, both parts are form-urlencoded before joining.
+fn basic_credentials(req: &HttpRequest) -> my_error::Result<Option<(String, String)>> {
+    let invalid_client = || MyError::OAuth(OAuthError::InvalidClient);
+    let Some(value) = req.headers().get(header::AUTHORIZATION) else {
+        return Ok(None);
+    };
+    let encoded = value
+        .to_str()
+        .ok()
+        .and_then(|value| value.strip_prefix("Basic "))
+        .ok_or_else(invalid_client)?;
+    let decoded = STANDARD
+        .decode(encoded.trim())
+        .ok()
+        .and_then(|decoded| String::from_utf8(decoded).ok())
+        .ok_or_else(invalid_client)?;
+    let (client_id, client_secret) = decoded.split_once(':').ok_or_else(invalid_client)?;
+    let decode = |part: &str| {
+        percent_decode_str(&part.replace('+', " "))
+            .decode_utf8()
+            .map(|part| part.into_owned())
+            .map_err(|_| invalid_client())
+    };
+    Ok(Some((decode(client_id)?, decode(client_secret)?)))
+}
@@ -0,0 +1,64 @@
+//! JSON extractor reporting field-level errors.
+
+use std::{future::Future, pin::Pin};
+
+use actix_web::{dev::Payload, web, FromRequest, HttpRequest};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use crate::error::{my_error::MyError, problem::FieldError};
+
+/// Like `web::Json`, but a body that does not fit `T` becomes
+/// `MyError::InvalidRequest` naming the offending field.
+///
+/// The body is read through `web::Json<Value>` first, so the `JsonConfig`
+/// limit, content type and error handler still apply.
+#[derive(Debug)]
+pub struct ValidatedJson<T>(pub T);
+
+impl<T> ValidatedJson<T> {
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> std::ops::Deref for ValidatedJson<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T: DeserializeOwned + 'static> FromRequest for ValidatedJson<T> {
+    type Error = actix_web::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self, Self::Error>>>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let json = web::Json::<Value>::from_request(req, payload);
+        Box::pin(async move {
+            let value = json.await?.into_inner();
+            serde_path_to_error::deserialize(value)
+                .map(ValidatedJson)
+                .map_err(|err| MyError::InvalidRequest(vec![field_error(err)]).into())
+        })
+    }
+}
+
+fn field_error(err: serde_path_to_error::Error<serde_json::Error>) -> FieldError {
+    let message = err.inner().to_string();
+    // A missing field is reported on its parent, the name is only in the message.
+    if let Some(field) = message
+        .strip_prefix("missing field `")
+        .and_then(|rest| rest.split('`').next())
+    {
+        let path = err.path().to_string();
+        let field = if path == "." {
+            field.to_owned()
+        } else {
+            format!("{}.{}", path, field)
+        };
+        return FieldError::of(field, "missing", message);
+    }
+    FieldError::of(err.path().to_string(), "invalid", message)
+}
@@ -0,0 +1,232 @@
+//! WebAuthn Resource.
+
+use actix_web::{http::header, HttpRequest, HttpResponse};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use serde::Deserialize;
+use utoipa::ToSchema;
+
+use crate::{
+    entity::{passkey::Passkey, user::User},
+    error::{
+        my_error::{self, MyError},
+        problem::ProblemDetails,
+    },
+    realm::realm_registry::Realm,
+    resource::{
+        current_realm::CurrentRealm,
+        model::response_model::SingInResponse,
+        session_cookie::{current_session, sign_in},
+        validated_json::ValidatedJson,
+    },
+    token::opaque_token::opaque_token,
+    webauthn::{
+        ceremony::{Ceremony, CeremonyKind},
+        client_data::CollectedClientData,
+        options::{CreationOptions, RequestOptions},
+        relying_party::RelyingParty,
+    },
+};
+
+/// `PublicKeyCredential` answering a registration, as `toJSON()` encodes it.
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct RegistrationCredential {
+    /// Credential id, base64url.
+    id: String,
+    response: AttestationResponse,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct AttestationResponse {
+    #[serde(rename = "clientDataJSON")]
+    client_data_json: String,
+    #[serde(rename = "attestationObject")]
+    attestation_object: String,
+}
+
+/// `PublicKeyCredential` answering a login, as `toJSON()` encodes it.
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct AuthenticationCredential {
+    /// Credential id, base64url.
+    id: String,
+    response: AssertionResponse,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct AssertionResponse {
+    #[serde(rename = "clientDataJSON")]
+    client_data_json: String,
+    #[serde(rename = "authenticatorData")]
+    authenticator_data: String,
+    signature: String,
+    #[serde(rename = "userHandle", default)]
+    user_handle: Option<String>,
+}
+
+#[utoipa::path(
+    post,
+    path = "/webauthn/register/options",
+    tag = "webauthn",
+    responses(
+        (status = 200, description = "Options for navigator.credentials.create()", body = CreationOptions),
+        (status = 401, description = "Not signed in", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Realm does not exist", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
+#[tracing::instrument(skip_all)]
+pub async fn passkey_registration_options_handler(
+    req: HttpRequest,
+    realm: CurrentRealm,
+) -> my_error::Result<HttpResponse> {
+    let mut user = signed_in_user(&req, &realm)?;
+    // Random rather than derived, so that it survives rotating the realm keys.
+    if user.user_handle.is_none() {
+        user.user_handle = Some(opaque_token()?);
+        realm.users.save(user.clone())?;
+    }
+    let ceremony = Ceremony::start(CeremonyKind::Registration, Some(user.email.clone()))?;
+    let options = RelyingParty::of(&realm)?.creation_options(&ceremony, &user)?;
+    realm.webauthn_ceremonies.save(ceremony)?;
+    Ok(HttpResponse::Ok()
+        .insert_header((header::CACHE_CONTROL, "no-store"))
+        .json(options))
+}
+
+#[utoipa::path(
+    post,
+    path = "/webauthn/register",
+    tag = "webauthn",
+    request_body = RegistrationCredential,
+    responses(
+        (status = 201, description = "Passkey registered", body = Passkey),
+        (status = 400, description = "Malformed credential or unsupported attestation", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Not signed in, or the ceremony did not verify", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Realm does not exist", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 409, description = "Passkey is already registered", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
+#[tracing::instrument(skip_all)]
+pub async fn passkey_registration_handler(
+    req: HttpRequest,
+    realm: CurrentRealm,
+    body: ValidatedJson<RegistrationCredential>,
+) -> my_error::Result<HttpResponse> {
+    let mut user = signed_in_user(&req, &realm)?;
+    let client_data_json = base64url(&body.response.client_data_json)?;
+    let ceremony = take_ceremony(&realm, &client_data_json)?;
+    if ceremony.email.as_ref() != Some(&user.email) {
+        return Err(MyError::Unauthorized);
+    }
+    let passkey = RelyingParty::of(&realm)?.register(
+        &ceremony,
+        &client_data_json,
+        &base64url(&body.response.attestation_object)?,
+    )?;
+    if passkey.id != body.id {
+        return Err(MyError::InvalidValue);
+    }
+    if realm.users.find_by_passkey(&passkey.id)?.is_some() {
+        return Err(MyError::AlreadyExists);
+    }
+    user.passkeys.push(passkey.clone());
+    realm.users.save(user)?;
+    Ok(HttpResponse::Created().json(passkey))
+}
+
+#[utoipa::path(
+    post,
+    path = "/webauthn/login/options",
+    tag = "webauthn",
+    responses(
+        (status = 200, description = "Options for navigator.credentials.get(), for a passkey as first factor", body = RequestOptions),
+        (status = 404, description = "Realm does not exist", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
+#[tracing::instrument(skip_all)]
+pub async fn passkey_login_options_handler(realm: CurrentRealm) -> my_error::Result<HttpResponse> {
+    let ceremony = Ceremony::start(CeremonyKind::Authentication, None)?;
+    let options = RelyingParty::of(&realm)?.request_options(&ceremony, &[]);
+    realm.webauthn_ceremonies.save(ceremony)?;
+    Ok(HttpResponse::Ok()
+        .insert_header((header::CACHE_CONTROL, "no-store"))
+        .json(options))
+}
+
+#[utoipa::path(
+    post,
+    path = "/webauthn/login",
+    tag = "webauthn",
+    request_body = AuthenticationCredential,
+    responses(
+        (status = 200, description = "Signed in, the session cookie is set", body = SingInResponse),
+        (status = 400, description = "Malformed credential", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Unknown passkey, or the ceremony did not verify", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Realm does not exist", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
+#[tracing::instrument(skip_all)]
+pub async fn passkey_login_handler(
+    realm: CurrentRealm,
+    body: ValidatedJson<AuthenticationCredential>,
+) -> my_error::Result<HttpResponse> {
+    let client_data_json = base64url(&body.response.client_data_json)?;
+    let ceremony = take_ceremony(&realm, &client_data_json)?;
+    let mut user = realm
+        .users
+        .find_by_passkey(&body.id)?
+        .ok_or(MyError::Unauthorized)?;
+    // After a password, only a passkey of that same user completes the login.
+    if ceremony
+        .email
+        .as_ref()
+        .is_some_and(|email| *email != user.email)
+    {
+        return Err(MyError::Unauthorized);
+    }
+    let rp = RelyingParty::of(&realm)?;
+    if body
+        .response
+        .user_handle
+        .as_ref()
+        .is_some_and(|handle| user.user_handle.as_ref() != Some(handle))
+    {
+        return Err(MyError::Unauthorized);
+    }
+
+    let passkey = user
+        .passkeys
+        .iter_mut()
+        .find(|passkey| passkey.id == body.id)
+        .ok_or(MyError::Unauthorized)?;
+    passkey.sign_count = rp.authenticate(
+        &ceremony,
+        passkey,
+        &client_data_json,
+        &base64url(&body.response.authenticator_data)?,
+        &base64url(&body.response.signature)?,
+    )?;
+    realm.users.save(user.clone())?;
+    sign_in(&realm, user)
+}
+
+fn signed_in_user(req: &HttpRequest, realm: &Realm) -> my_error::Result<User> {
+    let session = current_session(req, realm)?.ok_or(MyError::Unauthorized)?;
+    realm
+        .users
+        .find(&session.email)?
+        .ok_or(MyError::Unauthorized)
+}
+
+// The challenge in the client data names the ceremony, which is answered only once.
+fn take_ceremony(realm: &Realm, client_data_json: &[u8]) -> my_error::Result<Ceremony> {
+    let client_data = CollectedClientData::parse(client_data_json)?;
+    realm
+        .webauthn_ceremonies
+        .take(&client_data.challenge)?
+        .ok_or(MyError::Unauthorized)
+}
+
+fn base64url(value: &str) -> my_error::Result<Vec<u8>> {
+    URL_SAFE_NO_PAD
+        .decode(value)
+        .map_err(|_| MyError::InvalidValue)
+}
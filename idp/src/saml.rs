@@ -0,0 +1,5 @@
+pub mod authn_request;
+pub mod metadata;
+pub mod saml_credential;
+pub mod saml_response;
+pub mod xml;
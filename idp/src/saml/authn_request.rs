@@ -0,0 +1,121 @@
+//! SP-initiated `samlp:AuthnRequest`.
+
+use std::io::Read;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use flate2::read::DeflateDecoder;
+use quick_xml::{events::Event, Reader};
+
+use crate::error::my_error::{self, MyError};
+
+pub const HTTP_POST_BINDING: &str = "urn:oasis:names:tc:SAML:2.0:bindings:HTTP-POST";
+pub const HTTP_REDIRECT_BINDING: &str = "urn:oasis:names:tc:SAML:2.0:bindings:HTTP-Redirect";
+
+/// Inflated requests larger than this are rejected.
+const MAX_REQUEST_LEN: u64 = 64 * 1024;
+
+/// The parts of an authentication request the idp acts on.
+/// Request signatures are not checked, the response only ever goes to the
+/// assertion consumer service registered for the issuer.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct AuthnRequest {
+    pub id: String,
+    /// Entity id of the service provider.
+    pub issuer: String,
+    pub acs_url: Option<String>,
+    xml: String,
+}
+
+impl AuthnRequest {
+    /// `SAMLRequest` of the HTTP-Redirect binding: base64 of the DEFLATE'd XML.
+    pub fn from_redirect(saml_request: &str) -> my_error::Result<Self> {
+        let deflated = decode_base64(saml_request)?;
+        let mut xml = String::new();
+        DeflateDecoder::new(deflated.as_slice())
+            .take(MAX_REQUEST_LEN + 1)
+            .read_to_string(&mut xml)
+            .map_err(|_| MyError::InvalidValue)?;
+        if xml.len() as u64 > MAX_REQUEST_LEN {
+            return Err(MyError::PayloadTooLarge);
+        }
+        Self::parse(xml)
+    }
+
+    /// `SAMLRequest` of the HTTP-POST binding: base64 of the XML.
+    pub fn from_post(saml_request: &str) -> my_error::Result<Self> {
+        let xml =
+            String::from_utf8(decode_base64(saml_request)?).map_err(|_| MyError::InvalidValue)?;
+        Self::parse(xml)
+    }
+
+    pub fn parse(xml: String) -> my_error::Result<Self> {
+        let mut reader = Reader::from_str(&xml);
+        reader.config_mut().trim_text(true);
+        let mut id = None;
+        let mut acs_url = None;
+        let mut issuer = None;
+        let mut in_issuer = false;
+        let mut depth = 0;
+        loop {
+            match reader.read_event().map_err(|_| MyError::InvalidValue)? {
+                // Entities are never expanded, but there is no reason to accept a DTD at all.
+                Event::DocType(_) => return Err(MyError::InvalidValue),
+                Event::Start(e) | Event::Empty(e) if depth == 0 => {
+                    if e.local_name().as_ref() != b"AuthnRequest" {
+                        return Err(MyError::InvalidValue);
+                    }
+                    for attr in e.attributes() {
+                        let attr = attr.map_err(|_| MyError::InvalidValue)?;
+                        let value = attr
+                            .unescape_value()
+                            .map_err(|_| MyError::InvalidValue)?
+                            .into_owned();
+                        match attr.key.local_name().as_ref() {
+                            b"ID" => id = Some(value),
+                            b"AssertionConsumerServiceURL" => acs_url = Some(value),
+                            b"ProtocolBinding" if value != HTTP_POST_BINDING => {
+                                return Err(MyError::InvalidValue)
+                            }
+                            _ => {}
+                        }
+                    }
+                    depth += 1;
+                }
+                Event::Start(e) => {
+                    in_issuer = depth == 1 && e.local_name().as_ref() == b"Issuer";
+                    depth += 1;
+                }
+                Event::Text(text) if in_issuer => {
+                    issuer = Some(
+                        text.unescape()
+                            .map_err(|_| MyError::InvalidValue)?
+                            .into_owned(),
+                    );
+                }
+                Event::End(_) => {
+                    in_issuer = false;
+                    depth -= 1;
+                }
+                Event::Eof => break,
+                _ => {}
+            }
+        }
+        Ok(Self {
+            id: id.ok_or(MyError::InvalidValue)?,
+            issuer: issuer.ok_or(MyError::InvalidValue)?,
+            acs_url,
+            xml,
+        })
+    }
+
+    /// Encodes the request for the HTTP-POST binding, to carry it through the login form.
+    pub fn to_post(&self) -> String {
+        STANDARD.encode(&self.xml)
+    }
+}
+
+// Line breaks are common in posted values.
+fn decode_base64(encoded: &str) -> my_error::Result<Vec<u8>> {
+    let compact: String = encoded.split_whitespace().collect();
+    STANDARD.decode(compact).map_err(|_| MyError::InvalidValue)
+}
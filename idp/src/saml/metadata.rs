@@ -0,0 +1,31 @@
+//! IdP metadata, SAML 2.0 Metadata section 2.4.3.
+
+use crate::saml::{
+    authn_request::{HTTP_POST_BINDING, HTTP_REDIRECT_BINDING},
+    saml_credential::SamlCredential,
+    saml_response::EMAIL_NAME_ID_FORMAT,
+    xml::escape_attr,
+};
+
+pub const SAML_METADATA: &str = "application/samlmetadata+xml";
+
+/// Describes the realm `entity_id` whose single sign-on service lives at `sso_url`.
+pub fn idp_metadata(entity_id: &str, sso_url: &str, credential: &SamlCredential) -> String {
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<md:EntityDescriptor xmlns:md="urn:oasis:names:tc:SAML:2.0:metadata" xmlns:ds="http://www.w3.org/2000/09/xmldsig#" entityID="{entity_id}">
+  <md:IDPSSODescriptor WantAuthnRequestsSigned="false" protocolSupportEnumeration="urn:oasis:names:tc:SAML:2.0:protocol">
+    <md:KeyDescriptor use="signing">
+      <ds:KeyInfo><ds:X509Data><ds:X509Certificate>{certificate}</ds:X509Certificate></ds:X509Data></ds:KeyInfo>
+    </md:KeyDescriptor>
+    <md:NameIDFormat>{EMAIL_NAME_ID_FORMAT}</md:NameIDFormat>
+    <md:SingleSignOnService Binding="{HTTP_REDIRECT_BINDING}" Location="{sso_url}"/>
+    <md:SingleSignOnService Binding="{HTTP_POST_BINDING}" Location="{sso_url}"/>
+  </md:IDPSSODescriptor>
+</md:EntityDescriptor>
+"#,
+        entity_id = escape_attr(entity_id),
+        sso_url = escape_attr(sso_url),
+        certificate = credential.certificate_base64(),
+    )
+}
@@ -0,0 +1,123 @@
+//! Key material SAML assertions are signed with.
+
+use std::{env, fmt, fs, io, path::Path};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use ring::{
+    rand::SystemRandom,
+    signature::{EcdsaKeyPair, RsaKeyPair, ECDSA_P256_SHA256_FIXED_SIGNING, RSA_PKCS1_SHA256},
+};
+use rustls_pki_types::{pem::PemObject, CertificateDer, PrivateKeyDer};
+
+use crate::{
+    error::my_error::{self, MyError},
+    tls::TlsConfig,
+};
+
+const CERT_FILE_ENV: &str = "IDP_SAML_CERT_FILE";
+const KEY_FILE_ENV: &str = "IDP_SAML_KEY_FILE";
+
+const RSA_SHA256: &str = "http://www.w3.org/2001/04/xmldsig-more#rsa-sha256";
+const ECDSA_SHA256: &str = "http://www.w3.org/2001/04/xmldsig-more#ecdsa-sha256";
+
+enum Signer {
+    Rsa(RsaKeyPair),
+    /// Signatures are the fixed `r || s` encoding RFC 4051 asks for.
+    Ecdsa(EcdsaKeyPair),
+}
+
+/// Signing key and the certificate published in the metadata.
+pub struct SamlCredential {
+    certificate: CertificateDer<'static>,
+    signer: Signer,
+    rng: SystemRandom,
+}
+
+impl SamlCredential {
+    /// Reads `IDP_SAML_CERT_FILE` and `IDP_SAML_KEY_FILE`, falling back to the TLS
+    /// certificate. SAML is disabled when neither is configured.
+    pub fn from_env() -> io::Result<Option<Self>> {
+        let files = match (env::var_os(CERT_FILE_ENV), env::var_os(KEY_FILE_ENV)) {
+            (Some(cert_file), Some(key_file)) => Some((cert_file.into(), key_file.into())),
+            _ => TlsConfig::from_env().map(|tls| (tls.cert_file, tls.key_file)),
+        };
+        files
+            .map(|(cert_file, key_file)| Self::load(cert_file, key_file))
+            .transpose()
+    }
+
+    pub fn load<C: AsRef<Path>, K: AsRef<Path>>(cert_file: C, key_file: K) -> io::Result<Self> {
+        Self::from_pem(&fs::read(cert_file)?, &fs::read(key_file)?)
+    }
+
+    /// Takes the first certificate of the chain and an RSA or P-256 private key.
+    pub fn from_pem(cert_pem: &[u8], key_pem: &[u8]) -> io::Result<Self> {
+        let certificate = CertificateDer::pem_slice_iter(cert_pem)
+            .next()
+            .ok_or_else(|| invalid_data("no certificate found"))?
+            .map_err(invalid_data)?;
+        let rng = SystemRandom::new();
+        let signer = match PrivateKeyDer::from_pem_slice(key_pem).map_err(invalid_data)? {
+            PrivateKeyDer::Pkcs1(key) => {
+                Signer::Rsa(RsaKeyPair::from_der(key.secret_pkcs1_der()).map_err(invalid_data)?)
+            }
+            PrivateKeyDer::Pkcs8(key) => {
+                let der = key.secret_pkcs8_der();
+                match RsaKeyPair::from_pkcs8(der) {
+                    Ok(key_pair) => Signer::Rsa(key_pair),
+                    Err(_) => Signer::Ecdsa(
+                        EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, der, &rng)
+                            .map_err(invalid_data)?,
+                    ),
+                }
+            }
+            _ => return Err(invalid_data("unsupported private key format")),
+        };
+        Ok(Self {
+            certificate,
+            signer,
+            rng,
+        })
+    }
+
+    /// DER of the certificate in base64, as `ds:X509Certificate` carries it.
+    pub fn certificate_base64(&self) -> String {
+        STANDARD.encode(&self.certificate)
+    }
+
+    /// `ds:SignatureMethod` algorithm of `sign`.
+    pub fn signature_algorithm(&self) -> &'static str {
+        match self.signer {
+            Signer::Rsa(_) => RSA_SHA256,
+            Signer::Ecdsa(_) => ECDSA_SHA256,
+        }
+    }
+
+    pub fn sign(&self, message: &[u8]) -> my_error::Result<Vec<u8>> {
+        match &self.signer {
+            Signer::Rsa(key_pair) => {
+                let mut signature = vec![0; key_pair.public().modulus_len()];
+                key_pair
+                    .sign(&RSA_PKCS1_SHA256, &self.rng, message, &mut signature)
+                    .map_err(|_| MyError::Encode)?;
+                Ok(signature)
+            }
+            Signer::Ecdsa(key_pair) => key_pair
+                .sign(&self.rng, message)
+                .map(|signature| signature.as_ref().to_vec())
+                .map_err(|_| MyError::Encode),
+        }
+    }
+}
+
+impl fmt::Debug for SamlCredential {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("SamlCredential")
+            .field("signature_algorithm", &self.signature_algorithm())
+            .finish()
+    }
+}
+
+fn invalid_data<E: fmt::Display>(err: E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, err.to_string())
+}
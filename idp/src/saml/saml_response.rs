@@ -0,0 +1,180 @@
+//! Signed `samlp:Response` for the HTTP-POST binding.
+//!
+//! The assertion is written directly in Exclusive XML Canonicalization form:
+//! no whitespace between elements, no empty-element tags, attributes in
+//! lexicographic order and namespaces declared where they are used. Its digest
+//! and the signature over `ds:SignedInfo` can therefore be computed on the
+//! strings themselves.
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::{DateTime, Duration, SecondsFormat, Utc};
+use ring::{
+    digest::{digest, SHA256},
+    rand::{SecureRandom, SystemRandom},
+};
+
+use crate::{
+    entity::{service_provider::ServiceProvider, user::User},
+    error::my_error::{self, MyError},
+    saml::{
+        authn_request::AuthnRequest,
+        saml_credential::SamlCredential,
+        xml::{escape_attr, escape_text},
+    },
+};
+
+const ASSERTION_NS: &str = "urn:oasis:names:tc:SAML:2.0:assertion";
+const PROTOCOL_NS: &str = "urn:oasis:names:tc:SAML:2.0:protocol";
+const DSIG_NS: &str = "http://www.w3.org/2000/09/xmldsig#";
+const EXC_C14N: &str = "http://www.w3.org/2001/10/xml-exc-c14n#";
+const ENVELOPED_SIGNATURE: &str = "http://www.w3.org/2000/09/xmldsig#enveloped-signature";
+const SHA256_DIGEST: &str = "http://www.w3.org/2001/04/xmlenc#sha256";
+pub const EMAIL_NAME_ID_FORMAT: &str = "urn:oasis:names:tc:SAML:1.1:nameid-format:emailAddress";
+const BASIC_NAME_FORMAT: &str = "urn:oasis:names:tc:SAML:2.0:attrname-format:basic";
+const BEARER: &str = "urn:oasis:names:tc:SAML:2.0:cm:bearer";
+const PASSWORD_PROTECTED_TRANSPORT: &str =
+    "urn:oasis:names:tc:SAML:2.0:ac:classes:PasswordProtectedTransport";
+const SUCCESS: &str = "urn:oasis:names:tc:SAML:2.0:status:Success";
+
+/// How long the service provider may take to consume the assertion.
+const ASSERTION_LIFETIME_MINUTES: i64 = 5;
+
+/// Issues a response to `request` asserting that `user` signed in just now.
+pub fn signed_response(
+    credential: &SamlCredential,
+    issuer: &str,
+    service_provider: &ServiceProvider,
+    request: &AuthnRequest,
+    user: &User,
+    now: DateTime<Utc>,
+) -> my_error::Result<String> {
+    let assertion_id = random_id()?;
+    let instant = timestamp(now);
+    let not_on_or_after = timestamp(now + Duration::minutes(ASSERTION_LIFETIME_MINUTES));
+    let issuer = escape_text(issuer);
+    let acs_url = escape_attr(&service_provider.acs_url);
+    let in_response_to = escape_attr(&request.id);
+
+    let head = format!(
+        r#"<saml:Assertion xmlns:saml="{ASSERTION_NS}" ID="{assertion_id}" IssueInstant="{instant}" Version="2.0"><saml:Issuer>{issuer}</saml:Issuer>"#
+    );
+    let tail = format!(
+        concat!(
+            r#"<saml:Subject><saml:NameID Format="{}">{}</saml:NameID>"#,
+            r#"<saml:SubjectConfirmation Method="{}"><saml:SubjectConfirmationData InResponseTo="{}" NotOnOrAfter="{}" Recipient="{}"></saml:SubjectConfirmationData></saml:SubjectConfirmation></saml:Subject>"#,
+            r#"<saml:Conditions NotBefore="{}" NotOnOrAfter="{}"><saml:AudienceRestriction><saml:Audience>{}</saml:Audience></saml:AudienceRestriction></saml:Conditions>"#,
+            r#"<saml:AuthnStatement AuthnInstant="{}" SessionIndex="{}"><saml:AuthnContext><saml:AuthnContextClassRef>{}</saml:AuthnContextClassRef></saml:AuthnContext></saml:AuthnStatement>"#,
+            "{}</saml:Assertion>"
+        ),
+        EMAIL_NAME_ID_FORMAT,
+        escape_text(&String::from(user.email.clone())),
+        BEARER,
+        in_response_to,
+        not_on_or_after,
+        acs_url,
+        instant,
+        not_on_or_after,
+        escape_text(&service_provider.entity_id),
+        instant,
+        assertion_id,
+        PASSWORD_PROTECTED_TRANSPORT,
+        attribute_statement(service_provider, user),
+    );
+
+    // The enveloped-signature transform leaves exactly the unsigned assertion.
+    let digest_value = STANDARD.encode(digest(&SHA256, format!("{head}{tail}").as_bytes()));
+    let signed_info_content = format!(
+        concat!(
+            r#"<ds:CanonicalizationMethod Algorithm="{}"></ds:CanonicalizationMethod>"#,
+            r#"<ds:SignatureMethod Algorithm="{}"></ds:SignatureMethod>"#,
+            r##"<ds:Reference URI="#{}"><ds:Transforms><ds:Transform Algorithm="{}"></ds:Transform><ds:Transform Algorithm="{}"></ds:Transform></ds:Transforms>"##,
+            r#"<ds:DigestMethod Algorithm="{}"></ds:DigestMethod><ds:DigestValue>{}</ds:DigestValue></ds:Reference>"#
+        ),
+        EXC_C14N,
+        credential.signature_algorithm(),
+        assertion_id,
+        ENVELOPED_SIGNATURE,
+        EXC_C14N,
+        SHA256_DIGEST,
+        digest_value,
+    );
+    // Canonicalized on its own, SignedInfo declares the namespace it inherits.
+    let signature_value = STANDARD.encode(
+        credential.sign(
+            format!(r#"<ds:SignedInfo xmlns:ds="{DSIG_NS}">{signed_info_content}</ds:SignedInfo>"#)
+                .as_bytes(),
+        )?,
+    );
+    let signature = format!(
+        concat!(
+            r#"<ds:Signature xmlns:ds="{}"><ds:SignedInfo>{}</ds:SignedInfo><ds:SignatureValue>{}</ds:SignatureValue>"#,
+            "<ds:KeyInfo><ds:X509Data><ds:X509Certificate>{}</ds:X509Certificate></ds:X509Data></ds:KeyInfo></ds:Signature>"
+        ),
+        DSIG_NS,
+        signed_info_content,
+        signature_value,
+        credential.certificate_base64(),
+    );
+
+    Ok(format!(
+        concat!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>"#,
+            r#"<samlp:Response xmlns:samlp="{}" xmlns:saml="{}" Destination="{}" ID="{}" InResponseTo="{}" IssueInstant="{}" Version="2.0">"#,
+            r#"<saml:Issuer>{}</saml:Issuer><samlp:Status><samlp:StatusCode Value="{}"></samlp:StatusCode></samlp:Status>"#,
+            "{}{}{}</samlp:Response>"
+        ),
+        PROTOCOL_NS,
+        ASSERTION_NS,
+        acs_url,
+        random_id()?,
+        in_response_to,
+        instant,
+        issuer,
+        SUCCESS,
+        head,
+        signature,
+        tail,
+    ))
+}
+
+/// One attribute for each name the service provider is configured with and
+/// the user has a value for. Without any, the statement is left out, as it
+/// may not be empty.
+fn attribute_statement(service_provider: &ServiceProvider, user: &User) -> String {
+    let attributes: String = service_provider
+        .attributes
+        .iter()
+        .filter_map(|name| {
+            let value = match name.as_str() {
+                "email" => String::from(user.email.clone()),
+                name => user.attributes.get(name)?.clone(),
+            };
+            Some(format!(
+                r#"<saml:Attribute Name="{}" NameFormat="{}"><saml:AttributeValue>{}</saml:AttributeValue></saml:Attribute>"#,
+                escape_attr(name),
+                BASIC_NAME_FORMAT,
+                escape_text(&value)
+            ))
+        })
+        .collect();
+    if attributes.is_empty() {
+        return attributes;
+    }
+    format!("<saml:AttributeStatement>{attributes}</saml:AttributeStatement>")
+}
+
+fn timestamp(instant: DateTime<Utc>) -> String {
+    instant.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+// xs:ID values have to be NCNames, so they cannot start with a digit.
+fn random_id() -> my_error::Result<String> {
+    let mut bytes = [0u8; 20];
+    SystemRandom::new()
+        .fill(&mut bytes)
+        .map_err(|_| MyError::Internal)?;
+    Ok(bytes.iter().fold(String::from("_"), |mut id, b| {
+        id.push_str(&format!("{:02x}", b));
+        id
+    }))
+}
@@ -0,0 +1,28 @@
+//! Escaping that matches Exclusive XML Canonicalization, so that documents
+//! written with it are already in canonical form and can be signed as is.
+
+use crate::resource::html_page::escape;
+
+/// Character data, C14N 1.0 section 1.1 "Text Nodes".
+pub fn escape_text(text: &str) -> String {
+    escape(text, |c| match c {
+        '&' => Some("&amp;"),
+        '<' => Some("&lt;"),
+        '>' => Some("&gt;"),
+        '\r' => Some("&#xD;"),
+        _ => None,
+    })
+}
+
+/// Double quoted attribute values, C14N 1.0 section 1.1 "Attribute Nodes".
+pub fn escape_attr(value: &str) -> String {
+    escape(value, |c| match c {
+        '&' => Some("&amp;"),
+        '<' => Some("&lt;"),
+        '"' => Some("&quot;"),
+        '\t' => Some("&#x9;"),
+        '\n' => Some("&#xA;"),
+        '\r' => Some("&#xD;"),
+        _ => None,
+    })
+}
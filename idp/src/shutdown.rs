@@ -0,0 +1,39 @@
+//! Graceful shutdown.
+
+use actix_web::{dev::ServerHandle, web};
+
+use crate::app_state::AppState;
+
+/// Seconds in-flight requests get to finish once a shutdown signal arrives.
+pub const SHUTDOWN_TIMEOUT_SECS: u64 = 30;
+
+/// Waits for SIGTERM or Ctrl-C, flips readiness and drains open connections.
+pub async fn drain_on_signal(server: ServerHandle, state: web::Data<AppState>) {
+    wait_for_signal().await;
+    tracing::info!("shutdown signal received, draining connections");
+    state.begin_draining();
+    server.stop(true).await;
+}
+
+#[cfg(unix)]
+async fn wait_for_signal() {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut terminate = match signal(SignalKind::terminate()) {
+        Ok(s) => s,
+        Err(err) => {
+            tracing::warn!(error = %err, "cannot listen for SIGTERM");
+            let _ = tokio::signal::ctrl_c().await;
+            return;
+        }
+    };
+    tokio::select! {
+        _ = terminate.recv() => {},
+        _ = tokio::signal::ctrl_c() => {},
+    }
+}
+
+#[cfg(not(unix))]
+async fn wait_for_signal() {
+    let _ = tokio::signal::ctrl_c().await;
+}
@@ -0,0 +1,12 @@
+pub mod broker_login_store;
+pub mod client_store;
+pub mod consent_store;
+pub mod device_grant_store;
+pub mod dpop_replay_store;
+pub mod email_login_store;
+pub mod group_store;
+pub mod service_provider_store;
+pub mod session_store;
+pub mod token_denylist;
+pub mod user_store;
+pub mod webauthn_ceremony_store;
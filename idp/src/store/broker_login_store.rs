@@ -0,0 +1,29 @@
+//! Broker Login Store.
+
+use std::{collections::HashMap, sync::RwLock};
+
+use crate::{
+    broker::broker_login::BrokerLogin,
+    error::my_error::{self, MyError},
+};
+
+/// In-memory pending upstream logins keyed by `state`.
+#[derive(Debug, Default)]
+pub struct BrokerLoginStore {
+    logins: RwLock<HashMap<String, BrokerLogin>>,
+}
+
+impl BrokerLoginStore {
+    pub fn save(&self, login: BrokerLogin) -> my_error::Result<()> {
+        let mut logins = self.logins.write().map_err(|_| MyError::Storage)?;
+        logins.retain(|_, login| !login.is_expired());
+        logins.insert(login.state.clone(), login);
+        Ok(())
+    }
+
+    /// Removes the login so that a `state` can be used only once.
+    pub fn take(&self, state: &str) -> my_error::Result<Option<BrokerLogin>> {
+        let mut logins = self.logins.write().map_err(|_| MyError::Storage)?;
+        Ok(logins.remove(state).filter(|login| !login.is_expired()))
+    }
+}
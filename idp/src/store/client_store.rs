@@ -0,0 +1,35 @@
+//! Client Store.
+
+use std::{collections::HashMap, sync::RwLock};
+
+use crate::{
+    entity::client::Client,
+    error::my_error::{self, MyError},
+};
+
+/// In-memory client registry keyed by client id.
+#[derive(Debug, Default)]
+pub struct ClientStore {
+    clients: RwLock<HashMap<String, Client>>,
+}
+
+impl ClientStore {
+    pub fn save(&self, client: Client) -> my_error::Result<()> {
+        let mut clients = self.clients.write().map_err(|_| MyError::Storage)?;
+        clients.insert(client.client_id.clone(), client);
+        Ok(())
+    }
+
+    pub fn find(&self, client_id: &str) -> my_error::Result<Option<Client>> {
+        let clients = self.clients.read().map_err(|_| MyError::Storage)?;
+        Ok(clients.get(client_id).cloned())
+    }
+
+    /// Every client, by client id.
+    pub fn list(&self) -> my_error::Result<Vec<Client>> {
+        let clients = self.clients.read().map_err(|_| MyError::Storage)?;
+        let mut clients: Vec<Client> = clients.values().cloned().collect();
+        clients.sort_by(|a, b| a.client_id.cmp(&b.client_id));
+        Ok(clients)
+    }
+}
@@ -0,0 +1,64 @@
+//! Consent Store.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::RwLock,
+};
+
+use crate::{
+    domain::mail_address::MailAddress,
+    entity::consent::Consent,
+    error::my_error::{self, MyError},
+};
+
+/// In-memory consents keyed by mail address, then by client id.
+#[derive(Debug, Default)]
+pub struct ConsentStore {
+    consents: RwLock<HashMap<String, BTreeMap<String, Consent>>>,
+}
+
+impl ConsentStore {
+    /// Adds the scopes to what the user already allowed the client.
+    pub fn grant(&self, consent: Consent) -> my_error::Result<()> {
+        let mut consents = self.consents.write().map_err(|_| MyError::Storage)?;
+        let of_user = consents
+            .entry(String::from(consent.email.clone()))
+            .or_default();
+        match of_user.get_mut(&consent.client_id) {
+            Some(granted) => {
+                granted.scopes.extend(consent.scopes);
+                granted.granted_at = consent.granted_at;
+            }
+            None => {
+                of_user.insert(consent.client_id.clone(), consent);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn find(&self, email: &MailAddress, client_id: &str) -> my_error::Result<Option<Consent>> {
+        let consents = self.consents.read().map_err(|_| MyError::Storage)?;
+        Ok(consents
+            .get(&String::from(email.clone()))
+            .and_then(|of_user| of_user.get(client_id))
+            .cloned())
+    }
+
+    /// Consents of the user, by client id.
+    pub fn list(&self, email: &MailAddress) -> my_error::Result<Vec<Consent>> {
+        let consents = self.consents.read().map_err(|_| MyError::Storage)?;
+        Ok(consents
+            .get(&String::from(email.clone()))
+            .map(|of_user| of_user.values().cloned().collect())
+            .unwrap_or_default())
+    }
+
+    /// The user is asked again the next time the client wants scopes.
+    pub fn revoke(&self, email: &MailAddress, client_id: &str) -> my_error::Result<bool> {
+        let mut consents = self.consents.write().map_err(|_| MyError::Storage)?;
+        Ok(consents
+            .get_mut(&String::from(email.clone()))
+            .and_then(|of_user| of_user.remove(client_id))
+            .is_some())
+    }
+}
@@ -0,0 +1,74 @@
+//! Device Grant Store.
+
+use std::{collections::HashMap, sync::RwLock};
+
+use chrono::Utc;
+
+use crate::{
+    error::{
+        my_error::{self, MyError},
+        oauth_error::OAuthError,
+    },
+    oauth::device_grant::{Approval, DeviceGrant, DeviceGrantStatus},
+};
+
+/// In-memory device grants keyed by device code.
+#[derive(Debug, Default)]
+pub struct DeviceGrantStore {
+    grants: RwLock<HashMap<String, DeviceGrant>>,
+}
+
+impl DeviceGrantStore {
+    /// Grants that were never used are dropped once expired.
+    pub fn save(&self, grant: DeviceGrant) -> my_error::Result<()> {
+        let mut grants = self.grants.write().map_err(|_| MyError::Storage)?;
+        grants.retain(|_, grant| !grant.is_expired());
+        grants.insert(grant.device_code.clone(), grant);
+        Ok(())
+    }
+
+    /// The unexpired grant still waiting for the user with the code.
+    pub fn find_pending(&self, user_code: &str) -> my_error::Result<Option<DeviceGrant>> {
+        let grants = self.grants.read().map_err(|_| MyError::Storage)?;
+        Ok(grants
+            .values()
+            .find(|grant| {
+                grant.user_code == user_code
+                    && grant.status == DeviceGrantStatus::Pending
+                    && !grant.is_expired()
+            })
+            .cloned())
+    }
+
+    /// Records the answer of the user, if the grant is still waiting for one.
+    pub fn answer(&self, device_code: &str, status: DeviceGrantStatus) -> my_error::Result<()> {
+        let mut grants = self.grants.write().map_err(|_| MyError::Storage)?;
+        match grants.get_mut(device_code) {
+            Some(grant) if grant.status == DeviceGrantStatus::Pending && !grant.is_expired() => {
+                grant.status = status;
+                Ok(())
+            }
+            _ => Err(MyError::NotFound),
+        }
+    }
+
+    /// Polls the grant for the device. Once answered or expired, the grant is
+    /// removed, so that a device code is redeemed once.
+    pub fn poll(&self, device_code: &str, client_id: &str) -> my_error::Result<Approval> {
+        let mut grants = self.grants.write().map_err(|_| MyError::Storage)?;
+        let grant = grants
+            .get_mut(device_code)
+            .filter(|grant| grant.client_id == client_id)
+            .ok_or(MyError::OAuth(OAuthError::InvalidGrant))?;
+        let polled = grant.poll(Utc::now());
+        if !matches!(
+            polled,
+            Err(MyError::OAuth(
+                OAuthError::AuthorizationPending | OAuthError::SlowDown
+            ))
+        ) {
+            grants.remove(device_code);
+        }
+        polled
+    }
+}
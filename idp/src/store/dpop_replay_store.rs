@@ -0,0 +1,32 @@
+//! DPoP Replay Store.
+
+use std::{collections::HashMap, sync::RwLock};
+
+use chrono::Utc;
+
+use crate::{
+    error::my_error::{self, MyError},
+    token::dpop::{DpopProof, PROOF_WINDOW_SECS},
+};
+
+/// In-memory `jti`s of the DPoP proofs seen, per key, kept as long as the
+/// proofs would still be fresh.
+#[derive(Debug, Default)]
+pub struct DpopReplayStore {
+    seen: RwLock<HashMap<(String, String), i64>>,
+}
+
+impl DpopReplayStore {
+    /// Records the proof, false when it was seen before.
+    pub fn record(&self, proof: &DpopProof) -> my_error::Result<bool> {
+        let now = Utc::now().timestamp();
+        let mut seen = self.seen.write().map_err(|_| MyError::Storage)?;
+        seen.retain(|_, stale_at| *stale_at >= now);
+        let key = (proof.jkt.clone(), proof.jti.clone());
+        if seen.contains_key(&key) {
+            return Ok(false);
+        }
+        seen.insert(key, proof.iat + PROOF_WINDOW_SECS);
+        Ok(true)
+    }
+}
@@ -0,0 +1,146 @@
+//! Email Login Store.
+
+use std::{collections::HashMap, net::IpAddr, sync::RwLock};
+
+use chrono::{DateTime, Duration, Utc};
+
+use crate::{
+    credential::email_login::{
+        EmailLogin, EmailLoginMethod, ATTEMPT_WINDOW_MINUTES, MAX_CODE_ATTEMPTS,
+        MAX_GUESSES_PER_ADDRESS, MAX_GUESSES_PER_CLIENT, MAX_LOGINS_PER_ADDRESS,
+        MAX_LOGINS_PER_CLIENT, MAX_PENDING_LOGINS,
+    },
+    domain::mail_address::MailAddress,
+    error::my_error::{self, MyError},
+};
+
+/// Who an attempt is counted against.
+#[derive(PartialEq, Eq, Hash, Clone, Debug)]
+enum Attempter {
+    Address(MailAddress),
+    Client(IpAddr),
+}
+
+/// Times of the attempts within the window, by who made them.
+#[derive(Debug, Default)]
+struct AttemptLog(HashMap<Attempter, Vec<DateTime<Utc>>>);
+
+impl AttemptLog {
+    /// Forgets the attempts that left the window.
+    fn prune(&mut self) {
+        let since = Utc::now() - Duration::minutes(ATTEMPT_WINDOW_MINUTES);
+        self.0.retain(|_, times| {
+            times.retain(|time| *time > since);
+            !times.is_empty()
+        });
+    }
+
+    fn exceeded(&self, attempter: &Attempter, max: usize) -> bool {
+        self.0.get(attempter).map_or(0, Vec::len) >= max
+    }
+
+    fn record(&mut self, attempter: Attempter) {
+        self.0.entry(attempter).or_default().push(Utc::now());
+    }
+}
+
+/// In-memory pending mail logins keyed by id, with the attempts made on
+/// them. Locks are taken in field order.
+#[derive(Debug, Default)]
+pub struct EmailLoginStore {
+    logins: RwLock<HashMap<String, EmailLogin>>,
+    started: RwLock<AttemptLog>,
+    guessed: RwLock<AttemptLog>,
+}
+
+impl EmailLoginStore {
+    /// Replaces the oldest login of the address when it has too many
+    /// unexpired ones, so that no one can keep its owner from signing in.
+    /// Logins out of attempts still count until they expire. Refused when
+    /// the address or the client started too many logins lately.
+    pub fn save(&self, login: EmailLogin, client: Option<IpAddr>) -> my_error::Result<()> {
+        let mut logins = self.logins.write().map_err(|_| MyError::Storage)?;
+        let mut started = self.started.write().map_err(|_| MyError::Storage)?;
+        started.prune();
+        let address = Attempter::Address(login.email.clone());
+        let client = client.map(Attempter::Client);
+        if started.exceeded(&address, MAX_LOGINS_PER_ADDRESS)
+            || client
+                .as_ref()
+                .is_some_and(|client| started.exceeded(client, MAX_LOGINS_PER_CLIENT))
+        {
+            return Err(MyError::TooManyRequests);
+        }
+        started.record(address);
+        if let Some(client) = client {
+            started.record(client);
+        }
+
+        logins.retain(|_, login| !login.is_expired());
+        let mut pending: Vec<(String, DateTime<Utc>)> = logins
+            .values()
+            .filter(|pending| pending.email == login.email)
+            .map(|pending| (pending.id.clone(), pending.expires_at))
+            .collect();
+        pending.sort_by_key(|(_, expires_at)| *expires_at);
+        let excess = (pending.len() + 1).saturating_sub(MAX_PENDING_LOGINS);
+        for (id, _) in pending.iter().take(excess) {
+            logins.remove(id);
+        }
+        logins.insert(login.id.clone(), login);
+        Ok(())
+    }
+
+    /// Removes the login of a link, so that it works once.
+    pub fn take_link(&self, id: &str) -> my_error::Result<Option<EmailLogin>> {
+        let mut logins = self.logins.write().map_err(|_| MyError::Storage)?;
+        match logins.get(id) {
+            Some(login) if login.method == EmailLoginMethod::Link && !login.is_expired() => {
+                Ok(logins.remove(id))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Removes the login once its code is entered. A wrong code uses up one
+    /// of the attempts of the login, and counts against its address and the
+    /// client. Refused, even for the right code, when either entered too
+    /// many wrong codes lately.
+    pub fn take_code(
+        &self,
+        id: &str,
+        code: &str,
+        client: Option<IpAddr>,
+    ) -> my_error::Result<Option<EmailLogin>> {
+        let mut logins = self.logins.write().map_err(|_| MyError::Storage)?;
+        let mut guessed = self.guessed.write().map_err(|_| MyError::Storage)?;
+        guessed.prune();
+        let client = client.map(Attempter::Client);
+        if client
+            .as_ref()
+            .is_some_and(|client| guessed.exceeded(client, MAX_GUESSES_PER_CLIENT))
+        {
+            return Err(MyError::TooManyRequests);
+        }
+        let Some(login) = logins.get_mut(id).filter(|login| {
+            login.method == EmailLoginMethod::Code
+                && login.attempts < MAX_CODE_ATTEMPTS
+                && !login.is_expired()
+        }) else {
+            return Ok(None);
+        };
+        let address = Attempter::Address(login.email.clone());
+        if guessed.exceeded(&address, MAX_GUESSES_PER_ADDRESS) {
+            return Err(MyError::TooManyRequests);
+        }
+        if !login.matches_code(code) {
+            login.attempts += 1;
+            guessed.record(address);
+            if let Some(client) = client {
+                guessed.record(client);
+            }
+            return Ok(None);
+        }
+        Ok(logins.remove(id))
+    }
+}
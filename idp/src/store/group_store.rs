@@ -0,0 +1,43 @@
+//! Group Store.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::RwLock,
+};
+
+use crate::{
+    domain::mail_address::MailAddress,
+    entity::group::{memberships, Group},
+    error::my_error::{self, MyError},
+};
+
+/// In-memory groups keyed by name.
+#[derive(Debug, Default)]
+pub struct GroupStore {
+    groups: RwLock<BTreeMap<String, Group>>,
+}
+
+impl GroupStore {
+    pub fn save(&self, group: Group) -> my_error::Result<()> {
+        let mut groups = self.groups.write().map_err(|_| MyError::Storage)?;
+        groups.insert(group.name.clone(), group);
+        Ok(())
+    }
+
+    pub fn find(&self, name: &str) -> my_error::Result<Option<Group>> {
+        let groups = self.groups.read().map_err(|_| MyError::Storage)?;
+        Ok(groups.get(name).cloned())
+    }
+
+    /// Every group, by name.
+    pub fn list(&self) -> my_error::Result<Vec<Group>> {
+        let groups = self.groups.read().map_err(|_| MyError::Storage)?;
+        Ok(groups.values().cloned().collect())
+    }
+
+    /// The groups the user belongs to, nested ones expanded.
+    pub fn memberships(&self, email: &MailAddress) -> my_error::Result<BTreeSet<String>> {
+        let groups = self.groups.read().map_err(|_| MyError::Storage)?;
+        Ok(memberships(&groups, email))
+    }
+}
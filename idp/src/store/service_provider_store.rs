@@ -0,0 +1,33 @@
+//! Service Provider Store.
+
+use std::{collections::HashMap, sync::RwLock};
+
+use crate::{
+    entity::service_provider::ServiceProvider,
+    error::my_error::{self, MyError},
+};
+
+/// In-memory SAML service provider registry keyed by entity id.
+#[derive(Debug, Default)]
+pub struct ServiceProviderStore {
+    service_providers: RwLock<HashMap<String, ServiceProvider>>,
+}
+
+impl ServiceProviderStore {
+    pub fn save(&self, service_provider: ServiceProvider) -> my_error::Result<()> {
+        let mut service_providers = self
+            .service_providers
+            .write()
+            .map_err(|_| MyError::Storage)?;
+        service_providers.insert(service_provider.entity_id.clone(), service_provider);
+        Ok(())
+    }
+
+    pub fn find(&self, entity_id: &str) -> my_error::Result<Option<ServiceProvider>> {
+        let service_providers = self
+            .service_providers
+            .read()
+            .map_err(|_| MyError::Storage)?;
+        Ok(service_providers.get(entity_id).cloned())
+    }
+}
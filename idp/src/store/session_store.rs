@@ -0,0 +1,47 @@
+//! Session Store.
+
+use std::{collections::HashMap, sync::RwLock};
+
+use crate::{
+    entity::session::Session,
+    error::my_error::{self, MyError},
+};
+
+/// In-memory sessions keyed by session id.
+#[derive(Debug, Default)]
+pub struct SessionStore {
+    sessions: RwLock<HashMap<String, Session>>,
+}
+
+impl SessionStore {
+    pub fn save(&self, session: Session) -> my_error::Result<()> {
+        let mut sessions = self.sessions.write().map_err(|_| MyError::Storage)?;
+        sessions.retain(|_, session| !session.is_expired());
+        sessions.insert(session.id.clone(), session);
+        Ok(())
+    }
+
+    /// Expired sessions are not found.
+    pub fn find(&self, id: &str) -> my_error::Result<Option<Session>> {
+        let sessions = self.sessions.read().map_err(|_| MyError::Storage)?;
+        Ok(sessions
+            .get(id)
+            .filter(|session| !session.is_expired())
+            .cloned())
+    }
+
+    /// Records that the client got tokens in the session.
+    pub fn add_client(&self, id: &str, client_id: &str) -> my_error::Result<()> {
+        let mut sessions = self.sessions.write().map_err(|_| MyError::Storage)?;
+        if let Some(session) = sessions.get_mut(id) {
+            session.clients.insert(client_id.to_owned());
+        }
+        Ok(())
+    }
+
+    /// Ends the session.
+    pub fn remove(&self, id: &str) -> my_error::Result<Option<Session>> {
+        let mut sessions = self.sessions.write().map_err(|_| MyError::Storage)?;
+        Ok(sessions.remove(id).filter(|session| !session.is_expired()))
+    }
+}
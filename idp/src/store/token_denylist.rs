@@ -0,0 +1,52 @@
+//! Token Denylist.
+
+use std::{collections::HashSet, sync::RwLock};
+
+use crate::{
+    error::my_error::{self, MyError},
+    token::jwt::Claims,
+};
+
+/// In-memory set of tokens that must no longer be accepted, on their own or
+/// because the session they were issued in has ended.
+#[derive(Debug, Default)]
+pub struct TokenDenylist {
+    tokens: RwLock<HashSet<String>>,
+    session_ids: RwLock<HashSet<String>>,
+}
+
+impl TokenDenylist {
+    pub fn revoke(&self, token: &str) -> my_error::Result<()> {
+        let mut tokens = self.tokens.write().map_err(|_| MyError::Storage)?;
+        tokens.insert(token.to_owned());
+        Ok(())
+    }
+
+    pub fn is_revoked(&self, token: &str) -> my_error::Result<bool> {
+        let tokens = self.tokens.read().map_err(|_| MyError::Storage)?;
+        Ok(tokens.contains(token))
+    }
+
+    /// Revokes every token that carries the session id as `sid`.
+    pub fn revoke_session(&self, session_id: &str) -> my_error::Result<()> {
+        let mut session_ids = self.session_ids.write().map_err(|_| MyError::Storage)?;
+        session_ids.insert(session_id.to_owned());
+        Ok(())
+    }
+
+    /// Whether the token, which decoded to `claims`, is revoked.
+    pub fn rejects(&self, token: &str, claims: &Claims) -> my_error::Result<bool> {
+        if self.is_revoked(token)? {
+            return Ok(true);
+        }
+        let session_ids = self.session_ids.read().map_err(|_| MyError::Storage)?;
+        Ok(claims
+            .session_id()
+            .is_some_and(|session_id| session_ids.contains(session_id)))
+    }
+
+    /// Answers as long as the denylist can still be read.
+    pub fn ping(&self) -> my_error::Result<()> {
+        self.tokens.read().map(|_| ()).map_err(|_| MyError::Storage)
+    }
+}
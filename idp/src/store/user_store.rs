@@ -0,0 +1,82 @@
+//! User Store.
+
+use std::{collections::HashMap, sync::RwLock};
+
+use crate::{
+    domain::mail_address::MailAddress,
+    entity::user::User,
+    error::my_error::{self, MyError},
+};
+
+/// In-memory user store keyed by mail address.
+#[derive(Debug, Default)]
+pub struct UserStore {
+    users: RwLock<HashMap<String, User>>,
+}
+
+impl UserStore {
+    pub fn save(&self, user: User) -> my_error::Result<()> {
+        let mut users = self.users.write().map_err(|_| MyError::Storage)?;
+        users.insert(String::from(user.email.clone()), user);
+        Ok(())
+    }
+
+    /// Saves a user whose mail address is not registered yet.
+    pub fn create(&self, user: User) -> my_error::Result<()> {
+        let mut users = self.users.write().map_err(|_| MyError::Storage)?;
+        let key = String::from(user.email.clone());
+        if users.contains_key(&key) {
+            return Err(MyError::AlreadyExists);
+        }
+        users.insert(key, user);
+        Ok(())
+    }
+
+    /// Replaces the password hash, unless it changed since it was read.
+    pub fn replace_passwd_hash(
+        &self,
+        email: &MailAddress,
+        current: &str,
+        passwd_hash: String,
+    ) -> my_error::Result<bool> {
+        let mut users = self.users.write().map_err(|_| MyError::Storage)?;
+        match users.get_mut(&String::from(email.clone())) {
+            Some(user) if user.passwd_hash.as_deref() == Some(current) => {
+                user.passwd_hash = Some(passwd_hash);
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    pub fn find(&self, email: &MailAddress) -> my_error::Result<Option<User>> {
+        let users = self.users.read().map_err(|_| MyError::Storage)?;
+        Ok(users.get(&String::from(email.clone())).cloned())
+    }
+
+    /// Every user, by mail address.
+    pub fn list(&self) -> my_error::Result<Vec<User>> {
+        let users = self.users.read().map_err(|_| MyError::Storage)?;
+        let mut users: Vec<User> = users.values().cloned().collect();
+        users.sort_by(|a, b| a.email.cmp(&b.email));
+        Ok(users)
+    }
+
+    /// The user who registered the passkey with the base64url credential id.
+    pub fn find_by_passkey(&self, credential_id: &str) -> my_error::Result<Option<User>> {
+        let users = self.users.read().map_err(|_| MyError::Storage)?;
+        Ok(users
+            .values()
+            .find(|user| {
+                user.passkeys
+                    .iter()
+                    .any(|passkey| passkey.id == credential_id)
+            })
+            .cloned())
+    }
+
+    /// Answers as long as the store can still be read.
+    pub fn ping(&self) -> my_error::Result<()> {
+        self.users.read().map(|_| ()).map_err(|_| MyError::Storage)
+    }
+}
@@ -0,0 +1,31 @@
+//! WebAuthn Ceremony Store.
+
+use std::{collections::HashMap, sync::RwLock};
+
+use crate::{
+    error::my_error::{self, MyError},
+    webauthn::ceremony::Ceremony,
+};
+
+/// In-memory pending ceremonies keyed by challenge.
+#[derive(Debug, Default)]
+pub struct WebauthnCeremonyStore {
+    ceremonies: RwLock<HashMap<String, Ceremony>>,
+}
+
+impl WebauthnCeremonyStore {
+    pub fn save(&self, ceremony: Ceremony) -> my_error::Result<()> {
+        let mut ceremonies = self.ceremonies.write().map_err(|_| MyError::Storage)?;
+        ceremonies.retain(|_, ceremony| !ceremony.is_expired());
+        ceremonies.insert(ceremony.challenge.clone(), ceremony);
+        Ok(())
+    }
+
+    /// Removes the ceremony so that a challenge can be answered only once.
+    pub fn take(&self, challenge: &str) -> my_error::Result<Option<Ceremony>> {
+        let mut ceremonies = self.ceremonies.write().map_err(|_| MyError::Storage)?;
+        Ok(ceremonies
+            .remove(challenge)
+            .filter(|ceremony| !ceremony.is_expired()))
+    }
+}
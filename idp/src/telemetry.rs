@@ -0,0 +1,2 @@
+pub mod request_id;
+pub mod subscriber;
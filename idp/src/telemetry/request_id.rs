@@ -0,0 +1,97 @@
+//! Request ids.
+//!
+//! Every request gets an id: the `X-Request-Id` the caller sent, when it is
+//! short and plain enough to log, or else a fresh one. The id is echoed in
+//! the response and recorded on the span the request is handled in.
+
+use std::time::Instant;
+
+use actix_web::{
+    body::MessageBody,
+    dev::{ServiceRequest, ServiceResponse},
+    http::header::{HeaderName, HeaderValue},
+    middleware::Next,
+    HttpMessage,
+};
+use ring::rand::{SecureRandom, SystemRandom};
+use tracing::{field, Instrument};
+
+use crate::error::my_error::{self, MyError};
+
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+/// Longest id taken from a caller.
+pub const MAX_REQUEST_ID_LEN: usize = 128;
+
+const REQUEST_ID_BYTES: usize = 16;
+
+/// Id of the current request, in the request extensions for handlers that
+/// pass it on.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct RequestId(pub String);
+
+/// Middleware that runs each request in a `request` span with its id,
+/// method, path, route and status, and logs it once it is answered.
+pub async fn request_id(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse, actix_web::Error> {
+    let id = match req
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .filter(|id| is_valid_request_id(id))
+    {
+        Some(id) => id.to_owned(),
+        None => new_request_id()?,
+    };
+    req.extensions_mut().insert(RequestId(id.clone()));
+    let span = tracing::info_span!(
+        "request",
+        request_id = %id,
+        method = %req.method(),
+        path = %req.path(),
+        route = field::Empty,
+        status = field::Empty,
+    );
+
+    let started = Instant::now();
+    let mut res = next
+        .call(req)
+        .instrument(span.clone())
+        .await?
+        .map_into_boxed_body();
+    if let Some(route) = res.request().match_pattern() {
+        span.record("route", route);
+    }
+    span.record("status", res.status().as_u16());
+    span.in_scope(|| {
+        tracing::info!(
+            elapsed_ms = started.elapsed().as_millis() as u64,
+            "request answered"
+        )
+    });
+
+    let value = HeaderValue::from_str(&id).map_err(|_| MyError::Internal)?;
+    res.headers_mut()
+        .insert(HeaderName::from_static(REQUEST_ID_HEADER), value);
+    Ok(res)
+}
+
+/// Letters, digits and `-_.:` only, which covers UUIDs and the ids of common
+/// proxies, and keeps anything odd out of the logs.
+pub fn is_valid_request_id(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= MAX_REQUEST_ID_LEN
+        && id
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_' | b'.' | b':'))
+}
+
+/// 128 random bits in hex.
+pub fn new_request_id() -> my_error::Result<String> {
+    let mut bytes = [0u8; REQUEST_ID_BYTES];
+    SystemRandom::new()
+        .fill(&mut bytes)
+        .map_err(|_| MyError::Internal)?;
+    Ok(bytes.iter().map(|b| format!("{:02x}", b)).collect())
+}
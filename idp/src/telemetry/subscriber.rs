@@ -0,0 +1,73 @@
+//! Tracing subscriber.
+//!
+//! Events are written to stdout as JSON lines, each with the spans it
+//! happened in, so that every line of a request carries its id. When
+//! `OTEL_EXPORTER_OTLP_ENDPOINT` is set, e.g. to `http://localhost:4318` for
+//! a local collector, spans are also exported over OTLP/HTTP.
+
+use std::{env, io};
+
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry_sdk::{trace::SdkTracerProvider, Resource};
+use tracing::Subscriber;
+use tracing_subscriber::{
+    fmt::MakeWriter, layer::SubscriberExt, registry::LookupSpan, util::SubscriberInitExt,
+    EnvFilter, Layer,
+};
+
+/// Collector the spans are exported to, if any.
+pub const OTLP_ENDPOINT_ENV: &str = "OTEL_EXPORTER_OTLP_ENDPOINT";
+/// `service.name` of the exported spans.
+pub const SERVICE_NAME: &str = "idp";
+
+/// One JSON object per event, with its fields at the top level and the
+/// spans it is in under `spans`.
+pub fn json_layer<S, W>(writer: W) -> impl Layer<S>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    W: for<'w> MakeWriter<'w> + Send + Sync + 'static,
+{
+    tracing_subscriber::fmt::layer()
+        .json()
+        .flatten_event(true)
+        .with_current_span(false)
+        .with_span_list(true)
+        .with_writer(writer)
+}
+
+/// Installs the subscriber, filtered by `RUST_LOG` or else `default_filter`.
+/// Records of the `log` crate, as dependencies write them, go through it as
+/// well. Returns the tracer provider when spans are exported, to be shut
+/// down on exit so that the last ones are flushed.
+pub fn init(default_filter: &str) -> io::Result<Option<SdkTracerProvider>> {
+    let filter =
+        EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(default_filter));
+    let provider = otlp_tracer_provider()?;
+    let otlp_layer = provider
+        .as_ref()
+        .map(|provider| tracing_opentelemetry::layer().with_tracer(provider.tracer(SERVICE_NAME)));
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(json_layer(io::stdout))
+        .with(otlp_layer)
+        .try_init()
+        .map_err(io::Error::other)?;
+    Ok(provider)
+}
+
+// The exporter reads the endpoint, headers and timeout from the standard
+// OTEL_EXPORTER_OTLP_* variables itself.
+fn otlp_tracer_provider() -> io::Result<Option<SdkTracerProvider>> {
+    if env::var_os(OTLP_ENDPOINT_ENV).is_none() {
+        return Ok(None);
+    }
+    let exporter = opentelemetry_otlp::SpanExporter::builder()
+        .with_http()
+        .build()
+        .map_err(io::Error::other)?;
+    let provider = SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(Resource::builder().with_service_name(SERVICE_NAME).build())
+        .build();
+    Ok(Some(provider))
+}
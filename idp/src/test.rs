@@ -1,2 +1,18 @@
+pub mod broker;
+pub mod credential;
 pub mod domain;
 pub mod entity;
+pub mod error;
+pub mod i18n;
+pub mod mail;
+pub mod oauth;
+pub mod realm;
+pub mod resource;
+pub mod saml;
+pub mod telemetry;
+pub mod test_admin;
+pub mod test_app;
+pub mod test_fuzz_corpus;
+pub mod test_tls;
+pub mod token;
+pub mod webauthn;
@@ -0,0 +1 @@
+pub mod test_account_link;
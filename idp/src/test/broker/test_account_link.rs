@@ -0,0 +1,58 @@
+#[cfg(test)]
+mod tests {
+    use crate::{
+        broker::{account_link::link_account, upstream_provider::UpstreamIdentity},
+        domain::mail_address::MailAddress,
+        entity::user::{LinkedIdentity, User},
+        error::my_error::MyError,
+        store::user_store::UserStore,
+    };
+
+    fn identity(subject: &str) -> UpstreamIdentity {
+        UpstreamIdentity {
+            subject: subject.to_owned(),
+            email: MailAddress::of("kamino@example.com").unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_provisions_new_user() {
+        let users = UserStore::default();
+        let user = link_account(&users, "google", identity("g-1")).unwrap();
+        assert_eq!(
+            user.linked_identities,
+            vec![LinkedIdentity {
+                provider: "google".to_owned(),
+                subject: "g-1".to_owned(),
+            }]
+        );
+        assert_eq!(users.find(&user.email).unwrap(), Some(user));
+    }
+
+    #[test]
+    fn test_links_existing_user() {
+        let users = UserStore::default();
+        let mut existing = User::of(MailAddress::of("kamino@example.com").unwrap());
+        existing.passwd_hash = Some("$argon2id$...".to_owned());
+        users.create(existing).unwrap();
+
+        let user = link_account(&users, "google", identity("g-1")).unwrap();
+        assert_eq!(user.passwd_hash.as_deref(), Some("$argon2id$..."));
+        assert_eq!(user.linked_identities.len(), 1);
+
+        // Signing in again and through another provider keeps every link.
+        link_account(&users, "google", identity("g-1")).unwrap();
+        let user = link_account(&users, "github", identity("gh-7")).unwrap();
+        assert_eq!(user.linked_identities.len(), 2);
+    }
+
+    #[test]
+    fn test_other_subject_refused() {
+        let users = UserStore::default();
+        link_account(&users, "google", identity("g-1")).unwrap();
+        assert!(matches!(
+            link_account(&users, "google", identity("g-2")),
+            Err(MyError::Unauthorized)
+        ));
+    }
+}
@@ -0,0 +1,2 @@
+pub mod test_email_login;
+pub mod test_passwd_hasher;
@@ -0,0 +1,172 @@
+#[cfg(test)]
+mod tests {
+    use crate::{
+        credential::email_login::{
+            decode_link_token, EmailLogin, EmailLoginMethod, MAX_CODE_ATTEMPTS,
+            MAX_GUESSES_PER_ADDRESS, MAX_GUESSES_PER_CLIENT, MAX_LOGINS_PER_CLIENT,
+            MAX_PENDING_LOGINS,
+        },
+        domain::mail_address::MailAddress,
+        error::my_error::MyError,
+        store::email_login_store::EmailLoginStore,
+        token::{
+            jwt::{decode_access_token, make_jwt},
+            signing_key::SigningKeys,
+        },
+    };
+
+    const ISSUER: &str = "http://localhost:8080";
+
+    fn email() -> MailAddress {
+        MailAddress::of("alice@example.com").unwrap()
+    }
+
+    #[test]
+    fn test_code() {
+        let (login, code) = EmailLogin::start(email(), EmailLoginMethod::Code).unwrap();
+        let code = code.unwrap();
+        assert_eq!(code.len(), 6);
+        assert!(code.chars().all(|c| c.is_ascii_digit()));
+        assert!(login.matches_code(&code));
+        assert!(login.matches_code(&format!("{} {}", &code[..3], &code[3..])));
+        let wrong = format!("{:06}", (code.parse::<u32>().unwrap() + 1) % 1_000_000);
+        assert!(!login.matches_code(&wrong));
+
+        let (login, code) = EmailLogin::start(email(), EmailLoginMethod::Link).unwrap();
+        assert_eq!(code, None);
+        assert!(!login.matches_code(""));
+    }
+
+    #[test]
+    fn test_link_token() {
+        let keys = SigningKeys::of("test-secret");
+        let (login, _) = EmailLogin::start(email(), EmailLoginMethod::Link).unwrap();
+        let token = login.link_token(&keys, ISSUER).unwrap();
+        assert_eq!(
+            decode_link_token(&keys, ISSUER, &token).unwrap(),
+            (email(), login.id.clone())
+        );
+        assert!(decode_link_token(&SigningKeys::of("other-secret"), ISSUER, &token).is_err());
+        assert!(decode_link_token(&keys, "http://localhost:8080/realms/acme", &token).is_err());
+
+        // Neither passes for the other.
+        assert!(decode_access_token(&keys, ISSUER, &token).is_err());
+        let access_token = make_jwt(&keys, ISSUER, &email(), None, None).unwrap();
+        assert!(decode_link_token(&keys, ISSUER, &access_token).is_err());
+    }
+
+    #[test]
+    fn test_link_is_taken_once() {
+        let store = EmailLoginStore::default();
+        let (link, _) = EmailLogin::start(email(), EmailLoginMethod::Link).unwrap();
+        let (code, _) = EmailLogin::start(email(), EmailLoginMethod::Code).unwrap();
+        store.save(link.clone(), None).unwrap();
+        store.save(code.clone(), None).unwrap();
+
+        assert_eq!(store.take_link(&code.id).unwrap(), None);
+        assert_eq!(store.take_link(&link.id).unwrap(), Some(link.clone()));
+        assert_eq!(store.take_link(&link.id).unwrap(), None);
+    }
+
+    #[test]
+    fn test_code_attempts() {
+        let store = EmailLoginStore::default();
+        let (login, code) = EmailLogin::start(email(), EmailLoginMethod::Code).unwrap();
+        let code = code.unwrap();
+        store.save(login.clone(), None).unwrap();
+        assert_eq!(
+            store.take_code(&login.id, "not a code", None).unwrap(),
+            None
+        );
+        let taken = store.take_code(&login.id, &code, None).unwrap().unwrap();
+        assert_eq!(taken.attempts, 1);
+        assert_eq!(store.take_code(&login.id, &code, None).unwrap(), None);
+
+        let (login, code) = EmailLogin::start(email(), EmailLoginMethod::Code).unwrap();
+        store.save(login.clone(), None).unwrap();
+        for _ in 0..MAX_CODE_ATTEMPTS {
+            assert_eq!(store.take_code(&login.id, "", None).unwrap(), None);
+        }
+        // Out of attempts, the right code no longer helps.
+        assert_eq!(
+            store.take_code(&login.id, &code.unwrap(), None).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_pending_limit() {
+        let store = EmailLoginStore::default();
+        let mut pending = vec![];
+        for _ in 0..MAX_PENDING_LOGINS {
+            let (login, code) = EmailLogin::start(email(), EmailLoginMethod::Code).unwrap();
+            store.save(login.clone(), None).unwrap();
+            pending.push((login.id, code.unwrap()));
+        }
+        // The newest replaces the oldest, the others stay.
+        let (login, _) = EmailLogin::start(email(), EmailLoginMethod::Link).unwrap();
+        store.save(login.clone(), None).unwrap();
+        let (oldest, code) = &pending[0];
+        assert_eq!(store.take_code(oldest, code, None).unwrap(), None);
+        for (id, code) in &pending[1..] {
+            assert!(store.take_code(id, code, None).unwrap().is_some());
+        }
+        assert!(store.take_link(&login.id).unwrap().is_some());
+
+        let other = MailAddress::of("bob@example.com").unwrap();
+        let (login, _) = EmailLogin::start(other, EmailLoginMethod::Link).unwrap();
+        store.save(login, None).unwrap();
+    }
+
+    #[test]
+    fn test_guesses_span_logins() {
+        let store = EmailLoginStore::default();
+        let mut guesses = 0;
+        while guesses < MAX_GUESSES_PER_ADDRESS {
+            let (login, _) = EmailLogin::start(email(), EmailLoginMethod::Code).unwrap();
+            store.save(login.clone(), None).unwrap();
+            for _ in 0..MAX_CODE_ATTEMPTS.min((MAX_GUESSES_PER_ADDRESS - guesses) as u32) {
+                assert_eq!(store.take_code(&login.id, "", None).unwrap(), None);
+                guesses += 1;
+            }
+        }
+        // A fresh login does not reset the count, even for the right code.
+        let (login, code) = EmailLogin::start(email(), EmailLoginMethod::Code).unwrap();
+        store.save(login.clone(), None).unwrap();
+        assert!(matches!(
+            store.take_code(&login.id, &code.unwrap(), None),
+            Err(MyError::TooManyRequests)
+        ));
+
+        // A client is limited across addresses.
+        let client = Some("192.0.2.1".parse().unwrap());
+        for i in 0..MAX_GUESSES_PER_CLIENT {
+            let email = MailAddress::of(format!("user{}@example.com", i)).unwrap();
+            let (login, _) = EmailLogin::start(email, EmailLoginMethod::Code).unwrap();
+            store.save(login.clone(), None).unwrap();
+            assert_eq!(store.take_code(&login.id, "", client).unwrap(), None);
+        }
+        assert!(matches!(
+            store.take_code("unknown", "", client),
+            Err(MyError::TooManyRequests)
+        ));
+    }
+
+    #[test]
+    fn test_client_login_limit() {
+        let store = EmailLoginStore::default();
+        let client = Some("192.0.2.1".parse().unwrap());
+        for i in 0..MAX_LOGINS_PER_CLIENT {
+            let email = MailAddress::of(format!("user{}@example.com", i)).unwrap();
+            let (login, _) = EmailLogin::start(email, EmailLoginMethod::Link).unwrap();
+            store.save(login, client).unwrap();
+        }
+        let (login, _) = EmailLogin::start(email(), EmailLoginMethod::Link).unwrap();
+        assert!(matches!(
+            store.save(login.clone(), client),
+            Err(MyError::TooManyRequests)
+        ));
+        // Other clients still get through.
+        store.save(login, None).unwrap();
+    }
+}
@@ -0,0 +1,98 @@
+#[cfg(test)]
+mod tests {
+    use argon2::{
+        password_hash::{rand_core::OsRng, PasswordHasher, SaltString},
+        Algorithm, Argon2, Params, Version,
+    };
+    use pbkdf2::{password_hash::Ident, Pbkdf2};
+
+    use crate::credential::passwd_hasher::{
+        dummy_hash, is_supported_hash, needs_rehash, upgraded_hash, verify_password,
+    };
+
+    const PASSWD: &str = "Correct7Horse9Battery";
+
+    fn argon2(algorithm: Algorithm, params: Params) -> String {
+        let salt = SaltString::generate(&mut OsRng);
+        Argon2::new(algorithm, Version::V0x13, params)
+            .hash_password(PASSWD.as_bytes(), &salt)
+            .unwrap()
+            .to_string()
+    }
+
+    fn pbkdf2(ident: &str) -> String {
+        let salt = SaltString::generate(&mut OsRng);
+        let params = pbkdf2::Params {
+            rounds: 1000,
+            output_length: 32,
+        };
+        Pbkdf2
+            .hash_password_customized(
+                PASSWD.as_bytes(),
+                Some(Ident::new(ident).unwrap()),
+                None,
+                params,
+                &salt,
+            )
+            .unwrap()
+            .to_string()
+    }
+
+    fn legacy_hashes() -> Vec<String> {
+        vec![
+            bcrypt::hash(PASSWD, 4).unwrap(),
+            pbkdf2("pbkdf2-sha256"),
+            pbkdf2("pbkdf2-sha512"),
+            argon2(Algorithm::Argon2i, Params::default()),
+            argon2(Algorithm::Argon2id, Params::new(8, 1, 1, None).unwrap()),
+        ]
+    }
+
+    #[test]
+    fn test_legacy_hashes_verify() {
+        for hash in legacy_hashes() {
+            assert!(is_supported_hash(&hash), "{}", hash);
+            assert!(verify_password(PASSWD, &hash).unwrap(), "{}", hash);
+            assert!(
+                !verify_password("Wrong7Horse9Battery", &hash).unwrap(),
+                "{}",
+                hash
+            );
+            assert!(needs_rehash(&hash), "{}", hash);
+        }
+    }
+
+    #[test]
+    fn test_upgraded_hash() {
+        for hash in legacy_hashes() {
+            let upgraded = upgraded_hash(PASSWD, &hash).unwrap().unwrap();
+            assert!(upgraded.starts_with("$argon2id$v=19$"));
+            assert!(verify_password(PASSWD, &upgraded).unwrap());
+            assert!(!needs_rehash(&upgraded));
+            assert_eq!(upgraded_hash(PASSWD, &upgraded).unwrap(), None);
+        }
+    }
+
+    #[test]
+    fn test_unsupported_hashes() {
+        for hash in [
+            "",
+            "plaintext",
+            "$1$saltsalt$hash",
+            "$scrypt$ln=16,r=8,p=1$c2FsdA$aGFzaA",
+            "$2b$1é$",
+        ] {
+            assert!(!is_supported_hash(hash), "{}", hash);
+            assert!(verify_password(PASSWD, hash).is_err(), "{}", hash);
+        }
+    }
+
+    #[test]
+    fn test_dummy_hash() {
+        // Costs as much as the hashes of users.
+        assert!(dummy_hash().starts_with("$argon2id$v=19$"));
+        assert!(!needs_rehash(dummy_hash()));
+        assert!(!verify_password(PASSWD, dummy_hash()).unwrap());
+        assert_eq!(dummy_hash(), dummy_hash());
+    }
+}
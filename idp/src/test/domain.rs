@@ -1,2 +1,4 @@
 pub mod test_mail_address;
+pub mod test_mail_address_properties;
 pub mod test_my_float;
+pub mod test_password;
@@ -1,5 +1,7 @@
 #[cfg(test)]
 mod tests {
+    use std::collections::HashMap;
+
     use crate::domain::mail_address::MailAddress;
 
     #[test]
@@ -51,4 +53,140 @@ mod tests {
         assert_ne!(mail_2, mail_3);
         assert_ne!(mail_1, mail_3);
     }
+
+    #[test]
+    fn test_dot_is_not_a_wildcard() {
+        assert!(MailAddress::of("test,test@gmail.com").is_err());
+        assert!(MailAddress::of("test.test@gmail,com").is_err());
+    }
+
+    #[test]
+    fn test_dot_atom_ng() {
+        assert!(MailAddress::of(".test@gmail.com").is_err());
+        assert!(MailAddress::of("test.@gmail.com").is_err());
+        assert!(MailAddress::of("test..test@gmail.com").is_err());
+        assert!(MailAddress::of("@gmail.com").is_err());
+        assert!(MailAddress::of("test@").is_err());
+        assert!(MailAddress::of("test").is_err());
+    }
+
+    #[test]
+    fn test_atext_ok() {
+        let mail_string = "o'brien+tag!#$%&*/=?^_`{|}~-@example.com";
+        let result = MailAddress::of(mail_string);
+        assert_eq!(String::from(result.unwrap()), mail_string);
+    }
+
+    #[test]
+    fn test_quoted_local_part_ok() {
+        let result = MailAddress::of(r#""john doe"@example.com"#).unwrap();
+        assert_eq!(String::from(result), r#""john doe"@example.com"#);
+
+        let result = MailAddress::of(r#""at@sign"@example.com"#).unwrap();
+        assert_eq!(String::from(result), r#""at@sign"@example.com"#);
+
+        let result = MailAddress::of(r#""esc\"quote"@example.com"#).unwrap();
+        assert_eq!(String::from(result), r#""esc\"quote"@example.com"#);
+    }
+
+    #[test]
+    fn test_redundant_quotes_removed() {
+        let quoted = MailAddress::of(r#""fuga.test"@gmail.com"#).unwrap();
+        let plain = MailAddress::of("fuga.test@gmail.com").unwrap();
+        assert_eq!(quoted, plain);
+    }
+
+    #[test]
+    fn test_quoted_local_part_ng() {
+        assert!(MailAddress::of(r#""unterminated@example.com"#).is_err());
+        assert!(MailAddress::of(r#""a"b"@example.com"#).is_err());
+        assert!(MailAddress::of(r#"""@example.com"#).is_err());
+    }
+
+    #[test]
+    fn test_domain_case_insensitive() {
+        let lower = MailAddress::of("Fuga.Test@gmail.com").unwrap();
+        let upper = MailAddress::of("Fuga.Test@GMAIL.COM").unwrap();
+        assert_eq!(lower, upper);
+        assert_eq!(String::from(upper), "Fuga.Test@gmail.com");
+
+        // The local part stays case-sensitive.
+        let other = MailAddress::of("fuga.test@gmail.com").unwrap();
+        assert_ne!(lower, other);
+    }
+
+    #[test]
+    fn test_lookup_by_canonical_form() {
+        let mut users = HashMap::new();
+        users.insert(MailAddress::of("hoge@EXAMPLE.com").unwrap(), 1);
+        let key = MailAddress::of("hoge@example.COM").unwrap();
+        assert_eq!(users.get(&key), Some(&1));
+    }
+
+    #[test]
+    fn test_idn_domain_punycode() {
+        let result = MailAddress::of("user@Bücher.example").unwrap();
+        assert_eq!(String::from(result), "user@xn--bcher-kva.example");
+        assert_eq!(
+            MailAddress::of("user@bücher.example").unwrap(),
+            MailAddress::of("user@xn--bcher-kva.example").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_domain_ng() {
+        assert!(MailAddress::of("test@localhost").is_err());
+        assert!(MailAddress::of("test@-gmail.com").is_err());
+        assert!(MailAddress::of("test@gmail-.com").is_err());
+        assert!(MailAddress::of("test@gmail..com").is_err());
+        assert!(MailAddress::of("test@gmail_com.com").is_err());
+        assert!(MailAddress::of("test@1.2.3.4").is_err());
+    }
+
+    #[test]
+    fn test_address_literal() {
+        let v4 = MailAddress::of("test@[192.168.0.1]").unwrap();
+        assert_eq!(String::from(v4), "test@[192.168.0.1]");
+        let v6 = MailAddress::of("test@[ipv6:2001:DB8:0:0:0:0:0:1]").unwrap();
+        assert_eq!(String::from(v6), "test@[IPv6:2001:db8::1]");
+        assert!(MailAddress::of("test@[999.0.0.1]").is_err());
+    }
+
+    #[test]
+    fn test_length_limits() {
+        let local_64 = "a".repeat(64);
+        assert!(MailAddress::of(format!("{}@example.com", local_64)).is_ok());
+        assert!(MailAddress::of(format!("a{}@example.com", local_64)).is_err());
+
+        let label_63 = "b".repeat(63);
+        assert!(MailAddress::of(format!("a@{}.com", label_63)).is_ok());
+        assert!(MailAddress::of(format!("a@b{}.com", label_63)).is_err());
+
+        // Four labels of 63 octets make a 255 octet domain.
+        let long_domain = [label_63.as_str(); 4].join(".");
+        assert!(MailAddress::of(format!("a@{}", long_domain)).is_err());
+
+        // 64 + 1 + 190 octets exceeds the 254 octet path.
+        let domain_190 = format!("{}.{}.{}", label_63, label_63, "c".repeat(62));
+        assert!(MailAddress::of(format!("a@{}", domain_190)).is_ok());
+        assert!(MailAddress::of(format!("{}@{}", local_64, domain_190)).is_err());
+    }
+
+    #[test]
+    fn test_serde_as_string() {
+        let mail = MailAddress::of("hoge@Example.com").unwrap();
+        assert_eq!(
+            serde_json::to_string(&mail).unwrap(),
+            r#""hoge@example.com""#
+        );
+
+        let parsed: MailAddress = serde_json::from_str(r#""hoge@EXAMPLE.com""#).unwrap();
+        assert_eq!(parsed, mail);
+    }
+
+    #[test]
+    fn test_deserialize_validates() {
+        let result: Result<MailAddress, _> = serde_json::from_str(r#""test.test@@@gmail.com""#);
+        assert!(result.is_err());
+    }
 }
@@ -0,0 +1,82 @@
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use crate::domain::mail_address::MailAddress;
+
+    // Dot-atom local parts, already canonical.
+    fn local_part() -> impl Strategy<Value = String> {
+        prop::collection::vec("[a-zA-Z0-9!#$%&'*+/=?^_`{|}~-]{1,8}", 1..4)
+            .prop_map(|atoms| atoms.join("."))
+    }
+
+    // Lowercase host names with an alphabetic top-level label. Labels have no
+    // double hyphens, which IDNA reserves at the third and fourth position.
+    fn domain() -> impl Strategy<Value = String> {
+        (
+            prop::collection::vec("[a-z0-9](-?[a-z0-9]){0,8}", 1..4),
+            "[a-z]{2,6}",
+        )
+            .prop_map(|(labels, tld)| format!("{}.{}", labels.join("."), tld))
+    }
+
+    fn address() -> impl Strategy<Value = String> {
+        (local_part(), domain()).prop_map(|(local, domain)| format!("{}@{}", local, domain))
+    }
+
+    proptest! {
+        #[test]
+        fn test_valid_addresses_round_trip(address in address()) {
+            let email = MailAddress::of(address.clone()).unwrap();
+            prop_assert_eq!(String::from(email), address);
+        }
+
+        #[test]
+        fn test_domain_case_is_normalized(local in local_part(), domain in domain()) {
+            let upper = MailAddress::of(format!("{}@{}", local, domain.to_uppercase())).unwrap();
+            let lower = MailAddress::of(format!("{}@{}", local, domain)).unwrap();
+            prop_assert_eq!(upper.local_part(), local.as_str());
+            prop_assert_eq!(upper, lower);
+        }
+
+        #[test]
+        fn test_needless_quotes_are_dropped(local in local_part(), domain in domain()) {
+            let quoted = MailAddress::of(format!("\"{}\"@{}", local, domain)).unwrap();
+            prop_assert_eq!(quoted, MailAddress::of(format!("{}@{}", local, domain)).unwrap());
+        }
+
+        #[test]
+        fn test_canonical_form_is_a_fixed_point(input in "\\PC{0,80}") {
+            if let Ok(email) = MailAddress::of(input) {
+                let canonical = String::from(email.clone());
+                prop_assert!(canonical.len() <= 254);
+                prop_assert_eq!(MailAddress::of(canonical).unwrap(), email);
+            }
+        }
+
+        #[test]
+        fn test_specials_need_quoting(
+            local in local_part(),
+            domain in domain(),
+            special in "[ (),:;<>\\[\\]\\\\\"]",
+        ) {
+            let rejected = MailAddress::of(format!("{}{}@{}", local, special, domain)).is_err();
+            prop_assert!(rejected);
+        }
+
+        #[test]
+        fn test_malformed_addresses(local in local_part(), domain in domain()) {
+            for address in [
+                format!("{}{}", local, domain),
+                format!(".{}@{}", local, domain),
+                format!("{}.@{}", local, domain),
+                format!("{}..x@{}", local, domain),
+                format!("{}@{}.", local, domain),
+                format!("{}@{}", local, domain.replace('.', "_")),
+            ] {
+                let rejected = MailAddress::of(address.clone()).is_err();
+                prop_assert!(rejected, "{} was accepted", address);
+            }
+        }
+    }
+}
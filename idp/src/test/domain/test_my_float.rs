@@ -1,4 +1,4 @@
-use std::f64::NAN;
+#![cfg(test)]
 
 use crate::domain::my_float::MyFloat;
 
@@ -13,7 +13,7 @@ fn test_object_eq_ok() {
 
 #[test]
 fn test_nan_ne_ok() {
-    let num: f64 = NAN;
+    let num: f64 = f64::NAN;
     let result_1 = MyFloat::of(num);
     let result_2 = MyFloat::of(num);
     assert_ne!(result_1, result_2);
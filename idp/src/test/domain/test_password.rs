@@ -0,0 +1,139 @@
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, process};
+
+    use crate::{
+        domain::{
+            mail_address::MailAddress,
+            password::Password,
+            password_policy::{estimate_entropy, CharacterClass, PasswordPolicy, PolicyViolation},
+        },
+        error::my_error::MyError,
+    };
+
+    fn mail() -> MailAddress {
+        MailAddress::of("kamino@example.com").unwrap()
+    }
+
+    fn violations_of(result: Result<Password, MyError>) -> Vec<PolicyViolation> {
+        match result {
+            Err(MyError::PasswordPolicy(violations)) => violations,
+            other => panic!("expected a policy error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_password_ok() {
+        let policy = PasswordPolicy::default();
+        assert!(Password::of("Correct7Horse9Battery", &policy, &mail()).is_ok());
+    }
+
+    #[test]
+    fn test_lists_every_violation() {
+        let policy = PasswordPolicy::default();
+        let violations = violations_of(Password::of("aaaa", &policy, &mail()));
+        assert_eq!(violations.len(), 4);
+        assert_eq!(violations[0], PolicyViolation::TooShort { min_length: 12 });
+        assert_eq!(
+            violations[1],
+            PolicyViolation::MissingCharacterClass {
+                class: CharacterClass::Uppercase
+            }
+        );
+        assert_eq!(
+            violations[2],
+            PolicyViolation::MissingCharacterClass {
+                class: CharacterClass::Digit
+            }
+        );
+        assert!(matches!(violations[3], PolicyViolation::LowEntropy { .. }));
+    }
+
+    #[test]
+    fn test_local_part_banned() {
+        let policy = PasswordPolicy::default();
+        let violations = violations_of(Password::of("Xy7Kamino!2024q", &policy, &mail()));
+        assert_eq!(violations, vec![PolicyViolation::ContainsLocalPart]);
+    }
+
+    #[test]
+    fn test_configurable_policy() {
+        let mut policy = PasswordPolicy::default();
+        policy.min_length = 4;
+        policy.required_classes = vec![CharacterClass::Symbol];
+        policy.min_entropy_bits = 0.0;
+        assert!(Password::of("ab!d", &policy, &mail()).is_ok());
+        let violations = violations_of(Password::of("abcd", &policy, &mail()));
+        assert_eq!(
+            violations,
+            vec![PolicyViolation::MissingCharacterClass {
+                class: CharacterClass::Symbol
+            }]
+        );
+    }
+
+    #[test]
+    fn test_policy_from_vars() {
+        let vars = |pairs: &'static [(&'static str, &'static str)]| {
+            move |name: &str| {
+                pairs
+                    .iter()
+                    .find(|(key, _)| *key == name)
+                    .map(|(_, value)| value.to_string())
+            }
+        };
+        let policy = PasswordPolicy::from_vars(vars(&[
+            ("IDP_PASSWORD_MIN_LENGTH", "16"),
+            ("IDP_PASSWORD_MIN_ENTROPY_BITS", "60.5"),
+            ("IDP_PASSWORD_CHARACTER_CLASSES", "digit, symbol"),
+        ]))
+        .unwrap();
+        assert_eq!(policy.min_length, 16);
+        assert_eq!(policy.min_entropy_bits, 60.5);
+        assert_eq!(
+            policy.required_classes,
+            vec![CharacterClass::Digit, CharacterClass::Symbol]
+        );
+
+        // A typo refuses the start instead of weakening the policy.
+        for invalid in [
+            &[("IDP_PASSWORD_MIN_LENGTH", "twelve")][..],
+            &[("IDP_PASSWORD_MIN_LENGTH", "-1")],
+            &[("IDP_PASSWORD_MIN_ENTROPY_BITS", "NaN")],
+            &[("IDP_PASSWORD_CHARACTER_CLASSES", "digit,symbols")],
+        ] {
+            assert!(PasswordPolicy::from_vars(vars(invalid)).is_err());
+        }
+    }
+
+    #[test]
+    fn test_breached_list() {
+        let path = env::temp_dir().join(format!("idp-breached-{}.txt", process::id()));
+        fs::write(&path, "# comment\nPassword1234567\n\n").unwrap();
+        let policy = PasswordPolicy::default().with_breached_list(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let violations = violations_of(Password::of("password1234567", &policy, &mail()));
+        assert!(violations.contains(&PolicyViolation::Breached));
+    }
+
+    #[test]
+    fn test_breached_list_missing_file() {
+        let result = PasswordPolicy::default().with_breached_list("/nonexistent/breached.txt");
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_entropy_penalizes_sequences() {
+        assert!(estimate_entropy("abcdefgh") < estimate_entropy("hqzbmxwk"));
+        assert!(estimate_entropy("aaaaaaaa") < estimate_entropy("hqzbmxwk"));
+        assert_eq!(estimate_entropy(""), 0.0);
+    }
+
+    #[test]
+    fn test_debug_hides_password() {
+        let policy = PasswordPolicy::default();
+        let passwd = Password::of("Correct7Horse9Battery", &policy, &mail()).unwrap();
+        assert!(!format!("{:?}", passwd).contains("Horse"));
+    }
+}
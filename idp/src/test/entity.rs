@@ -1 +1,3 @@
+pub mod test_consent;
+pub mod test_group;
 pub mod test_user;
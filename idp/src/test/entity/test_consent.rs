@@ -0,0 +1,18 @@
+#[cfg(test)]
+mod tests {
+    use crate::{domain::mail_address::MailAddress, entity::consent::Consent};
+
+    fn scopes(scopes: &[&str]) -> Vec<String> {
+        scopes.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_covers() {
+        let email = MailAddress::of("kamino@example.com").unwrap();
+        let consent = Consent::of(email, "tv", &scopes(&["todo:read", "todo:write"]));
+        assert!(consent.covers(&[]));
+        assert!(consent.covers(&scopes(&["todo:read"])));
+        assert!(consent.covers(&scopes(&["todo:write", "todo:read"])));
+        assert!(!consent.covers(&scopes(&["todo:read", "todo:delete"])));
+    }
+}
@@ -0,0 +1,78 @@
+#[cfg(test)]
+mod tests {
+    use std::collections::{BTreeMap, BTreeSet};
+
+    use crate::{
+        domain::mail_address::MailAddress,
+        entity::group::{contains_group, is_group_name, memberships, Group},
+    };
+
+    fn email(address: &str) -> MailAddress {
+        MailAddress::of(address).unwrap()
+    }
+
+    fn groups(groups: Vec<Group>) -> BTreeMap<String, Group> {
+        groups
+            .into_iter()
+            .map(|group| (group.name.clone(), group))
+            .collect()
+    }
+
+    fn names(names: &[&str]) -> BTreeSet<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn test_nested_memberships() {
+        let groups = groups(vec![
+            Group::of("engineering")
+                .with_subgroup("backend")
+                .with_subgroup("frontend"),
+            Group::of("backend").with_member(email("kamino@example.com")),
+            Group::of("frontend").with_member(email("shun@example.com")),
+            Group::of("staff").with_subgroup("engineering"),
+            Group::of("oncall").with_member(email("kamino@example.com")),
+        ]);
+        assert_eq!(
+            memberships(&groups, &email("kamino@example.com")),
+            names(&["backend", "engineering", "oncall", "staff"])
+        );
+        assert_eq!(
+            memberships(&groups, &email("shun@example.com")),
+            names(&["engineering", "frontend", "staff"])
+        );
+        assert!(memberships(&groups, &email("guest@example.com")).is_empty());
+    }
+
+    #[test]
+    fn test_cycles() {
+        let mut groups = groups(vec![
+            Group::of("a").with_subgroup("b"),
+            Group::of("b").with_subgroup("c"),
+            Group::of("c").with_member(email("kamino@example.com")),
+        ]);
+        assert!(contains_group(&groups, "a", "c"));
+        assert!(contains_group(&groups, "a", "a"));
+        assert!(!contains_group(&groups, "c", "a"));
+
+        // A cycle from a hand-edited state file still expands.
+        groups
+            .get_mut("c")
+            .unwrap()
+            .subgroups
+            .insert("a".to_owned());
+        assert_eq!(
+            memberships(&groups, &email("kamino@example.com")),
+            names(&["a", "b", "c"])
+        );
+        assert!(contains_group(&groups, "c", "b"));
+    }
+
+    #[test]
+    fn test_group_names() {
+        assert!(is_group_name("team:payments"));
+        assert!(!is_group_name(""));
+        assert!(!is_group_name("two words"));
+        assert!(!is_group_name(&"x".repeat(65)));
+    }
+}
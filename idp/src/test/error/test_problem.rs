@@ -0,0 +1,166 @@
+#[cfg(test)]
+mod tests {
+    use actix_web::{
+        dev::{Service, ServiceResponse},
+        http::{header, StatusCode},
+        test, web, App,
+    };
+    use serde_json::{json, Value};
+
+    use crate::{
+        app_state::AppState,
+        domain::password_policy::PolicyViolation,
+        error::{
+            my_error::MyError,
+            problem::{json_error_handler, not_found_handler, ProblemDetails},
+        },
+        i18n::locale::Locale,
+        realm::realm_registry::{Realm, RealmRegistry, DEFAULT_REALM},
+        resource::idp_resource::{make_jwt_handler, validate_jwt_handler},
+        token::signing_key::SigningKeys,
+    };
+
+    async fn app(
+    ) -> impl Service<actix_http::Request, Response = ServiceResponse, Error = actix_web::Error>
+    {
+        let realm = Realm::of(
+            DEFAULT_REALM,
+            "http://localhost:8080",
+            SigningKeys::of("test-secret"),
+        );
+        let state = AppState::of(RealmRegistry::of(vec![realm]).unwrap());
+        test::init_service(
+            App::new()
+                .app_data(web::Data::new(state))
+                .app_data(
+                    web::JsonConfig::default()
+                        .limit(4096)
+                        .error_handler(json_error_handler),
+                )
+                .service(web::resource("/jwt").route(web::post().to(make_jwt_handler)))
+                .service(web::resource("/validate").route(web::post().to(validate_jwt_handler)))
+                .default_service(web::to(not_found_handler)),
+        )
+        .await
+    }
+
+    async fn problem(res: ServiceResponse) -> Value {
+        assert_eq!(
+            res.headers().get(header::CONTENT_TYPE).unwrap(),
+            "application/problem+json"
+        );
+        test::read_body_json(res).await
+    }
+
+    #[actix_web::test]
+    async fn test_invalid_email_is_field_error() {
+        let app = app().await;
+        let req = test::TestRequest::post()
+            .uri("/jwt")
+            .set_json(json!({"email": "test.test@@@gmail.com", "passwd": "x"}))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        let body = problem(res).await;
+        assert_eq!(body["type"], "/problems/invalid-request");
+        assert_eq!(body["title"], "Invalid Request");
+        assert_eq!(body["status"], 400);
+        assert!(body["detail"].is_string());
+        assert_eq!(body["errors"][0]["field"], "email");
+        assert_eq!(body["errors"][0]["code"], "invalid");
+    }
+
+    #[actix_web::test]
+    async fn test_missing_field_is_field_error() {
+        let app = app().await;
+        let req = test::TestRequest::post()
+            .uri("/jwt")
+            .set_json(json!({"email": "test@example.com"}))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        let body = problem(res).await;
+        assert_eq!(body["errors"][0]["field"], "passwd");
+        assert_eq!(body["errors"][0]["code"], "missing");
+    }
+
+    #[actix_web::test]
+    async fn test_malformed_body() {
+        let app = app().await;
+        let req = test::TestRequest::post()
+            .uri("/jwt")
+            .insert_header(header::ContentType::json())
+            .set_payload("{\"email\": ")
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(problem(res).await["type"], "/problems/malformed-body");
+    }
+
+    #[actix_web::test]
+    async fn test_wrong_content_type() {
+        let app = app().await;
+        let req = test::TestRequest::post()
+            .uri("/jwt")
+            .insert_header(header::ContentType::form_url_encoded())
+            .set_payload("email=test@example.com")
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+        assert_eq!(problem(res).await["status"], 415);
+    }
+
+    #[actix_web::test]
+    async fn test_payload_too_large() {
+        let app = app().await;
+        let req = test::TestRequest::post()
+            .uri("/jwt")
+            .set_json(json!({"email": "test@example.com", "passwd": "x".repeat(5000)}))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::PAYLOAD_TOO_LARGE);
+        assert_eq!(problem(res).await["type"], "/problems/payload-too-large");
+    }
+
+    #[actix_web::test]
+    async fn test_invalid_token() {
+        let app = app().await;
+        let req = test::TestRequest::post()
+            .uri("/validate")
+            .set_json(json!({"email": "test@example.com", "token": "not-a-token"}))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(problem(res).await["type"], "/problems/invalid-token");
+    }
+
+    #[actix_web::test]
+    async fn test_unknown_route() {
+        let app = app().await;
+        let req = test::TestRequest::get().uri("/nowhere").to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+        assert_eq!(problem(res).await["type"], "/problems/not-found");
+    }
+
+    #[actix_web::test]
+    async fn test_password_violations_are_localized() {
+        let err = MyError::PasswordPolicy(vec![PolicyViolation::TooShort { min_length: 12 }]);
+        let problem = ProblemDetails::localized(&err, Locale::Ja);
+        assert_eq!(problem.status, 400);
+        assert_eq!(problem.title, "脆弱なパスワード");
+        assert_eq!(problem.errors[0].code, "too_short");
+        assert_eq!(
+            problem.errors[0].message,
+            "パスワードは 12 文字以上にしてください"
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_too_many_requests() {
+        let problem = ProblemDetails::localized(&MyError::TooManyRequests, Locale::Ja);
+        assert_eq!(problem.status, 429);
+        assert_eq!(problem.problem_type, "/problems/too-many-requests");
+        assert_eq!(problem.title, "リクエスト過多");
+    }
+}
@@ -0,0 +1,2 @@
+pub mod test_catalog;
+pub mod test_locale;
@@ -0,0 +1,60 @@
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use crate::i18n::{
+        catalog::{fill, format_message, lookup, message, message_ids},
+        locale::Locale,
+    };
+
+    fn placeholders(template: &str) -> BTreeSet<&str> {
+        template
+            .split('{')
+            .skip(1)
+            .filter_map(|rest| rest.split_once('}').map(|(name, _)| name))
+            .collect()
+    }
+
+    #[test]
+    fn test_japanese_catalog_is_complete() {
+        for id in message_ids(Locale::En) {
+            let ja = lookup(Locale::Ja, id).unwrap_or_else(|| panic!("{} is not translated", id));
+            let en = lookup(Locale::En, id).unwrap();
+            assert_eq!(placeholders(en), placeholders(ja), "{}", id);
+        }
+        // Field messages stay the parser's in English.
+        for id in message_ids(Locale::Ja) {
+            assert!(
+                lookup(Locale::En, id).is_some() || id.starts_with("field."),
+                "{} is only in Japanese",
+                id
+            );
+        }
+    }
+
+    #[test]
+    fn test_fallback() {
+        assert_eq!(message(Locale::Ja, "page.deny"), "拒否");
+        assert_eq!(message(Locale::En, "page.deny"), "Deny");
+        assert_eq!(message(Locale::Ja, "no.such.message"), "no.such.message");
+        assert_eq!(lookup(Locale::En, "field.missing"), None);
+    }
+
+    #[test]
+    fn test_placeholders() {
+        assert_eq!(
+            format_message(Locale::En, "mail.login.subject", &[("realm", "acme")]),
+            "Sign in to acme"
+        );
+        assert_eq!(
+            format_message(Locale::Ja, "mail.login.subject", &[("realm", "acme")]),
+            "acme へのサインイン"
+        );
+        // Values are not read as placeholders, unknown ones are kept.
+        assert_eq!(
+            fill("{a} and {b} {c", &[("a", "{b}"), ("b", "x")]),
+            "{b} and x {c"
+        );
+        assert_eq!(fill("{unknown}", &[]), "{unknown}");
+    }
+}
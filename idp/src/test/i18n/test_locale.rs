@@ -0,0 +1,60 @@
+#[cfg(test)]
+mod tests {
+    use actix_web::{http::header, test::TestRequest};
+
+    use crate::i18n::locale::Locale;
+
+    #[test]
+    fn test_of_tag() {
+        assert_eq!(Locale::of_tag("ja"), Some(Locale::Ja));
+        assert_eq!(Locale::of_tag("ja-JP"), Some(Locale::Ja));
+        assert_eq!(Locale::of_tag("EN_us"), Some(Locale::En));
+        assert_eq!(Locale::of_tag("fr"), None);
+        assert_eq!(Locale::of_tag("*"), None);
+        assert_eq!(Locale::of_tag(""), None);
+    }
+
+    #[test]
+    fn test_accept_language() {
+        assert_eq!(
+            Locale::from_accept_language("ja,en-US;q=0.8"),
+            Some(Locale::Ja)
+        );
+        assert_eq!(
+            Locale::from_accept_language("en-US;q=0.5, ja-JP;q=0.9"),
+            Some(Locale::Ja)
+        );
+        // The first supported one, not the first one.
+        assert_eq!(
+            Locale::from_accept_language("fr-FR, ja;q=0.7, en;q=0.3"),
+            Some(Locale::Ja)
+        );
+        assert_eq!(Locale::from_accept_language("ja;q=0, en"), Some(Locale::En));
+        assert_eq!(Locale::from_accept_language("fr, *;q=0.5"), None);
+        assert_eq!(Locale::from_accept_language("ja;q=abc"), None);
+    }
+
+    #[test]
+    fn test_of_request() {
+        let req = TestRequest::get().uri("/device").to_http_request();
+        assert_eq!(Locale::of_request(&req), Locale::En);
+
+        let req = TestRequest::get()
+            .uri("/device")
+            .insert_header((header::ACCEPT_LANGUAGE, "ja-JP,ja;q=0.9"))
+            .to_http_request();
+        assert_eq!(Locale::of_request(&req), Locale::Ja);
+
+        // ui_locales wins over the browser, unless it names nothing supported.
+        let req = TestRequest::get()
+            .uri("/device?ui_locales=en%20ja")
+            .insert_header((header::ACCEPT_LANGUAGE, "ja"))
+            .to_http_request();
+        assert_eq!(Locale::of_request(&req), Locale::En);
+        let req = TestRequest::get()
+            .uri("/device?ui_locales=de+fr")
+            .insert_header((header::ACCEPT_LANGUAGE, "ja"))
+            .to_http_request();
+        assert_eq!(Locale::of_request(&req), Locale::Ja);
+    }
+}
@@ -0,0 +1,33 @@
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, process};
+
+    use crate::{
+        domain::mail_address::MailAddress,
+        mail::mailer::{Mail, Mailer, OutboxMailer},
+    };
+
+    #[test]
+    fn test_outbox_mailer() {
+        let dir = env::temp_dir().join(format!("idp-outbox-{}", process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let mailer = OutboxMailer::of(&dir).unwrap();
+        let mail = Mail {
+            to: MailAddress::of("alice@example.com").unwrap(),
+            subject: "Sign in to default".to_owned(),
+            body: "Your sign-in code is 123456.\n".to_owned(),
+        };
+        mailer.send(&mail).unwrap();
+        mailer.send(&mail).unwrap();
+
+        let files: Vec<_> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        assert_eq!(files.len(), 2);
+        let message = fs::read_to_string(&files[0]).unwrap();
+        assert!(message.starts_with("To: alice@example.com\r\nSubject: Sign in to default\r\n"));
+        assert!(message.ends_with("\r\n\r\nYour sign-in code is 123456.\n"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
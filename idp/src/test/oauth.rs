@@ -0,0 +1,2 @@
+pub mod test_device_grant;
+pub mod test_token_exchange;
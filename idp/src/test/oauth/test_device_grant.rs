@@ -0,0 +1,88 @@
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, Utc};
+
+    use crate::{
+        domain::mail_address::MailAddress,
+        error::{my_error::MyError, oauth_error::OAuthError},
+        oauth::device_grant::{
+            normalize_user_code, Approval, DeviceGrant, DeviceGrantStatus,
+            DEVICE_CODE_LIFETIME_SECS,
+        },
+    };
+
+    fn oauth_error(result: Result<Approval, MyError>) -> OAuthError {
+        match result {
+            Err(MyError::OAuth(err)) => err,
+            other => panic!("expected an OAuth error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_user_code() {
+        let grant = DeviceGrant::start("cli", vec![]).unwrap();
+        assert_eq!(grant.user_code.len(), 9);
+        assert_eq!(&grant.user_code[4..5], "-");
+        assert!(grant
+            .user_code
+            .chars()
+            .all(|c| c == '-' || "BCDFGHJKLMNPQRSTVWXZ".contains(c)));
+        assert_ne!(
+            grant.user_code,
+            DeviceGrant::start("cli", vec![]).unwrap().user_code
+        );
+    }
+
+    #[test]
+    fn test_normalize_user_code() {
+        assert_eq!(normalize_user_code("bcdf ghjk"), "BCDF-GHJK");
+        assert_eq!(normalize_user_code("BCDF-GHJK"), "BCDF-GHJK");
+        assert_eq!(normalize_user_code(" bcdfghjk\n"), "BCDF-GHJK");
+        assert_eq!(normalize_user_code("bcd"), "BCD");
+    }
+
+    #[test]
+    fn test_polling() {
+        let mut grant = DeviceGrant::start("cli", vec![]).unwrap();
+        let now = Utc::now();
+        assert_eq!(
+            oauth_error(grant.poll(now)),
+            OAuthError::AuthorizationPending
+        );
+        assert_eq!(
+            oauth_error(grant.poll(now + Duration::seconds(1))),
+            OAuthError::SlowDown
+        );
+        assert_eq!(grant.interval_secs, 10);
+        // The longer interval counts from the last poll.
+        assert_eq!(
+            oauth_error(grant.poll(now + Duration::seconds(6))),
+            OAuthError::SlowDown
+        );
+        assert_eq!(
+            oauth_error(grant.poll(now + Duration::seconds(30))),
+            OAuthError::AuthorizationPending
+        );
+
+        let approval = Approval {
+            email: MailAddress::of("kamino@example.com").unwrap(),
+            session_id: None,
+            scopes: vec![],
+        };
+        grant.status = DeviceGrantStatus::Approved(approval.clone());
+        assert_eq!(grant.poll(now + Duration::seconds(31)).unwrap(), approval);
+    }
+
+    #[test]
+    fn test_denied_and_expired() {
+        let mut grant = DeviceGrant::start("cli", vec![]).unwrap();
+        grant.status = DeviceGrantStatus::Denied;
+        assert_eq!(
+            oauth_error(grant.poll(Utc::now())),
+            OAuthError::AccessDenied
+        );
+
+        let later = Utc::now() + Duration::seconds(DEVICE_CODE_LIFETIME_SECS + 1);
+        assert_eq!(oauth_error(grant.poll(later)), OAuthError::ExpiredToken);
+    }
+}
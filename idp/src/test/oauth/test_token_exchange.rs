@@ -0,0 +1,186 @@
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use serde_json::{json, Value};
+
+    use crate::{
+        domain::mail_address::MailAddress,
+        entity::client::TokenExchangePolicy,
+        error::{my_error::MyError, oauth_error::OAuthError},
+        oauth::token_exchange::exchange,
+        token::{
+            jwt::{decode_subject_token, encode_claims, make_jwt, Claims},
+            signing_key::SigningKeys,
+        },
+    };
+
+    const SECRET: &str = "test-secret";
+    const ISSUER: &str = "http://localhost:8080/realms/acme";
+
+    fn policy(audiences: &[&str], scopes: &[&str]) -> TokenExchangePolicy {
+        TokenExchangePolicy {
+            audiences: audiences.iter().map(|s| s.to_string()).collect(),
+            scopes: scopes.iter().map(|s| s.to_string()).collect(),
+        }
+    }
+
+    fn user_claims() -> Claims {
+        let email = MailAddress::of("kamino@example.com").unwrap();
+        let claims = Claims::of(ISSUER, &email, Some("web"))
+            .with_scopes(&["read".to_owned(), "write".to_owned()]);
+        let token = encode_claims(&SigningKeys::of(SECRET), &claims).unwrap();
+        decode_subject_token(&SigningKeys::of(SECRET), ISSUER, &token, "api").unwrap()
+    }
+
+    fn json_of(claims: &Claims) -> Value {
+        serde_json::to_value(claims).unwrap()
+    }
+
+    fn oauth_error(result: Result<Claims, MyError>) -> OAuthError {
+        match result {
+            Err(MyError::OAuth(err)) => err,
+            other => panic!("expected an OAuth error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_exchange() {
+        let subject = user_claims();
+        let claims = exchange(
+            &policy(&["orders"], &["read", "write"]),
+            &subject,
+            "orders",
+            Some("read"),
+            "api",
+        )
+        .unwrap();
+        let json = json_of(&claims);
+        assert_eq!(json["aud"], "orders");
+        assert_eq!(json["sub"], "kamino@example.com");
+        assert_eq!(json["iss"], ISSUER);
+        assert_eq!(json["azp"], "api");
+        assert_eq!(json["scope"], "read");
+        assert_eq!(json["act"], json!({"sub": "api"}));
+
+        // Without a requested scope, everything the policy allows.
+        let claims = exchange(
+            &policy(&["orders"], &["read", "write"]),
+            &subject,
+            "orders",
+            None,
+            "api",
+        )
+        .unwrap();
+        assert_eq!(claims.scopes(), Some(vec!["read", "write"]));
+
+        let claims = exchange(&policy(&["orders"], &[]), &subject, "orders", None, "api").unwrap();
+        assert_eq!(claims.scopes(), None);
+    }
+
+    #[test]
+    fn test_unscoped_subject_token() {
+        // A token without scopes holds none, so exchanging it cannot add any.
+        let email = MailAddress::of("kamino@example.com").unwrap();
+        let token = make_jwt(&SigningKeys::of(SECRET), ISSUER, &email, Some("web"), None).unwrap();
+        let subject =
+            decode_subject_token(&SigningKeys::of(SECRET), ISSUER, &token, "api").unwrap();
+        let policy = policy(&["orders"], &["read", "write"]);
+        assert_eq!(
+            oauth_error(exchange(&policy, &subject, "orders", Some("read"), "api")),
+            OAuthError::InvalidScope
+        );
+        let claims = exchange(&policy, &subject, "orders", None, "api").unwrap();
+        assert_eq!(claims.scopes(), None);
+    }
+
+    #[test]
+    fn test_rejected_audience_and_scope() {
+        let subject = user_claims();
+        let policy = policy(&["orders"], &["read"]);
+        assert_eq!(
+            oauth_error(exchange(&policy, &subject, "billing", None, "api")),
+            OAuthError::InvalidTarget
+        );
+        assert_eq!(
+            oauth_error(exchange(
+                &policy,
+                &subject,
+                "orders",
+                Some("read write"),
+                "api"
+            )),
+            OAuthError::InvalidScope
+        );
+    }
+
+    #[test]
+    fn test_chained_exchange() {
+        let first = exchange(
+            &policy(&["orders"], &["read", "write"]),
+            &user_claims(),
+            "orders",
+            Some("read"),
+            "api",
+        )
+        .unwrap();
+        // The orders service passes the token further down.
+        let token = encode_claims(&SigningKeys::of(SECRET), &first).unwrap();
+        let subject =
+            decode_subject_token(&SigningKeys::of(SECRET), ISSUER, &token, "orders").unwrap();
+
+        // Scopes only narrow along the chain.
+        assert_eq!(
+            oauth_error(exchange(
+                &policy(&["stock"], &["read", "write"]),
+                &subject,
+                "stock",
+                Some("write"),
+                "orders",
+            )),
+            OAuthError::InvalidScope
+        );
+        let second = exchange(
+            &policy(&["stock"], &["read", "write"]),
+            &subject,
+            "stock",
+            None,
+            "orders",
+        )
+        .unwrap();
+        let json = json_of(&second);
+        assert_eq!(json["sub"], "kamino@example.com");
+        assert_eq!(json["scope"], "read");
+        assert_eq!(json["act"], json!({"sub": "orders", "act": {"sub": "api"}}));
+        assert!(json["exp"].as_i64().unwrap() <= json_of(&first)["exp"].as_i64().unwrap());
+        assert!(second.expires_in() <= first.expires_in());
+        assert!(json["iat"].as_i64().unwrap() <= Utc::now().timestamp());
+    }
+
+    #[test]
+    fn test_subject_token_audience() {
+        let email = MailAddress::of("kamino@example.com").unwrap();
+        let token = make_jwt(&SigningKeys::of(SECRET), ISSUER, &email, None, None).unwrap();
+        let first = exchange(
+            &policy(&["orders"], &[]),
+            &decode_subject_token(&SigningKeys::of(SECRET), ISSUER, &token, "api").unwrap(),
+            "orders",
+            None,
+            "api",
+        )
+        .unwrap();
+        let token = encode_claims(&SigningKeys::of(SECRET), &first).unwrap();
+        // Only the audience of a token may exchange it again.
+        assert!(decode_subject_token(&SigningKeys::of(SECRET), ISSUER, &token, "billing").is_err());
+        assert!(decode_subject_token(
+            &SigningKeys::of(SECRET),
+            "http://evil.test",
+            &token,
+            "orders"
+        )
+        .is_err());
+        assert!(
+            decode_subject_token(&SigningKeys::of("other-secret"), ISSUER, &token, "orders")
+                .is_err()
+        );
+    }
+}
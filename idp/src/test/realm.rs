@@ -0,0 +1,2 @@
+pub mod test_realm_config;
+pub mod test_realm_state;
@@ -0,0 +1,328 @@
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::{
+        domain::password_policy::{CharacterClass, PasswordPolicy},
+        realm::{
+            realm_config::{build_realms, RealmsFile},
+            realm_registry::RealmRegistry,
+        },
+        token::signing_key::SigningKey,
+    };
+
+    fn file(value: serde_json::Value) -> RealmsFile {
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn secret_of(name: &str) -> Option<String> {
+        match name {
+            "ACME_SECRET" => Some("acme-secret".to_owned()),
+            "GLOBEX_SECRET" => Some("globex-secret".to_owned()),
+            "GOOGLE_CLIENT_SECRET" => Some("google-secret".to_owned()),
+            "API_CLIENT_SECRET" => Some("api-secret".to_owned()),
+            "WEB_LOGOUT_SECRET" => Some("web-logout-secret".to_owned()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_build_realms() {
+        let file = file(json!({"realms": [
+            {
+                "name": "acme",
+                "secret_env": "ACME_SECRET",
+                "clients": [
+                    {"client_id": "web"},
+                    {"client_id": "tv", "scopes": ["todo:read"], "require_consent": true, "groups_limit": 5},
+                ],
+                "service_providers": [
+                    {"entity_id": "https://sp.example.com", "acs_url": "https://sp.example.com/acs", "attributes": ["email"]},
+                ],
+            },
+            {"name": "globex", "secret_env": "GLOBEX_SECRET", "issuer": "https://login.globex.test"},
+        ]}));
+        let realms = build_realms(
+            file,
+            "http://localhost:8080/",
+            &PasswordPolicy::default(),
+            secret_of,
+        )
+        .unwrap();
+        let registry = RealmRegistry::of(realms).unwrap();
+
+        let acme = registry.find("acme").unwrap();
+        assert_eq!(acme.issuer, "http://localhost:8080/realms/acme");
+        assert_eq!(acme.keys.secret(), "acme-secret");
+        assert!(acme.clients.find("web").unwrap().is_some());
+        assert!(acme.clients.find("mobile").unwrap().is_none());
+        let tv = acme.clients.find("tv").unwrap().unwrap();
+        assert_eq!(tv.scopes, vec!["todo:read"]);
+        assert!(tv.require_consent);
+        assert_eq!(tv.groups_limit, 5);
+        assert!(!acme.clients.find("web").unwrap().unwrap().require_consent);
+        let sp = acme
+            .service_providers
+            .find("https://sp.example.com")
+            .unwrap()
+            .unwrap();
+        assert_eq!(sp.acs_url, "https://sp.example.com/acs");
+        assert_eq!(sp.attributes, vec!["email"]);
+
+        let globex = registry.find("globex").unwrap();
+        assert_eq!(globex.issuer, "https://login.globex.test");
+        assert!(globex.clients.find("web").unwrap().is_none());
+
+        assert_eq!(registry.default_realm().name, "acme");
+        assert!(registry.find("initech").is_none());
+    }
+
+    #[test]
+    fn test_policy_overrides() {
+        let file = file(json!({"realms": [{
+            "name": "acme",
+            "secret_env": "ACME_SECRET",
+            "password_policy": {"min_length": 20, "character_classes": ["digit", "symbol"]},
+        }]}));
+        let realms = build_realms(file, "", &PasswordPolicy::default(), secret_of).unwrap();
+        let policy = &realms[0].password_policy;
+        assert_eq!(policy.min_length, 20);
+        assert_eq!(
+            policy.required_classes,
+            vec![CharacterClass::Digit, CharacterClass::Symbol]
+        );
+        assert_eq!(
+            policy.min_entropy_bits,
+            PasswordPolicy::default().min_entropy_bits
+        );
+    }
+
+    #[test]
+    fn test_upstream_providers() {
+        let realms_file = file(json!({"realms": [{
+            "name": "acme",
+            "secret_env": "ACME_SECRET",
+            "upstream_providers": [{
+                "alias": "google",
+                "issuer": "https://accounts.google.com",
+                "client_id": "acme-idp",
+                "client_secret_env": "GOOGLE_CLIENT_SECRET",
+            }],
+        }]}));
+        let realms = build_realms(realms_file, "", &PasswordPolicy::default(), secret_of).unwrap();
+        let google = &realms[0].upstream_providers["google"];
+        assert_eq!(google.issuer, "https://accounts.google.com");
+        assert_eq!(google.client_id, "acme-idp");
+        assert_eq!(google.scopes, vec!["openid", "email"]);
+
+        let realms_file = file(json!({"realms": [{
+            "name": "acme",
+            "secret_env": "ACME_SECRET",
+            "upstream_providers": [{
+                "alias": "google",
+                "issuer": "https://accounts.google.com",
+                "client_id": "acme-idp",
+                "client_secret_env": "UNSET_SECRET",
+            }],
+        }]}));
+        assert!(build_realms(realms_file, "", &PasswordPolicy::default(), secret_of).is_err());
+    }
+
+    #[test]
+    fn test_confidential_clients() {
+        let realms_file = file(json!({"realms": [{
+            "name": "acme",
+            "secret_env": "ACME_SECRET",
+            "clients": [
+                {"client_id": "cli"},
+                {
+                    "client_id": "api",
+                    "client_secret_env": "API_CLIENT_SECRET",
+                    "token_exchange": {"audiences": ["orders"], "scopes": ["orders:read"]},
+                },
+            ],
+        }]}));
+        let realms = build_realms(realms_file, "", &PasswordPolicy::default(), secret_of).unwrap();
+        let cli = realms[0].clients.find("cli").unwrap().unwrap();
+        assert!(!cli.is_confidential());
+        assert_eq!(cli.token_exchange, None);
+        let api = realms[0].clients.find("api").unwrap().unwrap();
+        assert!(api.verify_secret("api-secret"));
+        assert!(!api.verify_secret("wrong-secret"));
+        let policy = api.token_exchange.unwrap();
+        assert_eq!(policy.audiences, vec!["orders"]);
+        assert_eq!(policy.scopes, vec!["orders:read"]);
+
+        // Public clients cannot prove who is exchanging.
+        let realms_file = file(json!({"realms": [{
+            "name": "acme",
+            "secret_env": "ACME_SECRET",
+            "clients": [{"client_id": "cli", "token_exchange": {"audiences": ["orders"]}}],
+        }]}));
+        assert!(build_realms(realms_file, "", &PasswordPolicy::default(), secret_of).is_err());
+    }
+
+    #[test]
+    fn test_claim_mappers() {
+        let realms_file = file(json!({"realms": [{
+            "name": "acme",
+            "secret_env": "ACME_SECRET",
+            "clients": [{
+                "client_id": "web",
+                "claim_mappers": [
+                    {"type": "user_attribute", "claim": "preferred_username", "attribute": "preferred_username"},
+                ],
+            }],
+            "audience_claim_mappers": {
+                "orders": [{"type": "static", "claim": "tenant", "value": "acme"}],
+            },
+        }]}));
+        let realms = build_realms(realms_file, "", &PasswordPolicy::default(), secret_of).unwrap();
+        let web = realms[0].clients.find("web").unwrap().unwrap();
+        assert_eq!(web.claim_mappers[0].claim(), "preferred_username");
+        assert_eq!(realms[0].audience_mappers("orders")[0].claim(), "tenant");
+        assert!(realms[0].audience_mappers("stock").is_empty());
+
+        // Mappers may not override what the idp sets.
+        let realms_file = file(json!({"realms": [{
+            "name": "acme",
+            "secret_env": "ACME_SECRET",
+            "clients": [{
+                "client_id": "web",
+                "claim_mappers": [{"type": "static", "claim": "sub", "value": "admin"}],
+            }],
+        }]}));
+        assert!(build_realms(realms_file, "", &PasswordPolicy::default(), secret_of).is_err());
+        let realms_file = file(json!({"realms": [{
+            "name": "acme",
+            "secret_env": "ACME_SECRET",
+            "audience_claim_mappers": {
+                "orders": [{"type": "static", "claim": "exp", "value": 4102444800u64}],
+            },
+        }]}));
+        assert!(build_realms(realms_file, "", &PasswordPolicy::default(), secret_of).is_err());
+    }
+
+    #[test]
+    fn test_logout_uris() {
+        let realms_file = file(json!({"realms": [{
+            "name": "acme",
+            "secret_env": "ACME_SECRET",
+            "clients": [{
+                "client_id": "web",
+                "post_logout_redirect_uris": ["https://web.example.com/signed-out"],
+                "backchannel_logout_uri": "https://web.example.com/backchannel-logout",
+                "backchannel_logout_secret_env": "WEB_LOGOUT_SECRET",
+            }],
+        }]}));
+        let realms = build_realms(realms_file, "", &PasswordPolicy::default(), secret_of).unwrap();
+        let web = realms[0].clients.find("web").unwrap().unwrap();
+        assert_eq!(
+            web.post_logout_redirect_uris,
+            vec!["https://web.example.com/signed-out"]
+        );
+        assert_eq!(
+            web.backchannel_logout_uri.as_deref(),
+            Some("https://web.example.com/backchannel-logout")
+        );
+        assert_eq!(
+            web.backchannel_logout_key,
+            Some(SigningKey::of("web-logout-secret"))
+        );
+
+        // Logout tokens are not signed with the realm's own secret.
+        for client in [
+            json!({"client_id": "web", "backchannel_logout_uri": "https://web.example.com/bcl"}),
+            json!({"client_id": "web", "backchannel_logout_secret_env": "WEB_LOGOUT_SECRET"}),
+            json!({
+                "client_id": "web",
+                "backchannel_logout_uri": "https://web.example.com/bcl",
+                "backchannel_logout_secret_env": "UNSET_SECRET",
+            }),
+        ] {
+            let realms_file = file(json!({"realms": [{
+                "name": "acme",
+                "secret_env": "ACME_SECRET",
+                "clients": [client],
+            }]}));
+            assert!(build_realms(realms_file, "", &PasswordPolicy::default(), secret_of).is_err());
+        }
+
+        for uri in [
+            "/signed-out",
+            "javascript:alert(1)",
+            "https://web.example.com/#x",
+        ] {
+            let realms_file = file(json!({"realms": [{
+                "name": "acme",
+                "secret_env": "ACME_SECRET",
+                "clients": [{"client_id": "web", "post_logout_redirect_uris": [uri]}],
+            }]}));
+            assert!(
+                build_realms(realms_file, "", &PasswordPolicy::default(), secret_of).is_err(),
+                "{}",
+                uri
+            );
+        }
+    }
+
+    #[test]
+    fn test_encryption_key() {
+        let jwk = json!({
+            "kty": "EC",
+            "crv": "P-256",
+            "kid": "bff-1",
+            "x": "axfR8uEsQkf4vOblY6RA8ncDfYEt6zOg9KE5RdiYwpY",
+            "y": "T-NC4v4af5uO5-tKfA-eFivOM1drMV7Oy7ZAaDe_UfU",
+        });
+        let realms_file = file(json!({"realms": [{
+            "name": "acme",
+            "secret_env": "ACME_SECRET",
+            "clients": [{"client_id": "bff", "encryption_key": jwk}],
+        }]}));
+        let realms = build_realms(realms_file, "", &PasswordPolicy::default(), secret_of).unwrap();
+        let bff = realms[0].clients.find("bff").unwrap().unwrap();
+        assert_eq!(bff.encryption_key.unwrap().kid.as_deref(), Some("bff-1"));
+
+        let mut private = jwk.clone();
+        private["d"] = json!("AAAA");
+        let realms_file = file(json!({"realms": [{
+            "name": "acme",
+            "secret_env": "ACME_SECRET",
+            "clients": [{"client_id": "bff", "encryption_key": private}],
+        }]}));
+        assert!(build_realms(realms_file, "", &PasswordPolicy::default(), secret_of).is_err());
+    }
+
+    #[test]
+    fn test_missing_secret() {
+        let file = file(json!({"realms": [{"name": "acme", "secret_env": "UNSET_SECRET"}]}));
+        assert!(build_realms(file, "", &PasswordPolicy::default(), secret_of).is_err());
+    }
+
+    #[test]
+    fn test_invalid_realm_name() {
+        let file = file(json!({"realms": [{"name": "ac/me", "secret_env": "ACME_SECRET"}]}));
+        assert!(build_realms(file, "", &PasswordPolicy::default(), secret_of).is_err());
+    }
+
+    #[test]
+    fn test_unknown_character_class() {
+        let file = file(json!({"realms": [{
+            "name": "acme",
+            "secret_env": "ACME_SECRET",
+            "password_policy": {"character_classes": ["emoji"]},
+        }]}));
+        assert!(build_realms(file, "", &PasswordPolicy::default(), secret_of).is_err());
+    }
+
+    #[test]
+    fn test_duplicate_realm() {
+        let file = file(json!({"realms": [
+            {"name": "acme", "secret_env": "ACME_SECRET"},
+            {"name": "acme", "secret_env": "GLOBEX_SECRET"},
+        ]}));
+        let realms = build_realms(file, "", &PasswordPolicy::default(), secret_of).unwrap();
+        assert!(RealmRegistry::of(realms).is_err());
+    }
+}
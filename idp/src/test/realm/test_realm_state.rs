@@ -0,0 +1,187 @@
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, process, sync::mpsc, thread, time::Duration};
+
+    use chrono::Utc;
+
+    use crate::{
+        domain::mail_address::MailAddress,
+        entity::{client::Client, group::Group},
+        realm::{
+            realm_registry::Realm,
+            realm_state::{ClientRecord, KeyRecord, StateFile, UserRecord},
+        },
+        token::signing_key::{SigningKey, SigningKeys},
+    };
+
+    fn realm() -> Realm {
+        Realm::of(
+            "acme",
+            "http://localhost:8080/realms/acme",
+            SigningKeys::of("acme-secret"),
+        )
+    }
+
+    fn state() -> StateFile {
+        let mut state = StateFile::default();
+        let acme = state.realm_mut("acme");
+        let mut user = UserRecord::of(
+            MailAddress::of("kamino@example.com").unwrap(),
+            "hash".into(),
+        );
+        user.disabled = true;
+        acme.users.push(user);
+        acme.clients.push(ClientRecord {
+            client_id: "cli".to_owned(),
+            secret_digest: Client::of("cli").with_secret("cli-secret").secret_digest,
+            scopes: vec!["todo:read".to_owned()],
+            require_consent: true,
+            groups_limit: Some(10),
+        });
+        acme.groups
+            .push(Group::of("staff").with_subgroup("developers"));
+        acme.groups.push(
+            Group::of("developers").with_member(MailAddress::of("kamino@example.com").unwrap()),
+        );
+        let key = SigningKey::of("rotated-secret").with_created_at(Utc::now());
+        acme.signing_keys.push(KeyRecord::of(&key));
+        state
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let path = env::temp_dir().join(format!("idp-state-{}.json", process::id()));
+        assert!(StateFile::load(&path).unwrap().realms.is_empty());
+        state().save(&path).unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+        let loaded = StateFile::load(&path).unwrap();
+        assert_eq!(loaded.realms["acme"].users.len(), 1);
+        assert_eq!(loaded.realms["acme"].signing_keys.len(), 1);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_upgrade_passwd_hash() {
+        let path = env::temp_dir().join(format!("idp-state-upgrade-{}.json", process::id()));
+        state().save(&path).unwrap();
+        let email = MailAddress::of("kamino@example.com").unwrap();
+        let upgrade = |realm: &str, email: &MailAddress, verified: &str| {
+            StateFile::upgrade_passwd_hash(&path, realm, email, verified, "upgraded").unwrap()
+        };
+
+        // Someone else, another realm, or a hash changed since login.
+        assert!(!upgrade(
+            "acme",
+            &MailAddress::of("other@example.com").unwrap(),
+            "hash"
+        ));
+        assert!(!upgrade("other", &email, "hash"));
+        assert!(!upgrade("acme", &email, "stale"));
+        assert!(upgrade("acme", &email, "hash"));
+        let loaded = StateFile::load(&path).unwrap();
+        assert_eq!(
+            loaded.realms["acme"].users[0].passwd_hash.as_deref(),
+            Some("upgraded")
+        );
+        assert_eq!(loaded.realms["acme"].clients.len(), 1);
+        fs::remove_file(&path).unwrap();
+        fs::remove_file(path.with_extension("json.lock")).unwrap();
+    }
+
+    #[test]
+    fn test_upgrade_waits_for_lock() {
+        let path = env::temp_dir().join(format!("idp-state-lock-{}.json", process::id()));
+        state().save(&path).unwrap();
+        // Another process, such as idp-admin, between its load and save.
+        let lock = StateFile::lock(&path).unwrap();
+        let (done, finished) = mpsc::channel();
+        let upgrading = {
+            let path = path.clone();
+            thread::spawn(move || {
+                let email = MailAddress::of("kamino@example.com").unwrap();
+                let upgraded =
+                    StateFile::upgrade_passwd_hash(&path, "acme", &email, "hash", "upgraded");
+                done.send(()).unwrap();
+                upgraded.unwrap()
+            })
+        };
+        assert!(finished.recv_timeout(Duration::from_millis(200)).is_err());
+        let mut state = StateFile::load(&path).unwrap();
+        state.realm_mut("acme").groups.clear();
+        state.save(&path).unwrap();
+        drop(lock);
+
+        assert!(upgrading.join().unwrap());
+        let loaded = StateFile::load(&path).unwrap();
+        assert!(loaded.realms["acme"].groups.is_empty());
+        assert_eq!(
+            loaded.realms["acme"].users[0].passwd_hash.as_deref(),
+            Some("upgraded")
+        );
+        fs::remove_file(&path).unwrap();
+        fs::remove_file(path.with_extension("json.lock")).unwrap();
+    }
+
+    #[test]
+    fn test_apply() {
+        let mut realms = vec![realm()];
+        state().apply(&mut realms).unwrap();
+        let acme = &realms[0];
+
+        let user = acme
+            .users
+            .find(&MailAddress::of("kamino@example.com").unwrap())
+            .unwrap()
+            .unwrap();
+        assert!(user.disabled);
+        let client = acme.clients.find("cli").unwrap().unwrap();
+        assert!(client.verify_secret("cli-secret"));
+        assert!(client.require_consent);
+        assert_eq!(client.groups_limit, 10);
+        let email = MailAddress::of("kamino@example.com").unwrap();
+        assert_eq!(
+            acme.groups.memberships(&email).unwrap(),
+            ["developers".to_owned(), "staff".to_owned()].into()
+        );
+
+        // The rotated key signs, the configured one still verifies.
+        let kids: Vec<&str> = acme.keys.iter().map(|key| key.kid.as_str()).collect();
+        assert_eq!(
+            kids,
+            [
+                SigningKey::of("rotated-secret").kid.as_str(),
+                SigningKey::of("acme-secret").kid.as_str()
+            ]
+        );
+    }
+
+    #[test]
+    fn test_rejected_state() {
+        let mut unknown = StateFile::default();
+        unknown.realm_mut("globex");
+        assert!(unknown.apply(&mut [realm()]).is_err());
+
+        // Clients of the realms file are not registered twice.
+        let mut realms = vec![realm()];
+        realms[0].clients.save(Client::of("cli")).unwrap();
+        assert!(state().apply(&mut realms).is_err());
+
+        let mut tampered = StateFile::default();
+        let mut key = KeyRecord::of(&SigningKey::of("rotated-secret"));
+        key.kid = "tampered".to_owned();
+        tampered.realm_mut("acme").signing_keys.push(key);
+        assert!(tampered.apply(&mut [realm()]).is_err());
+
+        let mut dangling = StateFile::default();
+        dangling
+            .realm_mut("acme")
+            .groups
+            .push(Group::of("staff").with_subgroup("deleted"));
+        assert!(dangling.apply(&mut [realm()]).is_err());
+    }
+}
@@ -0,0 +1,13 @@
+pub mod test_broker_resource;
+pub mod test_consent_resource;
+pub mod test_device_resource;
+pub mod test_email_login_resource;
+pub mod test_health_resource;
+pub mod test_html_page;
+pub mod test_idp_resource;
+pub mod test_login_resource;
+pub mod test_logout_resource;
+pub mod test_openapi_resource;
+pub mod test_saml_resource;
+pub mod test_token_resource;
+pub mod test_webauthn_resource;
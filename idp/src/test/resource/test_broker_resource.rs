@@ -0,0 +1,443 @@
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, net::TcpListener, sync::Mutex};
+
+    use actix_web::{
+        cookie::Cookie,
+        dev::{Service, ServiceResponse},
+        http::{header, StatusCode},
+        test, web, App, HttpResponse, HttpServer,
+    };
+    use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+    use chrono::Utc;
+    use jsonwebtoken::{encode, Algorithm, EncodingKey, Header};
+    use ring::{
+        digest::{digest, SHA256},
+        rsa::PublicKeyComponents,
+        signature::{KeyPair, RsaKeyPair},
+    };
+    use rustls_pki_types::{pem::PemObject, PrivatePkcs8KeyDer};
+    use serde_json::{json, Value};
+
+    use crate::{
+        app_state::AppState,
+        broker::upstream_provider::UpstreamProvider,
+        domain::mail_address::MailAddress,
+        entity::{passkey::Passkey, user::User},
+        realm::realm_registry::{Realm, RealmRegistry},
+        resource::broker_resource::{broker_callback_handler, broker_login_handler},
+        token::{jwt::decode_jwt, signing_key::SigningKeys},
+        webauthn::cose_key::CoseKey,
+    };
+
+    const ISSUER: &str = "http://localhost:8080/realms/acme";
+    const CLIENT_ID: &str = "acme-idp";
+    const CLIENT_SECRET: &str = "mock-secret";
+    const KEY_PEM: &str = include_str!("../fixtures/saml_key.pem");
+
+    /// Local stand-in for an upstream OpenID Connect provider. The test plays
+    /// the user at its authorization endpoint by handing out codes directly.
+    struct MockProvider {
+        issuer: String,
+        /// Claims and PKCE challenge each code is redeemed for.
+        codes: Mutex<HashMap<String, (Value, String)>>,
+    }
+
+    async fn discovery(mock: web::Data<MockProvider>) -> HttpResponse {
+        HttpResponse::Ok().json(json!({
+            "issuer": mock.issuer,
+            "authorization_endpoint": format!("{}/authorize", mock.issuer),
+            "token_endpoint": format!("{}/token", mock.issuer),
+            "jwks_uri": format!("{}/jwks", mock.issuer),
+        }))
+    }
+
+    async fn jwks() -> HttpResponse {
+        let der = PrivatePkcs8KeyDer::from_pem_slice(KEY_PEM.as_bytes()).unwrap();
+        let key_pair = RsaKeyPair::from_pkcs8(der.secret_pkcs8_der()).unwrap();
+        let public = PublicKeyComponents::<Vec<u8>>::from(key_pair.public_key());
+        HttpResponse::Ok().json(json!({"keys": [{
+            "kty": "RSA",
+            "kid": "mock-key",
+            "use": "sig",
+            "alg": "RS256",
+            "n": URL_SAFE_NO_PAD.encode(public.n),
+            "e": URL_SAFE_NO_PAD.encode(public.e),
+        }]}))
+    }
+
+    async fn token(
+        mock: web::Data<MockProvider>,
+        form: web::Form<HashMap<String, String>>,
+    ) -> HttpResponse {
+        if form.get("client_secret").map(String::as_str) != Some(CLIENT_SECRET) {
+            return HttpResponse::Unauthorized().json(json!({"error": "invalid_client"}));
+        }
+        let redeemed = mock.codes.lock().unwrap().remove(&form["code"]);
+        let (claims, challenge) = match redeemed {
+            Some(redeemed) => redeemed,
+            None => return HttpResponse::BadRequest().json(json!({"error": "invalid_grant"})),
+        };
+        let verifier = &form["code_verifier"];
+        if URL_SAFE_NO_PAD.encode(digest(&SHA256, verifier.as_bytes())) != challenge {
+            return HttpResponse::BadRequest().json(json!({"error": "invalid_grant"}));
+        }
+        let mut header = Header::new(Algorithm::RS256);
+        header.kid = Some("mock-key".to_owned());
+        let id_token = encode(
+            &header,
+            &claims,
+            &EncodingKey::from_rsa_pem(KEY_PEM.as_bytes()).unwrap(),
+        )
+        .unwrap();
+        HttpResponse::Ok()
+            .json(json!({"access_token": "opaque", "token_type": "Bearer", "id_token": id_token}))
+    }
+
+    fn start_mock_provider() -> web::Data<MockProvider> {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mock = web::Data::new(MockProvider {
+            issuer: format!("http://{}", listener.local_addr().unwrap()),
+            codes: Mutex::new(HashMap::new()),
+        });
+        let data = mock.clone();
+        let server = HttpServer::new(move || {
+            App::new()
+                .app_data(data.clone())
+                .route(
+                    "/.well-known/openid-configuration",
+                    web::get().to(discovery),
+                )
+                .route("/jwks", web::get().to(jwks))
+                .route("/token", web::post().to(token))
+        })
+        .workers(1)
+        .listen(listener)
+        .unwrap()
+        .run();
+        actix_web::rt::spawn(server);
+        mock
+    }
+
+    async fn app(
+        realm: Realm,
+    ) -> impl Service<actix_http::Request, Response = ServiceResponse, Error = actix_web::Error>
+    {
+        let state = AppState::of(RealmRegistry::of(vec![realm]).unwrap());
+        test::init_service(
+            App::new().app_data(web::Data::new(state)).service(
+                web::scope("/realms/{realm}")
+                    .service(
+                        web::resource("/broker/{alias}/login")
+                            .route(web::get().to(broker_login_handler)),
+                    )
+                    .service(
+                        web::resource("/broker/{alias}/callback")
+                            .route(web::get().to(broker_callback_handler)),
+                    ),
+            ),
+        )
+        .await
+    }
+
+    fn realm(mock: &MockProvider) -> Realm {
+        Realm::of("acme", ISSUER, SigningKeys::of("test-secret")).with_upstream_provider(
+            UpstreamProvider::of("mock", mock.issuer.clone(), CLIENT_ID, CLIENT_SECRET),
+        )
+    }
+
+    fn id_token_claims(mock: &MockProvider, subject: &str, nonce: &str) -> Value {
+        let now = Utc::now().timestamp();
+        json!({
+            "iss": mock.issuer,
+            "aud": CLIENT_ID,
+            "sub": subject,
+            "iat": now,
+            "exp": now + 300,
+            "nonce": nonce,
+            "email": "kamino@example.com",
+            "email_verified": true,
+        })
+    }
+
+    /// Starts a login and returns the query of the upstream authorization
+    /// request, with the cookie that binds it to the browser.
+    async fn begin_login<S>(app: &S) -> (HashMap<String, String>, Cookie<'static>)
+    where
+        S: Service<actix_http::Request, Response = ServiceResponse, Error = actix_web::Error>,
+    {
+        let req = test::TestRequest::get()
+            .uri("/realms/acme/broker/mock/login")
+            .to_request();
+        let res = test::call_service(app, req).await;
+        assert_eq!(res.status(), StatusCode::FOUND);
+        let cookie = res
+            .response()
+            .cookies()
+            .find(|c| c.name() == "idp_broker_login_acme")
+            .unwrap()
+            .into_owned();
+        let location = res
+            .headers()
+            .get(header::LOCATION)
+            .unwrap()
+            .to_str()
+            .unwrap();
+        let (endpoint, query) = location.split_once('?').unwrap();
+        assert!(endpoint.ends_with("/authorize"));
+        (serde_urlencoded::from_str(query).unwrap(), cookie)
+    }
+
+    /// Hands out `code` for `claims` and returns the callback response.
+    async fn callback<S>(
+        app: &S,
+        mock: &MockProvider,
+        authorization: &HashMap<String, String>,
+        cookie: &Cookie<'static>,
+        code: &str,
+        claims: Value,
+    ) -> ServiceResponse
+    where
+        S: Service<actix_http::Request, Response = ServiceResponse, Error = actix_web::Error>,
+    {
+        mock.codes.lock().unwrap().insert(
+            code.to_owned(),
+            (claims, authorization["code_challenge"].clone()),
+        );
+        let req = test::TestRequest::get()
+            .uri(&format!(
+                "/realms/acme/broker/mock/callback?code={}&state={}",
+                code, authorization["state"]
+            ))
+            .cookie(cookie.clone())
+            .to_request();
+        test::call_service(app, req).await
+    }
+
+    #[actix_web::test]
+    async fn test_login_through_upstream() {
+        let mock = start_mock_provider();
+        let app = app(realm(&mock)).await;
+
+        let (authorization, cookie) = begin_login(&app).await;
+        assert_eq!(authorization["client_id"], CLIENT_ID);
+        assert_eq!(authorization["response_type"], "code");
+        assert_eq!(authorization["scope"], "openid email");
+        assert_eq!(authorization["code_challenge_method"], "S256");
+        assert_eq!(
+            authorization["redirect_uri"],
+            format!("{}/broker/mock/callback", ISSUER)
+        );
+
+        let claims = id_token_claims(&mock, "upstream-1", &authorization["nonce"]);
+        let res = callback(
+            &app,
+            &mock,
+            &authorization,
+            &cookie,
+            "code-1",
+            claims.clone(),
+        )
+        .await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let session = res
+            .response()
+            .cookies()
+            .find(|c| c.name() == "idp_session_acme")
+            .unwrap();
+        assert!(session.http_only().unwrap());
+        assert!(res
+            .response()
+            .cookies()
+            .any(|c| c.name() == "idp_broker_login_acme" && c.value().is_empty()));
+        let body: Value = test::read_body_json(res).await;
+        assert_eq!(body["user"]["email"], "kamino@example.com");
+        let email = MailAddress::of("kamino@example.com").unwrap();
+        assert!(decode_jwt(
+            &SigningKeys::of("test-secret"),
+            ISSUER,
+            body["token"].as_str().unwrap(),
+            &email
+        )
+        .is_ok());
+
+        // A state is good for one callback only.
+        let res = callback(&app, &mock, &authorization, &cookie, "code-2", claims).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[actix_web::test]
+    async fn test_state_is_bound_to_browser() {
+        let mock = start_mock_provider();
+        let app = app(realm(&mock)).await;
+
+        // Someone starts a login and hands its callback to another browser.
+        let (authorization, _) = begin_login(&app).await;
+        let (_, victim_cookie) = begin_login(&app).await;
+        let claims = id_token_claims(&mock, "upstream-1", &authorization["nonce"]);
+        let res = callback(
+            &app,
+            &mock,
+            &authorization,
+            &victim_cookie,
+            "code-1",
+            claims,
+        )
+        .await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        let req = test::TestRequest::get()
+            .uri(&format!(
+                "/realms/acme/broker/mock/callback?code=code-1&state={}",
+                authorization["state"]
+            ))
+            .to_request();
+        assert_eq!(
+            test::call_service(&app, req).await.status(),
+            StatusCode::BAD_REQUEST
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_existing_user_is_linked() {
+        let mock = start_mock_provider();
+        let realm = realm(&mock);
+        let mut user = User::of(MailAddress::of("kamino@example.com").unwrap());
+        user.passwd_hash = Some("$argon2id$...".to_owned());
+        realm.users.create(user.clone()).unwrap();
+        let state = AppState::of(RealmRegistry::of(vec![realm]).unwrap());
+        let realm = state.realms.default_realm();
+        let app = test::init_service(
+            App::new().app_data(web::Data::new(state)).service(
+                web::scope("/realms/{realm}")
+                    .route("/broker/{alias}/login", web::get().to(broker_login_handler))
+                    .route(
+                        "/broker/{alias}/callback",
+                        web::get().to(broker_callback_handler),
+                    ),
+            ),
+        )
+        .await;
+
+        let (authorization, cookie) = begin_login(&app).await;
+        let claims = id_token_claims(&mock, "upstream-1", &authorization["nonce"]);
+        let res = callback(&app, &mock, &authorization, &cookie, "code-1", claims).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let linked = realm.users.find(&user.email).unwrap().unwrap();
+        assert_eq!(linked.passwd_hash, user.passwd_hash);
+        assert_eq!(linked.linked_identities[0].subject, "upstream-1");
+
+        // Another upstream account with the same address cannot take it over.
+        let (authorization, cookie) = begin_login(&app).await;
+        let claims = id_token_claims(&mock, "upstream-2", &authorization["nonce"]);
+        let res = callback(&app, &mock, &authorization, &cookie, "code-2", claims).await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[actix_web::test]
+    async fn test_passkey_follows_upstream_login() {
+        let mock = start_mock_provider();
+        let realm = realm(&mock);
+        let mut user = User::of(MailAddress::of("kamino@example.com").unwrap());
+        user.passkeys.push(Passkey {
+            id: "credential".to_owned(),
+            public_key: CoseKey::Es256(vec![]),
+            sign_count: 0,
+            attestation_format: "none".to_owned(),
+        });
+        realm.users.create(user).unwrap();
+        let app = app(realm).await;
+
+        let (authorization, cookie) = begin_login(&app).await;
+        let claims = id_token_claims(&mock, "upstream-1", &authorization["nonce"]);
+        let res = callback(&app, &mock, &authorization, &cookie, "code-1", claims).await;
+        assert_eq!(res.status(), StatusCode::ACCEPTED);
+        assert!(!res
+            .response()
+            .cookies()
+            .any(|c| c.name() == "idp_session_acme"));
+        assert!(res
+            .response()
+            .cookies()
+            .any(|c| c.name() == "idp_broker_login_acme" && c.value().is_empty()));
+        let options: Value = test::read_body_json(res).await;
+        assert_eq!(options["allowCredentials"][0]["id"], "credential");
+    }
+
+    #[actix_web::test]
+    async fn test_unverified_mail_address() {
+        let mock = start_mock_provider();
+        let app = app(realm(&mock)).await;
+        let (authorization, cookie) = begin_login(&app).await;
+        let mut claims = id_token_claims(&mock, "upstream-1", &authorization["nonce"]);
+        claims["email_verified"] = json!(false);
+        let res = callback(&app, &mock, &authorization, &cookie, "code-1", claims).await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[actix_web::test]
+    async fn test_id_token_validation() {
+        let mock = start_mock_provider();
+        let app = app(realm(&mock)).await;
+
+        let (authorization, cookie) = begin_login(&app).await;
+        let claims = id_token_claims(&mock, "upstream-1", "replayed-nonce");
+        let res = callback(&app, &mock, &authorization, &cookie, "code-1", claims).await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+
+        let (authorization, cookie) = begin_login(&app).await;
+        let mut claims = id_token_claims(&mock, "upstream-1", &authorization["nonce"]);
+        claims["aud"] = json!("another-client");
+        let res = callback(&app, &mock, &authorization, &cookie, "code-2", claims).await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+
+        let (authorization, cookie) = begin_login(&app).await;
+        let mut claims = id_token_claims(&mock, "upstream-1", &authorization["nonce"]);
+        claims["exp"] = json!(Utc::now().timestamp() - 3600);
+        let res = callback(&app, &mock, &authorization, &cookie, "code-3", claims).await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[actix_web::test]
+    async fn test_upstream_refused() {
+        let mock = start_mock_provider();
+        let app = app(realm(&mock)).await;
+        let (authorization, cookie) = begin_login(&app).await;
+        let req = test::TestRequest::get()
+            .uri(&format!(
+                "/realms/acme/broker/mock/callback?error=access_denied&state={}",
+                authorization["state"]
+            ))
+            .cookie(cookie)
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[actix_web::test]
+    async fn test_unknown_provider() {
+        let mock = start_mock_provider();
+        let app = app(realm(&mock)).await;
+        let req = test::TestRequest::get()
+            .uri("/realms/acme/broker/initech/login")
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[actix_web::test]
+    async fn test_upstream_unreachable() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let issuer = format!("http://{}", listener.local_addr().unwrap());
+        drop(listener);
+        let realm =
+            Realm::of("acme", ISSUER, SigningKeys::of("test-secret")).with_upstream_provider(
+                UpstreamProvider::of("mock", issuer, CLIENT_ID, CLIENT_SECRET),
+            );
+        let app = app(realm).await;
+        let req = test::TestRequest::get()
+            .uri("/realms/acme/broker/mock/login")
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::BAD_GATEWAY);
+    }
+}
@@ -0,0 +1,331 @@
+#[cfg(test)]
+mod tests {
+    use actix_web::{
+        cookie::Cookie,
+        dev::{Service, ServiceResponse},
+        http::{header, StatusCode},
+        test, web, App,
+    };
+    use serde_json::Value;
+
+    use crate::{
+        app_state::AppState,
+        credential::passwd_hasher::hash_password,
+        domain::{mail_address::MailAddress, password::Password, password_policy::PasswordPolicy},
+        entity::{client::Client, user::User},
+        oauth::device_grant::DEVICE_CODE_GRANT_TYPE,
+        realm::realm_registry::{Realm, RealmRegistry},
+        resource::{
+            consent_resource::{consents_page_handler, revoke_consent_handler},
+            device_resource::{device_authorization_handler, device_verification_handler},
+            token_resource::token_handler,
+        },
+        token::{jwt::decode_jwt, signing_key::SigningKeys},
+    };
+
+    const ISSUER: &str = "http://localhost:8080/realms/acme";
+    const EMAIL: &str = "kamino@example.com";
+    const PASSWD: &str = "Correct7Horse9Battery";
+
+    async fn app(
+    ) -> impl Service<actix_http::Request, Response = ServiceResponse, Error = actix_web::Error>
+    {
+        let realm = Realm::of("acme", ISSUER, SigningKeys::of("test-secret"));
+        let scopes = vec!["todo:read".to_owned(), "todo:write".to_owned()];
+        realm
+            .clients
+            .save(
+                Client::of("third-party-tv")
+                    .with_scopes(scopes.clone())
+                    .with_required_consent(),
+            )
+            .unwrap();
+        realm
+            .clients
+            .save(Client::of("todo-cli").with_scopes(scopes))
+            .unwrap();
+        let email = MailAddress::of(EMAIL).unwrap();
+        let passwd = Password::of(PASSWD, &PasswordPolicy::default(), &email).unwrap();
+        let mut user = User::of(email);
+        user.passwd_hash = Some(hash_password(&passwd).unwrap());
+        realm.users.create(user).unwrap();
+        let state = AppState::of(RealmRegistry::of(vec![realm]).unwrap());
+        test::init_service(
+            App::new().app_data(web::Data::new(state)).service(
+                web::scope("/realms/{realm}")
+                    .route(
+                        "/device_authorization",
+                        web::post().to(device_authorization_handler),
+                    )
+                    .route("/device", web::post().to(device_verification_handler))
+                    .route("/token", web::post().to(token_handler))
+                    .route("/consents", web::get().to(consents_page_handler))
+                    .route("/consents/revoke", web::post().to(revoke_consent_handler)),
+            ),
+        )
+        .await
+    }
+
+    async fn post_form<S>(
+        app: &S,
+        path: &str,
+        form: &[(&str, &str)],
+        cookie: Option<&Cookie<'static>>,
+    ) -> ServiceResponse
+    where
+        S: Service<actix_http::Request, Response = ServiceResponse, Error = actix_web::Error>,
+    {
+        let mut req = test::TestRequest::post()
+            .uri(&format!("/realms/acme{}", path))
+            .set_form(form);
+        if let Some(cookie) = cookie {
+            req = req.cookie(cookie.clone());
+        }
+        test::call_service(app, req.to_request()).await
+    }
+
+    async fn authorize_device<S>(app: &S, client_id: &str, scope: &str) -> Value
+    where
+        S: Service<actix_http::Request, Response = ServiceResponse, Error = actix_web::Error>,
+    {
+        let res = post_form(
+            app,
+            "/device_authorization",
+            &[("client_id", client_id), ("scope", scope)],
+            None,
+        )
+        .await;
+        assert_eq!(res.status(), StatusCode::OK);
+        test::read_body_json(res).await
+    }
+
+    async fn approve<S>(
+        app: &S,
+        authorization: &Value,
+        credentials: &[(&str, &str)],
+        cookie: Option<&Cookie<'static>>,
+    ) -> ServiceResponse
+    where
+        S: Service<actix_http::Request, Response = ServiceResponse, Error = actix_web::Error>,
+    {
+        let mut form = vec![
+            ("user_code", authorization["user_code"].as_str().unwrap()),
+            ("action", "approve"),
+        ];
+        form.extend_from_slice(credentials);
+        post_form(app, "/device", &form, cookie).await
+    }
+
+    async fn body_text(res: ServiceResponse) -> String {
+        String::from_utf8(test::read_body(res).await.to_vec()).unwrap()
+    }
+
+    async fn poll<S>(app: &S, client_id: &str, authorization: &Value) -> ServiceResponse
+    where
+        S: Service<actix_http::Request, Response = ServiceResponse, Error = actix_web::Error>,
+    {
+        post_form(
+            app,
+            "/token",
+            &[
+                ("grant_type", DEVICE_CODE_GRANT_TYPE),
+                (
+                    "device_code",
+                    authorization["device_code"].as_str().unwrap(),
+                ),
+                ("client_id", client_id),
+            ],
+            None,
+        )
+        .await
+    }
+
+    #[actix_web::test]
+    async fn test_consent_flow() {
+        let app = app().await;
+        let authorization = authorize_device(&app, "third-party-tv", "todo:read").await;
+
+        // Signing in leads to the consent page, and to a session.
+        let res = approve(
+            &app,
+            &authorization,
+            &[("email", EMAIL), ("passwd", PASSWD)],
+            None,
+        )
+        .await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let cookie = res
+            .response()
+            .cookies()
+            .find(|c| c.name() == "idp_session_acme")
+            .unwrap()
+            .into_owned();
+        let page = body_text(res).await;
+        assert!(page.contains("third-party-tv asks to sign in as you"));
+        assert!(page.contains("<li>todo:read</li>"));
+        assert!(page.contains(r#"name="consent" value="todo:read""#));
+
+        let res = poll(&app, "third-party-tv", &authorization).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+        let res = approve(
+            &app,
+            &authorization,
+            &[("consent", "todo:read")],
+            Some(&cookie),
+        )
+        .await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert!(body_text(res).await.contains("The device is signed in"));
+        let res = poll(&app, "third-party-tv", &authorization).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let token: Value = test::read_body_json(res).await;
+        assert_eq!(token["scope"], "todo:read");
+        let claims = decode_jwt(
+            &SigningKeys::of("test-secret"),
+            ISSUER,
+            token["access_token"].as_str().unwrap(),
+            &MailAddress::of(EMAIL).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(claims.scopes(), Some(vec!["todo:read"]));
+
+        // Allowed once, not asked again for the same scopes.
+        let authorization = authorize_device(&app, "third-party-tv", "todo:read").await;
+        let res = approve(&app, &authorization, &[], Some(&cookie)).await;
+        assert!(body_text(res).await.contains("The device is signed in"));
+
+        // A scope not allowed yet is asked for.
+        let authorization = authorize_device(&app, "third-party-tv", "todo:read todo:write").await;
+        let res = approve(&app, &authorization, &[], Some(&cookie)).await;
+        assert!(body_text(res).await.contains("<li>todo:write</li>"));
+        // Consent to fewer scopes than requested is no consent.
+        let res = approve(
+            &app,
+            &authorization,
+            &[("consent", "todo:read")],
+            Some(&cookie),
+        )
+        .await;
+        assert!(body_text(res).await.contains("asks to sign in as you"));
+    }
+
+    #[actix_web::test]
+    async fn test_revoke_consent() {
+        let app = app().await;
+        let authorization = authorize_device(&app, "third-party-tv", "todo:write").await;
+        let res = approve(
+            &app,
+            &authorization,
+            &[
+                ("email", EMAIL),
+                ("passwd", PASSWD),
+                ("consent", "todo:write"),
+            ],
+            None,
+        )
+        .await;
+        assert!(body_text(res).await.contains("The device is signed in"));
+        let authorization = authorize_device(&app, "third-party-tv", "todo:write").await;
+        let res = approve(
+            &app,
+            &authorization,
+            &[("email", EMAIL), ("passwd", PASSWD)],
+            None,
+        )
+        .await;
+        let cookie = res
+            .response()
+            .cookies()
+            .find(|c| c.name() == "idp_session_acme")
+            .map(|c| c.into_owned());
+        assert!(cookie.is_none(), "asked for consent again");
+        assert!(body_text(res).await.contains("The device is signed in"));
+
+        // The consents page needs a session, which the consent step starts.
+        let req = test::TestRequest::get()
+            .uri("/realms/acme/consents")
+            .to_request();
+        assert_eq!(
+            test::call_service(&app, req).await.status(),
+            StatusCode::UNAUTHORIZED
+        );
+        let authorization = authorize_device(&app, "third-party-tv", "todo:read").await;
+        let res = approve(
+            &app,
+            &authorization,
+            &[("email", EMAIL), ("passwd", PASSWD)],
+            None,
+        )
+        .await;
+        let cookie = res
+            .response()
+            .cookies()
+            .find(|c| c.name() == "idp_session_acme")
+            .unwrap()
+            .into_owned();
+
+        let req = test::TestRequest::get()
+            .uri("/realms/acme/consents")
+            .cookie(cookie.clone())
+            .to_request();
+        let page = body_text(test::call_service(&app, req).await).await;
+        assert!(page.contains("third-party-tv since"));
+        assert!(page.contains("todo:write"));
+        assert!(page.contains(r#"name="client_id" value="third-party-tv""#));
+
+        let res = post_form(
+            &app,
+            "/consents/revoke",
+            &[("client_id", "third-party-tv")],
+            Some(&cookie),
+        )
+        .await;
+        assert_eq!(res.status(), StatusCode::SEE_OTHER);
+        assert_eq!(
+            res.headers().get(header::LOCATION).unwrap(),
+            "http://localhost:8080/realms/acme/consents"
+        );
+        let res = post_form(
+            &app,
+            "/consents/revoke",
+            &[("client_id", "third-party-tv")],
+            Some(&cookie),
+        )
+        .await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+        // Asked again after revoking.
+        let authorization = authorize_device(&app, "third-party-tv", "todo:write").await;
+        let res = approve(&app, &authorization, &[], Some(&cookie)).await;
+        assert!(body_text(res).await.contains("asks to sign in as you"));
+    }
+
+    #[actix_web::test]
+    async fn test_scopes_of_client() {
+        let app = app().await;
+        // First-party clients are not asked about.
+        let authorization = authorize_device(&app, "todo-cli", "todo:read todo:read").await;
+        let res = approve(
+            &app,
+            &authorization,
+            &[("email", EMAIL), ("passwd", PASSWD)],
+            None,
+        )
+        .await;
+        assert!(body_text(res).await.contains("The device is signed in"));
+        let token: Value = test::read_body_json(poll(&app, "todo-cli", &authorization).await).await;
+        assert_eq!(token["scope"], "todo:read");
+
+        let res = post_form(
+            &app,
+            "/device_authorization",
+            &[("client_id", "todo-cli"), ("scope", "todo:delete")],
+            None,
+        )
+        .await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        let body: Value = test::read_body_json(res).await;
+        assert_eq!(body["error"], "invalid_scope");
+    }
+}
@@ -0,0 +1,267 @@
+#[cfg(test)]
+mod tests {
+    use actix_web::{
+        dev::{Service, ServiceResponse},
+        http::{header, StatusCode},
+        test, web, App,
+    };
+    use serde_json::Value;
+
+    use crate::{
+        app_state::AppState,
+        credential::passwd_hasher::hash_password,
+        domain::{mail_address::MailAddress, password::Password, password_policy::PasswordPolicy},
+        entity::{client::Client, user::User},
+        oauth::device_grant::DEVICE_CODE_GRANT_TYPE,
+        realm::realm_registry::{Realm, RealmRegistry},
+        resource::{
+            device_resource::{
+                device_authorization_handler, device_page_handler, device_verification_handler,
+            },
+            token_resource::token_handler,
+        },
+        token::{jwt::decode_jwt, signing_key::SigningKeys},
+    };
+
+    const ISSUER: &str = "http://localhost:8080/realms/acme";
+    const EMAIL: &str = "kamino@example.com";
+    const PASSWD: &str = "Correct7Horse9Battery";
+
+    async fn app(
+    ) -> impl Service<actix_http::Request, Response = ServiceResponse, Error = actix_web::Error>
+    {
+        let realm = Realm::of("acme", ISSUER, SigningKeys::of("test-secret"));
+        realm.clients.save(Client::of("todo-cli")).unwrap();
+        let email = MailAddress::of(EMAIL).unwrap();
+        let passwd = Password::of(PASSWD, &PasswordPolicy::default(), &email).unwrap();
+        let mut user = User::of(email);
+        user.passwd_hash = Some(hash_password(&passwd).unwrap());
+        realm.users.create(user).unwrap();
+        let state = AppState::of(RealmRegistry::of(vec![realm]).unwrap());
+        test::init_service(
+            App::new().app_data(web::Data::new(state)).service(
+                web::scope("/realms/{realm}")
+                    .route(
+                        "/device_authorization",
+                        web::post().to(device_authorization_handler),
+                    )
+                    .route("/device", web::get().to(device_page_handler))
+                    .route("/device", web::post().to(device_verification_handler))
+                    .route("/token", web::post().to(token_handler)),
+            ),
+        )
+        .await
+    }
+
+    async fn post_form<S>(app: &S, path: &str, form: &[(&str, &str)]) -> ServiceResponse
+    where
+        S: Service<actix_http::Request, Response = ServiceResponse, Error = actix_web::Error>,
+    {
+        let req = test::TestRequest::post()
+            .uri(&format!("/realms/acme{}", path))
+            .set_form(form)
+            .to_request();
+        test::call_service(app, req).await
+    }
+
+    async fn authorize_device<S>(app: &S) -> Value
+    where
+        S: Service<actix_http::Request, Response = ServiceResponse, Error = actix_web::Error>,
+    {
+        let res = post_form(app, "/device_authorization", &[("client_id", "todo-cli")]).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        test::read_body_json(res).await
+    }
+
+    async fn poll<S>(app: &S, device_code: &str) -> ServiceResponse
+    where
+        S: Service<actix_http::Request, Response = ServiceResponse, Error = actix_web::Error>,
+    {
+        post_form(
+            app,
+            "/token",
+            &[
+                ("grant_type", DEVICE_CODE_GRANT_TYPE),
+                ("device_code", device_code),
+                ("client_id", "todo-cli"),
+            ],
+        )
+        .await
+    }
+
+    async fn oauth_error(res: ServiceResponse) -> String {
+        assert_eq!(
+            res.headers().get(header::CONTENT_TYPE).unwrap(),
+            "application/problem+json"
+        );
+        let body: Value = test::read_body_json(res).await;
+        body["error"].as_str().unwrap().to_owned()
+    }
+
+    #[actix_web::test]
+    async fn test_device_flow() {
+        let app = app().await;
+        let authorization = authorize_device(&app).await;
+        let device_code = authorization["device_code"].as_str().unwrap();
+        let user_code = authorization["user_code"].as_str().unwrap();
+        assert_eq!(
+            authorization["verification_uri"],
+            format!("{}/device", ISSUER)
+        );
+        assert_eq!(
+            authorization["verification_uri_complete"],
+            format!("{}/device?user_code={}", ISSUER, user_code)
+        );
+        assert_eq!(authorization["interval"], 5);
+
+        let res = poll(&app, device_code).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(oauth_error(res).await, "authorization_pending");
+        let res = poll(&app, device_code).await;
+        assert_eq!(oauth_error(res).await, "slow_down");
+
+        let req = test::TestRequest::get()
+            .uri(&format!(
+                "/realms/acme/device?user_code={}",
+                user_code.to_lowercase()
+            ))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let page = String::from_utf8(test::read_body(res).await.to_vec()).unwrap();
+        assert!(page.contains(&format!(r#"value="{}""#, user_code)));
+        assert!(page.contains(r#"name="passwd""#));
+        assert!(page.contains("todo-cli asks to sign in as you"));
+        assert!(page.contains("<li>sign in only</li>"));
+
+        let res = post_form(
+            &app,
+            "/device",
+            &[
+                ("user_code", user_code),
+                ("email", EMAIL),
+                ("passwd", PASSWD),
+                ("action", "approve"),
+            ],
+        )
+        .await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let res = poll(&app, device_code).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(
+            res.headers().get(header::CACHE_CONTROL).unwrap(),
+            "no-store"
+        );
+        let token: Value = test::read_body_json(res).await;
+        assert_eq!(token["token_type"], "Bearer");
+        let email = MailAddress::of(EMAIL).unwrap();
+        assert!(decode_jwt(
+            &SigningKeys::of("test-secret"),
+            ISSUER,
+            token["access_token"].as_str().unwrap(),
+            &email
+        )
+        .is_ok());
+
+        // Redeemed once.
+        let res = poll(&app, device_code).await;
+        assert_eq!(oauth_error(res).await, "invalid_grant");
+    }
+
+    #[actix_web::test]
+    async fn test_denied_device() {
+        let app = app().await;
+        let authorization = authorize_device(&app).await;
+        let res = post_form(
+            &app,
+            "/device",
+            &[
+                ("user_code", authorization["user_code"].as_str().unwrap()),
+                ("email", EMAIL),
+                ("passwd", PASSWD),
+                ("action", "deny"),
+            ],
+        )
+        .await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let res = poll(&app, authorization["device_code"].as_str().unwrap()).await;
+        assert_eq!(oauth_error(res).await, "access_denied");
+    }
+
+    #[actix_web::test]
+    async fn test_wrong_password_leaves_device_pending() {
+        let app = app().await;
+        let authorization = authorize_device(&app).await;
+        let res = post_form(
+            &app,
+            "/device",
+            &[
+                ("user_code", authorization["user_code"].as_str().unwrap()),
+                ("email", EMAIL),
+                ("passwd", "Wrong7Horse9Battery"),
+                ("action", "approve"),
+            ],
+        )
+        .await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+        let res = poll(&app, authorization["device_code"].as_str().unwrap()).await;
+        assert_eq!(oauth_error(res).await, "authorization_pending");
+    }
+
+    #[actix_web::test]
+    async fn test_unknown_user_code() {
+        let app = app().await;
+        let req = test::TestRequest::get()
+            .uri("/realms/acme/device?user_code=BCDF-GHJK")
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let page = String::from_utf8(test::read_body(res).await.to_vec()).unwrap();
+        assert!(page.contains(r#"value="BCDF-GHJK""#));
+        assert!(!page.contains(r#"value="approve""#));
+
+        let res = post_form(
+            &app,
+            "/device",
+            &[
+                ("user_code", "BCDF-GHJK"),
+                ("email", EMAIL),
+                ("passwd", PASSWD),
+                ("action", "approve"),
+            ],
+        )
+        .await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[actix_web::test]
+    async fn test_token_endpoint_errors() {
+        let app = app().await;
+        let res = post_form(&app, "/device_authorization", &[("client_id", "unknown")]).await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(oauth_error(res).await, "invalid_client");
+
+        let res = post_form(
+            &app,
+            "/token",
+            &[("grant_type", "password"), ("client_id", "todo-cli")],
+        )
+        .await;
+        assert_eq!(oauth_error(res).await, "unsupported_grant_type");
+
+        let res = post_form(
+            &app,
+            "/token",
+            &[
+                ("grant_type", DEVICE_CODE_GRANT_TYPE),
+                ("client_id", "todo-cli"),
+            ],
+        )
+        .await;
+        assert_eq!(oauth_error(res).await, "invalid_request");
+
+        let res = poll(&app, "unknown-device-code").await;
+        assert_eq!(oauth_error(res).await, "invalid_grant");
+    }
+}
@@ -0,0 +1,333 @@
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+
+    use actix_web::{
+        cookie::Cookie,
+        dev::{Service, ServiceResponse},
+        http::StatusCode,
+        test, web,
+    };
+    use serde_json::{json, Value};
+
+    use crate::{
+        app::app,
+        app_state::AppState,
+        credential::email_login::{MAX_CODE_ATTEMPTS, MAX_LOGINS_PER_ADDRESS, MAX_PENDING_LOGINS},
+        domain::mail_address::MailAddress,
+        entity::{group::Group, passkey::Passkey, user::User},
+        error::my_error,
+        mail::mailer::{Mail, Mailer},
+        realm::realm_registry::{Realm, RealmRegistry, DEFAULT_REALM},
+        token::{jwt::decode_jwt, signing_key::SigningKeys},
+        webauthn::cose_key::CoseKey,
+    };
+
+    const ISSUER: &str = "http://localhost:8080";
+    const EMAIL: &str = "alice@example.com";
+    const PASSKEY_EMAIL: &str = "carol@example.com";
+
+    /// Keeps the mail instead of sending it.
+    #[derive(Debug, Default)]
+    struct RecordingMailer {
+        sent: Mutex<Vec<Mail>>,
+    }
+
+    impl Mailer for RecordingMailer {
+        fn send(&self, mail: &Mail) -> my_error::Result<()> {
+            self.sent.lock().unwrap().push(mail.clone());
+            Ok(())
+        }
+    }
+
+    impl RecordingMailer {
+        /// The mail goes out after the answer, so it is waited for.
+        async fn last_body(&self, count: usize) -> String {
+            for _ in 0..100 {
+                if self.count() >= count {
+                    break;
+                }
+                actix_web::rt::time::sleep(Duration::from_millis(10)).await;
+            }
+            self.sent.lock().unwrap()[count - 1].body.clone()
+        }
+
+        fn count(&self) -> usize {
+            self.sent.lock().unwrap().len()
+        }
+    }
+
+    fn state(mailer: Option<Arc<RecordingMailer>>) -> web::Data<AppState> {
+        let realm = Realm::of(DEFAULT_REALM, ISSUER, SigningKeys::of("test-secret"));
+        let email = MailAddress::of(EMAIL).unwrap();
+        realm.users.create(User::of(email.clone())).unwrap();
+        realm
+            .groups
+            .save(Group::of("staff").with_member(email))
+            .unwrap();
+        let mut with_passkey = User::of(MailAddress::of(PASSKEY_EMAIL).unwrap());
+        with_passkey.passkeys.push(Passkey {
+            id: "credential".to_owned(),
+            public_key: CoseKey::Es256(vec![]),
+            sign_count: 0,
+            attestation_format: "none".to_owned(),
+        });
+        realm.users.create(with_passkey).unwrap();
+        let mut disabled = User::of(MailAddress::of("mallory@example.com").unwrap());
+        disabled.disabled = true;
+        realm.users.create(disabled).unwrap();
+        web::Data::new(
+            AppState::of(RealmRegistry::of(vec![realm]).unwrap())
+                .with_mailer(mailer.map(|mailer| mailer as Arc<dyn Mailer>)),
+        )
+    }
+
+    async fn start<S>(app: &S, email: &str, method: &str) -> ServiceResponse
+    where
+        S: Service<actix_http::Request, Response = ServiceResponse, Error = actix_web::Error>,
+    {
+        let req = test::TestRequest::post()
+            .uri("/login/email")
+            .set_json(json!({"email": email, "method": method}))
+            .to_request();
+        test::call_service(app, req).await
+    }
+
+    async fn enter_code<S>(app: &S, cookie: Option<&Cookie<'static>>, code: &str) -> ServiceResponse
+    where
+        S: Service<actix_http::Request, Response = ServiceResponse, Error = actix_web::Error>,
+    {
+        let mut req = test::TestRequest::post()
+            .uri("/login/email/verify")
+            .set_json(json!({"code": code}));
+        if let Some(cookie) = cookie {
+            req = req.cookie(cookie.clone());
+        }
+        test::call_service(app, req.to_request()).await
+    }
+
+    fn binding_cookie(res: &ServiceResponse) -> Option<Cookie<'static>> {
+        res.response()
+            .cookies()
+            .find(|c| c.name() == "idp_email_login_default")
+            .map(Cookie::into_owned)
+    }
+
+    /// Signed in as by password: session cookie and a token with the groups.
+    async fn assert_signed_in(res: ServiceResponse) {
+        assert_eq!(res.status(), StatusCode::OK);
+        assert!(res
+            .response()
+            .cookies()
+            .any(|c| c.name() == "idp_session_default" && !c.value().is_empty()));
+        let body: Value = test::read_body_json(res).await;
+        assert_eq!(body["user"]["email"], EMAIL);
+        let claims = decode_jwt(
+            &SigningKeys::of("test-secret"),
+            ISSUER,
+            body["token"].as_str().unwrap(),
+            &MailAddress::of(EMAIL).unwrap(),
+        )
+        .unwrap();
+        assert!(claims.session_id().is_some());
+        assert_eq!(claims.groups(), Some(&["staff".to_owned()][..]));
+    }
+
+    #[actix_web::test]
+    async fn test_login_by_link() {
+        let mailer = Arc::new(RecordingMailer::default());
+        let app = test::init_service(app(state(Some(mailer.clone())), false)).await;
+
+        let res = start(&app, EMAIL, "link").await;
+        assert_eq!(res.status(), StatusCode::ACCEPTED);
+        assert!(binding_cookie(&res).is_none());
+        let body: Value = test::read_body_json(res).await;
+        assert_eq!(body["expires_in"], 600);
+        let mail = mailer.last_body(1).await;
+        let link = mail
+            .lines()
+            .find(|line| line.starts_with("http://localhost:8080/login/email/verify?token="))
+            .unwrap();
+        let path = link.trim_start_matches(ISSUER).to_owned();
+
+        // Works in any browser, once.
+        let req = test::TestRequest::get().uri(&path).to_request();
+        assert_signed_in(test::call_service(&app, req).await).await;
+        let req = test::TestRequest::get().uri(&path).to_request();
+        assert_eq!(
+            test::call_service(&app, req).await.status(),
+            StatusCode::UNAUTHORIZED
+        );
+        let req = test::TestRequest::get()
+            .uri("/login/email/verify?token=forged")
+            .to_request();
+        assert_eq!(
+            test::call_service(&app, req).await.status(),
+            StatusCode::UNAUTHORIZED
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_login_by_code() {
+        let mailer = Arc::new(RecordingMailer::default());
+        let app = test::init_service(app(state(Some(mailer.clone())), false)).await;
+
+        let res = start(&app, EMAIL, "code").await;
+        assert_eq!(res.status(), StatusCode::ACCEPTED);
+        let cookie = binding_cookie(&res).unwrap();
+        assert!(cookie.http_only().unwrap());
+        let mail = mailer.last_body(1).await;
+        let code: String = mail.chars().filter(char::is_ascii_digit).take(6).collect();
+
+        // Not from another browser.
+        let res = enter_code(&app, None, &code).await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+        let other = start(&app, EMAIL, "code").await;
+        let other = binding_cookie(&other).unwrap();
+        let res = enter_code(&app, Some(&other), &code).await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+
+        let res = enter_code(&app, Some(&cookie), &code).await;
+        assert!(res
+            .response()
+            .cookies()
+            .any(|c| c.name() == "idp_email_login_default" && c.value().is_empty()));
+        assert_signed_in(res).await;
+        let res = enter_code(&app, Some(&cookie), &code).await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[actix_web::test]
+    async fn test_attempts_are_limited() {
+        let mailer = Arc::new(RecordingMailer::default());
+        let app = test::init_service(app(state(Some(mailer.clone())), false)).await;
+
+        let res = start(&app, EMAIL, "code").await;
+        let cookie = binding_cookie(&res).unwrap();
+        let mail = mailer.last_body(1).await;
+        let code: String = mail.chars().filter(char::is_ascii_digit).take(6).collect();
+        let wrong = format!("{:06}", (code.parse::<u32>().unwrap() + 1) % 1_000_000);
+        for _ in 0..MAX_CODE_ATTEMPTS {
+            let res = enter_code(&app, Some(&cookie), &wrong).await;
+            assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+        }
+        let res = enter_code(&app, Some(&cookie), &code).await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[actix_web::test]
+    async fn test_logins_started_are_limited() {
+        let mailer = Arc::new(RecordingMailer::default());
+        let app = test::init_service(app(state(Some(mailer.clone())), false)).await;
+
+        // Alternating methods and unknown addresses make no difference.
+        for (i, email) in [EMAIL, "bob@example.com"].into_iter().enumerate() {
+            for n in 0..MAX_LOGINS_PER_ADDRESS {
+                let method = if (n + i) % 2 == 0 { "link" } else { "code" };
+                let res = start(&app, email, method).await;
+                assert_eq!(res.status(), StatusCode::ACCEPTED);
+            }
+            let res = start(&app, email, "code").await;
+            assert_eq!(res.status(), StatusCode::TOO_MANY_REQUESTS);
+            assert!(binding_cookie(&res).is_none());
+            let body: Value = test::read_body_json(res).await;
+            assert_eq!(body["type"], "/problems/too-many-requests");
+        }
+        mailer.last_body(MAX_LOGINS_PER_ADDRESS).await;
+        assert_eq!(mailer.count(), MAX_LOGINS_PER_ADDRESS);
+    }
+
+    #[actix_web::test]
+    async fn test_new_login_replaces_the_oldest() {
+        let mailer = Arc::new(RecordingMailer::default());
+        let app = test::init_service(app(state(Some(mailer.clone())), false)).await;
+
+        let res = start(&app, EMAIL, "code").await;
+        let oldest = binding_cookie(&res).unwrap();
+        let oldest_code: String = mailer
+            .last_body(1)
+            .await
+            .chars()
+            .filter(char::is_ascii_digit)
+            .take(6)
+            .collect();
+        for _ in 0..MAX_PENDING_LOGINS {
+            let res = start(&app, EMAIL, "code").await;
+            assert_eq!(res.status(), StatusCode::ACCEPTED);
+        }
+        let res = start(&app, EMAIL, "code").await;
+        let newest = binding_cookie(&res).unwrap();
+        let mail = mailer.last_body(MAX_PENDING_LOGINS + 2).await;
+        let code: String = mail.chars().filter(char::is_ascii_digit).take(6).collect();
+
+        let res = enter_code(&app, Some(&oldest), &oldest_code).await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+        assert_signed_in(enter_code(&app, Some(&newest), &code).await).await;
+    }
+
+    #[actix_web::test]
+    async fn test_passkey_follows_mail() {
+        let mailer = Arc::new(RecordingMailer::default());
+        let app = test::init_service(app(state(Some(mailer.clone())), false)).await;
+
+        let res = start(&app, PASSKEY_EMAIL, "link").await;
+        assert_eq!(res.status(), StatusCode::ACCEPTED);
+        let mail = mailer.last_body(1).await;
+        let link = mail
+            .lines()
+            .find(|line| line.starts_with("http://localhost:8080/login/email/verify?token="))
+            .unwrap();
+        let req = test::TestRequest::get()
+            .uri(link.trim_start_matches(ISSUER))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::ACCEPTED);
+        assert!(!res
+            .response()
+            .cookies()
+            .any(|c| c.name() == "idp_session_default"));
+        let options: Value = test::read_body_json(res).await;
+        assert_eq!(options["allowCredentials"][0]["id"], "credential");
+
+        let res = start(&app, PASSKEY_EMAIL, "code").await;
+        let cookie = binding_cookie(&res).unwrap();
+        let mail = mailer.last_body(2).await;
+        let code: String = mail.chars().filter(char::is_ascii_digit).take(6).collect();
+        let res = enter_code(&app, Some(&cookie), &code).await;
+        assert_eq!(res.status(), StatusCode::ACCEPTED);
+        assert!(res
+            .response()
+            .cookies()
+            .any(|c| c.name() == "idp_email_login_default" && c.value().is_empty()));
+    }
+
+    #[actix_web::test]
+    async fn test_unknown_users_get_no_mail() {
+        let mailer = Arc::new(RecordingMailer::default());
+        let app = test::init_service(app(state(Some(mailer.clone())), false)).await;
+
+        for email in ["bob@example.com", "mallory@example.com"] {
+            let res = start(&app, email, "code").await;
+            assert_eq!(res.status(), StatusCode::ACCEPTED);
+            assert!(binding_cookie(&res).is_some());
+        }
+        assert_eq!(mailer.count(), 0);
+
+        let res = start(&app, "not-an-address", "code").await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        let res = start(&app, EMAIL, "sms").await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[actix_web::test]
+    async fn test_without_mailer() {
+        let app = test::init_service(app(state(None), false)).await;
+        assert_eq!(
+            start(&app, EMAIL, "link").await.status(),
+            StatusCode::NOT_FOUND
+        );
+    }
+}
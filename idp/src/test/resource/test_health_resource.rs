@@ -0,0 +1,87 @@
+#[cfg(test)]
+mod tests {
+    use actix_web::{http::StatusCode, test, web, App};
+    use serde_json::Value;
+
+    use crate::{
+        app_state::AppState,
+        realm::realm_registry::{Realm, RealmRegistry, DEFAULT_REALM},
+        resource::health_resource::{healthz_handler, readyz_handler},
+        token::signing_key::SigningKeys,
+    };
+
+    fn state_with_secret(secret: &str) -> web::Data<AppState> {
+        let realm = Realm::of(
+            DEFAULT_REALM,
+            "http://localhost:8080",
+            SigningKeys::of(secret),
+        );
+        web::Data::new(AppState::of(RealmRegistry::of(vec![realm]).unwrap()))
+    }
+
+    fn state() -> web::Data<AppState> {
+        state_with_secret("test-secret")
+    }
+
+    #[actix_web::test]
+    async fn test_healthz_ok() {
+        let app = test::init_service(
+            App::new().service(web::resource("/healthz").route(web::get().to(healthz_handler))),
+        )
+        .await;
+        let req = test::TestRequest::get().uri("/healthz").to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+
+    #[actix_web::test]
+    async fn test_readyz_lists_checks() {
+        let app = test::init_service(
+            App::new()
+                .app_data(state())
+                .service(web::resource("/readyz").route(web::get().to(readyz_handler))),
+        )
+        .await;
+        let req = test::TestRequest::get().uri("/readyz").to_request();
+        let body: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(body["status"], "up");
+        let names: Vec<&str> = body["checks"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|c| c["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(
+            names,
+            vec!["server", "signing_keys", "user_store", "token_denylist"]
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_readyz_unavailable_while_draining() {
+        let state = state();
+        state.begin_draining();
+        let app = test::init_service(
+            App::new()
+                .app_data(state)
+                .service(web::resource("/readyz").route(web::get().to(readyz_handler))),
+        )
+        .await;
+        let req = test::TestRequest::get().uri("/readyz").to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    #[actix_web::test]
+    async fn test_readyz_unavailable_without_keys() {
+        let app = test::init_service(
+            App::new()
+                .app_data(state_with_secret(""))
+                .service(web::resource("/readyz").route(web::get().to(readyz_handler))),
+        )
+        .await;
+        let req = test::TestRequest::get().uri("/readyz").to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+}
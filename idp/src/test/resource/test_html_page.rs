@@ -0,0 +1,22 @@
+#[cfg(test)]
+mod tests {
+    use crate::{
+        resource::html_page::escape_html,
+        saml::xml::{escape_attr, escape_text},
+    };
+
+    #[test]
+    fn test_escape_html() {
+        assert_eq!(
+            escape_html(r#"<a href='x' title="&">"#),
+            "&lt;a href=&#x27;x&#x27; title=&quot;&amp;&quot;&gt;"
+        );
+        assert_eq!(escape_html("ユーザー\n"), "ユーザー\n");
+    }
+
+    #[test]
+    fn test_xml_stays_canonical() {
+        assert_eq!(escape_text("a<b>'\"&\r"), "a&lt;b&gt;'\"&amp;&#xD;");
+        assert_eq!(escape_attr("a<b>'\"&\t\n"), "a&lt;b>'&quot;&amp;&#x9;&#xA;");
+    }
+}
@@ -0,0 +1,321 @@
+#[cfg(test)]
+mod tests {
+    use actix_web::{
+        dev::{Service, ServiceResponse},
+        http::StatusCode,
+        test, web, App,
+    };
+    use serde_json::{json, Value};
+
+    use crate::{
+        app_state::AppState,
+        credential::passwd_hasher::hash_password,
+        domain::{mail_address::MailAddress, password::Password, password_policy::PasswordPolicy},
+        entity::{client::Client, consent::Consent, passkey::Passkey, user::User},
+        realm::realm_registry::{Realm, RealmRegistry},
+        resource::idp_resource::{make_jwt_handler, validate_jwt_handler},
+        token::{jwt::make_jwt, signing_key::SigningKeys},
+        webauthn::cose_key::CoseKey,
+    };
+
+    const PASSWD: &str = "Correct7Horse9Battery";
+
+    fn user(email: &str) -> User {
+        let email = MailAddress::of(email).unwrap();
+        let passwd = Password::of(PASSWD, &PasswordPolicy::default(), &email).unwrap();
+        let mut user = User::of(email);
+        user.passwd_hash = Some(hash_password(&passwd).unwrap());
+        user
+    }
+
+    fn state() -> web::Data<AppState> {
+        let acme = Realm::of(
+            "acme",
+            "http://localhost:8080/realms/acme",
+            SigningKeys::of("acme-secret"),
+        );
+        acme.clients.save(Client::of("web")).unwrap();
+        acme.clients
+            .save(Client::of("api").with_secret("api-secret"))
+            .unwrap();
+        acme.clients
+            .save(Client::of("partner").with_required_consent())
+            .unwrap();
+        let mappers = serde_json::from_value(json!([
+            {"type": "user_attribute", "claim": "preferred_username", "attribute": "preferred_username"},
+            {"type": "static", "claim": "tenant", "value": "acme"},
+        ]))
+        .unwrap();
+        acme.clients
+            .save(Client::of("todo").with_claim_mappers(mappers))
+            .unwrap();
+        acme.users.create(user("kamino@example.com")).unwrap();
+        let mut mapped = user("mapped@example.com");
+        mapped
+            .attributes
+            .insert("preferred_username".to_owned(), "mapped".to_owned());
+        acme.users.create(mapped).unwrap();
+        let mut disabled = user("disabled@example.com");
+        disabled.disabled = true;
+        acme.users.create(disabled).unwrap();
+        // Same secret on purpose, so that only the issuer tells the realms apart.
+        let globex = Realm::of(
+            "globex",
+            "http://localhost:8080/realms/globex",
+            SigningKeys::of("acme-secret"),
+        );
+        web::Data::new(AppState::of(RealmRegistry::of(vec![acme, globex]).unwrap()))
+    }
+
+    async fn app(
+    ) -> impl Service<actix_http::Request, Response = ServiceResponse, Error = actix_web::Error>
+    {
+        service(state()).await
+    }
+
+    async fn service(
+        state: web::Data<AppState>,
+    ) -> impl Service<actix_http::Request, Response = ServiceResponse, Error = actix_web::Error>
+    {
+        test::init_service(
+            App::new().app_data(state).service(
+                web::scope("/realms/{realm}")
+                    .service(web::resource("/jwt").route(web::post().to(make_jwt_handler)))
+                    .service(
+                        web::resource("/validate").route(web::post().to(validate_jwt_handler)),
+                    ),
+            ),
+        )
+        .await
+    }
+
+    async fn sign_in<S>(app: &S, realm: &str, body: Value) -> ServiceResponse
+    where
+        S: Service<actix_http::Request, Response = ServiceResponse, Error = actix_web::Error>,
+    {
+        let req = test::TestRequest::post()
+            .uri(&format!("/realms/{}/jwt", realm))
+            .set_json(body)
+            .to_request();
+        test::call_service(app, req).await
+    }
+
+    #[actix_web::test]
+    async fn test_token_is_scoped_to_its_realm() {
+        let app = app().await;
+        let res = sign_in(
+            &app,
+            "acme",
+            json!({"email": "kamino@example.com", "passwd": PASSWD}),
+        )
+        .await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let body: Value = test::read_body_json(res).await;
+        let token = body["token"].as_str().unwrap();
+
+        let validate = json!({"email": "kamino@example.com", "token": token});
+        let req = test::TestRequest::post()
+            .uri("/realms/acme/validate")
+            .set_json(&validate)
+            .to_request();
+        let body: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(body["claims"]["iss"], "http://localhost:8080/realms/acme");
+
+        let req = test::TestRequest::post()
+            .uri("/realms/globex/validate")
+            .set_json(&validate)
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[actix_web::test]
+    async fn test_wrong_password() {
+        let state = state();
+        let app = service(state.clone()).await;
+        let res = sign_in(
+            &app,
+            "acme",
+            json!({"email": "kamino@example.com", "passwd": "Wrong7Horse9Battery"}),
+        )
+        .await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+
+        // Unknown addresses get no token, and are not registered either.
+        let res = sign_in(
+            &app,
+            "acme",
+            json!({"email": "unknown@example.com", "passwd": PASSWD}),
+        )
+        .await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+        let acme = state.realms.find("acme").unwrap();
+        let unknown = MailAddress::of("unknown@example.com").unwrap();
+        assert!(acme.users.find(&unknown).unwrap().is_none());
+
+        // A passkey is a second factor the endpoint does not ask for.
+        let mut with_passkey = user("passkey@example.com");
+        with_passkey.passkeys.push(Passkey {
+            id: "credential".to_owned(),
+            public_key: CoseKey::Es256(vec![]),
+            sign_count: 0,
+            attestation_format: "none".to_owned(),
+        });
+        acme.users.create(with_passkey).unwrap();
+        let res = sign_in(
+            &app,
+            "acme",
+            json!({"email": "passkey@example.com", "passwd": PASSWD}),
+        )
+        .await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[actix_web::test]
+    async fn test_disabled_user() {
+        let app = app().await;
+        let res = sign_in(
+            &app,
+            "acme",
+            json!({"email": "disabled@example.com", "passwd": PASSWD}),
+        )
+        .await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+
+        // Tokens issued before the user was disabled stop validating.
+        let email = MailAddress::of("disabled@example.com").unwrap();
+        let keys = SigningKeys::of("acme-secret");
+        let token = make_jwt(
+            &keys,
+            "http://localhost:8080/realms/acme",
+            &email,
+            None,
+            None,
+        )
+        .unwrap();
+        let req = test::TestRequest::post()
+            .uri("/realms/acme/validate")
+            .set_json(json!({"email": "disabled@example.com", "token": token}))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[actix_web::test]
+    async fn test_unknown_realm() {
+        let app = app().await;
+        let res = sign_in(
+            &app,
+            "initech",
+            json!({"email": "kamino@example.com", "passwd": PASSWD}),
+        )
+        .await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[actix_web::test]
+    async fn test_client_must_be_registered_in_realm() {
+        let app = app().await;
+        let body = json!({"email": "kamino@example.com", "passwd": PASSWD, "client_id": "web"});
+
+        let res = sign_in(&app, "acme", body.clone()).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let res: Value = test::read_body_json(res).await;
+        let token = res["token"].as_str().unwrap();
+        let req = test::TestRequest::post()
+            .uri("/realms/acme/validate")
+            .set_json(json!({"email": "kamino@example.com", "token": token}))
+            .to_request();
+        let res: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(res["claims"]["azp"], "web");
+
+        let res = sign_in(&app, "globex", body).await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[actix_web::test]
+    async fn test_client_claim_mappers() {
+        let app = app().await;
+        // The profile is released to a sign-in with the right password only.
+        let res = sign_in(
+            &app,
+            "acme",
+            json!({"email": "mapped@example.com", "passwd": "Wrong7Horse9Battery", "client_id": "todo"}),
+        )
+        .await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+        let body: Value = test::read_body_json(res).await;
+        assert!(body.get("token").is_none());
+
+        let res = sign_in(
+            &app,
+            "acme",
+            json!({"email": "mapped@example.com", "passwd": PASSWD, "client_id": "todo"}),
+        )
+        .await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let res: Value = test::read_body_json(res).await;
+        assert_eq!(res["user"]["attributes"]["preferred_username"], "mapped");
+        let req = test::TestRequest::post()
+            .uri("/realms/acme/validate")
+            .set_json(json!({"email": "mapped@example.com", "token": res["token"]}))
+            .to_request();
+        let res: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(res["claims"]["preferred_username"], "mapped");
+        assert_eq!(res["claims"]["tenant"], "acme");
+        assert_eq!(res["claims"]["sub"], "mapped@example.com");
+
+        // Other clients get none of them.
+        let res = sign_in(
+            &app,
+            "acme",
+            json!({"email": "mapped@example.com", "passwd": PASSWD, "client_id": "web"}),
+        )
+        .await;
+        let res: Value = test::read_body_json(res).await;
+        let req = test::TestRequest::post()
+            .uri("/realms/acme/validate")
+            .set_json(json!({"email": "mapped@example.com", "token": res["token"]}))
+            .to_request();
+        let res: Value = test::call_and_read_body_json(&app, req).await;
+        assert!(res["claims"].get("tenant").is_none());
+    }
+
+    #[actix_web::test]
+    async fn test_client_authentication() {
+        let app = app().await;
+        let body = |secret: Option<&str>| {
+            json!({
+                "email": "kamino@example.com",
+                "passwd": PASSWD,
+                "client_id": "api",
+                "client_secret": secret,
+            })
+        };
+        let res = sign_in(&app, "acme", body(None)).await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+        let res = sign_in(&app, "acme", body(Some("wrong-secret"))).await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+        let res = sign_in(&app, "acme", body(Some("api-secret"))).await;
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+
+    #[actix_web::test]
+    async fn test_client_requires_consent() {
+        let state = state();
+        let app = service(state.clone()).await;
+        let body = json!({"email": "kamino@example.com", "passwd": PASSWD, "client_id": "partner"});
+        let res = sign_in(&app, "acme", body.clone()).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        let res: Value = test::read_body_json(res).await;
+        assert_eq!(res["error"], "access_denied");
+
+        let acme = state.realms.find("acme").unwrap();
+        let email = MailAddress::of("kamino@example.com").unwrap();
+        acme.consents
+            .grant(Consent::of(email, "partner", &[]))
+            .unwrap();
+        let res = sign_in(&app, "acme", body).await;
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+}
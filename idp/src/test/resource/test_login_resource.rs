@@ -0,0 +1,82 @@
+#[cfg(test)]
+mod tests {
+    use actix_web::{http::StatusCode, test, web, App};
+    use serde_json::{json, Value};
+
+    use crate::{
+        app_state::AppState,
+        domain::mail_address::MailAddress,
+        entity::{group::Group, user::User},
+        realm::realm_registry::{Realm, RealmRegistry, DEFAULT_REALM},
+        resource::login_resource::password_login_handler,
+        token::{jwt::decode_jwt, signing_key::SigningKeys},
+    };
+
+    const EMAIL: &str = "imported@example.com";
+    const PASSWD: &str = "Correct7Horse9Battery";
+
+    #[actix_web::test]
+    async fn test_login_upgrades_imported_hash() {
+        let realm = Realm::of(
+            DEFAULT_REALM,
+            "http://localhost:8080",
+            SigningKeys::of("test-secret"),
+        );
+        let email = MailAddress::of(EMAIL).unwrap();
+        let mut user = User::of(email.clone());
+        user.passwd_hash = Some(bcrypt::hash(PASSWD, 4).unwrap());
+        realm.users.create(user).unwrap();
+        realm
+            .groups
+            .save(Group::of("migrated").with_member(email.clone()))
+            .unwrap();
+        let state = web::Data::new(AppState::of(RealmRegistry::of(vec![realm]).unwrap()));
+        let app = test::init_service(
+            App::new()
+                .app_data(state.clone())
+                .service(web::resource("/login").route(web::post().to(password_login_handler))),
+        )
+        .await;
+        let stored_hash = || {
+            state
+                .realms
+                .default_realm()
+                .users
+                .find(&email)
+                .unwrap()
+                .unwrap()
+                .passwd_hash
+                .unwrap()
+        };
+
+        // A wrong password leaves the hash alone.
+        let req = test::TestRequest::post()
+            .uri("/login")
+            .set_json(json!({"email": EMAIL, "passwd": "Wrong7Horse9Battery"}))
+            .to_request();
+        assert_eq!(
+            test::call_service(&app, req).await.status(),
+            StatusCode::UNAUTHORIZED
+        );
+        assert!(stored_hash().starts_with("$2b$"));
+
+        for _ in 0..2 {
+            let req = test::TestRequest::post()
+                .uri("/login")
+                .set_json(json!({"email": EMAIL, "passwd": PASSWD}))
+                .to_request();
+            let res = test::call_service(&app, req).await;
+            assert_eq!(res.status(), StatusCode::OK);
+            assert!(stored_hash().starts_with("$argon2id$"));
+            let body: Value = test::read_body_json(res).await;
+            let claims = decode_jwt(
+                &SigningKeys::of("test-secret"),
+                "http://localhost:8080",
+                body["token"].as_str().unwrap(),
+                &email,
+            )
+            .unwrap();
+            assert_eq!(claims.groups(), Some(&["migrated".to_owned()][..]));
+        }
+    }
+}
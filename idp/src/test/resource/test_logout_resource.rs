@@ -0,0 +1,420 @@
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, net::TcpListener, sync::Mutex, time::Duration};
+
+    use actix_web::{
+        cookie::Cookie,
+        dev::{Service, ServiceResponse},
+        http::{header, StatusCode},
+        test, web, App, HttpResponse, HttpServer,
+    };
+    use jsonwebtoken::{decode, decode_header, Algorithm, DecodingKey, Validation};
+    use serde_json::{json, Value};
+
+    use crate::{
+        app_state::AppState,
+        credential::passwd_hasher::hash_password,
+        domain::{mail_address::MailAddress, password::Password, password_policy::PasswordPolicy},
+        entity::{client::Client, user::User},
+        oauth::{
+            backchannel_logout::{BACKCHANNEL_LOGOUT_EVENT, LOGOUT_TOKEN_TYPE},
+            device_grant::DEVICE_CODE_GRANT_TYPE,
+        },
+        realm::realm_registry::{Realm, RealmRegistry},
+        resource::{
+            device_resource::{device_authorization_handler, device_verification_handler},
+            idp_resource::validate_jwt_handler,
+            login_resource::password_login_handler,
+            logout_resource::{logout_post_handler, logout_redirect_handler},
+            token_resource::token_handler,
+        },
+        token::{jwt::decode_jwt, signing_key::SigningKeys},
+    };
+
+    const SECRET: &str = "test-secret";
+    const TV_LOGOUT_SECRET: &str = "tv-logout-secret";
+    const ISSUER: &str = "http://localhost:8080/realms/acme";
+    const EMAIL: &str = "kamino@example.com";
+    const PASSWD: &str = "Correct7Horse9Battery";
+    const SIGNED_OUT: &str = "https://web.example.com/signed-out";
+
+    /// Local stand-in for the back-channel logout endpoints of clients.
+    struct MockClients {
+        base_url: String,
+        /// Logout tokens by the path they were posted to.
+        received: Mutex<Vec<(String, String)>>,
+        /// Server errors `/flaky` answers with before it accepts.
+        failures_left: Mutex<u32>,
+    }
+
+    async fn backchannel_logout(
+        mock: web::Data<MockClients>,
+        path: web::Path<String>,
+        form: web::Form<HashMap<String, String>>,
+    ) -> HttpResponse {
+        let path = path.into_inner();
+        mock.received
+            .lock()
+            .unwrap()
+            .push((path.clone(), form["logout_token"].clone()));
+        let mut failures_left = mock.failures_left.lock().unwrap();
+        match path.as_str() {
+            "flaky" if *failures_left > 0 => {
+                *failures_left -= 1;
+                HttpResponse::ServiceUnavailable().finish()
+            }
+            "reject" => HttpResponse::BadRequest().finish(),
+            _ => HttpResponse::Ok().finish(),
+        }
+    }
+
+    fn start_mock_clients() -> web::Data<MockClients> {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mock = web::Data::new(MockClients {
+            base_url: format!("http://{}", listener.local_addr().unwrap()),
+            received: Mutex::new(vec![]),
+            failures_left: Mutex::new(1),
+        });
+        let data = mock.clone();
+        let server = HttpServer::new(move || {
+            App::new()
+                .app_data(data.clone())
+                .route("/{path}", web::post().to(backchannel_logout))
+        })
+        .workers(1)
+        .listen(listener)
+        .unwrap()
+        .run();
+        actix_web::rt::spawn(server);
+        mock
+    }
+
+    async fn app(
+        mock: &MockClients,
+    ) -> impl Service<actix_http::Request, Response = ServiceResponse, Error = actix_web::Error>
+    {
+        let realm = Realm::of("acme", ISSUER, SigningKeys::of(SECRET));
+        realm
+            .clients
+            .save(Client::of("web").with_post_logout_redirect_uris(vec![SIGNED_OUT.to_owned()]))
+            .unwrap();
+        realm
+            .clients
+            .save(
+                Client::of("tv")
+                    .with_backchannel_logout(format!("{}/flaky", mock.base_url), TV_LOGOUT_SECRET),
+            )
+            .unwrap();
+        realm
+            .clients
+            .save(
+                Client::of("kiosk")
+                    .with_backchannel_logout(format!("{}/reject", mock.base_url), "kiosk-secret"),
+            )
+            .unwrap();
+        for email in [EMAIL, "other@example.com"] {
+            let email = MailAddress::of(email).unwrap();
+            let passwd = Password::of(PASSWD, &PasswordPolicy::default(), &email).unwrap();
+            let mut user = User::of(email);
+            user.passwd_hash = Some(hash_password(&passwd).unwrap());
+            realm.users.create(user).unwrap();
+        }
+        let state = AppState::of(RealmRegistry::of(vec![realm]).unwrap());
+        test::init_service(
+            App::new().app_data(web::Data::new(state)).service(
+                web::scope("/realms/{realm}")
+                    .route("/login", web::post().to(password_login_handler))
+                    .route("/logout", web::get().to(logout_redirect_handler))
+                    .route("/logout", web::post().to(logout_post_handler))
+                    .route("/validate", web::post().to(validate_jwt_handler))
+                    .route(
+                        "/device_authorization",
+                        web::post().to(device_authorization_handler),
+                    )
+                    .route("/device", web::post().to(device_verification_handler))
+                    .route("/token", web::post().to(token_handler)),
+            ),
+        )
+        .await
+    }
+
+    /// Session cookie and token of a password login.
+    async fn login<S>(app: &S, email: &str) -> (Cookie<'static>, String)
+    where
+        S: Service<actix_http::Request, Response = ServiceResponse, Error = actix_web::Error>,
+    {
+        let req = test::TestRequest::post()
+            .uri("/realms/acme/login")
+            .set_json(json!({"email": email, "passwd": PASSWD}))
+            .to_request();
+        let res = test::call_service(app, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let cookie = res
+            .response()
+            .cookies()
+            .find(|c| c.name() == "idp_session_acme")
+            .unwrap()
+            .into_owned();
+        let body: Value = test::read_body_json(res).await;
+        (cookie, body["token"].as_str().unwrap().to_owned())
+    }
+
+    async fn logout<S>(
+        app: &S,
+        query: &[(&str, &str)],
+        cookie: Option<&Cookie<'static>>,
+    ) -> ServiceResponse
+    where
+        S: Service<actix_http::Request, Response = ServiceResponse, Error = actix_web::Error>,
+    {
+        let mut req = test::TestRequest::get().uri(&format!(
+            "/realms/acme/logout?{}",
+            serde_urlencoded::to_string(query).unwrap()
+        ));
+        if let Some(cookie) = cookie {
+            req = req.cookie(cookie.clone());
+        }
+        test::call_service(app, req.to_request()).await
+    }
+
+    async fn is_valid<S>(app: &S, email: &str, token: &str) -> bool
+    where
+        S: Service<actix_http::Request, Response = ServiceResponse, Error = actix_web::Error>,
+    {
+        let req = test::TestRequest::post()
+            .uri("/realms/acme/validate")
+            .set_json(json!({"email": email, "token": token}))
+            .to_request();
+        test::call_service(app, req).await.status() == StatusCode::OK
+    }
+
+    async fn oauth_error(res: ServiceResponse) -> String {
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        let body: Value = test::read_body_json(res).await;
+        body["error"].as_str().unwrap().to_owned()
+    }
+
+    fn removes_session_cookie(res: &ServiceResponse) -> bool {
+        res.response()
+            .cookies()
+            .any(|c| c.name() == "idp_session_acme" && c.value().is_empty())
+    }
+
+    #[actix_web::test]
+    async fn test_logout_with_redirect() {
+        let mock = start_mock_clients();
+        let app = app(&mock).await;
+        let (cookie, token) = login(&app, EMAIL).await;
+        assert!(is_valid(&app, EMAIL, &token).await);
+
+        let res = logout(
+            &app,
+            &[
+                ("id_token_hint", &token),
+                ("client_id", "web"),
+                ("post_logout_redirect_uri", SIGNED_OUT),
+                ("state", "af0ifjsldkj"),
+            ],
+            Some(&cookie),
+        )
+        .await;
+        assert_eq!(res.status(), StatusCode::SEE_OTHER);
+        assert_eq!(
+            res.headers().get(header::LOCATION).unwrap(),
+            "https://web.example.com/signed-out?state=af0ifjsldkj"
+        );
+        assert!(removes_session_cookie(&res));
+        // Tokens issued in the session are revoked with it.
+        assert!(!is_valid(&app, EMAIL, &token).await);
+
+        // Without a session, there is nothing left to end.
+        let res = logout(&app, &[], Some(&cookie)).await;
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+
+    #[actix_web::test]
+    async fn test_logout_with_hint_only() {
+        let mock = start_mock_clients();
+        let app = app(&mock).await;
+        let (_, token) = login(&app, EMAIL).await;
+        let res = logout(&app, &[("id_token_hint", &token)], None).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert!(!is_valid(&app, EMAIL, &token).await);
+    }
+
+    #[actix_web::test]
+    async fn test_logout_without_hint_asks_first() {
+        let mock = start_mock_clients();
+        let app = app(&mock).await;
+        let (cookie, token) = login(&app, EMAIL).await;
+
+        let res = logout(
+            &app,
+            &[
+                ("client_id", "web"),
+                ("post_logout_redirect_uri", SIGNED_OUT),
+                ("state", "af0ifjsldkj"),
+            ],
+            Some(&cookie),
+        )
+        .await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert!(!removes_session_cookie(&res));
+        let page = String::from_utf8(test::read_body(res).await.to_vec()).unwrap();
+        assert!(page.contains(r#"<form method="post" action="logout">"#));
+        assert!(page.contains(
+            r#"name="post_logout_redirect_uri" value="https://web.example.com/signed-out""#
+        ));
+        assert!(is_valid(&app, EMAIL, &token).await);
+
+        // The form of the page is the answer.
+        let req = test::TestRequest::post()
+            .uri("/realms/acme/logout")
+            .cookie(cookie)
+            .set_form([
+                ("client_id", "web"),
+                ("post_logout_redirect_uri", SIGNED_OUT),
+                ("state", "af0ifjsldkj"),
+            ])
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::SEE_OTHER);
+        assert!(removes_session_cookie(&res));
+        assert!(!is_valid(&app, EMAIL, &token).await);
+    }
+
+    #[actix_web::test]
+    async fn test_rejected_logout() {
+        let mock = start_mock_clients();
+        let app = app(&mock).await;
+        let (cookie, token) = login(&app, EMAIL).await;
+
+        let res = logout(
+            &app,
+            &[
+                ("client_id", "web"),
+                ("post_logout_redirect_uri", "https://evil.example.com/"),
+            ],
+            Some(&cookie),
+        )
+        .await;
+        assert_eq!(oauth_error(res).await, "invalid_request");
+
+        // A redirect needs to know whose redirect URIs to check.
+        let res = logout(
+            &app,
+            &[("post_logout_redirect_uri", SIGNED_OUT)],
+            Some(&cookie),
+        )
+        .await;
+        assert_eq!(oauth_error(res).await, "invalid_request");
+
+        let res = logout(&app, &[("id_token_hint", "not-a-token")], Some(&cookie)).await;
+        assert_eq!(oauth_error(res).await, "invalid_request");
+
+        // The hint is for someone else than the browser's user.
+        let (_, other_token) = login(&app, "other@example.com").await;
+        let res = logout(&app, &[("id_token_hint", &other_token)], Some(&cookie)).await;
+        assert_eq!(oauth_error(res).await, "invalid_request");
+
+        // Nothing of it ended the session.
+        assert!(is_valid(&app, EMAIL, &token).await);
+    }
+
+    #[actix_web::test]
+    async fn test_backchannel_logout() {
+        let mock = start_mock_clients();
+        let app = app(&mock).await;
+        let (cookie, _) = login(&app, EMAIL).await;
+
+        // Both devices are approved in the browser's session.
+        let mut device_tokens = vec![];
+        for client_id in ["tv", "kiosk"] {
+            let req = test::TestRequest::post()
+                .uri("/realms/acme/device_authorization")
+                .set_form([("client_id", client_id)])
+                .to_request();
+            let authorization: Value = test::call_and_read_body_json(&app, req).await;
+            let req = test::TestRequest::post()
+                .uri("/realms/acme/device")
+                .cookie(cookie.clone())
+                .set_form([
+                    ("user_code", authorization["user_code"].as_str().unwrap()),
+                    ("action", "approve"),
+                ])
+                .to_request();
+            assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+            let req = test::TestRequest::post()
+                .uri("/realms/acme/token")
+                .set_form([
+                    ("grant_type", DEVICE_CODE_GRANT_TYPE),
+                    (
+                        "device_code",
+                        authorization["device_code"].as_str().unwrap(),
+                    ),
+                    ("client_id", client_id),
+                ])
+                .to_request();
+            let token: Value = test::call_and_read_body_json(&app, req).await;
+            device_tokens.push(token["access_token"].as_str().unwrap().to_owned());
+        }
+
+        let req = test::TestRequest::post()
+            .uri("/realms/acme/logout")
+            .cookie(cookie)
+            .set_form([("client_id", "tv")])
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        for token in &device_tokens {
+            assert!(!is_valid(&app, EMAIL, token).await);
+        }
+
+        // The flaky client gets the token again after its server error.
+        for _ in 0..50 {
+            if mock.received.lock().unwrap().len() >= 3 {
+                break;
+            }
+            actix_web::rt::time::sleep(Duration::from_millis(100)).await;
+        }
+        let received = mock.received.lock().unwrap().clone();
+        let paths: Vec<&str> = received.iter().map(|(path, _)| path.as_str()).collect();
+        assert_eq!(paths.iter().filter(|path| **path == "flaky").count(), 2);
+        // Turned down, so sent once.
+        assert_eq!(paths.iter().filter(|path| **path == "reject").count(), 1);
+
+        let (_, logout_token) = received.iter().find(|(path, _)| path == "flaky").unwrap();
+        let header = decode_header(logout_token).unwrap();
+        assert_eq!(header.typ.as_deref(), Some(LOGOUT_TOKEN_TYPE));
+        assert_eq!(header.alg, Algorithm::HS256);
+        let mut validation = Validation::new(Algorithm::HS256);
+        validation.set_audience(&["tv"]);
+        validation.set_issuer(&[ISSUER]);
+        let claims = decode::<Value>(
+            logout_token,
+            &DecodingKey::from_secret(TV_LOGOUT_SECRET.as_ref()),
+            &validation,
+        )
+        .unwrap()
+        .claims;
+        // The realm's keys do not check it, nor does it pass for its tokens.
+        assert!(decode::<Value>(
+            logout_token,
+            &DecodingKey::from_secret(SECRET.as_ref()),
+            &validation,
+        )
+        .is_err());
+        assert!(decode_jwt(
+            &SigningKeys::of(SECRET),
+            ISSUER,
+            logout_token,
+            &MailAddress::of(EMAIL).unwrap(),
+        )
+        .is_err());
+        assert_eq!(claims["sub"], EMAIL);
+        assert_eq!(claims["events"], json!({BACKCHANNEL_LOGOUT_EVENT: {}}));
+        assert!(claims["sid"].is_string());
+        assert!(claims["jti"].is_string());
+        assert!(claims.get("nonce").is_none());
+    }
+}
@@ -0,0 +1,148 @@
+#[cfg(test)]
+mod tests {
+    use actix_web::{
+        http::{header, StatusCode},
+        test, web, App,
+    };
+    use serde_json::Value;
+
+    use utoipa::OpenApi;
+
+    use crate::{
+        app::{IDP_ROUTES, ROOT_ROUTES},
+        resource::openapi_resource::{
+            openapi_json_handler, swagger_ui, swagger_ui_handler, ApiDoc,
+        },
+    };
+
+    // Registered with its own attribute rather than from a route table.
+    const HELLO_HTML: (&str, &str) = ("/{id}/{name}/index.html", "get");
+
+    #[actix_web::test]
+    async fn test_document_matches_routes() {
+        let doc = serde_json::to_value(ApiDoc::openapi()).unwrap();
+        let served: Vec<(&str, &str)> = IDP_ROUTES
+            .iter()
+            .chain(ROOT_ROUTES)
+            .copied()
+            .chain([HELLO_HTML])
+            .collect();
+        for (path, method) in &served {
+            assert!(
+                doc["paths"][path].get(method).is_some(),
+                "{} {} is served but not documented",
+                method,
+                path
+            );
+        }
+        for (path, item) in doc["paths"].as_object().unwrap() {
+            for method in item.as_object().unwrap().keys() {
+                assert!(
+                    served.contains(&(path.as_str(), method.as_str())),
+                    "{} {} is documented but not served",
+                    method,
+                    path
+                );
+            }
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_openapi_json_lists_routes() {
+        let app = test::init_service(
+            App::new()
+                .service(web::resource("/openapi.json").route(web::get().to(openapi_json_handler))),
+        )
+        .await;
+        let req = test::TestRequest::get().uri("/openapi.json").to_request();
+        let body: Value = test::call_and_read_body_json(&app, req).await;
+
+        assert!(body["openapi"].as_str().unwrap().starts_with("3."));
+        for path in [
+            "/jwt",
+            "/validate",
+            "/healthz",
+            "/readyz",
+            "/rest",
+            "/{id}/{name}/index.html",
+        ] {
+            assert!(body["paths"].get(path).is_some(), "missing path {}", path);
+        }
+        assert!(body["paths"]["/jwt"].get("post").is_some());
+        for schema in [
+            "AuthenticationReqBody",
+            "SingInResponse",
+            "TokenValidatedResponse",
+        ] {
+            assert!(
+                body["components"]["schemas"].get(schema).is_some(),
+                "missing schema {}",
+                schema
+            );
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_root_paths_name_their_server() {
+        let app = test::init_service(
+            App::new()
+                .service(web::resource("/openapi.json").route(web::get().to(openapi_json_handler))),
+        )
+        .await;
+        let req = test::TestRequest::get().uri("/openapi.json").to_request();
+        let body: Value = test::call_and_read_body_json(&app, req).await;
+        for (path, method) in [
+            ("/healthz", "get"),
+            ("/readyz", "get"),
+            ("/rest", "post"),
+            ("/openapi.json", "get"),
+            ("/swagger-ui", "get"),
+            ("/{id}/{name}/index.html", "get"),
+        ] {
+            assert_eq!(
+                body["paths"][path][method]["servers"][0]["url"], "/",
+                "{} claims to be served per realm",
+                path
+            );
+        }
+        // Realm paths fall back to the document's servers.
+        assert!(body["paths"]["/jwt"]["post"].get("servers").is_none());
+        assert_eq!(body["servers"][1]["url"], "/realms/{realm}");
+    }
+
+    #[actix_web::test]
+    async fn test_swagger_ui_is_embedded() {
+        let app = test::init_service(
+            App::new()
+                .service(web::resource("/swagger-ui").route(web::get().to(swagger_ui_handler)))
+                .service(swagger_ui()),
+        )
+        .await;
+        let req = test::TestRequest::get().uri("/swagger-ui").to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::PERMANENT_REDIRECT);
+        assert_eq!(res.headers().get(header::LOCATION).unwrap(), "/swagger-ui/");
+
+        let req = test::TestRequest::get().uri("/swagger-ui/").to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let page = test::read_body(res).await;
+        let page = std::str::from_utf8(&page).unwrap();
+        assert!(page.contains("swagger-ui-bundle.js"));
+        assert!(!page.contains("https://"));
+
+        for asset in ["swagger-ui-bundle.js", "swagger-ui.css"] {
+            let req = test::TestRequest::get()
+                .uri(&format!("/swagger-ui/{}", asset))
+                .to_request();
+            assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+        }
+        let req = test::TestRequest::get()
+            .uri("/swagger-ui/swagger-initializer.js")
+            .to_request();
+        let script = test::call_and_read_body(&app, req).await;
+        assert!(std::str::from_utf8(&script)
+            .unwrap()
+            .contains("/openapi.json"));
+    }
+}
@@ -0,0 +1,343 @@
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use actix_web::{
+        dev::{Service, ServiceResponse},
+        http::{header, StatusCode},
+        test, web, App,
+    };
+    use base64::{engine::general_purpose::STANDARD, Engine};
+    use flate2::{write::DeflateEncoder, Compression};
+    use ring::digest::{digest, SHA256};
+    use rustls_pki_types::CertificateDer;
+
+    use crate::{
+        app_state::AppState,
+        credential::passwd_hasher::hash_password,
+        domain::{mail_address::MailAddress, password::Password},
+        entity::{service_provider::ServiceProvider, user::User},
+        error::problem::form_error_handler,
+        realm::realm_registry::{Realm, RealmRegistry},
+        resource::saml_resource::{
+            saml_metadata_handler, sso_login_handler, sso_post_handler, sso_redirect_handler,
+        },
+        saml::saml_credential::SamlCredential,
+        token::signing_key::SigningKeys,
+    };
+
+    const ISSUER: &str = "http://localhost:8080/realms/acme";
+    const SP_ENTITY_ID: &str = "https://sp.example.com";
+    const SP_ACS_URL: &str = "https://sp.example.com/acs";
+    const EMAIL: &str = "kamino@example.com";
+    const PASSWD: &str = "Correct7Horse9Battery";
+
+    async fn app(
+    ) -> impl Service<actix_http::Request, Response = ServiceResponse, Error = actix_web::Error>
+    {
+        let realm = Realm::of("acme", ISSUER, SigningKeys::of("test-secret"));
+        realm
+            .service_providers
+            .save(ServiceProvider {
+                entity_id: SP_ENTITY_ID.to_owned(),
+                acs_url: SP_ACS_URL.to_owned(),
+                attributes: vec!["email".to_owned(), "preferred_username".to_owned()],
+            })
+            .unwrap();
+        let email = MailAddress::of(EMAIL).unwrap();
+        let passwd = Password::of(PASSWD, &realm.password_policy, &email).unwrap();
+        let mut user = User::of(email);
+        user.passwd_hash = Some(hash_password(&passwd).unwrap());
+        user.attributes
+            .insert("preferred_username".to_owned(), "kamino".to_owned());
+        user.attributes
+            .insert("employee_id".to_owned(), "E-1024".to_owned());
+        realm.users.create(user).unwrap();
+
+        let credential = SamlCredential::from_pem(
+            include_bytes!("../fixtures/saml_cert.pem"),
+            include_bytes!("../fixtures/saml_key.pem"),
+        )
+        .unwrap();
+        let state = AppState::of(RealmRegistry::of(vec![realm]).unwrap())
+            .with_saml_credential(Some(credential));
+        test::init_service(
+            App::new()
+                .app_data(web::Data::new(state))
+                .app_data(web::FormConfig::default().error_handler(form_error_handler))
+                .service(
+                    web::scope("/realms/{realm}")
+                        .service(
+                            web::resource("/saml/metadata")
+                                .route(web::get().to(saml_metadata_handler)),
+                        )
+                        .service(
+                            web::resource("/saml/sso")
+                                .route(web::get().to(sso_redirect_handler))
+                                .route(web::post().to(sso_post_handler)),
+                        )
+                        .service(
+                            web::resource("/saml/login").route(web::post().to(sso_login_handler)),
+                        ),
+                ),
+        )
+        .await
+    }
+
+    // What the service provider sends, here with the redirect binding.
+    fn authn_request(id: &str, issuer: &str) -> String {
+        format!(
+            r#"<samlp:AuthnRequest xmlns:samlp="urn:oasis:names:tc:SAML:2.0:protocol" xmlns:saml="urn:oasis:names:tc:SAML:2.0:assertion" ID="{}" Version="2.0" IssueInstant="2022-05-01T00:00:00Z" AssertionConsumerServiceURL="{}"><saml:Issuer>{}</saml:Issuer></samlp:AuthnRequest>"#,
+            id, SP_ACS_URL, issuer
+        )
+    }
+
+    fn redirect_query(xml: &str) -> String {
+        let mut encoder = DeflateEncoder::new(vec![], Compression::default());
+        encoder.write_all(xml.as_bytes()).unwrap();
+        let encoded = STANDARD
+            .encode(encoder.finish().unwrap())
+            .replace('+', "%2B")
+            .replace('/', "%2F")
+            .replace('=', "%3D");
+        format!("SAMLRequest={}&RelayState=%2Fdashboard", encoded)
+    }
+
+    fn between<'a>(s: &'a str, start: &str, end: &str) -> &'a str {
+        let from = s.find(start).unwrap() + start.len();
+        let to = from + s[from..].find(end).unwrap();
+        &s[from..to]
+    }
+
+    fn form_value<'a>(html: &'a str, name: &str) -> &'a str {
+        between(html, &format!(r#"name="{}" value=""#, name), "\"")
+    }
+
+    /// Checks the assertion signature the way a service provider does and
+    /// returns the assertion.
+    fn verify_assertion(response: &str, certificate: &[u8]) -> String {
+        let assertion = format!(
+            "<saml:Assertion{}</saml:Assertion>",
+            between(response, "<saml:Assertion", "</saml:Assertion>")
+        );
+        let signature = format!(
+            "<ds:Signature{}</ds:Signature>",
+            between(&assertion, "<ds:Signature", "</ds:Signature>")
+        );
+        let unsigned = assertion.replace(&signature, "");
+        assert_eq!(
+            STANDARD.encode(digest(&SHA256, unsigned.as_bytes())),
+            between(&signature, "<ds:DigestValue>", "</ds:DigestValue>")
+        );
+        let signed_info = format!(
+            r#"<ds:SignedInfo xmlns:ds="http://www.w3.org/2000/09/xmldsig#">{}</ds:SignedInfo>"#,
+            between(&signature, "<ds:SignedInfo>", "</ds:SignedInfo>")
+        );
+        let signature_value = STANDARD
+            .decode(between(
+                &signature,
+                "<ds:SignatureValue>",
+                "</ds:SignatureValue>",
+            ))
+            .unwrap();
+        let certificate = CertificateDer::from(certificate);
+        webpki::EndEntityCert::try_from(&certificate)
+            .unwrap()
+            .verify_signature(
+                webpki::ring::RSA_PKCS1_2048_8192_SHA256,
+                signed_info.as_bytes(),
+                &signature_value,
+            )
+            .unwrap();
+        assertion
+    }
+
+    async fn login<S>(app: &S, login_form: &str, passwd: &str) -> ServiceResponse
+    where
+        S: Service<actix_http::Request, Response = ServiceResponse, Error = actix_web::Error>,
+    {
+        let req = test::TestRequest::post()
+            .uri("/realms/acme/saml/login")
+            .set_form([
+                ("SAMLRequest", form_value(login_form, "SAMLRequest")),
+                ("RelayState", form_value(login_form, "RelayState")),
+                ("email", EMAIL),
+                ("passwd", passwd),
+            ])
+            .to_request();
+        test::call_service(app, req).await
+    }
+
+    #[actix_web::test]
+    async fn test_sp_initiated_sso() {
+        let app = app().await;
+
+        let req = test::TestRequest::get()
+            .uri("/realms/acme/saml/metadata")
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(
+            res.headers().get(header::CONTENT_TYPE).unwrap(),
+            "application/samlmetadata+xml"
+        );
+        let metadata = String::from_utf8(test::read_body(res).await.to_vec()).unwrap();
+        assert!(metadata.contains(&format!(r#"entityID="{}""#, ISSUER)));
+        assert!(metadata.contains(&format!(r#"Location="{}/saml/sso""#, ISSUER)));
+        let certificate = STANDARD
+            .decode(between(
+                &metadata,
+                "<ds:X509Certificate>",
+                "</ds:X509Certificate>",
+            ))
+            .unwrap();
+
+        let req = test::TestRequest::get()
+            .uri(&format!(
+                "/realms/acme/saml/sso?{}",
+                redirect_query(&authn_request("_sp-request-1", SP_ENTITY_ID))
+            ))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let login_form = String::from_utf8(test::read_body(res).await.to_vec()).unwrap();
+        assert_eq!(form_value(&login_form, "RelayState"), "/dashboard");
+
+        let res = login(&app, &login_form, PASSWD).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(
+            res.headers().get(header::CACHE_CONTROL).unwrap(),
+            "no-store"
+        );
+        let page = String::from_utf8(test::read_body(res).await.to_vec()).unwrap();
+        assert_eq!(between(&page, r#"action=""#, "\""), SP_ACS_URL);
+        assert_eq!(form_value(&page, "RelayState"), "/dashboard");
+        let response =
+            String::from_utf8(STANDARD.decode(form_value(&page, "SAMLResponse")).unwrap()).unwrap();
+
+        assert!(response.contains(&format!(r#"Destination="{}""#, SP_ACS_URL)));
+        assert!(response.contains(r#"InResponseTo="_sp-request-1""#));
+        assert!(response.contains(r#"Value="urn:oasis:names:tc:SAML:2.0:status:Success""#));
+
+        let assertion = verify_assertion(&response, &certificate);
+        assert_eq!(between(&assertion, "<saml:Issuer>", "</"), ISSUER);
+        assert_eq!(
+            between(&assertion, "<saml:NameID", "</").split('>').nth(1),
+            Some(EMAIL)
+        );
+        assert_eq!(between(&assertion, "<saml:Audience>", "</"), SP_ENTITY_ID);
+        assert!(assertion.contains(&format!(
+            r#"<saml:Attribute Name="email" NameFormat="urn:oasis:names:tc:SAML:2.0:attrname-format:basic"><saml:AttributeValue>{}</saml:AttributeValue></saml:Attribute>"#,
+            EMAIL
+        )));
+        assert!(assertion.contains(r#"<saml:Attribute Name="preferred_username" NameFormat="urn:oasis:names:tc:SAML:2.0:attrname-format:basic"><saml:AttributeValue>kamino</saml:AttributeValue></saml:Attribute>"#));
+        // Only what the service provider is configured with.
+        assert!(!assertion.contains("E-1024"));
+        assert!(!assertion.contains(r#"Name="attributes""#));
+    }
+
+    #[actix_web::test]
+    async fn test_post_binding() {
+        let app = app().await;
+        let req = test::TestRequest::post()
+            .uri("/realms/acme/saml/sso")
+            .set_form([(
+                "SAMLRequest",
+                STANDARD.encode(authn_request("_sp-request-2", SP_ENTITY_ID)),
+            )])
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let login_form = String::from_utf8(test::read_body(res).await.to_vec()).unwrap();
+        assert!(!login_form.contains("RelayState"));
+    }
+
+    #[actix_web::test]
+    async fn test_session_skips_login() {
+        let app = app().await;
+        let req = test::TestRequest::get()
+            .uri(&format!(
+                "/realms/acme/saml/sso?{}",
+                redirect_query(&authn_request("_sp-request-3", SP_ENTITY_ID))
+            ))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        let login_form = String::from_utf8(test::read_body(res).await.to_vec()).unwrap();
+        let res = login(&app, &login_form, PASSWD).await;
+        let cookie = res
+            .response()
+            .cookies()
+            .find(|c| c.name() == "idp_session_acme")
+            .unwrap()
+            .into_owned();
+
+        let req = test::TestRequest::get()
+            .uri(&format!(
+                "/realms/acme/saml/sso?{}",
+                redirect_query(&authn_request("_sp-request-4", SP_ENTITY_ID))
+            ))
+            .cookie(cookie)
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let page = String::from_utf8(test::read_body(res).await.to_vec()).unwrap();
+        let response =
+            String::from_utf8(STANDARD.decode(form_value(&page, "SAMLResponse")).unwrap()).unwrap();
+        assert!(response.contains(r#"InResponseTo="_sp-request-4""#));
+    }
+
+    #[actix_web::test]
+    async fn test_wrong_password() {
+        let app = app().await;
+        let req = test::TestRequest::get()
+            .uri(&format!(
+                "/realms/acme/saml/sso?{}",
+                redirect_query(&authn_request("_sp-request-3", SP_ENTITY_ID))
+            ))
+            .to_request();
+        let login_form =
+            String::from_utf8(test::call_and_read_body(&app, req).await.to_vec()).unwrap();
+        let res = login(&app, &login_form, "Wrong7Horse9Battery").await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[actix_web::test]
+    async fn test_unknown_service_provider() {
+        let app = app().await;
+        let req = test::TestRequest::get()
+            .uri(&format!(
+                "/realms/acme/saml/sso?{}",
+                redirect_query(&authn_request("_sp-request-4", "https://evil.example.com"))
+            ))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[actix_web::test]
+    async fn test_acs_url_must_match_registration() {
+        let app = app().await;
+        let request = authn_request("_sp-request-5", SP_ENTITY_ID)
+            .replace(SP_ACS_URL, "https://evil.example.com/acs");
+        let req = test::TestRequest::post()
+            .uri("/realms/acme/saml/sso")
+            .set_form([("SAMLRequest", STANDARD.encode(request))])
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[actix_web::test]
+    async fn test_malformed_form() {
+        let app = app().await;
+        let req = test::TestRequest::post()
+            .uri("/realms/acme/saml/sso")
+            .set_form([("RelayState", "/dashboard")])
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(
+            res.headers().get(header::CONTENT_TYPE).unwrap(),
+            "application/problem+json"
+        );
+    }
+}
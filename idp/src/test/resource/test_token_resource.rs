@@ -0,0 +1,422 @@
+#[cfg(test)]
+mod tests {
+    use actix_web::{
+        dev::{Service, ServiceResponse},
+        http::{header, StatusCode},
+        test, web, App,
+    };
+    use base64::{engine::general_purpose::STANDARD, Engine};
+    use serde_json::{json, Value};
+
+    use crate::{
+        app_state::AppState,
+        domain::mail_address::MailAddress,
+        entity::{
+            client::{Client, TokenExchangePolicy},
+            user::User,
+        },
+        oauth::token_exchange::{ACCESS_TOKEN_TYPE, TOKEN_EXCHANGE_GRANT_TYPE},
+        realm::realm_registry::{Realm, RealmRegistry},
+        resource::token_resource::token_handler,
+        test::token::dpop_client::DpopClient,
+        token::{
+            jwt::{decode_subject_token, encode_claims, Claims},
+            signing_key::SigningKeys,
+        },
+    };
+
+    const SECRET: &str = "test-secret";
+    const ISSUER: &str = "http://localhost:8080/realms/acme";
+    const EMAIL: &str = "kamino@example.com";
+
+    fn exchange_policy(audiences: &[&str]) -> TokenExchangePolicy {
+        TokenExchangePolicy {
+            audiences: audiences.iter().map(|s| s.to_string()).collect(),
+            scopes: vec!["orders:read".to_owned(), "orders:write".to_owned()],
+        }
+    }
+
+    async fn app(
+        state: web::Data<AppState>,
+    ) -> impl Service<actix_http::Request, Response = ServiceResponse, Error = actix_web::Error>
+    {
+        test::init_service(
+            App::new().app_data(state).service(
+                web::scope("/realms/{realm}").route("/token", web::post().to(token_handler)),
+            ),
+        )
+        .await
+    }
+
+    fn state() -> web::Data<AppState> {
+        let mappers = serde_json::from_value(json!([
+            {"type": "template", "claim": "orders_user", "template": "acme:${preferred_username}"},
+        ]))
+        .unwrap();
+        let realm = Realm::of("acme", ISSUER, SigningKeys::of(SECRET))
+            .with_audience_claim_mappers("orders", mappers);
+        let mut user = User::of(MailAddress::of(EMAIL).unwrap());
+        user.attributes
+            .insert("preferred_username".to_owned(), "kamino".to_owned());
+        realm.users.create(user).unwrap();
+        realm.clients.save(Client::of("todo-cli")).unwrap();
+        realm
+            .clients
+            .save(
+                Client::of("api")
+                    .with_secret("api-secret")
+                    .with_token_exchange(exchange_policy(&["orders"])),
+            )
+            .unwrap();
+        realm
+            .clients
+            .save(
+                Client::of("orders")
+                    .with_secret("orders secret")
+                    .with_token_exchange(exchange_policy(&["stock"])),
+            )
+            .unwrap();
+        web::Data::new(AppState::of(RealmRegistry::of(vec![realm]).unwrap()))
+    }
+
+    fn user_token() -> String {
+        let email = MailAddress::of(EMAIL).unwrap();
+        let claims =
+            Claims::of(ISSUER, &email, Some("web")).with_scopes(&["orders:read".to_owned()]);
+        encode_claims(&SigningKeys::of(SECRET), &claims).unwrap()
+    }
+
+    async fn post_exchange<S>(
+        app: &S,
+        basic: Option<(&str, &str)>,
+        form: &[(&str, &str)],
+    ) -> ServiceResponse
+    where
+        S: Service<actix_http::Request, Response = ServiceResponse, Error = actix_web::Error>,
+    {
+        let mut form = form.to_vec();
+        form.push(("grant_type", TOKEN_EXCHANGE_GRANT_TYPE));
+        let mut req = test::TestRequest::post().uri("/realms/acme/token");
+        if let Some((client_id, client_secret)) = basic {
+            let credentials = STANDARD.encode(format!("{}:{}", client_id, client_secret));
+            req = req.insert_header((header::AUTHORIZATION, format!("Basic {}", credentials)));
+        }
+        test::call_service(app, req.set_form(&form).to_request()).await
+    }
+
+    async fn oauth_error(res: ServiceResponse) -> String {
+        let body: Value = test::read_body_json(res).await;
+        body["error"].as_str().unwrap().to_owned()
+    }
+
+    fn claims_of(token: &Value, actor: &str) -> Value {
+        let claims = decode_subject_token(
+            &SigningKeys::of(SECRET),
+            ISSUER,
+            token["access_token"].as_str().unwrap(),
+            actor,
+        )
+        .unwrap();
+        serde_json::to_value(claims).unwrap()
+    }
+
+    #[actix_web::test]
+    async fn test_token_exchange() {
+        let app = app(state()).await;
+        let subject_token = user_token();
+        let res = post_exchange(
+            &app,
+            Some(("api", "api-secret")),
+            &[
+                ("subject_token", &subject_token),
+                ("subject_token_type", ACCESS_TOKEN_TYPE),
+                ("audience", "orders"),
+                ("scope", "orders:read"),
+            ],
+        )
+        .await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(
+            res.headers().get(header::CACHE_CONTROL).unwrap(),
+            "no-store"
+        );
+        let token: Value = test::read_body_json(res).await;
+        assert_eq!(token["issued_token_type"], ACCESS_TOKEN_TYPE);
+        assert_eq!(token["token_type"], "Bearer");
+        assert_eq!(token["scope"], "orders:read");
+        assert!(token["expires_in"].as_i64().unwrap() > 0);
+        let claims = claims_of(&token, "orders");
+        assert_eq!(claims["aud"], "orders");
+        assert_eq!(claims["sub"], EMAIL);
+        assert_eq!(claims["act"], json!({"sub": "api"}));
+        assert_eq!(claims["orders_user"], "acme:kamino");
+
+        // The orders service exchanges it again, with the secret in the form.
+        let res = post_exchange(
+            &app,
+            None,
+            &[
+                ("client_id", "orders"),
+                ("client_secret", "orders secret"),
+                ("subject_token", token["access_token"].as_str().unwrap()),
+                ("subject_token_type", ACCESS_TOKEN_TYPE),
+                ("audience", "stock"),
+            ],
+        )
+        .await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let token: Value = test::read_body_json(res).await;
+        assert_eq!(token["scope"], "orders:read");
+        let claims = claims_of(&token, "stock");
+        assert_eq!(claims["aud"], "stock");
+        assert_eq!(claims["sub"], EMAIL);
+        assert_eq!(
+            claims["act"],
+            json!({"sub": "orders", "act": {"sub": "api"}})
+        );
+        // Mapped for the orders audience only.
+        assert!(claims.get("orders_user").is_none());
+    }
+
+    #[actix_web::test]
+    async fn test_dpop_bound_exchange() {
+        let app = app(state()).await;
+        let client = DpopClient::new();
+        let subject_token = user_token();
+        let credentials = STANDARD.encode("api:api-secret");
+        let form = [
+            ("grant_type", TOKEN_EXCHANGE_GRANT_TYPE),
+            ("subject_token", &subject_token),
+            ("subject_token_type", ACCESS_TOKEN_TYPE),
+            ("audience", "orders"),
+        ];
+        let proof = client.proof("POST", "http://localhost:8080/realms/acme/token", None);
+        let req = test::TestRequest::post()
+            .uri("/realms/acme/token")
+            .insert_header((header::AUTHORIZATION, format!("Basic {}", credentials)))
+            .insert_header(("DPoP", proof.clone()))
+            .set_form(form)
+            .to_request();
+        let token: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(token["token_type"], "DPoP");
+        assert_eq!(claims_of(&token, "orders")["cnf"]["jkt"], client.jkt());
+
+        let req = test::TestRequest::post()
+            .uri("/realms/acme/token")
+            .insert_header((header::AUTHORIZATION, format!("Basic {}", credentials)))
+            .insert_header(("DPoP", proof))
+            .set_form(form)
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(oauth_error(res).await, "invalid_dpop_proof");
+
+        // The htu is the issuer's, whatever host the request claims to be for.
+        let proof = client.proof("POST", "https://evil.example/realms/acme/token", None);
+        let req = test::TestRequest::post()
+            .uri("/realms/acme/token")
+            .insert_header((header::AUTHORIZATION, format!("Basic {}", credentials)))
+            .insert_header(("X-Forwarded-Host", "evil.example"))
+            .insert_header(("X-Forwarded-Proto", "https"))
+            .insert_header(("DPoP", proof))
+            .set_form(form)
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(oauth_error(res).await, "invalid_dpop_proof");
+    }
+
+    #[actix_web::test]
+    async fn test_exchange_of_bound_token() {
+        let app = app(state()).await;
+        let holder = DpopClient::new();
+        let email = MailAddress::of(EMAIL).unwrap();
+        let claims = Claims::of(ISSUER, &email, Some("web"))
+            .with_scopes(&["orders:read".to_owned()])
+            .with_confirmation(Some(&holder.jkt()));
+        let subject_token = encode_claims(&SigningKeys::of(SECRET), &claims).unwrap();
+        let credentials = STANDARD.encode("api:api-secret");
+        let form = [
+            ("grant_type", TOKEN_EXCHANGE_GRANT_TYPE),
+            ("subject_token", &subject_token),
+            ("subject_token_type", ACCESS_TOKEN_TYPE),
+            ("audience", "orders"),
+        ];
+        let exchange = |proof: Option<String>| {
+            let mut req = test::TestRequest::post()
+                .uri("/realms/acme/token")
+                .insert_header((header::AUTHORIZATION, format!("Basic {}", credentials)))
+                .set_form(form);
+            if let Some(proof) = proof {
+                req = req.insert_header(("DPoP", proof));
+            }
+            req.to_request()
+        };
+        let token_uri = "http://localhost:8080/realms/acme/token";
+
+        // Without a proof, or with one of another key.
+        let res = test::call_service(&app, exchange(None)).await;
+        assert_eq!(oauth_error(res).await, "invalid_dpop_proof");
+        let other = DpopClient::new().proof("POST", token_uri, None);
+        let res = test::call_service(&app, exchange(Some(other))).await;
+        assert_eq!(oauth_error(res).await, "invalid_dpop_proof");
+
+        let proof = holder.proof("POST", token_uri, None);
+        let res = test::call_service(&app, exchange(Some(proof))).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let token: Value = test::read_body_json(res).await;
+        assert_eq!(token["token_type"], "DPoP");
+        assert_eq!(claims_of(&token, "orders")["cnf"]["jkt"], holder.jkt());
+    }
+
+    #[actix_web::test]
+    async fn test_client_authentication() {
+        let app = app(state()).await;
+        let subject_token = user_token();
+        let form = [
+            ("subject_token", subject_token.as_str()),
+            ("subject_token_type", ACCESS_TOKEN_TYPE),
+            ("audience", "orders"),
+        ];
+
+        let res = post_exchange(&app, Some(("api", "wrong-secret")), &form).await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(oauth_error(res).await, "invalid_client");
+
+        let mut without_secret = form.to_vec();
+        without_secret.push(("client_id", "api"));
+        let res = post_exchange(&app, None, &without_secret).await;
+        assert_eq!(oauth_error(res).await, "invalid_client");
+
+        // Basic credentials are form-urlencoded before they are joined.
+        let res = post_exchange(
+            &app,
+            Some(("orders", "orders+secret")),
+            &[
+                ("subject_token", &subject_token),
+                ("subject_token_type", ACCESS_TOKEN_TYPE),
+                ("audience", "stock"),
+            ],
+        )
+        .await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        // A public client has no exchange policy.
+        let mut public = form.to_vec();
+        public.push(("client_id", "todo-cli"));
+        let res = post_exchange(&app, None, &public).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(oauth_error(res).await, "unauthorized_client");
+    }
+
+    #[actix_web::test]
+    async fn test_rejected_exchange() {
+        let state = state();
+        let app = app(state.clone()).await;
+        let subject_token = user_token();
+        let basic = Some(("api", "api-secret"));
+
+        let res = post_exchange(
+            &app,
+            basic,
+            &[
+                ("subject_token", &subject_token),
+                ("subject_token_type", ACCESS_TOKEN_TYPE),
+                ("audience", "billing"),
+            ],
+        )
+        .await;
+        assert_eq!(oauth_error(res).await, "invalid_target");
+
+        let res = post_exchange(
+            &app,
+            basic,
+            &[
+                ("subject_token", &subject_token),
+                ("subject_token_type", ACCESS_TOKEN_TYPE),
+                ("audience", "orders"),
+                ("scope", "orders:delete"),
+            ],
+        )
+        .await;
+        assert_eq!(oauth_error(res).await, "invalid_scope");
+
+        let res = post_exchange(
+            &app,
+            basic,
+            &[
+                ("subject_token", &subject_token),
+                (
+                    "subject_token_type",
+                    "urn:ietf:params:oauth:token-type:saml2",
+                ),
+                ("audience", "orders"),
+            ],
+        )
+        .await;
+        assert_eq!(oauth_error(res).await, "invalid_request");
+
+        let res = post_exchange(
+            &app,
+            basic,
+            &[
+                ("subject_token", &subject_token),
+                ("subject_token_type", ACCESS_TOKEN_TYPE),
+                ("audience", "orders"),
+                ("actor_token", &subject_token),
+            ],
+        )
+        .await;
+        assert_eq!(oauth_error(res).await, "invalid_request");
+
+        let res = post_exchange(
+            &app,
+            basic,
+            &[
+                ("subject_token", "not-a-token"),
+                ("subject_token_type", ACCESS_TOKEN_TYPE),
+                ("audience", "orders"),
+            ],
+        )
+        .await;
+        assert_eq!(oauth_error(res).await, "invalid_request");
+
+        // A token for another service may not be exchanged by this one.
+        let res = post_exchange(
+            &app,
+            Some(("orders", "orders secret")),
+            &[
+                ("subject_token", &subject_token),
+                ("subject_token_type", ACCESS_TOKEN_TYPE),
+                ("audience", "stock"),
+            ],
+        )
+        .await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let token: Value = test::read_body_json(res).await;
+        let res = post_exchange(
+            &app,
+            basic,
+            &[
+                ("subject_token", token["access_token"].as_str().unwrap()),
+                ("subject_token_type", ACCESS_TOKEN_TYPE),
+                ("audience", "orders"),
+            ],
+        )
+        .await;
+        assert_eq!(oauth_error(res).await, "invalid_request");
+
+        state.denylist.revoke(&subject_token).unwrap();
+        let res = post_exchange(
+            &app,
+            basic,
+            &[
+                ("subject_token", &subject_token),
+                ("subject_token_type", ACCESS_TOKEN_TYPE),
+                ("audience", "orders"),
+            ],
+        )
+        .await;
+        assert_eq!(oauth_error(res).await, "invalid_request");
+    }
+}
@@ -0,0 +1,229 @@
+#[cfg(test)]
+mod tests {
+    use actix_web::{
+        cookie::Cookie,
+        dev::{Service, ServiceResponse},
+        http::StatusCode,
+        test, web, App,
+    };
+    use serde_json::{json, Value};
+
+    use crate::{
+        app_state::AppState,
+        credential::passwd_hasher::hash_password,
+        domain::{mail_address::MailAddress, password::Password, password_policy::PasswordPolicy},
+        entity::user::User,
+        realm::realm_registry::{Realm, RealmRegistry},
+        resource::{
+            login_resource::password_login_handler,
+            webauthn_resource::{
+                passkey_login_handler, passkey_login_options_handler, passkey_registration_handler,
+                passkey_registration_options_handler,
+            },
+        },
+        test::webauthn::software_authenticator::{Attestation, SoftwareAuthenticator},
+        token::signing_key::SigningKeys,
+    };
+
+    const ISSUER: &str = "http://localhost:8080/realms/acme";
+    const ORIGIN: &str = "http://localhost:8080";
+    const PASSWD: &str = "Correct7Horse9Battery";
+
+    fn user(email: &str) -> User {
+        let email = MailAddress::of(email).unwrap();
+        let passwd = Password::of(PASSWD, &PasswordPolicy::default(), &email).unwrap();
+        let mut user = User::of(email);
+        user.passwd_hash = Some(hash_password(&passwd).unwrap());
+        user
+    }
+
+    async fn app(
+    ) -> impl Service<actix_http::Request, Response = ServiceResponse, Error = actix_web::Error>
+    {
+        let realm = Realm::of("acme", ISSUER, SigningKeys::of("test-secret"));
+        realm.users.create(user("kamino@example.com")).unwrap();
+        realm.users.create(user("shunichirou@example.com")).unwrap();
+        let state = AppState::of(RealmRegistry::of(vec![realm]).unwrap());
+        test::init_service(
+            App::new().app_data(web::Data::new(state)).service(
+                web::scope("/realms/{realm}")
+                    .route("/login", web::post().to(password_login_handler))
+                    .route(
+                        "/webauthn/register/options",
+                        web::post().to(passkey_registration_options_handler),
+                    )
+                    .route(
+                        "/webauthn/register",
+                        web::post().to(passkey_registration_handler),
+                    )
+                    .route(
+                        "/webauthn/login/options",
+                        web::post().to(passkey_login_options_handler),
+                    )
+                    .route("/webauthn/login", web::post().to(passkey_login_handler)),
+            ),
+        )
+        .await
+    }
+
+    async fn post<S>(
+        app: &S,
+        path: &str,
+        body: Value,
+        cookie: Option<&Cookie<'static>>,
+    ) -> ServiceResponse
+    where
+        S: Service<actix_http::Request, Response = ServiceResponse, Error = actix_web::Error>,
+    {
+        let mut req = test::TestRequest::post()
+            .uri(&format!("/realms/acme{}", path))
+            .set_json(body);
+        if let Some(cookie) = cookie {
+            req = req.cookie(cookie.clone());
+        }
+        test::call_service(app, req.to_request()).await
+    }
+
+    fn session_cookie(res: &ServiceResponse) -> Cookie<'static> {
+        res.response()
+            .cookies()
+            .find(|c| c.name() == "idp_session_acme")
+            .unwrap()
+            .into_owned()
+    }
+
+    async fn password_login<S>(app: &S, email: &str) -> ServiceResponse
+    where
+        S: Service<actix_http::Request, Response = ServiceResponse, Error = actix_web::Error>,
+    {
+        post(
+            app,
+            "/login",
+            json!({"email": email, "passwd": PASSWD}),
+            None,
+        )
+        .await
+    }
+
+    /// Signs in with the password and registers a passkey on the authenticator.
+    async fn register_passkey<S>(app: &S, email: &str) -> SoftwareAuthenticator
+    where
+        S: Service<actix_http::Request, Response = ServiceResponse, Error = actix_web::Error>,
+    {
+        let res = password_login(app, email).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let cookie = session_cookie(&res);
+
+        let res = post(app, "/webauthn/register/options", json!({}), Some(&cookie)).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let options: Value = test::read_body_json(res).await;
+        assert_eq!(options["rp"]["id"], "localhost");
+        assert_eq!(options["user"]["name"], email);
+
+        let mut authenticator = SoftwareAuthenticator::new(ORIGIN);
+        let credential = authenticator.create(&options, Attestation::PackedSelf);
+        let res = post(app, "/webauthn/register", credential, Some(&cookie)).await;
+        assert_eq!(res.status(), StatusCode::CREATED);
+        let passkey: Value = test::read_body_json(res).await;
+        assert_eq!(passkey["id"], authenticator.id());
+        authenticator
+    }
+
+    #[actix_web::test]
+    async fn test_passkey_as_second_factor() {
+        let app = app().await;
+        let mut authenticator = register_passkey(&app, "kamino@example.com").await;
+
+        let res = password_login(&app, "kamino@example.com").await;
+        assert_eq!(res.status(), StatusCode::ACCEPTED);
+        assert!(res.response().cookies().next().is_none());
+        let options: Value = test::read_body_json(res).await;
+        assert_eq!(options["allowCredentials"][0]["id"], authenticator.id());
+
+        let credential = authenticator.get(&options, None);
+        let res = post(&app, "/webauthn/login", credential.clone(), None).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        session_cookie(&res);
+        let body: Value = test::read_body_json(res).await;
+        assert_eq!(body["user"]["email"], "kamino@example.com");
+
+        // The challenge is answered once.
+        let res = post(&app, "/webauthn/login", credential, None).await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[actix_web::test]
+    async fn test_passkey_as_first_factor() {
+        let app = app().await;
+        let mut authenticator = register_passkey(&app, "kamino@example.com").await;
+
+        let res = post(&app, "/webauthn/login/options", json!({}), None).await;
+        let options: Value = test::read_body_json(res).await;
+        assert_eq!(options["userVerification"], "required");
+        assert_eq!(options["allowCredentials"], json!([]));
+
+        let credential = authenticator.get(&options, None);
+        let res = post(&app, "/webauthn/login", credential, None).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let body: Value = test::read_body_json(res).await;
+        assert_eq!(body["user"]["email"], "kamino@example.com");
+
+        // Without user verification the passkey is not enough on its own.
+        authenticator.user_verification = false;
+        let res = post(&app, "/webauthn/login/options", json!({}), None).await;
+        let options: Value = test::read_body_json(res).await;
+        let credential = authenticator.get(&options, None);
+        let res = post(&app, "/webauthn/login", credential, None).await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[actix_web::test]
+    async fn test_passkey_of_other_user() {
+        let app = app().await;
+        let mut authenticator = register_passkey(&app, "shunichirou@example.com").await;
+        register_passkey(&app, "kamino@example.com").await;
+
+        let res = password_login(&app, "kamino@example.com").await;
+        let options: Value = test::read_body_json(res).await;
+        let credential = authenticator.get(&options, None);
+        let res = post(&app, "/webauthn/login", credential, None).await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[actix_web::test]
+    async fn test_user_handle_must_match() {
+        let app = app().await;
+        let mut authenticator = register_passkey(&app, "kamino@example.com").await;
+        let res = post(&app, "/webauthn/login/options", json!({}), None).await;
+        let options: Value = test::read_body_json(res).await;
+        let credential = authenticator.get(&options, Some("c29tZW9uZS1lbHNl"));
+        let res = post(&app, "/webauthn/login", credential, None).await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[actix_web::test]
+    async fn test_registration_requires_session() {
+        let app = app().await;
+        let res = post(&app, "/webauthn/register/options", json!({}), None).await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[actix_web::test]
+    async fn test_passkey_registered_once() {
+        let app = app().await;
+        let mut authenticator = register_passkey(&app, "kamino@example.com").await;
+
+        let res = post(&app, "/webauthn/login/options", json!({}), None).await;
+        let options: Value = test::read_body_json(res).await;
+        let credential = authenticator.get(&options, None);
+        let res = post(&app, "/webauthn/login", credential, None).await;
+        let cookie = session_cookie(&res);
+
+        let res = post(&app, "/webauthn/register/options", json!({}), Some(&cookie)).await;
+        let options: Value = test::read_body_json(res).await;
+        assert_eq!(options["excludeCredentials"][0]["id"], authenticator.id());
+        let credential = authenticator.create(&options, Attestation::None);
+        let res = post(&app, "/webauthn/register", credential, Some(&cookie)).await;
+        assert_eq!(res.status(), StatusCode::CONFLICT);
+    }
+}
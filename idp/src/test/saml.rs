@@ -0,0 +1,2 @@
+pub mod test_authn_request;
+pub mod test_saml_credential;
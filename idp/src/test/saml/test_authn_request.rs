@@ -0,0 +1,90 @@
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use base64::{engine::general_purpose::STANDARD, Engine};
+    use flate2::{write::DeflateEncoder, Compression};
+
+    use crate::{error::my_error::MyError, saml::authn_request::AuthnRequest};
+
+    const REQUEST: &str = r#"<samlp:AuthnRequest xmlns:samlp="urn:oasis:names:tc:SAML:2.0:protocol" xmlns:saml="urn:oasis:names:tc:SAML:2.0:assertion" ID="_req1" Version="2.0" IssueInstant="2022-05-01T00:00:00Z" AssertionConsumerServiceURL="https://sp.example.com/acs" ProtocolBinding="urn:oasis:names:tc:SAML:2.0:bindings:HTTP-POST">
+  <saml:Issuer>https://sp.example.com</saml:Issuer>
+  <samlp:NameIDPolicy Format="urn:oasis:names:tc:SAML:1.1:nameid-format:emailAddress" AllowCreate="true"/>
+</samlp:AuthnRequest>"#;
+
+    fn deflate(xml: &str) -> String {
+        let mut encoder = DeflateEncoder::new(vec![], Compression::default());
+        encoder.write_all(xml.as_bytes()).unwrap();
+        STANDARD.encode(encoder.finish().unwrap())
+    }
+
+    #[test]
+    fn test_parse() {
+        let request = AuthnRequest::parse(REQUEST.to_owned()).unwrap();
+        assert_eq!(request.id, "_req1");
+        assert_eq!(request.issuer, "https://sp.example.com");
+        assert_eq!(
+            request.acs_url.as_deref(),
+            Some("https://sp.example.com/acs")
+        );
+    }
+
+    #[test]
+    fn test_bindings_decode_the_same_request() {
+        let redirect = AuthnRequest::from_redirect(&deflate(REQUEST)).unwrap();
+        let post = AuthnRequest::from_post(&STANDARD.encode(REQUEST)).unwrap();
+        assert_eq!(redirect, post);
+        assert_eq!(AuthnRequest::from_post(&redirect.to_post()).unwrap(), post);
+    }
+
+    #[test]
+    fn test_wrapped_base64() {
+        let encoded = STANDARD.encode(REQUEST);
+        let (head, tail) = encoded.split_at(40);
+        let wrapped = format!("{}\r\n{}", head, tail);
+        assert!(AuthnRequest::from_post(&wrapped).is_ok());
+    }
+
+    #[test]
+    fn test_not_an_authn_request() {
+        let logout =
+            r#"<samlp:LogoutRequest xmlns:samlp="urn:oasis:names:tc:SAML:2.0:protocol" ID="_x"/>"#;
+        assert!(AuthnRequest::parse(logout.to_owned()).is_err());
+        assert!(AuthnRequest::from_post("not base64!").is_err());
+        assert!(AuthnRequest::from_redirect(&STANDARD.encode(REQUEST)).is_err());
+    }
+
+    #[test]
+    fn test_missing_issuer_or_id() {
+        let no_issuer =
+            r#"<samlp:AuthnRequest xmlns:samlp="urn:oasis:names:tc:SAML:2.0:protocol" ID="_x"/>"#;
+        assert!(AuthnRequest::parse(no_issuer.to_owned()).is_err());
+        let no_id = REQUEST.replace(r#"ID="_req1" "#, "");
+        assert!(AuthnRequest::parse(no_id).is_err());
+    }
+
+    #[test]
+    fn test_unsupported_protocol_binding() {
+        let artifact = REQUEST.replace("HTTP-POST", "HTTP-Artifact");
+        assert!(AuthnRequest::parse(artifact).is_err());
+    }
+
+    #[test]
+    fn test_doctype_rejected() {
+        let xml = format!(
+            r#"<?xml version="1.0"?><!DOCTYPE x [<!ENTITY e "https://evil.example.com">]>{}"#,
+            REQUEST
+        );
+        assert!(AuthnRequest::parse(xml).is_err());
+    }
+
+    #[test]
+    fn test_inflate_limit() {
+        let padding = " ".repeat(70 * 1024);
+        let large = REQUEST.replace("<saml:Issuer>", &format!("{}<saml:Issuer>", padding));
+        assert!(matches!(
+            AuthnRequest::from_redirect(&deflate(&large)),
+            Err(MyError::PayloadTooLarge)
+        ));
+    }
+}
@@ -0,0 +1,42 @@
+#[cfg(test)]
+mod tests {
+    use crate::saml::saml_credential::SamlCredential;
+
+    #[test]
+    fn test_rsa_key() {
+        let credential = SamlCredential::from_pem(
+            include_bytes!("../fixtures/saml_cert.pem"),
+            include_bytes!("../fixtures/saml_key.pem"),
+        )
+        .unwrap();
+        assert_eq!(
+            credential.signature_algorithm(),
+            "http://www.w3.org/2001/04/xmldsig-more#rsa-sha256"
+        );
+        assert_eq!(credential.sign(b"signed info").unwrap().len(), 256);
+    }
+
+    #[test]
+    fn test_ecdsa_key() {
+        let key_pair = rcgen::KeyPair::generate().unwrap();
+        let cert = rcgen::CertificateParams::new(vec!["idp.local".to_owned()])
+            .unwrap()
+            .self_signed(&key_pair)
+            .unwrap();
+        let credential =
+            SamlCredential::from_pem(cert.pem().as_bytes(), key_pair.serialize_pem().as_bytes())
+                .unwrap();
+        assert_eq!(
+            credential.signature_algorithm(),
+            "http://www.w3.org/2001/04/xmldsig-more#ecdsa-sha256"
+        );
+        // r || s of P-256, not DER.
+        assert_eq!(credential.sign(b"signed info").unwrap().len(), 64);
+    }
+
+    #[test]
+    fn test_missing_certificate() {
+        let key = include_bytes!("../fixtures/saml_key.pem");
+        assert!(SamlCredential::from_pem(b"", key).is_err());
+    }
+}
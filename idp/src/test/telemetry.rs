@@ -0,0 +1,2 @@
+pub mod test_request_id;
+pub mod test_subscriber;
@@ -0,0 +1,81 @@
+#[cfg(test)]
+mod tests {
+    use actix_web::{
+        dev::{Service, ServiceResponse},
+        http::StatusCode,
+        test, web,
+    };
+
+    use crate::{
+        app::app,
+        app_state::AppState,
+        realm::realm_registry::{Realm, RealmRegistry, DEFAULT_REALM},
+        telemetry::request_id::{is_valid_request_id, MAX_REQUEST_ID_LEN, REQUEST_ID_HEADER},
+        token::signing_key::SigningKeys,
+    };
+
+    async fn service(
+    ) -> impl Service<actix_http::Request, Response = ServiceResponse, Error = actix_web::Error>
+    {
+        let realm = Realm::of(
+            DEFAULT_REALM,
+            "http://localhost:8080",
+            SigningKeys::of("test-secret"),
+        );
+        let state = AppState::of(RealmRegistry::of(vec![realm]).unwrap());
+        test::init_service(app(web::Data::new(state), false)).await
+    }
+
+    async fn request_id_of<S>(app: &S, uri: &str, sent: Option<&str>) -> String
+    where
+        S: Service<actix_http::Request, Response = ServiceResponse, Error = actix_web::Error>,
+    {
+        let mut req = test::TestRequest::get().uri(uri);
+        if let Some(sent) = sent {
+            req = req.insert_header((REQUEST_ID_HEADER, sent));
+        }
+        let res = test::call_service(app, req.to_request()).await;
+        res.headers()
+            .get(REQUEST_ID_HEADER)
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .to_owned()
+    }
+
+    #[actix_web::test]
+    async fn test_echoes_request_id() {
+        let app = service().await;
+        let id = "0f8fad5b-d9cb-469f-a165-70867728950e";
+        assert_eq!(request_id_of(&app, "/healthz", Some(id)).await, id);
+
+        // Problems carry it too.
+        let req = test::TestRequest::get()
+            .uri("/no-such-page")
+            .insert_header((REQUEST_ID_HEADER, "trace:42"))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+        assert_eq!(res.headers().get(REQUEST_ID_HEADER).unwrap(), "trace:42");
+    }
+
+    #[actix_web::test]
+    async fn test_generates_request_id() {
+        let app = service().await;
+        let first = request_id_of(&app, "/healthz", None).await;
+        let second = request_id_of(&app, "/healthz", None).await;
+        assert_eq!(first.len(), 32);
+        assert!(first.bytes().all(|b| b.is_ascii_hexdigit()));
+        assert_ne!(first, second);
+
+        let too_long = "a".repeat(MAX_REQUEST_ID_LEN + 1);
+        for sent in ["", "two words", "<script>", too_long.as_str()] {
+            let id = request_id_of(&app, "/healthz", Some(sent)).await;
+            assert_ne!(id, sent);
+            assert!(is_valid_request_id(&id));
+        }
+        assert!(is_valid_request_id(&"a".repeat(MAX_REQUEST_ID_LEN)));
+        assert!(!is_valid_request_id("ünïcode"));
+        assert!(!is_valid_request_id("a=b"));
+    }
+}
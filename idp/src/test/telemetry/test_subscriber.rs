@@ -0,0 +1,93 @@
+#[cfg(test)]
+mod tests {
+    use std::{
+        io,
+        sync::{Arc, Mutex},
+    };
+
+    use actix_web::{http::StatusCode, test, web};
+    use serde_json::Value;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    use crate::{
+        app::app,
+        app_state::AppState,
+        realm::realm_registry::{Realm, RealmRegistry, DEFAULT_REALM},
+        telemetry::{request_id::REQUEST_ID_HEADER, subscriber::json_layer},
+        token::signing_key::SigningKeys,
+    };
+
+    /// Collects what the layer writes.
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+    impl io::Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Buffer {
+        fn lines(&self) -> Vec<Value> {
+            String::from_utf8(self.0.lock().unwrap().clone())
+                .unwrap()
+                .lines()
+                .map(|line| serde_json::from_str(line).unwrap())
+                .collect()
+        }
+    }
+
+    fn span_names(line: &Value) -> Vec<&str> {
+        line["spans"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|span| span["name"].as_str().unwrap())
+            .collect()
+    }
+
+    #[actix_web::test]
+    async fn test_json_lines_carry_request_span() {
+        let buffer = Buffer::default();
+        let writer = buffer.clone();
+        let subscriber = tracing_subscriber::registry().with(json_layer(move || writer.clone()));
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let realm = Realm::of(
+            DEFAULT_REALM,
+            "http://localhost:8080",
+            SigningKeys::of("test-secret"),
+        );
+        let state = AppState::of(RealmRegistry::of(vec![realm]).unwrap());
+        let app = test::init_service(app(web::Data::new(state), false)).await;
+        let req = test::TestRequest::post()
+            .uri("/rest")
+            .insert_header((REQUEST_ID_HEADER, "req-1"))
+            .set_json(serde_json::json!({"name": "idp", "number": 7}))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+
+        let lines = buffer.lines();
+        let echo = lines
+            .iter()
+            .find(|line| line["message"] == "echoing the request body")
+            .unwrap();
+        assert_eq!(echo["level"], "INFO");
+        assert_eq!(span_names(echo), ["request", "hello_handler"]);
+        assert_eq!(echo["spans"][0]["request_id"], "req-1");
+        assert_eq!(echo["spans"][0]["method"], "POST");
+
+        let answered = lines
+            .iter()
+            .find(|line| line["message"] == "request answered")
+            .unwrap();
+        assert_eq!(answered["spans"][0]["route"], "/rest");
+        assert_eq!(answered["spans"][0]["status"], 200);
+        assert!(answered["elapsed_ms"].is_u64());
+    }
+}
@@ -0,0 +1,280 @@
+#[cfg(test)]
+mod tests {
+    use std::{
+        env, fs,
+        path::{Path, PathBuf},
+        process,
+    };
+
+    use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+    use clap::Parser;
+    use serde_json::{json, Value};
+
+    use crate::{
+        admin::{render_table, run, AdminCli},
+        realm::realm_state::StateFile,
+    };
+
+    const PASSWD: &[u8] = b"Correct-Horse-9-Battery!\n";
+
+    fn state_file(name: &str) -> PathBuf {
+        let path = env::temp_dir().join(format!("idp-admin-{}-{}.json", name, process::id()));
+        let _ = fs::remove_file(&path);
+        path
+    }
+
+    /// The output, and whether the server has to restart to see the change.
+    fn run_admin(state: &Path, args: &[&str], input: &[u8]) -> Result<(Value, bool), String> {
+        let cli = AdminCli::try_parse_from(
+            ["idp-admin", "--state", state.to_str().unwrap()]
+                .iter()
+                .chain(args),
+        )
+        .unwrap();
+        run(&cli, input).map_err(|err| err.to_string())
+    }
+
+    fn admin(state: &Path, args: &[&str], input: &[u8]) -> Result<Value, String> {
+        run_admin(state, args, input).map(|(value, _)| value)
+    }
+
+    #[test]
+    fn test_users() {
+        let state = state_file("users");
+        let user = admin(&state, &["users", "create", "kamino@example.com"], PASSWD).unwrap();
+        assert_eq!(user["email"], "kamino@example.com");
+        assert!(admin(&state, &["users", "create", "kamino@example.com"], PASSWD).is_err());
+        assert!(admin(
+            &state,
+            &["users", "create", "weak@example.com"],
+            b"passwd\n"
+        )
+        .is_err());
+
+        let (user, changed) =
+            run_admin(&state, &["users", "disable", "kamino@example.com"], b"").unwrap();
+        assert_eq!(user["disabled"], true);
+        assert!(changed);
+        assert!(!run_admin(&state, &["users", "list"], b"").unwrap().1);
+        let users = admin(&state, &["users", "list"], b"").unwrap();
+        assert_eq!(users.as_array().unwrap().len(), 1);
+        assert_eq!(users[0]["disabled"], true);
+
+        let old_hash = StateFile::load(&state).unwrap().realms["default"].users[0]
+            .passwd_hash
+            .clone();
+        admin(
+            &state,
+            &["users", "reset-password", "kamino@example.com"],
+            b"Another-Horse-7-Staple!\n",
+        )
+        .unwrap();
+        let saved = StateFile::load(&state).unwrap();
+        assert_ne!(saved.realms["default"].users[0].passwd_hash, old_hash);
+        assert!(admin(&state, &["users", "enable", "nobody@example.com"], b"").is_err());
+
+        let hash = bcrypt::hash("Correct-Horse-9-Battery!", 4).unwrap();
+        let imported = admin(
+            &state,
+            &["users", "import", "imported@example.com"],
+            format!("{}\n", hash).as_bytes(),
+        )
+        .unwrap();
+        assert_eq!(imported["email"], "imported@example.com");
+        assert!(admin(
+            &state,
+            &["users", "import", "plain@example.com"],
+            b"passwd\n"
+        )
+        .is_err());
+        let saved = StateFile::load(&state).unwrap();
+        assert_eq!(saved.realms["default"].users[1].passwd_hash, Some(hash));
+        fs::remove_file(&state).unwrap();
+    }
+
+    #[test]
+    fn test_clients_and_tokens() {
+        let state = state_file("clients");
+        let client = admin(
+            &state,
+            &[
+                "clients",
+                "register",
+                "cli",
+                "--confidential",
+                "--scope",
+                "todo:read",
+            ],
+            b"",
+        )
+        .unwrap();
+        let secret = client["client_secret"].as_str().unwrap().to_owned();
+        assert_eq!(client["scopes"][0], "todo:read");
+        // Only the digest is saved.
+        assert!(!fs::read_to_string(&state).unwrap().contains(&secret));
+        assert!(admin(&state, &["clients", "register", "cli"], b"").is_err());
+
+        let token = admin(
+            &state,
+            &["token", "mint", "kamino@example.com", "--client", "cli"],
+            b"",
+        )
+        .unwrap();
+        assert_eq!(token["token_type"], "Bearer");
+        assert!(admin(
+            &state,
+            &["token", "mint", "kamino@example.com", "--client", "web"],
+            b""
+        )
+        .is_err());
+
+        admin(&state, &["users", "create", "kamino@example.com"], PASSWD).unwrap();
+        admin(&state, &["users", "disable", "kamino@example.com"], b"").unwrap();
+        assert!(admin(&state, &["token", "mint", "kamino@example.com"], b"").is_err());
+        fs::remove_file(&state).unwrap();
+    }
+
+    fn claims(token: &Value) -> Value {
+        let token = token["access_token"].as_str().unwrap();
+        let payload = token.split('.').nth(1).unwrap();
+        serde_json::from_slice(&URL_SAFE_NO_PAD.decode(payload).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_groups() {
+        let state = state_file("groups");
+        for name in ["staff", "engineering", "backend"] {
+            admin(&state, &["groups", "create", name], b"").unwrap();
+        }
+        assert!(admin(&state, &["groups", "create", "staff"], b"").is_err());
+        assert!(admin(&state, &["groups", "create", "two words"], b"").is_err());
+        let nest = |outer: &str, inner: &str| {
+            admin(
+                &state,
+                &["groups", "add-member", outer, "--group", inner],
+                b"",
+            )
+        };
+        nest("staff", "engineering").unwrap();
+        nest("engineering", "backend").unwrap();
+        assert!(nest("backend", "staff").is_err());
+        assert!(nest("backend", "backend").is_err());
+        assert!(nest("backend", "unknown").is_err());
+        let group = admin(
+            &state,
+            &[
+                "groups",
+                "add-member",
+                "backend",
+                "--user",
+                "kamino@example.com",
+            ],
+            b"",
+        )
+        .unwrap();
+        assert_eq!(group["members"], json!(["kamino@example.com"]));
+        let memberships = admin(
+            &state,
+            &["groups", "memberships", "kamino@example.com"],
+            b"",
+        )
+        .unwrap();
+        assert_eq!(memberships, json!(["backend", "engineering", "staff"]));
+
+        let token = admin(&state, &["token", "mint", "kamino@example.com"], b"").unwrap();
+        assert_eq!(
+            claims(&token)["groups"],
+            json!(["backend", "engineering", "staff"])
+        );
+        admin(
+            &state,
+            &["clients", "register", "tv", "--groups-limit", "2"],
+            b"",
+        )
+        .unwrap();
+        let token = admin(
+            &state,
+            &["token", "mint", "kamino@example.com", "--client", "tv"],
+            b"",
+        )
+        .unwrap();
+        assert!(claims(&token).get("groups").is_none());
+        assert_eq!(claims(&token)["groups_overage"], true);
+
+        admin(&state, &["groups", "delete", "engineering"], b"").unwrap();
+        let groups = admin(&state, &["groups", "list"], b"").unwrap();
+        assert_eq!(groups[1]["name"], "staff");
+        assert_eq!(groups[1]["subgroups"], json!([]));
+        let memberships = admin(
+            &state,
+            &["groups", "memberships", "kamino@example.com"],
+            b"",
+        )
+        .unwrap();
+        assert_eq!(memberships, json!(["backend"]));
+        fs::remove_file(&state).unwrap();
+    }
+
+    #[test]
+    fn test_keys() {
+        let state = state_file("keys");
+        let before = admin(&state, &["keys", "list"], b"").unwrap();
+        assert_eq!(before.as_array().unwrap().len(), 1);
+
+        let rotated = admin(&state, &["keys", "rotate"], b"").unwrap();
+        let keys = admin(&state, &["keys", "list"], b"").unwrap();
+        assert_eq!(keys.as_array().unwrap().len(), 2);
+        assert_eq!(keys[0]["kid"], rotated["kid"]);
+        assert_eq!(keys[0]["current"], true);
+        assert_eq!(keys[1]["kid"], before[0]["kid"]);
+
+        // Generated secrets are printed, not kept.
+        let generated = admin(&state, &["keys", "generate"], b"").unwrap();
+        assert!(generated["secret"].as_str().unwrap().len() >= 43);
+        let keys = admin(&state, &["keys", "list"], b"").unwrap();
+        assert_eq!(keys.as_array().unwrap().len(), 2);
+
+        // Retiring drops the configured key, which otherwise verifies forever.
+        let rotated_kid = rotated["kid"].as_str().unwrap();
+        assert!(admin(&state, &["keys", "retire", rotated_kid], b"").is_err());
+        assert!(admin(&state, &["keys", "retire", "unknown"], b"").is_err());
+        let retired = admin(
+            &state,
+            &["keys", "retire", before[0]["kid"].as_str().unwrap()],
+            b"",
+        )
+        .unwrap();
+        assert_eq!(retired["kid"], before[0]["kid"]);
+        let keys = admin(&state, &["keys", "list"], b"").unwrap();
+        assert_eq!(keys.as_array().unwrap().len(), 1);
+        assert_eq!(keys[0]["kid"], rotated["kid"]);
+
+        // A key rotated out goes from the state file.
+        let newest = admin(&state, &["keys", "rotate"], b"").unwrap();
+        admin(&state, &["keys", "retire", rotated_kid], b"").unwrap();
+        let keys = admin(&state, &["keys", "list"], b"").unwrap();
+        assert_eq!(keys.as_array().unwrap().len(), 1);
+        assert_eq!(keys[0]["kid"], newest["kid"]);
+        assert_eq!(
+            StateFile::load(&state).unwrap().realms["default"]
+                .signing_keys
+                .len(),
+            1
+        );
+        fs::remove_file(&state).unwrap();
+    }
+
+    #[test]
+    fn test_render_table() {
+        let value = serde_json::json!([
+            {"client_id": "cli", "scopes": ["a", "b"]},
+            {"client_id": "web-app", "scopes": []},
+        ]);
+        assert_eq!(
+            render_table(&value),
+            "CLIENT_ID  SCOPES\ncli        a b\nweb-app"
+        );
+        let value = serde_json::json!({"kid": "k1", "current": true});
+        assert_eq!(render_table(&value), "CURRENT  true\nKID      k1");
+    }
+}
@@ -0,0 +1,380 @@
+#[cfg(test)]
+mod tests {
+    use actix_web::{
+        dev::{Service, ServiceResponse},
+        http::{header, StatusCode},
+        test, web,
+    };
+    use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+    use chrono::{Duration, Utc};
+    use serde_json::{json, Value};
+
+    use crate::{
+        app::{app, JSON_LIMIT},
+        app_state::AppState,
+        credential::passwd_hasher::hash_password,
+        domain::{mail_address::MailAddress, password::Password, password_policy::PasswordPolicy},
+        entity::user::User,
+        realm::realm_registry::{Realm, RealmRegistry, DEFAULT_REALM},
+        test::token::dpop_client::DpopClient,
+        token::{jwt::encode_claims, signing_key::SigningKeys},
+    };
+
+    const ISSUER: &str = "http://localhost:8080";
+    const SECRET: &str = "e2e-secret";
+    const EMAIL: &str = "kamino@example.com";
+    const PASSWD: &str = "Correct7Horse9Battery";
+
+    async fn service(
+        tls_enabled: bool,
+    ) -> impl Service<actix_http::Request, Response = ServiceResponse, Error = actix_web::Error>
+    {
+        let realm = Realm::of(DEFAULT_REALM, ISSUER, SigningKeys::of(SECRET));
+        let email = MailAddress::of(EMAIL).unwrap();
+        let passwd = Password::of(PASSWD, &PasswordPolicy::default(), &email).unwrap();
+        let mut user = User::of(email);
+        user.passwd_hash = Some(hash_password(&passwd).unwrap());
+        realm.users.create(user).unwrap();
+        let state = AppState::of(RealmRegistry::of(vec![realm]).unwrap());
+        test::init_service(app(web::Data::new(state), tls_enabled)).await
+    }
+
+    async fn post_json<S>(app: &S, uri: &str, body: Value) -> ServiceResponse
+    where
+        S: Service<actix_http::Request, Response = ServiceResponse, Error = actix_web::Error>,
+    {
+        let req = test::TestRequest::post()
+            .uri(uri)
+            .set_json(body)
+            .to_request();
+        test::call_service(app, req).await
+    }
+
+    async fn issue<S>(app: &S, uri: &str) -> String
+    where
+        S: Service<actix_http::Request, Response = ServiceResponse, Error = actix_web::Error>,
+    {
+        let res = post_json(app, uri, json!({"email": EMAIL, "passwd": PASSWD})).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let body: Value = test::read_body_json(res).await;
+        body["token"].as_str().unwrap().to_owned()
+    }
+
+    async fn validate<S>(app: &S, token: &str) -> ServiceResponse
+    where
+        S: Service<actix_http::Request, Response = ServiceResponse, Error = actix_web::Error>,
+    {
+        post_json(app, "/validate", json!({"email": EMAIL, "token": token})).await
+    }
+
+    fn problem_type(res: &ServiceResponse) -> String {
+        res.headers()
+            .get(header::CONTENT_TYPE)
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .to_owned()
+    }
+
+    #[actix_web::test]
+    async fn test_issue_and_validate() {
+        let app = service(false).await;
+        for uri in ["/jwt", "/realms/default/jwt"] {
+            let token = issue(&app, uri).await;
+            let res = validate(&app, &token).await;
+            assert_eq!(res.status(), StatusCode::OK);
+            let body: Value = test::read_body_json(res).await;
+            assert_eq!(body["claims"]["iss"], ISSUER);
+            assert_eq!(body["claims"]["sub"], EMAIL);
+            assert_eq!(body["user"]["email"], EMAIL);
+        }
+
+        // Issued to one address, presented for another.
+        let token = issue(&app, "/jwt").await;
+        let res = post_json(
+            &app,
+            "/validate",
+            json!({"email": "other@example.com", "token": token}),
+        )
+        .await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[actix_web::test]
+    async fn test_expired_token() {
+        let app = service(false).await;
+        let issued_at = Utc::now() - Duration::hours(9);
+        let claims = json!({
+            "iss": ISSUER,
+            "aud": EMAIL,
+            "sub": EMAIL,
+            "iat": issued_at.timestamp(),
+            "exp": (issued_at + Duration::hours(8)).timestamp(),
+        });
+        let token = encode_claims(&SigningKeys::of(SECRET), &claims).unwrap();
+        let res = validate(&app, &token).await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(problem_type(&res), "application/problem+json");
+    }
+
+    #[actix_web::test]
+    async fn test_tampered_token() {
+        let app = service(false).await;
+        let token = issue(&app, "/jwt").await;
+        let parts: Vec<&str> = token.split('.').collect();
+
+        // Another subject under the original signature.
+        let mut claims: Value =
+            serde_json::from_slice(&URL_SAFE_NO_PAD.decode(parts[1]).unwrap()).unwrap();
+        claims["sub"] = json!("admin@example.com");
+        let payload = URL_SAFE_NO_PAD.encode(serde_json::to_vec(&claims).unwrap());
+        let forged = format!("{}.{}.{}", parts[0], payload, parts[2]);
+        let res = validate(&app, &forged).await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+
+        // A signature that is not the realm's.
+        let mut signature = URL_SAFE_NO_PAD.decode(parts[2]).unwrap();
+        signature[0] ^= 0x01;
+        let forged = format!(
+            "{}.{}.{}",
+            parts[0],
+            parts[1],
+            URL_SAFE_NO_PAD.encode(signature)
+        );
+        let res = validate(&app, &forged).await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+
+        // Signed with another secret.
+        let claims = json!({
+            "iss": ISSUER,
+            "aud": EMAIL,
+            "sub": EMAIL,
+            "iat": Utc::now().timestamp(),
+            "exp": (Utc::now() + Duration::hours(1)).timestamp(),
+        });
+        let forged = encode_claims(&SigningKeys::of("guessed-secret"), &claims).unwrap();
+        let res = validate(&app, &forged).await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+
+        let res = validate(&app, "not-a-token").await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[actix_web::test]
+    async fn test_rest_echo() {
+        let app = service(false).await;
+        let res = post_json(&app, "/rest", json!({"name": "idp", "number": 7})).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let body: Value = test::read_body_json(res).await;
+        assert_eq!(body, json!({"name": "idp", "number": 7}));
+
+        let res = post_json(&app, "/rest", json!({"name": "idp"})).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(problem_type(&res), "application/problem+json");
+    }
+
+    #[actix_web::test]
+    async fn test_content_types() {
+        let app = service(false).await;
+        let body = json!({"email": EMAIL, "passwd": PASSWD}).to_string();
+
+        // Besides JSON, text/plain is accepted.
+        let req = test::TestRequest::post()
+            .uri("/jwt")
+            .insert_header((header::CONTENT_TYPE, "text/plain"))
+            .set_payload(body.clone())
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        for content_type in ["application/xml", "application/x-www-form-urlencoded"] {
+            let req = test::TestRequest::post()
+                .uri("/jwt")
+                .insert_header((header::CONTENT_TYPE, content_type))
+                .set_payload(body.clone())
+                .to_request();
+            let res = test::call_service(&app, req).await;
+            assert_eq!(res.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+            assert_eq!(problem_type(&res), "application/problem+json");
+        }
+
+        let req = test::TestRequest::post()
+            .uri("/rest")
+            .set_payload(r#"{"name": "idp", "number": 7}"#)
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    }
+
+    #[actix_web::test]
+    async fn test_body_limit() {
+        let app = service(false).await;
+        // `{"name":"…","number":7}` takes 22 bytes around the name.
+        let fits = "x".repeat(JSON_LIMIT - 22);
+        let res = post_json(&app, "/rest", json!({"name": fits, "number": 7})).await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let too_large = "x".repeat(JSON_LIMIT - 21);
+        let res = post_json(&app, "/rest", json!({"name": too_large, "number": 7})).await;
+        assert_eq!(res.status(), StatusCode::PAYLOAD_TOO_LARGE);
+        assert_eq!(problem_type(&res), "application/problem+json");
+
+        let res = post_json(
+            &app,
+            "/jwt",
+            json!({"email": EMAIL, "passwd": "x".repeat(JSON_LIMIT)}),
+        )
+        .await;
+        assert_eq!(res.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    #[actix_web::test]
+    async fn test_unknown_route_and_hsts() {
+        let app = service(false).await;
+        let req = test::TestRequest::get().uri("/nothing-here").to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+        assert_eq!(problem_type(&res), "application/problem+json");
+        assert!(!res
+            .headers()
+            .contains_key(header::STRICT_TRANSPORT_SECURITY));
+
+        let app = service(true).await;
+        let req = test::TestRequest::get().uri("/healthz").to_request();
+        let res = test::call_service(&app, req).await;
+        assert!(res
+            .headers()
+            .contains_key(header::STRICT_TRANSPORT_SECURITY));
+    }
+
+    fn userinfo(token: &str, scheme: &str, proof: Option<String>) -> actix_http::Request {
+        let mut req = test::TestRequest::get()
+            .uri("/userinfo")
+            .insert_header((header::AUTHORIZATION, format!("{} {}", scheme, token)));
+        if let Some(proof) = proof {
+            req = req.insert_header(("DPoP", proof));
+        }
+        req.to_request()
+    }
+
+    #[actix_web::test]
+    async fn test_dpop_bound_token() {
+        let app = service(false).await;
+        let client = DpopClient::new();
+        let req = test::TestRequest::post()
+            .uri("/jwt")
+            .insert_header((
+                "DPoP",
+                client.proof("POST", "http://localhost:8080/jwt", None),
+            ))
+            .set_json(json!({"email": EMAIL, "passwd": PASSWD}))
+            .to_request();
+        let body: Value = test::call_and_read_body_json(&app, req).await;
+        let token = body["token"].as_str().unwrap();
+
+        // Leaked, it is of no use without the key.
+        assert_eq!(
+            validate(&app, token).await.status(),
+            StatusCode::UNAUTHORIZED
+        );
+        let proof = client.proof("POST", "http://localhost:8080/validate", Some(token));
+        let validate_with = |proof: String| {
+            test::TestRequest::post()
+                .uri("/validate")
+                .insert_header(("DPoP", proof))
+                .set_json(json!({"email": EMAIL, "token": token}))
+                .to_request()
+        };
+        let body: Value = test::call_and_read_body_json(&app, validate_with(proof.clone())).await;
+        assert_eq!(body["claims"]["cnf"]["jkt"], client.jkt());
+        // Proofs are used once.
+        let res = test::call_service(&app, validate_with(proof)).await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+        let other = DpopClient::new();
+        let proof = other.proof("POST", "http://localhost:8080/validate", Some(token));
+        let res = test::call_service(&app, validate_with(proof)).await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+
+        let proof = client.proof("GET", "http://localhost:8080/userinfo", Some(token));
+        let res = test::call_service(&app, userinfo(token, "Bearer", Some(proof))).await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+        let proof = client.proof("GET", "http://localhost:8080/userinfo", Some(token));
+        let body: Value =
+            test::call_and_read_body_json(&app, userinfo(token, "DPoP", Some(proof))).await;
+        assert_eq!(body["email"], EMAIL);
+        let res = test::call_service(&app, userinfo(token, "DPoP", None)).await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[actix_web::test]
+    async fn test_bearer_token() {
+        let app = service(false).await;
+        let token = issue(&app, "/jwt").await;
+        let body: Value =
+            test::call_and_read_body_json(&app, userinfo(&token, "Bearer", None)).await;
+        assert_eq!(body["email"], EMAIL);
+        // Unbound tokens are not sent as DPoP tokens.
+        let res = test::call_service(&app, userinfo(&token, "DPoP", None)).await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+        let req = test::TestRequest::get().uri("/userinfo").to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+
+        // A proof for another endpoint gets no token at all.
+        let client = DpopClient::new();
+        let req = test::TestRequest::post()
+            .uri("/jwt")
+            .insert_header((
+                "DPoP",
+                client.proof("POST", "http://localhost:8080/token", None),
+            ))
+            .set_json(json!({"email": EMAIL, "passwd": PASSWD}))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        let body: Value = test::read_body_json(res).await;
+        assert_eq!(body["error"], "invalid_dpop_proof");
+    }
+
+    #[actix_web::test]
+    async fn test_localized_responses() {
+        let app = service(false).await;
+        let get = |uri: &str, accept_language: &str| {
+            test::TestRequest::get()
+                .uri(uri)
+                .insert_header((header::ACCEPT_LANGUAGE, accept_language))
+                .to_request()
+        };
+
+        let res = test::call_service(&app, get("/nothing-here", "ja-JP,en;q=0.5")).await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+        assert_eq!(problem_type(&res), "application/problem+json");
+        assert_eq!(res.headers().get(header::CONTENT_LANGUAGE).unwrap(), "ja");
+        let body: Value = test::read_body_json(res).await;
+        assert_eq!(body["type"], "/problems/not-found");
+        assert_eq!(body["title"], "見つかりません");
+
+        let res = test::call_service(&app, get("/nothing-here?ui_locales=en", "ja")).await;
+        let body: Value = test::read_body_json(res).await;
+        assert_eq!(body["title"], "Not Found");
+
+        // Field errors, by code.
+        let req = test::TestRequest::post()
+            .uri("/jwt")
+            .insert_header((header::ACCEPT_LANGUAGE, "ja"))
+            .set_json(json!({"email": EMAIL}))
+            .to_request();
+        let body: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(body["errors"][0]["message"], "passwd は必須です");
+
+        // Pages.
+        let res = test::call_service(&app, get("/logout", "ja")).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let page = String::from_utf8(test::read_body(res).await.to_vec()).unwrap();
+        assert!(page.contains(r#"<html lang="ja">"#));
+        assert!(page.contains("サインアウトしました。"));
+        let res = test::call_service(&app, get("/logout", "fr")).await;
+        let page = String::from_utf8(test::read_body(res).await.to_vec()).unwrap();
+        assert!(page.contains("You are signed out."));
+    }
+}
@@ -0,0 +1,46 @@
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::PathBuf};
+
+    use crate::{
+        domain::mail_address::MailAddress,
+        token::{jwt::decode_jwt, signing_key::SigningKeys},
+    };
+
+    // The regression corpus of a fuzz target, so that stable builds replay it too.
+    fn corpus(target: &str) -> Vec<(PathBuf, String)> {
+        let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("fuzz/corpus")
+            .join(target);
+        let inputs: Vec<(PathBuf, String)> = fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter_map(|path| {
+                let input = String::from_utf8(fs::read(&path).unwrap()).ok()?;
+                Some((path, input))
+            })
+            .collect();
+        assert!(!inputs.is_empty());
+        inputs
+    }
+
+    #[test]
+    fn test_mail_address_corpus() {
+        for (path, input) in corpus("mail_address") {
+            if let Ok(email) = MailAddress::of(input) {
+                let canonical = String::from(email.clone());
+                assert_eq!(MailAddress::of(canonical).ok(), Some(email), "{:?}", path);
+            }
+        }
+    }
+
+    #[test]
+    fn test_decode_jwt_corpus() {
+        let keys = SigningKeys::of("fuzz-secret");
+        let email = MailAddress::of("kamino@example.com").unwrap();
+        for (path, token) in corpus("decode_jwt") {
+            let decoded = decode_jwt(&keys, "http://localhost:8080", &token, &email);
+            assert!(decoded.is_err(), "{:?}", path);
+        }
+    }
+}
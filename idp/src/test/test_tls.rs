@@ -0,0 +1,75 @@
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, path::PathBuf, process, sync::Arc, time::Duration};
+
+    use crate::tls::{server_config, ReloadingCertResolver, TlsConfig};
+
+    fn config(name: &str) -> TlsConfig {
+        let dir = env::temp_dir().join(format!("idp-tls-{}-{}", name, process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        TlsConfig {
+            cert_file: dir.join("cert.pem"),
+            key_file: dir.join("key.pem"),
+            reload_interval: Duration::from_secs(1),
+        }
+    }
+
+    // Writes a fresh self-signed certificate and returns its DER bytes.
+    fn write_cert(config: &TlsConfig) -> Vec<u8> {
+        let generated = rcgen::generate_simple_self_signed(vec!["localhost".to_owned()]).unwrap();
+        fs::write(&config.cert_file, generated.cert.pem()).unwrap();
+        fs::write(&config.key_file, generated.key_pair.serialize_pem()).unwrap();
+        generated.cert.der().to_vec()
+    }
+
+    fn current_der(resolver: &ReloadingCertResolver) -> Vec<u8> {
+        resolver.current().unwrap().cert[0].to_vec()
+    }
+
+    fn cleanup(config: &TlsConfig) {
+        let dir: PathBuf = config.cert_file.parent().unwrap().into();
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_load_and_build_server_config() {
+        let config = config("load");
+        let der = write_cert(&config);
+        let resolver = Arc::new(ReloadingCertResolver::load(config.clone()).unwrap());
+        assert_eq!(current_der(&resolver), der);
+        assert!(server_config(resolver).is_ok());
+        cleanup(&config);
+    }
+
+    #[test]
+    fn test_reload_rotated_certificate() {
+        let config = config("rotate");
+        write_cert(&config);
+        let resolver = ReloadingCertResolver::load(config.clone()).unwrap();
+        assert!(!resolver.reload_if_changed().unwrap());
+
+        let rotated = write_cert(&config);
+        assert!(resolver.reload_if_changed().unwrap());
+        assert_eq!(current_der(&resolver), rotated);
+        cleanup(&config);
+    }
+
+    #[test]
+    fn test_broken_rotation_keeps_certificate() {
+        let config = config("broken");
+        let der = write_cert(&config);
+        let resolver = ReloadingCertResolver::load(config.clone()).unwrap();
+
+        fs::write(&config.key_file, "not a key").unwrap();
+        assert!(resolver.reload_if_changed().is_err());
+        assert_eq!(current_der(&resolver), der);
+        cleanup(&config);
+    }
+
+    #[test]
+    fn test_missing_files() {
+        let config = config("missing");
+        assert!(ReloadingCertResolver::load(config.clone()).is_err());
+        cleanup(&config);
+    }
+}
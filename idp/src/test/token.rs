@@ -0,0 +1,7 @@
+#[cfg(test)]
+pub mod dpop_client;
+pub mod test_claim_mapping;
+pub mod test_dpop;
+pub mod test_jwe;
+pub mod test_jwt_properties;
+pub mod test_signing_key;
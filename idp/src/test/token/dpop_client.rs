@@ -0,0 +1,79 @@
+//! A client holding a DPoP key, signing proofs the way RFC 9449 has them.
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::Utc;
+use ring::{
+    rand::{SecureRandom, SystemRandom},
+    signature::{EcdsaKeyPair, KeyPair, ECDSA_P256_SHA256_FIXED_SIGNING},
+};
+use serde_json::{json, Value};
+
+use crate::token::dpop::{access_token_hash, thumbprint};
+
+pub struct DpopClient {
+    key_pair: EcdsaKeyPair,
+    rng: SystemRandom,
+}
+
+impl DpopClient {
+    pub fn new() -> Self {
+        let rng = SystemRandom::new();
+        let pkcs8 = EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, &rng).unwrap();
+        let key_pair =
+            EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, pkcs8.as_ref(), &rng)
+                .unwrap();
+        Self { key_pair, rng }
+    }
+
+    /// The public key, an uncompressed P-256 point.
+    pub fn jwk(&self) -> Value {
+        let point = self.key_pair.public_key().as_ref();
+        json!({
+            "kty": "EC",
+            "crv": "P-256",
+            "x": URL_SAFE_NO_PAD.encode(&point[1..33]),
+            "y": URL_SAFE_NO_PAD.encode(&point[33..]),
+        })
+    }
+
+    pub fn jkt(&self) -> String {
+        thumbprint(&self.jwk()).unwrap()
+    }
+
+    /// A fresh proof for the request, and for the token sent with it if any.
+    pub fn proof(&self, htm: &str, htu: &str, access_token: Option<&str>) -> String {
+        let mut jti = [0u8; 16];
+        self.rng.fill(&mut jti).unwrap();
+        let mut claims = json!({
+            "jti": URL_SAFE_NO_PAD.encode(jti),
+            "htm": htm,
+            "htu": htu,
+            "iat": Utc::now().timestamp(),
+        });
+        if let Some(access_token) = access_token {
+            claims["ath"] = json!(access_token_hash(access_token));
+        }
+        self.sign(&self.header(), &claims)
+    }
+
+    pub fn header(&self) -> Value {
+        json!({"typ": "dpop+jwt", "alg": "ES256", "jwk": self.jwk()})
+    }
+
+    pub fn sign(&self, header: &Value, claims: &Value) -> String {
+        let signing_input = format!(
+            "{}.{}",
+            URL_SAFE_NO_PAD.encode(header.to_string()),
+            URL_SAFE_NO_PAD.encode(claims.to_string())
+        );
+        let signature = self
+            .key_pair
+            .sign(&self.rng, signing_input.as_bytes())
+            .unwrap();
+        format!(
+            "{}.{}",
+            signing_input,
+            URL_SAFE_NO_PAD.encode(signature.as_ref())
+        )
+    }
+}
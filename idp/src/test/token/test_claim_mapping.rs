@@ -0,0 +1,77 @@
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use crate::{
+        domain::mail_address::MailAddress,
+        entity::user::User,
+        token::claim_mapping::{check_mappers, map_claims, ClaimMapper},
+    };
+
+    fn user() -> User {
+        let mut user = User::of(MailAddress::of("kamino@example.com").unwrap());
+        user.attributes
+            .insert("preferred_username".to_owned(), "kamino".to_owned());
+        user
+    }
+
+    fn mappers(value: Value) -> Vec<ClaimMapper> {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn test_map_claims() {
+        let mappers = mappers(json!([
+            {"type": "user_attribute", "claim": "preferred_username", "attribute": "preferred_username"},
+            {"type": "user_attribute", "claim": "mail", "attribute": "email"},
+            {"type": "user_attribute", "claim": "locale", "attribute": "locale"},
+            {"type": "static", "claim": "https://todo.example.com/tier", "value": {"plan": "gold"}},
+            {"type": "template", "claim": "upn", "template": "${preferred_username}@corp.example.com"},
+            {"type": "template", "claim": "display", "template": "${locale} user"},
+        ]));
+        assert!(check_mappers(&mappers).is_ok());
+        let claims = map_claims(&mappers, &user());
+        assert_eq!(claims["preferred_username"], "kamino");
+        assert_eq!(claims["mail"], "kamino@example.com");
+        assert_eq!(
+            claims["https://todo.example.com/tier"],
+            json!({"plan": "gold"})
+        );
+        assert_eq!(claims["upn"], "kamino@corp.example.com");
+        // Missing attributes leave the claim out.
+        assert!(!claims.contains_key("locale"));
+        assert!(!claims.contains_key("display"));
+    }
+
+    #[test]
+    fn test_reserved_claims() {
+        for claim in ["iss", "sub", "aud", "exp", "iat", "azp", "act", "scope", ""] {
+            let mappers = mappers(json!([{"type": "static", "claim": claim, "value": "x"}]));
+            assert!(check_mappers(&mappers).is_err(), "{}", claim);
+        }
+    }
+
+    #[test]
+    fn test_invalid_mappers() {
+        let duplicate = mappers(json!([
+            {"type": "static", "claim": "tier", "value": "gold"},
+            {"type": "user_attribute", "claim": "tier", "attribute": "tier"},
+        ]));
+        assert!(check_mappers(&duplicate).is_err());
+
+        for template in ["${email", "${}", "x ${email} ${"] {
+            let mappers =
+                mappers(json!([{"type": "template", "claim": "t", "template": template}]));
+            assert!(check_mappers(&mappers).is_err(), "{}", template);
+        }
+
+        assert!(serde_json::from_value::<Vec<ClaimMapper>>(
+            json!([{"type": "script", "claim": "t", "source": "1 + 1"}])
+        )
+        .is_err());
+        assert!(serde_json::from_value::<Vec<ClaimMapper>>(
+            json!([{"type": "static", "claim": "t", "value": 1, "extra": true}])
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,103 @@
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use serde_json::json;
+
+    use crate::{
+        store::dpop_replay_store::DpopReplayStore,
+        test::token::dpop_client::DpopClient,
+        token::dpop::{thumbprint, verify_proof, PROOF_WINDOW_SECS},
+    };
+
+    const TOKEN_URI: &str = "http://localhost:8080/token";
+
+    #[test]
+    fn test_valid_proof() {
+        let client = DpopClient::new();
+        let proof = client.proof("POST", TOKEN_URI, None);
+        let verified = verify_proof(&proof, "POST", TOKEN_URI, None).unwrap();
+        assert_eq!(verified.jkt, client.jkt());
+
+        // Query and fragment are not compared.
+        let proof = client.proof("GET", "http://localhost:8080/userinfo?x=1", Some("token"));
+        let verified = verify_proof(
+            &proof,
+            "GET",
+            "http://localhost:8080/userinfo",
+            Some("token"),
+        )
+        .unwrap();
+        assert_eq!(verified.jkt, client.jkt());
+    }
+
+    #[test]
+    fn test_proof_for_another_request() {
+        let client = DpopClient::new();
+        let proof = client.proof("POST", TOKEN_URI, None);
+        assert!(verify_proof(&proof, "GET", TOKEN_URI, None).is_err());
+        assert!(verify_proof(&proof, "POST", "http://localhost:8080/jwt", None).is_err());
+        // Resources need the hash of the token the proof comes with.
+        assert!(verify_proof(&proof, "POST", TOKEN_URI, Some("token")).is_err());
+        let proof = client.proof("POST", TOKEN_URI, Some("other"));
+        assert!(verify_proof(&proof, "POST", TOKEN_URI, Some("token")).is_err());
+    }
+
+    #[test]
+    fn test_rejected_proofs() {
+        let client = DpopClient::new();
+        let claims = |iat: i64| json!({"jti": "1", "htm": "POST", "htu": TOKEN_URI, "iat": iat});
+        let now = Utc::now().timestamp();
+
+        let stale = client.sign(&client.header(), &claims(now - PROOF_WINDOW_SECS - 1));
+        assert!(verify_proof(&stale, "POST", TOKEN_URI, None).is_err());
+        let future = client.sign(&client.header(), &claims(now + PROOF_WINDOW_SECS + 1));
+        assert!(verify_proof(&future, "POST", TOKEN_URI, None).is_err());
+
+        let mut header = client.header();
+        header["typ"] = json!("JWT");
+        let untyped = client.sign(&header, &claims(now));
+        assert!(verify_proof(&untyped, "POST", TOKEN_URI, None).is_err());
+
+        let mut header = client.header();
+        header["jwk"]["d"] = json!("AAAA");
+        let private = client.sign(&header, &claims(now));
+        assert!(verify_proof(&private, "POST", TOKEN_URI, None).is_err());
+
+        let mut header = client.header();
+        header["alg"] = json!("HS256");
+        let symmetric = client.sign(&header, &claims(now));
+        assert!(verify_proof(&symmetric, "POST", TOKEN_URI, None).is_err());
+
+        // Signed by another key than the one in the header.
+        let other = DpopClient::new();
+        let forged = other.sign(&client.header(), &claims(now));
+        assert!(verify_proof(&forged, "POST", TOKEN_URI, None).is_err());
+    }
+
+    #[test]
+    fn test_thumbprint() {
+        let client = DpopClient::new();
+        let mut jwk = client.jwk();
+        // Only the required members count.
+        jwk["kid"] = json!("key-1");
+        jwk["alg"] = json!("ES256");
+        assert_eq!(thumbprint(&jwk).unwrap(), client.jkt());
+        assert_ne!(DpopClient::new().jkt(), client.jkt());
+        assert!(thumbprint(&json!({"kty": "oct", "k": "AAAA"})).is_none());
+    }
+
+    #[test]
+    fn test_replayed_proof() {
+        let client = DpopClient::new();
+        let proof = client.proof("POST", TOKEN_URI, None);
+        let verified = verify_proof(&proof, "POST", TOKEN_URI, None).unwrap();
+        let store = DpopReplayStore::default();
+        assert!(store.record(&verified).unwrap());
+        assert!(!store.record(&verified).unwrap());
+
+        // The jti of another key is another proof.
+        let mut other = verified.clone();
+        other.jkt = DpopClient::new().jkt();
+        assert!(store.record(&other).unwrap());
+    }
+}
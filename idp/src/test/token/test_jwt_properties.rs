@@ -0,0 +1,70 @@
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use crate::{
+        domain::mail_address::MailAddress,
+        token::{
+            jwt::{decode_jwt, make_jwt},
+            signing_key::SigningKeys,
+        },
+    };
+
+    const ISSUER: &str = "http://localhost:8080";
+    const BASE64URL: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+    fn email() -> impl Strategy<Value = MailAddress> {
+        ("[a-z0-9._-]{0,10}[a-z0-9]", "[a-z]{1,10}\\.[a-z]{2,4}")
+            .prop_map(|(local, domain)| format!("x{}@{}", local, domain))
+            .prop_filter_map("valid address", |address| MailAddress::of(address).ok())
+    }
+
+    proptest! {
+        #[test]
+        fn test_issued_tokens_decode(email in email(), secret in "[ -~]{1,32}") {
+            let keys = SigningKeys::of(secret);
+            let token = make_jwt(&keys, ISSUER, &email, None, None).unwrap();
+            let claims = decode_jwt(&keys, ISSUER, &token, &email).unwrap();
+            let subject = String::from(email);
+            prop_assert_eq!(claims.subject(), subject.as_str());
+        }
+
+        #[test]
+        fn test_arbitrary_input_is_rejected(token in "\\PC{0,200}") {
+            let keys = SigningKeys::of("secret");
+            let email = MailAddress::of("kamino@example.com").unwrap();
+            prop_assert!(decode_jwt(&keys, ISSUER, &token, &email).is_err());
+        }
+
+        #[test]
+        fn test_arbitrary_segments_are_rejected(
+            segments in prop::collection::vec("[A-Za-z0-9_-]{0,64}", 3),
+        ) {
+            let keys = SigningKeys::of("secret");
+            let email = MailAddress::of("kamino@example.com").unwrap();
+            prop_assert!(decode_jwt(&keys, ISSUER, &segments.join("."), &email).is_err());
+        }
+
+        // Any last character of a segment may carry unused bits, the others
+        // always change the decoded bytes.
+        #[test]
+        fn test_tampered_tokens_are_rejected(
+            email in email(),
+            position in any::<prop::sample::Index>(),
+            replacement in any::<prop::sample::Index>(),
+        ) {
+            let keys = SigningKeys::of("secret");
+            let token = make_jwt(&keys, ISSUER, &email, None, None).unwrap();
+            let bytes = token.as_bytes();
+            let positions: Vec<usize> = (0..bytes.len() - 1)
+                .filter(|&i| bytes[i] != b'.' && bytes[i + 1] != b'.')
+                .collect();
+            let i = *position.get(&positions);
+            let alphabet: Vec<u8> = BASE64URL.iter().copied().filter(|&c| c != bytes[i]).collect();
+            let mut tampered = bytes.to_vec();
+            tampered[i] = *replacement.get(&alphabet);
+            let tampered = String::from_utf8(tampered).unwrap();
+            prop_assert!(decode_jwt(&keys, ISSUER, &tampered, &email).is_err());
+        }
+    }
+}
@@ -0,0 +1,43 @@
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+
+    use crate::{
+        domain::mail_address::MailAddress,
+        token::{
+            jwt::{decode_jwt, make_jwt},
+            signing_key::{SigningKey, SigningKeys},
+        },
+    };
+
+    const ISSUER: &str = "http://localhost:8080";
+
+    #[test]
+    fn test_kid_is_derived_from_the_secret() {
+        assert_eq!(SigningKey::of("secret").kid, SigningKey::of("secret").kid);
+        assert_ne!(SigningKey::of("secret").kid, SigningKey::of("other").kid);
+        assert!(SigningKeys::from_keys(vec![]).is_err());
+    }
+
+    #[test]
+    fn test_rotated_keys_keep_verifying() {
+        let email = MailAddress::of("kamino@example.com").unwrap();
+        let old = SigningKeys::of("old-secret");
+        let old_token = make_jwt(&old, ISSUER, &email, None, None).unwrap();
+
+        let rotated = SigningKeys::from_keys(vec![
+            SigningKey::of("new-secret").with_created_at(Utc::now()),
+            SigningKey::of("old-secret"),
+        ])
+        .unwrap();
+        assert_eq!(rotated.current().kid, SigningKey::of("new-secret").kid);
+        let new_token = make_jwt(&rotated, ISSUER, &email, None, None).unwrap();
+        assert!(decode_jwt(&rotated, ISSUER, &old_token, &email).is_ok());
+        assert!(decode_jwt(&rotated, ISSUER, &new_token, &email).is_ok());
+
+        // Once the old key is dropped, its kid is unknown.
+        let dropped = SigningKeys::of("new-secret");
+        assert!(decode_jwt(&dropped, ISSUER, &new_token, &email).is_ok());
+        assert!(decode_jwt(&dropped, ISSUER, &old_token, &email).is_err());
+    }
+}
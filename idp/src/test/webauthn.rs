@@ -0,0 +1,3 @@
+#[cfg(test)]
+pub mod software_authenticator;
+pub mod test_relying_party;
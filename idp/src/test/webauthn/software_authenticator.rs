@@ -0,0 +1,187 @@
+//! An authenticator in software, answering ceremonies the way a security key would.
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use ciborium::Value;
+use ring::{
+    digest::{digest, SHA256},
+    rand::SystemRandom,
+    signature::{EcdsaKeyPair, KeyPair, ECDSA_P256_SHA256_ASN1_SIGNING},
+};
+use serde_json::{json, Value as Json};
+
+/// How the authenticator attests a new credential.
+pub enum Attestation {
+    None,
+    /// Packed, signed with the credential itself.
+    PackedSelf,
+    /// Packed, signed with an attestation certificate.
+    PackedCertificate,
+}
+
+pub struct SoftwareAuthenticator {
+    key_pair: EcdsaKeyPair,
+    pub credential_id: Vec<u8>,
+    pub sign_count: u32,
+    /// Whether the user is verified, with a PIN or biometrics.
+    pub user_verification: bool,
+    pub origin: String,
+    rng: SystemRandom,
+}
+
+impl SoftwareAuthenticator {
+    pub fn new(origin: &str) -> Self {
+        let rng = SystemRandom::new();
+        let pkcs8 = EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, &rng).unwrap();
+        let key_pair =
+            EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, pkcs8.as_ref(), &rng)
+                .unwrap();
+        let credential_id = digest(&SHA256, pkcs8.as_ref()).as_ref()[..16].to_vec();
+        Self {
+            key_pair,
+            credential_id,
+            sign_count: 0,
+            user_verification: true,
+            origin: origin.to_owned(),
+            rng,
+        }
+    }
+
+    pub fn id(&self) -> String {
+        URL_SAFE_NO_PAD.encode(&self.credential_id)
+    }
+
+    /// Answers `navigator.credentials.create()`, in the `toJSON()` form.
+    pub fn create(&mut self, options: &Json, attestation: Attestation) -> Json {
+        let client_data_json = self.client_data("webauthn.create", options);
+        let auth_data = self.authenticator_data(options["rp"]["id"].as_str().unwrap(), true);
+        let signed = [
+            auth_data.as_slice(),
+            digest(&SHA256, &client_data_json).as_ref(),
+        ]
+        .concat();
+        let (fmt, att_stmt) = match attestation {
+            Attestation::None => ("none", vec![]),
+            Attestation::PackedSelf => ("packed", self.packed_statement(&self.key_pair, &signed)),
+            Attestation::PackedCertificate => {
+                let key = rcgen::KeyPair::generate().unwrap();
+                let cert = rcgen::CertificateParams::new(vec![])
+                    .unwrap()
+                    .self_signed(&key)
+                    .unwrap();
+                let key_pair = EcdsaKeyPair::from_pkcs8(
+                    &ECDSA_P256_SHA256_ASN1_SIGNING,
+                    &key.serialize_der(),
+                    &self.rng,
+                )
+                .unwrap();
+                let mut att_stmt = self.packed_statement(&key_pair, &signed);
+                att_stmt.push((
+                    text("x5c"),
+                    Value::Array(vec![Value::Bytes(cert.der().to_vec())]),
+                ));
+                ("packed", att_stmt)
+            }
+        };
+        let attestation_object = cbor(&Value::Map(vec![
+            (text("fmt"), text(fmt)),
+            (text("attStmt"), Value::Map(att_stmt)),
+            (text("authData"), Value::Bytes(auth_data)),
+        ]));
+        json!({
+            "id": self.id(),
+            "rawId": self.id(),
+            "type": "public-key",
+            "response": {
+                "clientDataJSON": URL_SAFE_NO_PAD.encode(client_data_json),
+                "attestationObject": URL_SAFE_NO_PAD.encode(attestation_object),
+            },
+        })
+    }
+
+    /// Answers `navigator.credentials.get()`, in the `toJSON()` form.
+    pub fn get(&mut self, options: &Json, user_handle: Option<&str>) -> Json {
+        self.sign_count += 1;
+        let client_data_json = self.client_data("webauthn.get", options);
+        let auth_data = self.authenticator_data(options["rpId"].as_str().unwrap(), false);
+        let signed = [
+            auth_data.as_slice(),
+            digest(&SHA256, &client_data_json).as_ref(),
+        ]
+        .concat();
+        let signature = self.key_pair.sign(&self.rng, &signed).unwrap();
+        json!({
+            "id": self.id(),
+            "rawId": self.id(),
+            "type": "public-key",
+            "response": {
+                "clientDataJSON": URL_SAFE_NO_PAD.encode(client_data_json),
+                "authenticatorData": URL_SAFE_NO_PAD.encode(auth_data),
+                "signature": URL_SAFE_NO_PAD.encode(signature.as_ref()),
+                "userHandle": user_handle,
+            },
+        })
+    }
+
+    fn client_data(&self, ceremony_type: &str, options: &Json) -> Vec<u8> {
+        serde_json::to_vec(&json!({
+            "type": ceremony_type,
+            "challenge": options["challenge"],
+            "origin": self.origin,
+            "crossOrigin": false,
+        }))
+        .unwrap()
+    }
+
+    fn authenticator_data(&self, rp_id: &str, attested: bool) -> Vec<u8> {
+        let mut flags = 0x01;
+        if self.user_verification {
+            flags |= 0x04;
+        }
+        if attested {
+            flags |= 0x40;
+        }
+        let mut data = digest(&SHA256, rp_id.as_bytes()).as_ref().to_vec();
+        data.push(flags);
+        data.extend_from_slice(&self.sign_count.to_be_bytes());
+        if attested {
+            data.extend_from_slice(&[0; 16]);
+            data.extend_from_slice(&(self.credential_id.len() as u16).to_be_bytes());
+            data.extend_from_slice(&self.credential_id);
+            data.extend(cbor(&self.cose_key()));
+        }
+        data
+    }
+
+    fn cose_key(&self) -> Value {
+        let point = self.key_pair.public_key().as_ref();
+        Value::Map(vec![
+            (int(1), int(2)),
+            (int(3), int(-7)),
+            (int(-1), int(1)),
+            (int(-2), Value::Bytes(point[1..33].to_vec())),
+            (int(-3), Value::Bytes(point[33..].to_vec())),
+        ])
+    }
+
+    fn packed_statement(&self, key_pair: &EcdsaKeyPair, signed: &[u8]) -> Vec<(Value, Value)> {
+        let sig = key_pair.sign(&self.rng, signed).unwrap();
+        vec![
+            (text("alg"), int(-7)),
+            (text("sig"), Value::Bytes(sig.as_ref().to_vec())),
+        ]
+    }
+}
+
+fn text(value: &str) -> Value {
+    Value::Text(value.to_owned())
+}
+
+fn int(value: i64) -> Value {
+    Value::Integer(value.into())
+}
+
+pub fn cbor(value: &Value) -> Vec<u8> {
+    let mut bytes = vec![];
+    ciborium::into_writer(value, &mut bytes).unwrap();
+    bytes
+}
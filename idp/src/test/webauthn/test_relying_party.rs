@@ -0,0 +1,190 @@
+#[cfg(test)]
+mod tests {
+    use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+    use serde_json::Value;
+
+    use crate::{
+        domain::mail_address::MailAddress,
+        entity::{passkey::Passkey, user::User},
+        error::my_error::MyError,
+        realm::realm_registry::Realm,
+        test::webauthn::software_authenticator::{Attestation, SoftwareAuthenticator},
+        token::signing_key::SigningKeys,
+        webauthn::{
+            ceremony::{Ceremony, CeremonyKind},
+            relying_party::RelyingParty,
+        },
+    };
+
+    const ORIGIN: &str = "http://localhost:8080";
+
+    fn rp() -> RelyingParty {
+        let realm = Realm::of(
+            "acme",
+            "http://localhost:8080/realms/acme",
+            SigningKeys::of("test-secret"),
+        );
+        RelyingParty::of(&realm).unwrap()
+    }
+
+    fn user() -> User {
+        let mut user = User::of(MailAddress::of("kamino@example.com").unwrap());
+        user.user_handle = Some("dXNlci1oYW5kbGU".to_owned());
+        user
+    }
+
+    fn decode(credential: &Value, field: &str) -> Vec<u8> {
+        URL_SAFE_NO_PAD
+            .decode(credential["response"][field].as_str().unwrap())
+            .unwrap()
+    }
+
+    fn register(
+        rp: &RelyingParty,
+        authenticator: &mut SoftwareAuthenticator,
+        attestation: Attestation,
+    ) -> Result<Passkey, MyError> {
+        let user = user();
+        let ceremony =
+            Ceremony::start(CeremonyKind::Registration, Some(user.email.clone())).unwrap();
+        let options = serde_json::to_value(rp.creation_options(&ceremony, &user).unwrap()).unwrap();
+        let credential = authenticator.create(&options, attestation);
+        rp.register(
+            &ceremony,
+            &decode(&credential, "clientDataJSON"),
+            &decode(&credential, "attestationObject"),
+        )
+    }
+
+    fn authenticate(
+        rp: &RelyingParty,
+        authenticator: &mut SoftwareAuthenticator,
+        passkey: &Passkey,
+        email: Option<MailAddress>,
+    ) -> Result<u32, MyError> {
+        let ceremony = Ceremony::start(CeremonyKind::Authentication, email).unwrap();
+        let options = serde_json::to_value(rp.request_options(&ceremony, &[])).unwrap();
+        let credential = authenticator.get(&options, None);
+        rp.authenticate(
+            &ceremony,
+            passkey,
+            &decode(&credential, "clientDataJSON"),
+            &decode(&credential, "authenticatorData"),
+            &decode(&credential, "signature"),
+        )
+    }
+
+    #[test]
+    fn test_relying_party_of_realm() {
+        let rp = rp();
+        assert_eq!(rp.id, "localhost");
+        assert_eq!(rp.origin, ORIGIN);
+    }
+
+    #[test]
+    fn test_user_handle_outlives_key_rotation() {
+        let ceremony = Ceremony::start(CeremonyKind::Registration, None).unwrap();
+        let rotated = RelyingParty::of(&Realm::of(
+            "acme",
+            "http://localhost:8080/realms/acme",
+            SigningKeys::of("rotated-secret"),
+        ))
+        .unwrap();
+        for rp in [rp(), rotated] {
+            let options = rp.creation_options(&ceremony, &user()).unwrap();
+            assert_eq!(options.user.id, "dXNlci1oYW5kbGU");
+        }
+        let anonymous = User::of(MailAddress::of("kamino@example.com").unwrap());
+        assert!(rp().creation_options(&ceremony, &anonymous).is_err());
+    }
+
+    #[test]
+    fn test_attestation_formats() {
+        let rp = rp();
+        for (attestation, fmt) in [
+            (Attestation::None, "none"),
+            (Attestation::PackedSelf, "packed"),
+            (Attestation::PackedCertificate, "packed"),
+        ] {
+            let mut authenticator = SoftwareAuthenticator::new(ORIGIN);
+            let passkey = register(&rp, &mut authenticator, attestation).unwrap();
+            assert_eq!(passkey.id, authenticator.id());
+            assert_eq!(passkey.attestation_format, fmt);
+            assert_eq!(passkey.sign_count, 0);
+        }
+    }
+
+    #[test]
+    fn test_registration_from_other_origin() {
+        let mut authenticator = SoftwareAuthenticator::new("https://evil.example.com");
+        assert!(matches!(
+            register(&rp(), &mut authenticator, Attestation::None),
+            Err(MyError::Unauthorized)
+        ));
+    }
+
+    #[test]
+    fn test_tampered_attestation() {
+        let rp = rp();
+        let user = user();
+        let ceremony =
+            Ceremony::start(CeremonyKind::Registration, Some(user.email.clone())).unwrap();
+        let options = serde_json::to_value(rp.creation_options(&ceremony, &user).unwrap()).unwrap();
+        let mut authenticator = SoftwareAuthenticator::new(ORIGIN);
+        let credential = authenticator.create(&options, Attestation::PackedSelf);
+        let mut attestation_object = decode(&credential, "attestationObject");
+        // The last byte belongs to the y coordinate of the credential key.
+        *attestation_object.last_mut().unwrap() ^= 0x01;
+        assert!(rp
+            .register(
+                &ceremony,
+                &decode(&credential, "clientDataJSON"),
+                &attestation_object,
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn test_authentication_and_sign_count() {
+        let rp = rp();
+        let mut authenticator = SoftwareAuthenticator::new(ORIGIN);
+        let mut passkey = register(&rp, &mut authenticator, Attestation::None).unwrap();
+
+        passkey.sign_count = authenticate(&rp, &mut authenticator, &passkey, None).unwrap();
+        assert_eq!(passkey.sign_count, 1);
+        passkey.sign_count = authenticate(&rp, &mut authenticator, &passkey, None).unwrap();
+        assert_eq!(passkey.sign_count, 2);
+
+        // A clone still at the old counter.
+        authenticator.sign_count = 0;
+        assert!(matches!(
+            authenticate(&rp, &mut authenticator, &passkey, None),
+            Err(MyError::Unauthorized)
+        ));
+    }
+
+    #[test]
+    fn test_user_verification() {
+        let rp = rp();
+        let mut authenticator = SoftwareAuthenticator::new(ORIGIN);
+        let passkey = register(&rp, &mut authenticator, Attestation::None).unwrap();
+        authenticator.user_verification = false;
+
+        // Alone a passkey has to verify the user, after a password it only has to be present.
+        assert!(authenticate(&rp, &mut authenticator, &passkey, None).is_err());
+        let email = MailAddress::of("kamino@example.com").unwrap();
+        assert!(authenticate(&rp, &mut authenticator, &passkey, Some(email)).is_ok());
+    }
+
+    #[test]
+    fn test_signature_of_other_authenticator() {
+        let rp = rp();
+        let mut authenticator = SoftwareAuthenticator::new(ORIGIN);
+        let passkey = register(&rp, &mut authenticator, Attestation::None).unwrap();
+        let mut other = SoftwareAuthenticator::new(ORIGIN);
+        assert!(matches!(
+            authenticate(&rp, &mut other, &passkey, None),
+            Err(MyError::Unauthorized)
+        ));
+    }
+}
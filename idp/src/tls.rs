@@ -0,0 +1,158 @@
+//! TLS termination with certificates reloaded from disk.
+
+use std::{
+    env, fmt, fs, io,
+    path::PathBuf,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+use rustls::{
+    crypto::ring,
+    server::{ClientHello, ResolvesServerCert},
+    sign::CertifiedKey,
+    ServerConfig,
+};
+use rustls_pki_types::{pem::PemObject, CertificateDer, PrivateKeyDer};
+
+const CERT_FILE_ENV: &str = "IDP_TLS_CERT_FILE";
+const KEY_FILE_ENV: &str = "IDP_TLS_KEY_FILE";
+const RELOAD_INTERVAL_ENV: &str = "IDP_TLS_RELOAD_INTERVAL_SECS";
+
+const DEFAULT_RELOAD_INTERVAL: Duration = Duration::from_secs(60);
+
+/// `Strict-Transport-Security` sent on every response once HTTPS is on.
+pub const HSTS_HEADER_VALUE: &str = "max-age=31536000; includeSubDomains";
+
+/// Where the PEM files live and how often they are checked for rotation.
+#[derive(Clone, Debug)]
+pub struct TlsConfig {
+    pub cert_file: PathBuf,
+    pub key_file: PathBuf,
+    pub reload_interval: Duration,
+}
+
+impl TlsConfig {
+    /// HTTPS is enabled only when both `IDP_TLS_CERT_FILE` and `IDP_TLS_KEY_FILE` are set.
+    pub fn from_env() -> Option<Self> {
+        let cert_file = env::var_os(CERT_FILE_ENV)?;
+        let key_file = env::var_os(KEY_FILE_ENV)?;
+        let reload_interval = env::var(RELOAD_INTERVAL_ENV)
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .map_or(DEFAULT_RELOAD_INTERVAL, Duration::from_secs);
+        Some(Self {
+            cert_file: cert_file.into(),
+            key_file: key_file.into(),
+            reload_interval,
+        })
+    }
+}
+
+struct Loaded {
+    pem: (Vec<u8>, Vec<u8>),
+    key: Arc<CertifiedKey>,
+}
+
+/// Hands out the current certificate and swaps it when the files change.
+pub struct ReloadingCertResolver {
+    config: TlsConfig,
+    loaded: RwLock<Loaded>,
+}
+
+impl ReloadingCertResolver {
+    pub fn load(config: TlsConfig) -> io::Result<Self> {
+        let pem = read_pem(&config)?;
+        let key = certified_key(&pem)?;
+        Ok(Self {
+            config,
+            loaded: RwLock::new(Loaded { pem, key }),
+        })
+    }
+
+    /// Re-reads the files and returns whether a new certificate was installed.
+    /// A broken rotation keeps the previous certificate in service.
+    pub fn reload_if_changed(&self) -> io::Result<bool> {
+        let pem = read_pem(&self.config)?;
+        if self.loaded.read().is_ok_and(|loaded| loaded.pem == pem) {
+            return Ok(false);
+        }
+        let key = certified_key(&pem)?;
+        let mut loaded = self
+            .loaded
+            .write()
+            .map_err(|_| io::Error::other("certificate lock poisoned"))?;
+        *loaded = Loaded { pem, key };
+        Ok(true)
+    }
+
+    pub fn current(&self) -> Option<Arc<CertifiedKey>> {
+        self.loaded.read().ok().map(|loaded| loaded.key.clone())
+    }
+
+    /// Polls the files every `reload_interval` on the actix runtime.
+    pub fn spawn_reloader(self: Arc<Self>) {
+        let interval = self.config.reload_interval;
+        actix_web::rt::spawn(async move {
+            let mut ticks = actix_web::rt::time::interval(interval);
+            ticks.tick().await;
+            loop {
+                ticks.tick().await;
+                match self.reload_if_changed() {
+                    Ok(true) => tracing::info!("reloaded rotated TLS certificate"),
+                    Ok(false) => {}
+                    Err(err) => {
+                        tracing::warn!(error = %err, "keeping current TLS certificate")
+                    }
+                }
+            }
+        });
+    }
+}
+
+impl ResolvesServerCert for ReloadingCertResolver {
+    fn resolve(&self, _client_hello: ClientHello<'_>) -> Option<Arc<CertifiedKey>> {
+        self.current()
+    }
+}
+
+impl fmt::Debug for ReloadingCertResolver {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ReloadingCertResolver")
+            .field("config", &self.config)
+            .finish()
+    }
+}
+
+/// rustls configuration serving whatever the resolver currently holds.
+pub fn server_config(resolver: Arc<ReloadingCertResolver>) -> io::Result<ServerConfig> {
+    let mut config = ServerConfig::builder_with_provider(Arc::new(ring::default_provider()))
+        .with_safe_default_protocol_versions()
+        .map_err(io::Error::other)?
+        .with_no_client_auth()
+        .with_cert_resolver(resolver);
+    config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+    Ok(config)
+}
+
+fn read_pem(config: &TlsConfig) -> io::Result<(Vec<u8>, Vec<u8>)> {
+    Ok((fs::read(&config.cert_file)?, fs::read(&config.key_file)?))
+}
+
+fn certified_key((cert_pem, key_pem): &(Vec<u8>, Vec<u8>)) -> io::Result<Arc<CertifiedKey>> {
+    let certs = CertificateDer::pem_slice_iter(cert_pem)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(invalid_data)?;
+    if certs.is_empty() {
+        return Err(invalid_data("no certificate found"));
+    }
+    let key = PrivateKeyDer::from_pem_slice(key_pem).map_err(invalid_data)?;
+    let signing_key = ring::sign::any_supported_type(&key).map_err(invalid_data)?;
+    let certified = CertifiedKey::new(certs, signing_key);
+    certified.keys_match().map_err(invalid_data)?;
+    Ok(Arc::new(certified))
+}
+
+fn invalid_data<E: fmt::Display>(err: E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, err.to_string())
+}
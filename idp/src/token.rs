@@ -1 +1,6 @@
+pub mod claim_mapping;
+pub mod dpop;
+pub mod jwe;
 pub mod jwt;
+pub mod opaque_token;
+pub mod signing_key;
@@ -0,0 +1,116 @@
+//! Custom claims that clients and audiences map from user attributes.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::entity::user::User;
+
+/// Claims the idp sets itself, which no mapper may override.
+pub const RESERVED_CLAIMS: &[&str] = &[
+    "iss",
+    "sub",
+    "aud",
+    "exp",
+    "nbf",
+    "iat",
+    "jti",
+    "azp",
+    "scope",
+    "act",
+    "cnf",
+    "sid",
+    "events",
+    "groups",
+    "groups_overage",
+    "nonce",
+];
+
+/// Declares one custom claim of the issued token.
+#[derive(Clone, PartialEq, Eq, Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum ClaimMapper {
+    /// Copies an attribute of the user, `email` or one of `attributes`.
+    UserAttribute { claim: String, attribute: String },
+    /// The same JSON value in every token.
+    Static { claim: String, value: Value },
+    /// A string with `${attribute}` placeholders, such as `${email}`.
+    Template { claim: String, template: String },
+}
+
+impl ClaimMapper {
+    pub fn claim(&self) -> &str {
+        match self {
+            ClaimMapper::UserAttribute { claim, .. }
+            | ClaimMapper::Static { claim, .. }
+            | ClaimMapper::Template { claim, .. } => claim,
+        }
+    }
+
+    /// The claim value for `user`, none when an attribute it reads is missing.
+    pub fn value_for(&self, user: &User) -> Option<Value> {
+        match self {
+            ClaimMapper::UserAttribute { attribute, .. } => {
+                attribute_of(user, attribute).map(Value::String)
+            }
+            ClaimMapper::Static { value, .. } => Some(value.clone()),
+            ClaimMapper::Template { template, .. } => {
+                render(template, |name| attribute_of(user, name)).map(Value::String)
+            }
+        }
+    }
+}
+
+/// Rejects mappers that set a reserved claim, set a claim twice or have a
+/// template that does not parse, with the reason.
+pub fn check_mappers(mappers: &[ClaimMapper]) -> Result<(), String> {
+    let mut claims = BTreeSet::new();
+    for mapper in mappers {
+        let claim = mapper.claim();
+        if claim.is_empty() || RESERVED_CLAIMS.contains(&claim) {
+            return Err(format!("claim {:?} is reserved", claim));
+        }
+        if !claims.insert(claim) {
+            return Err(format!("claim {:?} is mapped twice", claim));
+        }
+        if let ClaimMapper::Template { template, .. } = mapper {
+            if render(template, |_| Some(String::new())).is_none() {
+                return Err(format!("template of claim {:?} is malformed", claim));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Custom claims for `user`, in the order of `mappers`.
+pub fn map_claims(mappers: &[ClaimMapper], user: &User) -> BTreeMap<String, Value> {
+    mappers
+        .iter()
+        .filter_map(|mapper| Some((mapper.claim().to_owned(), mapper.value_for(user)?)))
+        .collect()
+}
+
+fn attribute_of(user: &User, name: &str) -> Option<String> {
+    match name {
+        "email" => Some(String::from(user.email.clone())),
+        _ => user.attributes.get(name).cloned(),
+    }
+}
+
+// Substitutes every `${name}`, none on an unclosed or empty placeholder.
+fn render<F: Fn(&str) -> Option<String>>(template: &str, lookup: F) -> Option<String> {
+    let mut rendered = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find("${") {
+        rendered.push_str(&rest[..start]);
+        let (name, after) = rest[start + 2..].split_once('}')?;
+        if name.is_empty() {
+            return None;
+        }
+        rendered.push_str(&lookup(name)?);
+        rest = after;
+    }
+    rendered.push_str(rest);
+    Some(rendered)
+}
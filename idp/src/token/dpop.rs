@@ -0,0 +1,158 @@
+//! DPoP proofs.
+//!
+//! RFC 9449. A client proves with a JWT signed by its own key that it holds
+//! the key, on every request. Tokens issued under a proof carry the key's
+//! thumbprint in `cnf.jkt` and are only accepted with a proof by that key.
+
+use std::collections::HashSet;
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::Utc;
+use jsonwebtoken::{decode, Algorithm, DecodingKey, Validation};
+use ring::digest::{digest, SHA256};
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::error::my_error::{self, MyError};
+
+/// Request header that carries the proof.
+pub const DPOP_HEADER: &str = "DPoP";
+/// `token_type` of bound tokens, and the authorization scheme they are sent with.
+pub const DPOP_TOKEN_TYPE: &str = "DPoP";
+/// How far the `iat` of a proof may be from now, either way.
+pub const PROOF_WINDOW_SECS: i64 = 60;
+
+const PROOF_TYPE: &str = "dpop+jwt";
+// Members only private JWKs have.
+const PRIVATE_MEMBERS: [&str; 6] = ["d", "p", "q", "dp", "dq", "qi"];
+
+#[derive(Deserialize, Debug)]
+struct ProofClaims {
+    jti: String,
+    htm: String,
+    htu: String,
+    iat: i64,
+    ath: Option<String>,
+}
+
+/// What a valid proof tells.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct DpopProof {
+    /// Thumbprint of the key that signed the proof, RFC 7638.
+    pub jkt: String,
+    pub jti: String,
+    pub iat: i64,
+}
+
+/// Checks a proof for a request with method `htm` to `htu`. At resources it
+/// is sent with the access token, whose hash it has to carry in `ath`.
+#[tracing::instrument(skip_all)]
+pub fn verify_proof(
+    proof: &str,
+    htm: &str,
+    htu: &str,
+    access_token: Option<&str>,
+) -> my_error::Result<DpopProof> {
+    let header = proof.split('.').next().ok_or(MyError::Decode)?;
+    let header: Value = URL_SAFE_NO_PAD
+        .decode(header)
+        .ok()
+        .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+        .ok_or(MyError::Decode)?;
+    if header["typ"] != PROOF_TYPE {
+        tracing::debug!("Proof type is invalid");
+        return Err(MyError::Decode);
+    }
+    let jwk = &header["jwk"];
+    let (algorithm, decoding_key) = proof_key(header["alg"].as_str(), jwk)?;
+    let mut validation = Validation::new(algorithm);
+    validation.required_spec_claims = HashSet::new();
+    validation.validate_exp = false;
+    let claims = decode::<ProofClaims>(proof, &decoding_key, &validation)
+        .map_err(|err| {
+            tracing::debug!(error = %err, "Proof is rejected");
+            MyError::Decode
+        })?
+        .claims;
+
+    if claims.htm != htm || without_query(&claims.htu) != without_query(htu) {
+        tracing::debug!("Proof is for another request");
+        return Err(MyError::Decode);
+    }
+    if (Utc::now().timestamp() - claims.iat).abs() > PROOF_WINDOW_SECS {
+        tracing::debug!("Proof is stale");
+        return Err(MyError::Decode);
+    }
+    if claims.ath != access_token.map(access_token_hash) {
+        tracing::debug!("Proof is for another access token");
+        return Err(MyError::Decode);
+    }
+    Ok(DpopProof {
+        jkt: thumbprint(jwk).ok_or(MyError::Decode)?,
+        jti: claims.jti,
+        iat: claims.iat,
+    })
+}
+
+/// RFC 7638 thumbprint of a public P-256 or RSA JWK: SHA-256 of its required
+/// members in lexicographic order, base64url.
+pub fn thumbprint(jwk: &Value) -> Option<String> {
+    let member = |name: &str| jwk.get(name).and_then(Value::as_str).map(json_string);
+    let canonical = match jwk.get("kty").and_then(Value::as_str)? {
+        "EC" => format!(
+            r#"{{"crv":{},"kty":"EC","x":{},"y":{}}}"#,
+            member("crv")?,
+            member("x")?,
+            member("y")?
+        ),
+        "RSA" => format!(
+            r#"{{"e":{},"kty":"RSA","n":{}}}"#,
+            member("e")?,
+            member("n")?
+        ),
+        _ => return None,
+    };
+    Some(URL_SAFE_NO_PAD.encode(digest(&SHA256, canonical.as_bytes())))
+}
+
+/// Value of `ath`, base64url of the SHA-256 of the token.
+pub fn access_token_hash(access_token: &str) -> String {
+    URL_SAFE_NO_PAD.encode(digest(&SHA256, access_token.as_bytes()))
+}
+
+// ES256 over P-256, or RS256 and PS256, with the public key in the header.
+fn proof_key(alg: Option<&str>, jwk: &Value) -> my_error::Result<(Algorithm, DecodingKey)> {
+    if PRIVATE_MEMBERS.iter().any(|name| jwk.get(name).is_some()) {
+        tracing::debug!("Proof carries a private key");
+        return Err(MyError::Decode);
+    }
+    let member = |name: &str| jwk.get(name).and_then(Value::as_str).ok_or(MyError::Decode);
+    let (algorithm, decoding_key) = match (alg, member("kty")?) {
+        (Some("ES256"), "EC") if member("crv")? == "P-256" => (
+            Algorithm::ES256,
+            DecodingKey::from_ec_components(member("x")?, member("y")?),
+        ),
+        (Some("RS256"), "RSA") => (
+            Algorithm::RS256,
+            DecodingKey::from_rsa_components(member("n")?, member("e")?),
+        ),
+        (Some("PS256"), "RSA") => (
+            Algorithm::PS256,
+            DecodingKey::from_rsa_components(member("n")?, member("e")?),
+        ),
+        _ => {
+            tracing::debug!("Proof algorithm is not supported");
+            return Err(MyError::Decode);
+        }
+    };
+    Ok((algorithm, decoding_key.map_err(|_| MyError::Decode)?))
+}
+
+// `htu` is compared without query and fragment, RFC 9449 section 4.3.
+fn without_query(uri: &str) -> &str {
+    uri.split(['?', '#']).next().unwrap_or_default()
+}
+
+fn json_string(value: &str) -> String {
+    Value::String(value.to_owned()).to_string()
+}
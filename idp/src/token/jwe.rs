@@ -0,0 +1,278 @@
+//! Encrypted tokens.
+//!
+//! A signed JWT nested in a JWE, RFC 7516 compact serialization, so that only
+//! the client holding the private key reads its claims. The content is
+//! encrypted with A256GCM under a key wrapped with RSA-OAEP or agreed with
+//! ECDH-ES on P-256.
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use p256::{
+    ecdh::{diffie_hellman, EphemeralSecret},
+    elliptic_curve::sec1::ToEncodedPoint,
+};
+use ring::{
+    aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM},
+    digest::{digest, SHA256},
+    rand::{SecureRandom, SystemRandom},
+};
+use rsa::{rand_core::OsRng, traits::PublicKeyParts, BigUint, Oaep, RsaPrivateKey, RsaPublicKey};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha1::Sha1;
+
+use crate::error::my_error::{self, MyError};
+
+const RSA_OAEP: &str = "RSA-OAEP";
+const ECDH_ES: &str = "ECDH-ES";
+const A256GCM: &str = "A256GCM";
+const CEK_BYTES: usize = 32;
+const IV_BYTES: usize = 12;
+const TAG_BYTES: usize = 16;
+const MIN_RSA_BITS: usize = 2048;
+
+/// Public key a client has its tokens encrypted to.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct EncryptionKey {
+    /// Named in the JWE header, so that the client picks the private key.
+    pub kid: Option<String>,
+    key: PublicKey,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+enum PublicKey {
+    Rsa(RsaPublicKey),
+    P256(p256::PublicKey),
+}
+
+/// Private key that opens the tokens encrypted to its public half.
+#[derive(Clone, Debug)]
+pub enum DecryptionKey {
+    Rsa(Box<RsaPrivateKey>),
+    P256(p256::SecretKey),
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct Header {
+    alg: String,
+    enc: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cty: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    kid: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    epk: Option<EcJwk>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct EcJwk {
+    kty: String,
+    crv: String,
+    x: String,
+    y: String,
+}
+
+impl EncryptionKey {
+    /// Reads a public RSA or P-256 JWK, whose `alg` is `RSA-OAEP` or `ECDH-ES`
+    /// when given.
+    pub fn from_jwk(jwk: &Value) -> Result<Self, String> {
+        let member = |name: &str| jwk.get(name).and_then(Value::as_str);
+        if jwk.get("d").is_some() {
+            return Err("the encryption key holds private key material".to_owned());
+        }
+        if member("use").is_some_and(|key_use| key_use != "enc") {
+            return Err("the encryption key is not for encryption".to_owned());
+        }
+        let key = match (member("kty"), member("alg")) {
+            (Some("RSA"), None | Some(RSA_OAEP)) => {
+                let n = biguint(member("n"))?;
+                let e = biguint(member("e"))?;
+                let key = RsaPublicKey::new(n, e).map_err(|err| err.to_string())?;
+                if key.size() * 8 < MIN_RSA_BITS {
+                    return Err(format!("RSA keys have at least {} bits", MIN_RSA_BITS));
+                }
+                PublicKey::Rsa(key)
+            }
+            (Some("EC"), None | Some(ECDH_ES)) if member("crv") == Some("P-256") => {
+                PublicKey::P256(p256_public_key(member("x"), member("y"))?)
+            }
+            _ => return Err("only RSA-OAEP and ECDH-ES on P-256 keys are supported".to_owned()),
+        };
+        Ok(Self {
+            kid: member("kid").map(str::to_owned),
+            key,
+        })
+    }
+
+    pub fn of(key: &DecryptionKey) -> Self {
+        let key = match key {
+            DecryptionKey::Rsa(key) => PublicKey::Rsa(key.to_public_key()),
+            DecryptionKey::P256(key) => PublicKey::P256(key.public_key()),
+        };
+        Self { kid: None, key }
+    }
+
+    pub fn with_kid<T: Into<String>>(mut self, kid: T) -> Self {
+        self.kid = Some(kid.into());
+        self
+    }
+}
+
+/// Wraps a signed JWT into a JWE for the holder of `key`.
+#[tracing::instrument(skip_all)]
+pub fn encrypt(jwt: &str, key: &EncryptionKey) -> my_error::Result<String> {
+    let (alg, cek, encrypted_key, epk) = match &key.key {
+        PublicKey::Rsa(public_key) => {
+            let cek = random_bytes::<CEK_BYTES>()?;
+            let encrypted_key = public_key
+                .encrypt(&mut OsRng, Oaep::new::<Sha1>(), &cek)
+                .map_err(|_| MyError::Encode)?;
+            (RSA_OAEP, cek, encrypted_key, None)
+        }
+        PublicKey::P256(public_key) => {
+            let ephemeral = EphemeralSecret::random(&mut OsRng);
+            let shared = ephemeral.diffie_hellman(public_key);
+            let cek = concat_kdf(shared.raw_secret_bytes());
+            let epk = ec_jwk(&ephemeral.public_key());
+            (ECDH_ES, cek, vec![], Some(epk))
+        }
+    };
+    let header = Header {
+        alg: alg.to_owned(),
+        enc: A256GCM.to_owned(),
+        cty: Some("JWT".to_owned()),
+        kid: key.kid.clone(),
+        epk,
+    };
+    let header = URL_SAFE_NO_PAD.encode(serde_json::to_vec(&header).map_err(|_| MyError::Encode)?);
+    let iv = random_bytes::<IV_BYTES>()?;
+    let mut content = jwt.as_bytes().to_vec();
+    let tag = aead_key(&cek)?
+        .seal_in_place_separate_tag(
+            Nonce::assume_unique_for_key(iv),
+            Aad::from(header.as_bytes()),
+            &mut content,
+        )
+        .map_err(|_| MyError::Encode)?;
+    Ok([
+        header,
+        URL_SAFE_NO_PAD.encode(encrypted_key),
+        URL_SAFE_NO_PAD.encode(iv),
+        URL_SAFE_NO_PAD.encode(content),
+        URL_SAFE_NO_PAD.encode(tag.as_ref()),
+    ]
+    .join("."))
+}
+
+/// The signed JWT inside a JWE, still to be verified.
+#[tracing::instrument(skip_all)]
+pub fn decrypt(jwe: &str, key: &DecryptionKey) -> my_error::Result<String> {
+    let parts: Vec<&str> = jwe.split('.').collect();
+    let [header_part, encrypted_key, iv, content, tag] = parts[..] else {
+        return Err(MyError::Decode);
+    };
+    let decode = |part: &str| URL_SAFE_NO_PAD.decode(part).map_err(|_| MyError::Decode);
+    let header: Header =
+        serde_json::from_slice(&decode(header_part)?).map_err(|_| MyError::Decode)?;
+    if header.enc != A256GCM {
+        return Err(MyError::Decode);
+    }
+    let cek = match (header.alg.as_str(), key) {
+        (RSA_OAEP, DecryptionKey::Rsa(private_key)) => {
+            let cek = private_key
+                .decrypt(Oaep::new::<Sha1>(), &decode(encrypted_key)?)
+                .map_err(|_| MyError::Decode)?;
+            <[u8; CEK_BYTES]>::try_from(cek).map_err(|_| MyError::Decode)?
+        }
+        (ECDH_ES, DecryptionKey::P256(secret_key)) if encrypted_key.is_empty() => {
+            let epk = header.epk.as_ref().ok_or(MyError::Decode)?;
+            if epk.kty != "EC" || epk.crv != "P-256" {
+                return Err(MyError::Decode);
+            }
+            let ephemeral =
+                p256_public_key(Some(&epk.x), Some(&epk.y)).map_err(|_| MyError::Decode)?;
+            let shared = diffie_hellman(secret_key.to_nonzero_scalar(), ephemeral.as_affine());
+            concat_kdf(shared.raw_secret_bytes())
+        }
+        _ => return Err(MyError::Decode),
+    };
+    let iv = <[u8; IV_BYTES]>::try_from(decode(iv)?).map_err(|_| MyError::Decode)?;
+    let tag = decode(tag)?;
+    if tag.len() != TAG_BYTES {
+        return Err(MyError::Decode);
+    }
+    let mut sealed = decode(content)?;
+    sealed.extend_from_slice(&tag);
+    let opened = aead_key(&cek)?
+        .open_in_place(
+            Nonce::assume_unique_for_key(iv),
+            Aad::from(header_part.as_bytes()),
+            &mut sealed,
+        )
+        .map_err(|_| MyError::Decode)?;
+    String::from_utf8(opened.to_vec()).map_err(|_| MyError::Decode)
+}
+
+/// Compact JWEs have five parts, signed JWTs three.
+pub fn is_jwe(token: &str) -> bool {
+    token.split('.').count() == 5
+}
+
+// Concat KDF of RFC 7518 4.6.2 for direct key agreement: one SHA-256 round
+// gives the 256 bits A256GCM needs, no party info.
+fn concat_kdf(shared_secret: &[u8]) -> [u8; CEK_BYTES] {
+    let mut input = vec![0, 0, 0, 1];
+    input.extend_from_slice(shared_secret);
+    input.extend_from_slice(&(A256GCM.len() as u32).to_be_bytes());
+    input.extend_from_slice(A256GCM.as_bytes());
+    input.extend_from_slice(&0u32.to_be_bytes()); // apu
+    input.extend_from_slice(&0u32.to_be_bytes()); // apv
+    input.extend_from_slice(&((CEK_BYTES * 8) as u32).to_be_bytes());
+    let mut cek = [0u8; CEK_BYTES];
+    cek.copy_from_slice(digest(&SHA256, &input).as_ref());
+    cek
+}
+
+fn aead_key(cek: &[u8]) -> my_error::Result<LessSafeKey> {
+    UnboundKey::new(&AES_256_GCM, cek)
+        .map(LessSafeKey::new)
+        .map_err(|_| MyError::Internal)
+}
+
+fn random_bytes<const N: usize>() -> my_error::Result<[u8; N]> {
+    let mut bytes = [0u8; N];
+    SystemRandom::new()
+        .fill(&mut bytes)
+        .map_err(|_| MyError::Internal)?;
+    Ok(bytes)
+}
+
+fn biguint(member: Option<&str>) -> Result<BigUint, String> {
+    let bytes = URL_SAFE_NO_PAD
+        .decode(member.ok_or("the RSA key misses n or e")?)
+        .map_err(|_| "n and e of the RSA key are base64url")?;
+    Ok(BigUint::from_bytes_be(&bytes))
+}
+
+fn p256_public_key(x: Option<&str>, y: Option<&str>) -> Result<p256::PublicKey, String> {
+    let coordinate = |value: Option<&str>| {
+        URL_SAFE_NO_PAD
+            .decode(value.ok_or("the EC key misses x or y")?)
+            .ok()
+            .filter(|bytes| bytes.len() == 32)
+            .ok_or("x and y of the EC key are 32 bytes, base64url")
+    };
+    let mut point = vec![0x04];
+    point.extend(coordinate(x)?);
+    point.extend(coordinate(y)?);
+    p256::PublicKey::from_sec1_bytes(&point).map_err(|_| "the EC key is not on P-256".to_owned())
+}
+
+fn ec_jwk(public_key: &p256::PublicKey) -> EcJwk {
+    let point = public_key.to_encoded_point(false);
+    EcJwk {
+        kty: "EC".to_owned(),
+        crv: "P-256".to_owned(),
+        x: URL_SAFE_NO_PAD.encode(point.x().map_or(&[][..], |x| x.as_slice())),
+        y: URL_SAFE_NO_PAD.encode(point.y().map_or(&[][..], |y| y.as_slice())),
+    }
+}
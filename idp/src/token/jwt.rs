@@ -1,34 +1,222 @@
+use std::collections::{BTreeMap, BTreeSet};
+
 use chrono::{Duration, Utc};
 use jsonwebtoken::{
-    decode, encode, errors::ErrorKind, Algorithm, DecodingKey, EncodingKey, Header, Validation,
+    decode, decode_header, encode, errors::ErrorKind, Algorithm, DecodingKey, EncodingKey, Header,
+    Validation,
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+use utoipa::ToSchema;
+
+use crate::{
+    domain::mail_address::MailAddress,
+    error::my_error,
+    token::{
+        jwe::{self, DecryptionKey, EncryptionKey},
+        signing_key::SigningKeys,
+    },
 };
-use serde::{Deserialize, Serialize};
 
-use crate::{domain::mail_address::MailAddress, error::my_error};
+/// Lifetime of issued tokens.
+pub const TOKEN_LIFETIME_HOURS: i64 = 8;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct Claims {
-    iss: String, // Issuer , this idp itself.
+    iss: String, // Issuer , the realm of this idp.
     aud: String, // Audience, idp user.
     sub: String, // User identifier.
     iat: i64,    // Timing of issue
     exp: i64,    // expiration time
+    #[serde(skip_serializing_if = "Option::is_none")]
+    azp: Option<String>, // Authorized party, the client the token was issued to.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    scope: Option<String>, // Space separated scopes of an exchanged token.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    act: Option<Actor>, // The client acting for the subject, on exchanged tokens.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sid: Option<String>, // Session the token was issued in, it ends with it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cnf: Option<Confirmation>, // Key the token is bound to with DPoP.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    groups: Option<Vec<String>>, // Groups of the subject, nested ones expanded.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    groups_overage: bool, // More groups than the client takes, none are listed.
+    #[serde(flatten)]
+    custom: BTreeMap<String, Value>, // Mapped for the client or audience.
 }
 
-pub fn make_jwt(secret: &str, aud: &MailAddress) -> my_error::Result<String> {
-    let header = Header::new(Algorithm::HS256);
-    let now = Utc::now();
-    let iat = now.timestamp();
-    let exp = (now + Duration::hours(8)).timestamp();
-    let my_claims = Claims {
-        iss: "example_system".to_owned(),
-        aud: String::from(aud.clone()),
-        sub: String::from(aud.clone()),
-        iat,
-        exp,
-    };
-    let encoding_key = EncodingKey::from_secret(secret.as_ref());
-    let token = match encode(&header, &my_claims, &encoding_key) {
+/// RFC 8693 section 4.1, nested once per exchange along a chain of services.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct Actor {
+    pub sub: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(no_recursion)]
+    pub act: Option<Box<Actor>>,
+}
+
+/// RFC 9449 section 6, the thumbprint of the key a DPoP bound token needs
+/// proofs by.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct Confirmation {
+    pub jkt: String,
+}
+
+impl Claims {
+    /// Claims of a token for the user, issued to the client `azp` if any.
+    pub fn of(issuer: &str, aud: &MailAddress, azp: Option<&str>) -> Self {
+        let now = Utc::now();
+        Self {
+            iss: issuer.to_owned(),
+            aud: String::from(aud.clone()),
+            sub: String::from(aud.clone()),
+            iat: now.timestamp(),
+            exp: (now + Duration::hours(TOKEN_LIFETIME_HOURS)).timestamp(),
+            azp: azp.map(str::to_owned),
+            scope: None,
+            act: None,
+            sid: None,
+            cnf: None,
+            groups: None,
+            groups_overage: false,
+            custom: BTreeMap::new(),
+        }
+    }
+
+    /// Space separated, none when empty.
+    pub fn with_scopes(mut self, scopes: &[String]) -> Self {
+        self.scope = (!scopes.is_empty()).then(|| scopes.join(" "));
+        self
+    }
+
+    /// Issued in the session, so that logging out revokes it.
+    pub fn with_session_id(mut self, session_id: Option<&str>) -> Self {
+        self.sid = session_id.map(str::to_owned);
+        self
+    }
+
+    /// Bound to the key with the thumbprint, if any.
+    pub fn with_confirmation(mut self, jkt: Option<&str>) -> Self {
+        self.cnf = jkt.map(|jkt| Confirmation {
+            jkt: jkt.to_owned(),
+        });
+        self
+    }
+
+    /// Lists the groups, by name, unless they are more than `limit`. Then
+    /// none are and `groups_overage` tells so. A limit of 0 leaves both out.
+    pub fn with_groups(mut self, groups: BTreeSet<String>, limit: usize) -> Self {
+        self.groups = None;
+        self.groups_overage = false;
+        if limit == 0 || groups.is_empty() {
+            return self;
+        }
+        if groups.len() > limit {
+            self.groups_overage = true;
+        } else {
+            self.groups = Some(groups.into_iter().collect());
+        }
+        self
+    }
+
+    /// Mappers never name reserved claims, so these cannot clash with them.
+    pub fn with_custom_claims(mut self, custom: BTreeMap<String, Value>) -> Self {
+        self.custom = custom;
+        self
+    }
+
+    /// Claims of a token for `audience` that speaks for the subject of
+    /// `subject` and records `actor` as acting for it. It never outlives
+    /// the subject token.
+    pub fn exchanged(subject: &Claims, audience: &str, scope: Option<String>, actor: &str) -> Self {
+        let now = Utc::now();
+        Self {
+            iss: subject.iss.clone(),
+            aud: audience.to_owned(),
+            sub: subject.sub.clone(),
+            iat: now.timestamp(),
+            exp: subject
+                .exp
+                .min((now + Duration::hours(TOKEN_LIFETIME_HOURS)).timestamp()),
+            azp: Some(actor.to_owned()),
+            scope,
+            act: Some(Actor {
+                sub: actor.to_owned(),
+                act: subject.act.clone().map(Box::new),
+            }),
+            sid: subject.sid.clone(),
+            cnf: None,
+            groups: None,
+            groups_overage: false,
+            custom: BTreeMap::new(),
+        }
+    }
+
+    pub fn subject(&self) -> &str {
+        &self.sub
+    }
+
+    pub fn authorized_party(&self) -> Option<&str> {
+        self.azp.as_deref()
+    }
+
+    pub fn session_id(&self) -> Option<&str> {
+        self.sid.as_deref()
+    }
+
+    /// Thumbprint of the key the token is bound to.
+    pub fn confirmation(&self) -> Option<&str> {
+        self.cnf.as_ref().map(|cnf| cnf.jkt.as_str())
+    }
+
+    pub fn groups(&self) -> Option<&[String]> {
+        self.groups.as_deref()
+    }
+
+    pub fn scopes(&self) -> Option<Vec<&str>> {
+        self.scope
+            .as_deref()
+            .map(|scope| scope.split_whitespace().collect())
+    }
+
+    /// Seconds left until the token expires.
+    pub fn expires_in(&self) -> i64 {
+        self.exp - Utc::now().timestamp()
+    }
+}
+
+/// A signed token, wrapped in a JWE when `encryption_key` is given.
+#[tracing::instrument(skip_all)]
+pub fn make_jwt(
+    keys: &SigningKeys,
+    issuer: &str,
+    aud: &MailAddress,
+    azp: Option<&str>,
+    encryption_key: Option<&EncryptionKey>,
+) -> my_error::Result<String> {
+    let token = encode_claims(keys, &Claims::of(issuer, aud, azp))?;
+    encrypt_for(token, encryption_key)
+}
+
+/// Signs, then encrypts the signed token to the key if any.
+pub fn encrypt_for(
+    token: String,
+    encryption_key: Option<&EncryptionKey>,
+) -> my_error::Result<String> {
+    match encryption_key {
+        Some(key) => jwe::encrypt(&token, key),
+        None => Ok(token),
+    }
+}
+
+/// Signs with the current key, named in the `kid` header.
+#[tracing::instrument(skip_all)]
+pub fn encode_claims<C: Serialize>(keys: &SigningKeys, claims: &C) -> my_error::Result<String> {
+    let key = keys.current();
+    let mut header = Header::new(Algorithm::HS256);
+    header.kid = Some(key.kid.clone());
+    let encoding_key = EncodingKey::from_secret(key.secret().as_ref());
+    let token = match encode(&header, claims, &encoding_key) {
         Ok(t) => t,
         Err(_) => return Err(my_error::MyError::Encode),
     };
@@ -36,18 +224,117 @@ pub fn make_jwt(secret: &str, aud: &MailAddress) -> my_error::Result<String> {
     Ok(token)
 }
 
-pub fn decode_jwt(secret: &str, token: &str, aud: &MailAddress) -> my_error::Result<Claims> {
+#[tracing::instrument(skip_all)]
+pub fn decode_jwt(
+    keys: &SigningKeys,
+    issuer: &str,
+    token: &str,
+    aud: &MailAddress,
+) -> my_error::Result<Claims> {
     let mut validation = Validation::new(Algorithm::HS256);
-    let decode_key = DecodingKey::from_secret(secret.as_ref());
+    validation.set_issuer(&[issuer]);
     validation.sub = Some(String::from(aud.clone()));
     validation.set_audience(&[String::from(aud.clone())]);
-    let token_data = match decode::<Claims>(token, &decode_key, &validation) {
+    decode_claims(keys, token, &validation)
+}
+
+/// Decrypts a token `make_jwt` encrypted, then validates the signed token
+/// inside as `decode_jwt` does.
+#[tracing::instrument(skip_all)]
+pub fn decode_encrypted_jwt(
+    keys: &SigningKeys,
+    issuer: &str,
+    token: &str,
+    aud: &MailAddress,
+    decryption_key: &DecryptionKey,
+) -> my_error::Result<Claims> {
+    let signed = jwe::decrypt(token, decryption_key).inspect_err(|_| {
+        tracing::debug!("Token does not decrypt");
+    })?;
+    decode_jwt(keys, issuer, &signed, aud)
+}
+
+/// A token handed in for exchange. It was issued to its user, or exchanged
+/// to `actor` before, which passes it further down the chain.
+#[tracing::instrument(skip_all)]
+pub fn decode_subject_token(
+    keys: &SigningKeys,
+    issuer: &str,
+    token: &str,
+    actor: &str,
+) -> my_error::Result<Claims> {
+    let mut validation = Validation::new(Algorithm::HS256);
+    validation.set_issuer(&[issuer]);
+    // Checked below, against either of two values.
+    validation.aud = None;
+    let claims: Claims = decode_claims(keys, token, &validation)?;
+    if claims.aud != claims.sub && claims.aud != actor {
+        tracing::debug!("Audience is invalid");
+        return Err(my_error::MyError::Decode);
+    }
+    Ok(claims)
+}
+
+/// A token the realm issued to its user, presented at one of the realm's own
+/// endpoints. Exchanged tokens are for other APIs.
+#[tracing::instrument(skip_all)]
+pub fn decode_access_token(
+    keys: &SigningKeys,
+    issuer: &str,
+    token: &str,
+) -> my_error::Result<Claims> {
+    let mut validation = Validation::new(Algorithm::HS256);
+    validation.set_issuer(&[issuer]);
+    // Checked below, against the subject.
+    validation.aud = None;
+    let claims: Claims = decode_claims(keys, token, &validation)?;
+    if claims.aud != claims.sub {
+        tracing::debug!("Audience is invalid");
+        return Err(my_error::MyError::Decode);
+    }
+    Ok(claims)
+}
+
+/// A token the realm issued, expired or not, that tells who is logging out.
+#[tracing::instrument(skip_all)]
+pub fn decode_id_token_hint(
+    keys: &SigningKeys,
+    issuer: &str,
+    token: &str,
+) -> my_error::Result<Claims> {
+    let mut validation = Validation::new(Algorithm::HS256);
+    validation.set_issuer(&[issuer]);
+    validation.validate_exp = false;
+    validation.aud = None;
+    decode_claims(keys, token, &validation)
+}
+
+/// Checks the signature with the key named in `kid`, then `validation`.
+#[tracing::instrument(skip_all)]
+pub fn decode_claims<C: DeserializeOwned>(
+    keys: &SigningKeys,
+    token: &str,
+    validation: &Validation,
+) -> my_error::Result<C> {
+    let kid = decode_header(token)
+        .map_err(|_| my_error::MyError::Decode)?
+        .kid;
+    let Some(key) = keys.find(kid.as_deref()) else {
+        tracing::debug!("Signing key is unknown");
+        return Err(my_error::MyError::Decode);
+    };
+    let decode_key = DecodingKey::from_secret(key.secret().as_ref());
+    let token_data = match decode::<C>(token, &decode_key, validation) {
         Ok(c) => c,
-        Err(err) => match *err.kind() {
-            ErrorKind::InvalidToken => panic!("Token is invalid"), // Example on how to handle a specific error
-            ErrorKind::InvalidIssuer => panic!("Issuer is invalid"), // Example on how to handle a specific error
-            _ => panic!("Some other errors"),
-        },
+        Err(err) => {
+            match *err.kind() {
+                ErrorKind::InvalidToken => tracing::debug!("Token is invalid"),
+                ErrorKind::InvalidIssuer => tracing::debug!("Issuer is invalid"),
+                ErrorKind::ExpiredSignature => tracing::debug!("Token is expired"),
+                _ => tracing::debug!(error = %err, "Token is rejected"),
+            }
+            return Err(my_error::MyError::Decode);
+        }
     };
 
     Ok(token_data.claims)
@@ -0,0 +1,17 @@
+//! Random, unguessable tokens that carry no claims.
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use ring::rand::{SecureRandom, SystemRandom};
+
+use crate::error::my_error::{self, MyError};
+
+const TOKEN_BYTES: usize = 32;
+
+/// 256 random bits, base64url encoded so they fit in URLs and cookies.
+pub fn opaque_token() -> my_error::Result<String> {
+    let mut bytes = [0u8; TOKEN_BYTES];
+    SystemRandom::new()
+        .fill(&mut bytes)
+        .map_err(|_| MyError::Internal)?;
+    Ok(URL_SAFE_NO_PAD.encode(bytes))
+}
@@ -0,0 +1,155 @@
+//! Signing key material.
+
+use std::{env, fmt};
+
+use chrono::{DateTime, Utc};
+use ring::{
+    digest::{digest, SHA256},
+    rand::{SecureRandom, SystemRandom},
+};
+
+use crate::error::my_error::{self, MyError};
+
+const SECRET_ENV: &str = "IDP_JWT_SECRET";
+// todo making secret
+const DEVELOPMENT_SECRET: &str = "secret";
+const GENERATED_SECRET_BYTES: usize = 32;
+
+/// One HS256 secret, named in the `kid` header of the tokens it signs.
+#[derive(Clone, PartialEq, Eq)]
+pub struct SigningKey {
+    pub kid: String,
+    secret: String,
+    /// Only known for keys generated by rotation.
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+impl SigningKey {
+    /// The key id is derived from the secret, so that it stays the same
+    /// wherever the secret is configured.
+    pub fn of<T: Into<String>>(secret: T) -> Self {
+        let secret = secret.into();
+        let kid = digest(&SHA256, secret.as_bytes()).as_ref()[..8]
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect();
+        Self {
+            kid,
+            secret,
+            created_at: None,
+        }
+    }
+
+    pub fn with_created_at(mut self, created_at: DateTime<Utc>) -> Self {
+        self.created_at = Some(created_at);
+        self
+    }
+
+    /// A new random key.
+    pub fn generate() -> my_error::Result<Self> {
+        Ok(Self::of(generate_secret()?).with_created_at(Utc::now()))
+    }
+
+    pub fn secret(&self) -> &str {
+        &self.secret
+    }
+}
+
+// Never print the secret itself.
+impl fmt::Debug for SigningKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("SigningKey")
+            .field("kid", &self.kid)
+            .field("created_at", &self.created_at)
+            .finish()
+    }
+}
+
+/// Key material used to sign and verify issued tokens. The newest key signs,
+/// older ones still verify the tokens they signed until they are dropped.
+#[derive(Clone)]
+pub struct SigningKeys {
+    /// Newest first, never empty.
+    keys: Vec<SigningKey>,
+}
+
+impl SigningKeys {
+    pub fn of<T: Into<String>>(secret: T) -> Self {
+        Self {
+            keys: vec![SigningKey::of(secret)],
+        }
+    }
+
+    /// Keys newest first, none is an error.
+    pub fn from_keys(keys: Vec<SigningKey>) -> my_error::Result<Self> {
+        if keys.is_empty() {
+            return Err(MyError::InvalidValue);
+        }
+        Ok(Self { keys })
+    }
+
+    /// Loads the secret from `IDP_JWT_SECRET`, falling back to the development secret.
+    pub fn from_env() -> Self {
+        match env::var(SECRET_ENV) {
+            Ok(secret) if !secret.is_empty() => Self::of(secret),
+            _ => {
+                tracing::warn!(
+                    "{} is not set, falling back to the development secret",
+                    SECRET_ENV
+                );
+                Self::of(DEVELOPMENT_SECRET)
+            }
+        }
+    }
+
+    pub fn is_loaded(&self) -> bool {
+        !self.current().secret.is_empty()
+    }
+
+    /// The key that signs.
+    pub fn current(&self) -> &SigningKey {
+        &self.keys[0]
+    }
+
+    pub fn secret(&self) -> &str {
+        self.current().secret()
+    }
+
+    /// The key that signed a token, by its `kid`. Tokens without one were
+    /// signed before keys had ids, with the current key.
+    pub fn find(&self, kid: Option<&str>) -> Option<&SigningKey> {
+        match kid {
+            Some(kid) => self.keys.iter().find(|key| key.kid == kid),
+            None => Some(self.current()),
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &SigningKey> {
+        self.keys.iter()
+    }
+}
+
+// Never print the secret itself.
+impl fmt::Debug for SigningKeys {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("SigningKeys")
+            .field("loaded", &self.is_loaded())
+            .field(
+                "kids",
+                &self.keys.iter().map(|key| &key.kid).collect::<Vec<_>>(),
+            )
+            .finish()
+    }
+}
+
+/// A random secret for `IDP_JWT_SECRET` or a realm's `secret_env`, base64url.
+pub fn generate_secret() -> my_error::Result<String> {
+    let mut bytes = [0u8; GENERATED_SECRET_BYTES];
+    SystemRandom::new()
+        .fill(&mut bytes)
+        .map_err(|_| MyError::Internal)?;
+    Ok(base64::Engine::encode(
+        &base64::engine::general_purpose::URL_SAFE_NO_PAD,
+        bytes,
+    ))
+}
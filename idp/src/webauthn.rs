@@ -0,0 +1,8 @@
+pub mod attestation;
+pub mod authenticator_data;
+pub mod cbor;
+pub mod ceremony;
+pub mod client_data;
+pub mod cose_key;
+pub mod options;
+pub mod relying_party;
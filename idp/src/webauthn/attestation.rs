@@ -0,0 +1,92 @@
+//! Attestation objects of the "none" and "packed" formats.
+//!
+//! A packed statement is checked to be signed by the credential itself or by
+//! the certificate it carries. Whether that certificate chains to a known
+//! authenticator vendor is not evaluated, any passkey may register.
+
+use ciborium::Value;
+use rustls_pki_types::CertificateDer;
+use webpki::{ring as webpki_algs, EndEntityCert};
+
+use crate::{
+    error::my_error::{self, MyError},
+    webauthn::{
+        authenticator_data::{AttestedCredential, AuthenticatorData},
+        cbor::{as_bytes, as_int, as_map, as_text, decode, text_entry},
+        cose_key::{ES256, RS256},
+    },
+};
+
+pub const NONE_FORMAT: &str = "none";
+pub const PACKED_FORMAT: &str = "packed";
+
+#[derive(Debug)]
+pub struct AttestationObject {
+    pub fmt: String,
+    att_stmt: Vec<(Value, Value)>,
+    auth_data_bytes: Vec<u8>,
+    pub auth_data: AuthenticatorData,
+}
+
+impl AttestationObject {
+    pub fn parse(bytes: &[u8]) -> my_error::Result<Self> {
+        let value = decode(bytes)?;
+        let map = as_map(&value)?;
+        let auth_data_bytes = as_bytes(text_entry(map, "authData")?)?.to_vec();
+        Ok(Self {
+            fmt: as_text(text_entry(map, "fmt")?)?.to_owned(),
+            att_stmt: as_map(text_entry(map, "attStmt")?)?.to_vec(),
+            auth_data: AuthenticatorData::parse(&auth_data_bytes)?,
+            auth_data_bytes,
+        })
+    }
+
+    /// Checks the statement over the authenticator data and the hash of the
+    /// client data, and hands out the credential it attests.
+    pub fn verify(&self, client_data_hash: &[u8]) -> my_error::Result<&AttestedCredential> {
+        let credential = self
+            .auth_data
+            .attested_credential
+            .as_ref()
+            .ok_or(MyError::InvalidValue)?;
+        match self.fmt.as_str() {
+            NONE_FORMAT if self.att_stmt.is_empty() => Ok(credential),
+            PACKED_FORMAT => {
+                let alg = as_int(text_entry(&self.att_stmt, "alg")?)?;
+                let sig = as_bytes(text_entry(&self.att_stmt, "sig")?)?;
+                let signed = [self.auth_data_bytes.as_slice(), client_data_hash].concat();
+                match text_entry(&self.att_stmt, "x5c") {
+                    Ok(x5c) => verify_with_certificate(x5c, alg, &signed, sig)?,
+                    // Self attestation, signed with the new credential.
+                    Err(_) if alg == credential.public_key.alg() => {
+                        credential.public_key.verify(&signed, sig)?
+                    }
+                    Err(_) => return Err(MyError::Unauthorized),
+                }
+                Ok(credential)
+            }
+            _ => Err(MyError::InvalidValue),
+        }
+    }
+}
+
+fn verify_with_certificate(
+    x5c: &Value,
+    alg: i64,
+    signed: &[u8],
+    sig: &[u8],
+) -> my_error::Result<()> {
+    let leaf = x5c
+        .as_array()
+        .and_then(|chain| chain.first())
+        .ok_or(MyError::InvalidValue)?;
+    let der = CertificateDer::from(as_bytes(leaf)?);
+    let cert = EndEntityCert::try_from(&der).map_err(|_| MyError::InvalidValue)?;
+    let algorithm = match alg {
+        ES256 => webpki_algs::ECDSA_P256_SHA256,
+        RS256 => webpki_algs::RSA_PKCS1_2048_8192_SHA256,
+        _ => return Err(MyError::InvalidValue),
+    };
+    cert.verify_signature(algorithm, signed, sig)
+        .map_err(|_| MyError::Unauthorized)
+}
@@ -0,0 +1,92 @@
+//! Authenticator data, the part of every ceremony the authenticator signs.
+
+use ciborium::Value;
+use ring::digest::{digest, SHA256};
+
+use crate::{
+    error::my_error::{self, MyError},
+    webauthn::cose_key::CoseKey,
+};
+
+const USER_PRESENT: u8 = 0x01;
+const USER_VERIFIED: u8 = 0x04;
+const ATTESTED_CREDENTIAL_DATA: u8 = 0x40;
+const EXTENSION_DATA: u8 = 0x80;
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct AuthenticatorData {
+    pub rp_id_hash: Vec<u8>,
+    pub flags: u8,
+    /// Signature counter, 0 when the authenticator keeps none.
+    pub sign_count: u32,
+    /// Only present on registration.
+    pub attested_credential: Option<AttestedCredential>,
+}
+
+/// The new credential, as the authenticator reports it on registration.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct AttestedCredential {
+    pub aaguid: Vec<u8>,
+    pub credential_id: Vec<u8>,
+    pub public_key: CoseKey,
+}
+
+impl AuthenticatorData {
+    pub fn parse(bytes: &[u8]) -> my_error::Result<Self> {
+        if bytes.len() < 37 {
+            return Err(MyError::InvalidValue);
+        }
+        let flags = bytes[32];
+        let mut data = Self {
+            rp_id_hash: bytes[..32].to_vec(),
+            flags,
+            sign_count: u32::from_be_bytes([bytes[33], bytes[34], bytes[35], bytes[36]]),
+            attested_credential: None,
+        };
+        let mut rest = &bytes[37..];
+        if flags & ATTESTED_CREDENTIAL_DATA != 0 {
+            if rest.len() < 18 {
+                return Err(MyError::InvalidValue);
+            }
+            let (aaguid, tail) = rest.split_at(16);
+            let (length, tail) = tail.split_at(2);
+            let length = usize::from(u16::from_be_bytes([length[0], length[1]]));
+            if tail.len() < length {
+                return Err(MyError::InvalidValue);
+            }
+            let (credential_id, mut tail) = tail.split_at(length);
+            // The key is followed by the extensions without a length in between.
+            let public_key: Value =
+                ciborium::from_reader(&mut tail).map_err(|_| MyError::InvalidValue)?;
+            data.attested_credential = Some(AttestedCredential {
+                aaguid: aaguid.to_vec(),
+                credential_id: credential_id.to_vec(),
+                public_key: CoseKey::from_cbor(&public_key)?,
+            });
+            rest = tail;
+        }
+        if flags & EXTENSION_DATA == 0 && !rest.is_empty() {
+            return Err(MyError::InvalidValue);
+        }
+        Ok(data)
+    }
+
+    pub fn user_present(&self) -> bool {
+        self.flags & USER_PRESENT != 0
+    }
+
+    pub fn user_verified(&self) -> bool {
+        self.flags & USER_VERIFIED != 0
+    }
+
+    /// The authenticator has to have scoped the credential to `rp_id` and seen the user.
+    pub fn verify(&self, rp_id: &str, user_verification: bool) -> my_error::Result<()> {
+        if self.rp_id_hash != digest(&SHA256, rp_id.as_bytes()).as_ref()
+            || !self.user_present()
+            || (user_verification && !self.user_verified())
+        {
+            return Err(MyError::Unauthorized);
+        }
+        Ok(())
+    }
+}
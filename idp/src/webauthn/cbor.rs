@@ -0,0 +1,50 @@
+//! Lookups in decoded CBOR, malformed input is `MyError::InvalidValue`.
+
+use ciborium::Value;
+
+use crate::error::my_error::{self, MyError};
+
+pub fn decode(bytes: &[u8]) -> my_error::Result<Value> {
+    ciborium::from_reader(bytes).map_err(|_| MyError::InvalidValue)
+}
+
+pub fn as_map(value: &Value) -> my_error::Result<&[(Value, Value)]> {
+    value
+        .as_map()
+        .map(Vec::as_slice)
+        .ok_or(MyError::InvalidValue)
+}
+
+pub fn as_bytes(value: &Value) -> my_error::Result<&[u8]> {
+    value
+        .as_bytes()
+        .map(Vec::as_slice)
+        .ok_or(MyError::InvalidValue)
+}
+
+pub fn as_text(value: &Value) -> my_error::Result<&str> {
+    value.as_text().ok_or(MyError::InvalidValue)
+}
+
+pub fn as_int(value: &Value) -> my_error::Result<i64> {
+    value
+        .as_integer()
+        .and_then(|int| i64::try_from(int).ok())
+        .ok_or(MyError::InvalidValue)
+}
+
+/// Entry of a map with an integer key, as COSE keys use.
+pub fn int_entry(map: &[(Value, Value)], key: i64) -> my_error::Result<&Value> {
+    map.iter()
+        .find(|(k, _)| k.as_integer().and_then(|k| i64::try_from(k).ok()) == Some(key))
+        .map(|(_, v)| v)
+        .ok_or(MyError::InvalidValue)
+}
+
+/// Entry of a map with a text key, as attestation objects use.
+pub fn text_entry<'a>(map: &'a [(Value, Value)], key: &str) -> my_error::Result<&'a Value> {
+    map.iter()
+        .find(|(k, _)| k.as_text() == Some(key))
+        .map(|(_, v)| v)
+        .ok_or(MyError::InvalidValue)
+}
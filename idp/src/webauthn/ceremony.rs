@@ -0,0 +1,42 @@
+//! A WebAuthn ceremony waiting for the browser to answer its challenge.
+
+use chrono::{DateTime, Duration, Utc};
+
+use crate::{
+    domain::mail_address::MailAddress, error::my_error, token::opaque_token::opaque_token,
+};
+
+/// How long the user may take at the authenticator.
+pub const CEREMONY_TIMEOUT_SECS: i64 = 300;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum CeremonyKind {
+    Registration,
+    Authentication,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Ceremony {
+    /// base64url, the key of the ceremony.
+    pub challenge: String,
+    pub kind: CeremonyKind,
+    /// The user the ceremony is for. An authentication without one is a
+    /// passkey login as first factor, with one it follows a password.
+    pub email: Option<MailAddress>,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl Ceremony {
+    pub fn start(kind: CeremonyKind, email: Option<MailAddress>) -> my_error::Result<Self> {
+        Ok(Self {
+            challenge: opaque_token()?,
+            kind,
+            email,
+            expires_at: Utc::now() + Duration::seconds(CEREMONY_TIMEOUT_SECS),
+        })
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.expires_at <= Utc::now()
+    }
+}
@@ -0,0 +1,43 @@
+//! Client data, what the browser says about the ceremony it ran.
+
+use serde::Deserialize;
+
+use crate::error::my_error::{self, MyError};
+
+pub const WEBAUTHN_CREATE: &str = "webauthn.create";
+pub const WEBAUTHN_GET: &str = "webauthn.get";
+
+/// `CollectedClientData`, parsed from `clientDataJSON`.
+#[derive(Debug, Deserialize)]
+pub struct CollectedClientData {
+    #[serde(rename = "type")]
+    pub ceremony_type: String,
+    /// base64url, as it was sent in the options.
+    pub challenge: String,
+    pub origin: String,
+    #[serde(rename = "crossOrigin", default)]
+    pub cross_origin: bool,
+}
+
+impl CollectedClientData {
+    pub fn parse(client_data_json: &[u8]) -> my_error::Result<Self> {
+        serde_json::from_slice(client_data_json).map_err(|_| MyError::InvalidValue)
+    }
+
+    /// Embedding the ceremony in a page of another origin is not supported.
+    pub fn verify(
+        &self,
+        ceremony_type: &str,
+        challenge: &str,
+        origin: &str,
+    ) -> my_error::Result<()> {
+        if self.ceremony_type != ceremony_type
+            || self.challenge != challenge
+            || self.origin != origin
+            || self.cross_origin
+        {
+            return Err(MyError::Unauthorized);
+        }
+        Ok(())
+    }
+}
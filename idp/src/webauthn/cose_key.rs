@@ -0,0 +1,84 @@
+//! Credential public keys in their COSE_Key encoding, RFC 9052.
+
+use ciborium::Value;
+use ring::signature::{
+    RsaPublicKeyComponents, UnparsedPublicKey, ECDSA_P256_SHA256_ASN1, RSA_PKCS1_2048_8192_SHA256,
+};
+
+use crate::{
+    error::my_error::{self, MyError},
+    webauthn::cbor::{as_bytes, as_int, as_map, int_entry},
+};
+
+/// ECDSA with P-256 and SHA-256.
+pub const ES256: i64 = -7;
+/// RSASSA-PKCS1-v1_5 with SHA-256.
+pub const RS256: i64 = -257;
+
+const KTY: i64 = 1;
+const ALG: i64 = 3;
+const KTY_EC2: i64 = 2;
+const KTY_RSA: i64 = 3;
+const EC2_CRV: i64 = -1;
+const EC2_X: i64 = -2;
+const EC2_Y: i64 = -3;
+const CRV_P256: i64 = 1;
+const RSA_N: i64 = -1;
+const RSA_E: i64 = -2;
+
+/// The algorithms a passkey may sign with, the ones announced in the creation options.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum CoseKey {
+    /// Uncompressed P-256 point.
+    Es256(Vec<u8>),
+    Rs256 {
+        n: Vec<u8>,
+        e: Vec<u8>,
+    },
+}
+
+impl CoseKey {
+    pub fn from_cbor(value: &Value) -> my_error::Result<Self> {
+        let map = as_map(value)?;
+        match (as_int(int_entry(map, KTY)?)?, as_int(int_entry(map, ALG)?)?) {
+            (KTY_EC2, ES256) => {
+                if as_int(int_entry(map, EC2_CRV)?)? != CRV_P256 {
+                    return Err(MyError::InvalidValue);
+                }
+                let x = as_bytes(int_entry(map, EC2_X)?)?;
+                let y = as_bytes(int_entry(map, EC2_Y)?)?;
+                if x.len() != 32 || y.len() != 32 {
+                    return Err(MyError::InvalidValue);
+                }
+                Ok(Self::Es256([&[0x04], x, y].concat()))
+            }
+            (KTY_RSA, RS256) => Ok(Self::Rs256 {
+                n: as_bytes(int_entry(map, RSA_N)?)?.to_vec(),
+                e: as_bytes(int_entry(map, RSA_E)?)?.to_vec(),
+            }),
+            _ => Err(MyError::InvalidValue),
+        }
+    }
+
+    pub fn alg(&self) -> i64 {
+        match self {
+            Self::Es256(_) => ES256,
+            Self::Rs256 { .. } => RS256,
+        }
+    }
+
+    /// ES256 signatures come DER encoded, as authenticators produce them.
+    pub fn verify(&self, message: &[u8], signature: &[u8]) -> my_error::Result<()> {
+        match self {
+            Self::Es256(point) => {
+                UnparsedPublicKey::new(&ECDSA_P256_SHA256_ASN1, point).verify(message, signature)
+            }
+            Self::Rs256 { n, e } => RsaPublicKeyComponents { n, e }.verify(
+                &RSA_PKCS1_2048_8192_SHA256,
+                message,
+                signature,
+            ),
+        }
+        .map_err(|_| MyError::Unauthorized)
+    }
+}
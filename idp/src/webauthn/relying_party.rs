@@ -0,0 +1,167 @@
+//! The realm as a WebAuthn relying party.
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use ring::digest::{digest, SHA256};
+use url::Url;
+
+use crate::{
+    entity::{passkey::Passkey, user::User},
+    error::my_error::{self, MyError},
+    realm::realm_registry::Realm,
+    webauthn::{
+        attestation::AttestationObject,
+        authenticator_data::AuthenticatorData,
+        ceremony::{Ceremony, CeremonyKind, CEREMONY_TIMEOUT_SECS},
+        client_data::{CollectedClientData, WEBAUTHN_CREATE, WEBAUTHN_GET},
+        cose_key::{ES256, RS256},
+        options::{
+            AuthenticatorSelection, CreationOptions, CredentialDescriptor, CredentialParameters,
+            RequestOptions, RpEntity, UserEntity,
+        },
+    },
+};
+
+const PUBLIC_KEY: &str = "public-key";
+
+/// Passkeys are scoped to the host of the realm issuer, and the ceremonies
+/// run on pages of the issuer origin.
+pub struct RelyingParty {
+    pub id: String,
+    pub origin: String,
+    name: String,
+}
+
+impl RelyingParty {
+    pub fn of(realm: &Realm) -> my_error::Result<Self> {
+        let issuer = Url::parse(&realm.issuer).map_err(|_| MyError::Internal)?;
+        Ok(Self {
+            id: issuer.host_str().ok_or(MyError::Internal)?.to_owned(),
+            origin: issuer.origin().ascii_serialization(),
+            name: realm.name.clone(),
+        })
+    }
+
+    /// The user has to carry a handle already, so that passkeys registered
+    /// before and after share it.
+    pub fn creation_options(
+        &self,
+        ceremony: &Ceremony,
+        user: &User,
+    ) -> my_error::Result<CreationOptions> {
+        let name = String::from(user.email.clone());
+        Ok(CreationOptions {
+            rp: RpEntity {
+                id: self.id.clone(),
+                name: self.name.clone(),
+            },
+            user: UserEntity {
+                id: user.user_handle.clone().ok_or(MyError::Internal)?,
+                name: name.clone(),
+                display_name: name,
+            },
+            challenge: ceremony.challenge.clone(),
+            pub_key_cred_params: [ES256, RS256]
+                .into_iter()
+                .map(|alg| CredentialParameters {
+                    credential_type: PUBLIC_KEY.to_owned(),
+                    alg,
+                })
+                .collect(),
+            timeout: CEREMONY_TIMEOUT_SECS * 1000,
+            exclude_credentials: descriptors(&user.passkeys),
+            authenticator_selection: AuthenticatorSelection {
+                resident_key: "preferred".to_owned(),
+                user_verification: "preferred".to_owned(),
+            },
+            attestation: "direct".to_owned(),
+        })
+    }
+
+    /// A passkey as first factor has to verify the user itself.
+    pub fn request_options(&self, ceremony: &Ceremony, passkeys: &[Passkey]) -> RequestOptions {
+        let user_verification = match ceremony.email {
+            Some(_) => "preferred",
+            None => "required",
+        };
+        RequestOptions {
+            challenge: ceremony.challenge.clone(),
+            timeout: CEREMONY_TIMEOUT_SECS * 1000,
+            rp_id: self.id.clone(),
+            allow_credentials: descriptors(passkeys),
+            user_verification: user_verification.to_owned(),
+        }
+    }
+
+    /// Verifies the answer to `navigator.credentials.create()`.
+    pub fn register(
+        &self,
+        ceremony: &Ceremony,
+        client_data_json: &[u8],
+        attestation_object: &[u8],
+    ) -> my_error::Result<Passkey> {
+        if ceremony.kind != CeremonyKind::Registration {
+            return Err(MyError::Unauthorized);
+        }
+        CollectedClientData::parse(client_data_json)?.verify(
+            WEBAUTHN_CREATE,
+            &ceremony.challenge,
+            &self.origin,
+        )?;
+        let attestation = AttestationObject::parse(attestation_object)?;
+        attestation.auth_data.verify(&self.id, false)?;
+        let credential = attestation.verify(digest(&SHA256, client_data_json).as_ref())?;
+        Ok(Passkey {
+            id: URL_SAFE_NO_PAD.encode(&credential.credential_id),
+            public_key: credential.public_key.clone(),
+            sign_count: attestation.auth_data.sign_count,
+            attestation_format: attestation.fmt.clone(),
+        })
+    }
+
+    /// Verifies the answer to `navigator.credentials.get()` and returns the new
+    /// signature counter of the passkey.
+    pub fn authenticate(
+        &self,
+        ceremony: &Ceremony,
+        passkey: &Passkey,
+        client_data_json: &[u8],
+        authenticator_data: &[u8],
+        signature: &[u8],
+    ) -> my_error::Result<u32> {
+        if ceremony.kind != CeremonyKind::Authentication {
+            return Err(MyError::Unauthorized);
+        }
+        CollectedClientData::parse(client_data_json)?.verify(
+            WEBAUTHN_GET,
+            &ceremony.challenge,
+            &self.origin,
+        )?;
+        let data = AuthenticatorData::parse(authenticator_data)?;
+        data.verify(&self.id, ceremony.email.is_none())?;
+        let signed = [
+            authenticator_data,
+            digest(&SHA256, client_data_json).as_ref(),
+        ]
+        .concat();
+        passkey.public_key.verify(&signed, signature)?;
+
+        // A counter that does not move forward hints at a cloned authenticator.
+        if (data.sign_count != 0 || passkey.sign_count != 0)
+            && data.sign_count <= passkey.sign_count
+        {
+            tracing::warn!(passkey = %passkey.id, "signature counter went backwards");
+            return Err(MyError::Unauthorized);
+        }
+        Ok(data.sign_count)
+    }
+}
+
+fn descriptors(passkeys: &[Passkey]) -> Vec<CredentialDescriptor> {
+    passkeys
+        .iter()
+        .map(|passkey| CredentialDescriptor {
+            credential_type: PUBLIC_KEY.to_owned(),
+            id: passkey.id.clone(),
+        })
+        .collect()
+}